@@ -6,7 +6,7 @@ use std::process::{Child, ExitStatus};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::{Receiver, RecvTimeoutError};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{env, fmt, thread};
 
 use console::Color::{Cyan, Green, Red, Yellow};
@@ -49,6 +49,15 @@ pub struct MakeOpts {
     /// Keep the TeX file when generating PDF. Use twice to keep TeX build directory as well.
     #[arg(short = 'k', long, action = clap::ArgAction::Count)]
     pub keep: u8,
+    /// Render all outputs, even those which haven't changed since the last build
+    #[arg(short = 'f', long)]
+    pub force: bool,
+    /// Fail the build on warnings about song files, as if `strict = true` was set in bard.toml
+    #[arg(long)]
+    pub deny_warnings: bool,
+    /// Only build outputs with these names (or file names), all outputs are built by default
+    #[arg(value_name = "OUTPUT")]
+    pub outputs: Vec<String>,
     #[clap(flatten)]
     pub stdio: StdioOpts,
 }
@@ -75,6 +84,7 @@ pub mod keeplevel {
 }
 
 pub type ParserDiags = Arc<Mutex<Vec<Diagnostic>>>;
+pub type Warnings = Arc<Mutex<Vec<String>>>;
 
 #[derive(Clone, Copy, Debug)]
 #[repr(transparent)]
@@ -133,6 +143,12 @@ pub struct App {
     post_process: bool,
     /// See `keeplevel` for levels.
     keep_interm: u8,
+    /// Ignore the render cache and render all outputs.
+    force_rebuild: bool,
+    /// Treat parser warnings as errors.
+    deny_warnings: bool,
+    /// Names of outputs to build, all outputs if empty.
+    selected_outputs: Vec<String>,
 
     // stdio stuff
     term: Term,
@@ -150,6 +166,8 @@ pub struct App {
 
     /// Parser diagnostic messages, these are only collected in `test_mode`.
     parser_diags: Option<ParserDiags>,
+    /// Warning messages, these are only collected in `test_mode`.
+    warnings: Option<Warnings>,
 }
 
 impl App {
@@ -158,6 +176,9 @@ impl App {
             interrupt,
             post_process: !opts.no_postprocess,
             keep_interm: opts.keep,
+            force_rebuild: opts.force,
+            deny_warnings: opts.deny_warnings,
+            selected_outputs: opts.outputs.clone(),
             term: Term::stderr(),
             verbosity: opts.stdio.verbosity(),
             test_mode: false,
@@ -165,6 +186,7 @@ impl App {
             self_name: "bard",
             img_cache: ImgCache::new(),
             parser_diags: None,
+            warnings: None,
         }
     }
 
@@ -175,6 +197,9 @@ impl App {
             interrupt,
             post_process,
             keep_interm: keeplevel::ALL,
+            force_rebuild: false,
+            deny_warnings: false,
+            selected_outputs: vec![],
             term: Term::stderr(),
             verbosity: 2,
             test_mode: true,
@@ -182,6 +207,7 @@ impl App {
             self_name: "bard",
             img_cache: ImgCache::new(),
             parser_diags: Some(Arc::new(Mutex::new(vec![]))),
+            warnings: Some(Arc::new(Mutex::new(vec![]))),
         }
     }
 
@@ -201,6 +227,34 @@ impl App {
         self.keep_interm
     }
 
+    pub fn selected_outputs(&self) -> &[String] {
+        &self.selected_outputs
+    }
+
+    pub fn force_rebuild(&self) -> bool {
+        self.force_rebuild
+    }
+
+    pub fn with_force_rebuild(mut self, force_rebuild: bool) -> Self {
+        self.force_rebuild = force_rebuild;
+        self
+    }
+
+    pub fn deny_warnings(&self) -> bool {
+        self.deny_warnings
+    }
+
+    pub fn with_deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.deny_warnings = deny_warnings;
+        self
+    }
+
+    /// Only build outputs with these names, all outputs if empty.
+    pub fn with_selected_outputs(mut self, outputs: Vec<String>) -> Self {
+        self.selected_outputs = outputs;
+        self
+    }
+
     pub fn verbosity(&self) -> u8 {
         self.verbosity
     }
@@ -221,6 +275,10 @@ impl App {
         self.parser_diags.as_ref().unwrap()
     }
 
+    pub fn warnings(&self) -> &Warnings {
+        self.warnings.as_ref().unwrap()
+    }
+
     // SIGINT support
 
     pub fn check_interrupted(&self) -> Result<(), InterruptError> {
@@ -243,6 +301,28 @@ impl App {
         }
     }
 
+    /// Like `child_wait()`, but gives up after `timeout` and returns `None`.
+    /// The child is left running in that case.
+    pub fn child_wait_timeout(
+        &self,
+        child: &mut Child,
+        timeout: Duration,
+    ) -> Result<Option<ExitStatus>> {
+        let start = Instant::now();
+        loop {
+            self.check_interrupted()?;
+
+            if let Some(status) = child.try_wait()? {
+                return Ok(Some(status));
+            }
+            if start.elapsed() >= timeout {
+                return Ok(None);
+            }
+
+            thread::sleep(Duration::from_millis(50));
+        }
+    }
+
     // stdio helpers
 
     fn color(&self, color: Color) -> Style {
@@ -293,6 +373,10 @@ impl App {
     }
 
     pub fn warning(&self, msg: impl Display) {
+        if let Some(warnings) = self.warnings.as_ref() {
+            warnings.lock().push(msg.to_string());
+        }
+
         self.status_inner("Warning", &self.color(Yellow), msg);
     }
 
@@ -326,6 +410,7 @@ impl App {
         self.status_inner("Error", &self.color(Red), msg);
     }
 
+    /// Report a parser diagnostic along with an excerpt of the offending line, if its span is known.
     pub fn parser_diag(&self, diag: Diagnostic) {
         if self.test_mode {
             self.parser_diags
@@ -335,11 +420,15 @@ impl App {
                 .push(diag.clone());
         }
 
+        let excerpt = diag.excerpt();
         if diag.is_error() {
             self.error_generic(diag);
         } else {
             self.warning(diag);
         }
+        if let Some(excerpt) = excerpt {
+            self.indent(excerpt);
+        }
     }
 
     pub fn subprocess_output(
@@ -1,19 +1,20 @@
 //! AST of a bard songbook
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::{iter, mem};
 
 use image::image_dimensions;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 
-use crate::music::Notation;
+use crate::music::{Accidentals, Notation};
 use crate::prelude::*;
-use crate::project::Settings;
+use crate::project::{Format, LabelNumbering, Metadata, Settings, Value};
 use crate::util::{sort_lexical_by, BStr, ImgCache};
 
 pub mod version;
 mod xml;
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(tag = "type")]
 pub enum Block {
     #[serde(rename = "b-verse")]
@@ -22,9 +23,17 @@ pub enum Block {
     BulletList(BulletList),
     #[serde(rename = "b-horizontal-line")]
     HorizontalLine,
+    /// Preformatted text, `info` is the info string of a fenced code block, if any.
     #[serde(rename = "b-pre")]
-    Pre { text: BStr },
-    /// An HTML block contains inlines which can only be `Text`, `HtmlTag`, or `Break`.
+    Pre { text: BStr, info: BStr },
+    /// Guitar tablature, given as a code block with the `tab` info string.
+    #[serde(rename = "b-tab")]
+    Tab { text: BStr },
+    /// A standalone image, given as a paragraph starting with an image on a line of its own
+    /// followed by the caption.
+    #[serde(rename = "b-image")]
+    Image { image: Image, caption: BStr },
+    /// An HTML block contains inlines which can only be `Text`, `HtmlTag`, `Raw`, or `Break`.
     #[serde(rename = "b-html-block")]
     HtmlBlock(Inlines),
 }
@@ -44,16 +53,21 @@ impl Block {
 
     /// Used to remove chorus numbers in case there's one chorus.
     pub fn remove_chorus_num(&mut self) {
+        self.set_chorus_num(None);
+    }
+
+    /// Sets the number of the chorus label and chorus references, if any, to `num`.
+    pub fn set_chorus_num(&mut self, num: Option<u32>) {
         if let Self::Verse(verse) = self {
-            if let VerseLabel::Chorus(num) = &mut verse.label {
-                *num = None;
+            if let VerseLabel::Chorus(n) = &mut verse.label {
+                *n = num;
             }
 
             verse
                 .paragraphs
                 .iter_mut()
                 .flat_map(|p| p.iter_mut())
-                .for_each(Inline::remove_chorus_num);
+                .for_each(|inline| inline.set_chorus_num(num));
         }
     }
 
@@ -70,10 +84,27 @@ impl Block {
             _ => None,
         }
     }
+
+    /// Images in a verse or a standalone image.
+    fn images(&self) -> Box<dyn Iterator<Item = &Image> + '_> {
+        match self {
+            Self::Verse(verse) => Box::new(verse.inlines().filter_map(Inline::image)),
+            Self::Image { image, .. } => Box::new(iter::once(image)),
+            _ => Box::new(iter::empty()),
+        }
+    }
+
+    fn images_mut(&mut self) -> Box<dyn Iterator<Item = &mut Image> + '_> {
+        match self {
+            Self::Verse(verse) => Box::new(verse.inlines_mut().filter_map(Inline::image_mut)),
+            Self::Image { image, .. } => Box::new(iter::once(image)),
+            _ => Box::new(iter::empty()),
+        }
+    }
 }
 
 /// Needed for Inline enum tagging in JSON and similar...
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Inlines {
     pub inlines: Box<[Inline]>,
 }
@@ -83,8 +114,10 @@ impl Inlines {
         Self { inlines }
     }
 
-    fn remove_chorus_num(&mut self) {
-        self.inlines.iter_mut().for_each(Inline::remove_chorus_num);
+    fn set_chorus_num(&mut self, num: Option<u32>) {
+        self.inlines
+            .iter_mut()
+            .for_each(|inline| inline.set_chorus_num(num));
     }
 }
 
@@ -102,7 +135,7 @@ impl AsRef<[Inline]> for Inlines {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 #[serde(rename_all = "snake_case")]
 #[serde(tag = "type")]
 pub enum Inline {
@@ -123,8 +156,22 @@ pub enum Inline {
     Image(Image),
     #[serde(rename = "i-chorus-ref")]
     ChorusRef(ChorusRef),
+    #[serde(rename = "i-verse-ref")]
+    VerseRef(VerseRef),
+    /// Start of a repeated section, `|:` in the source.
+    #[serde(rename = "i-repeat-start")]
+    RepeatStart,
+    /// End of a repeated section, `:|` or `:|x2` in the source.
+    #[serde(rename = "i-repeat-end")]
+    RepeatEnd(RepeatEnd),
     #[serde(rename = "i-tag")]
     HtmlTag(HtmlTag),
+    /// Raw TeX code, `<tex code="..."/>` in the source. Emitted verbatim by TeX templates.
+    #[serde(rename = "i-raw")]
+    Raw { tex: BStr },
+    /// A footnote, `^[text]` in the source.
+    #[serde(rename = "i-footnote")]
+    Footnote(Footnote),
 
     /// Only used internally by the parser to apply transposition.
     /// Removed from the resulting AST, except in tests where this
@@ -153,12 +200,27 @@ impl Inline {
         }
     }
 
-    fn remove_chorus_num(&mut self) {
+    fn set_chorus_num(&mut self, num: Option<u32>) {
+        match self {
+            Inline::Chord(c) => c.set_chorus_num(num),
+            Inline::Emph(e) => e.set_chorus_num(num),
+            Inline::Strong(s) => s.set_chorus_num(num),
+            Inline::ChorusRef(cr) => cr.num = num,
+            _ => {}
+        }
+    }
+
+    /// Removes chorus references nested in this inline for which `exists` is true,
+    /// numbers of the removed references are appended to `refs`.
+    fn take_chorus_refs(
+        &mut self,
+        refs: &mut Vec<Option<u32>>,
+        exists: &dyn Fn(Option<u32>) -> bool,
+    ) {
         match self {
-            Inline::Chord(c) => c.remove_chorus_num(),
-            Inline::Emph(e) => e.remove_chorus_num(),
-            Inline::Strong(s) => s.remove_chorus_num(),
-            Inline::ChorusRef(cr) => cr.num = None,
+            Inline::Chord(c) => take_chorus_refs(&mut c.inlines, refs, exists),
+            Inline::Emph(e) => take_chorus_refs(&mut e.inlines, refs, exists),
+            Inline::Strong(s) => take_chorus_refs(&mut s.inlines, refs, exists),
             _ => {}
         }
     }
@@ -178,13 +240,57 @@ impl Inline {
     }
 }
 
-#[derive(Serialize, Debug)]
+/// Appends the text of `inlines` to `out` up to the first line break, returns whether a break was found.
+fn line_text(inlines: &[Inline], out: &mut String) -> bool {
+    for inline in inlines {
+        let found_break = match inline {
+            Inline::Text { text } => {
+                out.push_str(text);
+                false
+            }
+            Inline::Chord(chord) => line_text(&chord.inlines, out),
+            Inline::Emph(i) | Inline::Strong(i) => line_text(&i.inlines, out),
+            Inline::Break => true,
+            _ => false,
+        };
+        if found_break {
+            return true;
+        }
+    }
+    false
+}
+
+fn take_chorus_refs(
+    inlines: &mut Box<[Inline]>,
+    refs: &mut Vec<Option<u32>>,
+    exists: &dyn Fn(Option<u32>) -> bool,
+) {
+    let taken: Vec<_> = mem::take(inlines)
+        .into_vec()
+        .into_iter()
+        .filter_map(|mut inline| match &inline {
+            Inline::ChorusRef(cr) if exists(cr.num) => {
+                refs.push(cr.num);
+                None
+            }
+            _ => {
+                inline.take_chorus_refs(refs, exists);
+                Some(inline)
+            }
+        })
+        .collect();
+    *inlines = taken.into();
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Chord {
     pub chord: BStr,
     pub alt_chord: Option<BStr>,
     pub backticks: usize,
     pub baseline: bool,
     pub inlines: Box<[Inline]>,
+    /// Whether the chord is on a line consisting only of chords, such as an intro.
+    pub chords_only: bool,
 }
 
 impl Chord {
@@ -201,15 +307,18 @@ impl Chord {
             backticks,
             baseline,
             inlines: inlines.into(),
+            chords_only: false,
         }
     }
 
-    fn remove_chorus_num(&mut self) {
-        self.inlines.iter_mut().for_each(Inline::remove_chorus_num);
+    fn set_chorus_num(&mut self, num: Option<u32>) {
+        self.inlines
+            .iter_mut()
+            .for_each(|inline| inline.set_chorus_num(num));
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Link {
     pub url: BStr,
     pub title: BStr,
@@ -222,7 +331,7 @@ impl Link {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Image {
     pub path: BStr,
     pub title: BStr,
@@ -274,7 +383,7 @@ impl Image {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct ChorusRef {
     pub num: Option<u32>,
     pub prefix_space: BStr,
@@ -289,14 +398,58 @@ impl ChorusRef {
     }
 }
 
-#[derive(Serialize, Debug)]
+/// Reference to a verse with a custom label, eg. `!>Bridge`.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct VerseRef {
+    pub label: BStr,
+    pub prefix_space: BStr,
+}
+
+impl VerseRef {
+    pub fn new(label: impl Into<BStr>, prefix_space: bool) -> Self {
+        Self {
+            label: label.into(),
+            prefix_space: if prefix_space { " ".into() } else { "".into() },
+        }
+    }
+}
+
+/// End of a repeated section, `count` is `None` if not specified.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct RepeatEnd {
+    pub count: Option<u32>,
+}
+
+impl RepeatEnd {
+    pub fn new(count: Option<u32>) -> Self {
+        Self { count }
+    }
+}
+
+/// A footnote, `num` is its number within the song, starting from 1.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct Footnote {
+    pub num: u32,
+    pub inlines: Box<[Inline]>,
+}
+
+impl Footnote {
+    pub fn new(num: u32, inlines: Vec<Inline>) -> Self {
+        Self {
+            num,
+            inlines: inlines.into(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct HtmlTag {
     pub name: BStr,
     pub attrs: BTreeMap<BStr, BStr>,
 }
 
 /// Transposition extensions. See Comment in `Inline`.
-#[derive(Serialize, Clone, Copy, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
 pub enum Transpose {
     #[serde(rename = "t-transpose")]
     Transpose(i32),
@@ -309,9 +462,15 @@ pub enum Transpose {
     /// Turn off alt chords
     #[serde(rename = "t-alt-none")]
     AltNone,
+    /// Notation the following chords are written in, `None` to switch back to the song's notation.
+    #[serde(rename = "t-src-notation")]
+    SrcNotation(Option<Notation>),
+    /// Spelling of accidentals in transposed chords, `!#` for sharps or `!b` for flats.
+    #[serde(rename = "t-accidentals")]
+    Accidentals(Accidentals),
 }
 
-#[derive(Serialize, Clone, PartialEq, Eq, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
 #[serde(rename_all = "snake_case")]
 pub enum VerseLabel {
     Verse(u32),
@@ -328,15 +487,43 @@ impl VerseLabel {
 
 pub type Paragraph = Box<[Inline]>;
 
-#[derive(Serialize, Debug)]
+/// Pushes `inlines` as a new paragraph, unless there's nothing but line breaks.
+/// Leading and trailing line breaks are removed.
+fn push_paragraph(paragraphs: &mut Vec<Paragraph>, mut inlines: Vec<Inline>) {
+    while inlines.last().map_or(false, Inline::is_break) {
+        inlines.pop();
+    }
+    let start = inlines
+        .iter()
+        .position(|inline| !inline.is_break())
+        .unwrap_or(inlines.len());
+    inlines.drain(..start);
+
+    if !inlines.is_empty() {
+        paragraphs.push(inlines.into());
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Verse {
     pub label: VerseLabel,
     pub paragraphs: Vec<Paragraph>,
+    /// How many times the verse is to be sung, given by a marker such as `x2`.
+    /// `None` if there's no marker (or it's `x1`).
+    pub repeat_count: Option<u32>,
+    /// Notation of the verse's chords, if it differs from the song's notation,
+    /// ie. when switched with a `!=notation` marker.
+    pub notation: Option<Notation>,
 }
 
 impl Verse {
-    pub fn new(label: VerseLabel, paragraphs: Vec<Paragraph>) -> Self {
-        Self { label, paragraphs }
+    pub fn new(label: VerseLabel, paragraphs: Vec<Paragraph>, repeat_count: Option<u32>) -> Self {
+        Self {
+            label,
+            paragraphs,
+            repeat_count,
+            notation: None,
+        }
     }
 
     pub fn is_empty(&self) -> bool {
@@ -352,20 +539,36 @@ impl Verse {
     }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct BulletList {
     pub items: Box<[BStr]>,
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
 pub struct Song {
     pub title: BStr,
+    /// Identifier of the song, unique within the book, generated from the title
+    /// unless given explicitly with `!id:` metadata.
+    pub id: BStr,
     pub subtitles: Box<[BStr]>,
+    /// Free-form song metadata given as `!key: value` lines after the title, eg. `author` or `key`.
+    pub metadata: Metadata,
+    /// Key of the song, see `detect_key()`.
+    pub key: Option<BStr>,
+    /// Name of the chapter the song belongs to, as configured in `bard.toml`, if any.
+    pub chapter: Option<BStr>,
     pub blocks: Vec<Block>,
+    /// Footnotes of the song in the order of their numbers, also found inline as `i-footnote`.
+    pub footnotes: Vec<Footnote>,
     pub notation: Notation,
 }
 
 impl Song {
+    /// Images in verses and standalone images of the song.
+    pub fn images(&self) -> impl Iterator<Item = &Image> {
+        self.blocks.iter().flat_map(Block::images)
+    }
+
     /// AST postprocessing.
     ///
     /// This entails removing empty paragraphs and verses
@@ -386,11 +589,187 @@ impl Song {
             _ => true,
         });
     }
+
+    /// The song's key as given by the `key` metadata field,
+    /// or otherwise the first chord of the first verse with chords, if any.
+    pub fn detect_key(&self) -> Option<BStr> {
+        match self.metadata.get("key") {
+            Some(Value::String(key)) if !key.is_empty() => return Some(key.as_str().into()),
+            Some(Value::String(..)) | None => {}
+            Some(other) => return Some(other.to_string().into()),
+        }
+
+        self.blocks
+            .iter()
+            .filter_map(Block::verse)
+            .find_map(|verse| {
+                verse.inlines().find_map(|inline| match inline {
+                    Inline::Chord(chord) => Some(chord.chord.clone()),
+                    _ => None,
+                })
+            })
+    }
+
+    /// Whether there are any chords in the song's verses.
+    pub fn has_chords(&self) -> bool {
+        self.blocks
+            .iter()
+            .filter_map(Block::verse)
+            .flat_map(|verse| verse.inlines())
+            .any(|inline| matches!(inline, Inline::Chord(..)))
+    }
+
+    /// Text of the first line of lyrics, ie. of the first verse which has some text, without chords.
+    pub fn first_line(&self) -> Option<BStr> {
+        self.blocks
+            .iter()
+            .filter_map(Block::verse)
+            .flat_map(|verse| verse.paragraphs.iter())
+            .find_map(|para| {
+                let mut line = String::new();
+                line_text(para, &mut line);
+                let line = line.trim();
+                (!line.is_empty()).then(|| line.into())
+            })
+    }
+
+    /// Tags given by the `tags` metadata field, either as an array or as a comma-separated string.
+    pub fn tags(&self) -> Vec<BStr> {
+        match self.metadata.get("tags") {
+            Some(Value::Array(tags)) => tags
+                .iter()
+                .map(|tag| match tag {
+                    Value::String(tag) => tag.as_str().into(),
+                    other => other.to_string().into(),
+                })
+                .collect(),
+            Some(Value::String(tags)) => tags
+                .split(',')
+                .map(str::trim)
+                .filter(|tag| !tag.is_empty())
+                .map(BStr::from)
+                .collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Custom template filename to render this song with in outputs of `format`, if any.
+    ///
+    /// Given by the `template` metadata field, either as a string applying to all templated formats,
+    /// or as a table keyed by format name, eg. `!template: { html = "bonus.hbs" }`.
+    pub fn template(&self, format: Format) -> Option<&str> {
+        match self.metadata.get("template")? {
+            Value::String(template) => Some(template.as_str()),
+            Value::Table(table) => table.get(&format.to_string())?.as_str(),
+            _ => None,
+        }
+    }
+
+    /// Adjusts numbers of verse and chorus labels (and chorus references) according to `numbering`.
+    ///
+    /// Verse labels consist of the number only, so verses that aren't to be numbered lose their label.
+    pub fn number_labels(&mut self, numbering: LabelNumbering) {
+        let verses = self
+            .blocks
+            .iter()
+            .filter_map(Block::verse)
+            .filter(|verse| matches!(verse.label, VerseLabel::Verse(_)))
+            .count();
+        let choruses: HashSet<_> = self
+            .blocks
+            .iter()
+            .filter_map(Block::verse)
+            .filter_map(|verse| match verse.label {
+                VerseLabel::Chorus(num) => Some(num),
+                _ => None,
+            })
+            .collect();
+
+        let (number_verses, number_choruses) = match numbering {
+            LabelNumbering::Always => (true, true),
+            LabelNumbering::Never => (false, false),
+            LabelNumbering::Auto => (verses > 1, choruses.len() > 1),
+        };
+
+        for block in self.blocks.iter_mut() {
+            if let Block::Verse(verse) = block {
+                if !number_verses && matches!(verse.label, VerseLabel::Verse(_)) {
+                    verse.label = VerseLabel::None {};
+                }
+            }
+        }
+
+        if !number_choruses {
+            self.blocks.iter_mut().for_each(Block::remove_chorus_num);
+        } else if choruses.contains(&None) {
+            // The parser removes the number if there's just one chorus
+            self.blocks
+                .iter_mut()
+                .for_each(|block| block.set_chorus_num(Some(1)));
+        }
+    }
+
+    /// Replaces chorus references with a copy of the referenced chorus' paragraphs.
+    ///
+    /// The paragraph containing a reference is split at the reference
+    /// and the chorus paragraphs are inserted in between.
+    /// If a song contains more verses with the same chorus label, the first one is used.
+    /// References to choruses which don't exist in the song are kept as they are,
+    /// the parser reports those as warnings.
+    pub fn expand_chorus_refs(&mut self) {
+        let choruses: Vec<(Option<u32>, Vec<Paragraph>)> = self
+            .blocks
+            .iter()
+            .filter_map(Block::verse)
+            .filter_map(|verse| match verse.label {
+                VerseLabel::Chorus(num) => Some((num, verse.paragraphs.clone())),
+                _ => None,
+            })
+            .collect();
+
+        let find_chorus = |num: Option<u32>| {
+            choruses
+                .iter()
+                .find(|(n, _)| *n == num)
+                .map(|(_, paras)| paras)
+        };
+        let exists = |num: Option<u32>| find_chorus(num).is_some();
+
+        for verse in self.blocks.iter_mut().filter_map(Block::verse_mut) {
+            let mut paragraphs = Vec::with_capacity(verse.paragraphs.len());
+            for para in mem::take(&mut verse.paragraphs) {
+                let mut current = vec![];
+                for mut inline in para.into_vec() {
+                    let mut refs = vec![];
+                    match &inline {
+                        Inline::ChorusRef(cr) if exists(cr.num) => refs.push(cr.num),
+                        _ => {
+                            inline.take_chorus_refs(&mut refs, &exists);
+                            current.push(inline);
+                        }
+                    }
+
+                    if refs.is_empty() {
+                        continue;
+                    }
+
+                    push_paragraph(&mut paragraphs, mem::take(&mut current));
+                    for num in refs {
+                        paragraphs.extend(find_chorus(num).into_iter().flatten().cloned());
+                    }
+                }
+                push_paragraph(&mut paragraphs, current);
+            }
+
+            verse.paragraphs = paragraphs;
+        }
+    }
 }
 
-#[derive(Serialize, Debug)]
+#[derive(Serialize, Clone, Debug)]
 pub struct SongRef {
     pub title: BStr,
+    pub id: BStr,
     /// index of the song in the Book::songs vector
     pub idx: usize,
 }
@@ -399,11 +778,35 @@ impl SongRef {
     pub fn new((idx, songs): (usize, &Song)) -> Self {
         Self {
             title: songs.title.clone(),
+            id: songs.id.clone(),
             idx,
         }
     }
 }
 
+/// A group of songs as configured with `[[chapter]]` in `bard.toml`.
+///
+/// Songs not belonging to any chapter are grouped in a chapter with no name.
+#[derive(Serialize, Clone, Debug)]
+pub struct Chapter {
+    pub name: Option<BStr>,
+    pub songs: Vec<SongRef>,
+}
+
+/// An entry of the table of contents, see `Book::toc()`.
+#[derive(Serialize, Clone, Debug)]
+pub struct TocEntry {
+    pub title: BStr,
+    /// The song's ID, usable as an anchor.
+    pub slug: BStr,
+    /// Tags of the song given by the `tags` metadata field.
+    pub tags: Vec<BStr>,
+    /// Text of the first line of lyrics, without chords.
+    pub first_line: Option<BStr>,
+    /// index of the song in the songs array
+    pub idx: usize,
+}
+
 #[derive(Debug)]
 pub struct Book {
     pub songs: Vec<Song>,
@@ -428,12 +831,13 @@ impl Book {
     /// Book-level postprocessing.
     ///
     /// Steps taken:
-    /// 1. Generation of the songs_sorted vec,
-    /// 2. Resolving of image elements (checking path, reading image dimensions).
+    /// 1. Deduplication of song IDs,
+    /// 2. Generation of the songs_sorted vec,
+    /// 3. Resolving of image elements (checking path, reading image dimensions).
     pub fn postprocess(&mut self, output_dir: &Path, img_cache: &ImgCache) -> Result<()> {
         self.songs.shrink_to_fit();
-        self.songs_sorted = self.songs.iter().enumerate().map(SongRef::new).collect();
-        sort_lexical_by(&mut self.songs_sorted, |songref| songref.title.as_ref());
+        Self::dedup_ids(&mut self.songs);
+        self.songs_sorted = Self::sort_songs(&self.songs);
 
         for image in self.iter_images_mut() {
             image.resolve(output_dir, img_cache)?;
@@ -442,22 +846,68 @@ impl Book {
         Ok(())
     }
 
-    pub fn iter_images(&self) -> impl Iterator<Item = &Image> {
-        self.songs
+    /// Makes song IDs unique by adding a `-2`, `-3`, ... suffix to repeated IDs,
+    /// songs appearing earlier in the input keep their ID.
+    fn dedup_ids(songs: &mut [Song]) {
+        let mut seen = HashSet::new();
+        for song in songs.iter_mut() {
+            if seen.contains(&song.id) {
+                song.id = (2..)
+                    .map(|n| BStr::from(format!("{}-{}", song.id, n)))
+                    .find(|id| !seen.contains(id))
+                    .unwrap();
+            }
+            seen.insert(song.id.clone());
+        }
+    }
+
+    /// Returns references to `songs` sorted by title.
+    pub fn sort_songs(songs: &[Song]) -> Vec<SongRef> {
+        let mut sorted: Vec<_> = songs.iter().enumerate().map(SongRef::new).collect();
+        sort_lexical_by(&mut sorted, |songref| songref.title.as_ref());
+        sorted
+    }
+
+    /// Groups `songs` by chapter, chapters are ordered by their first song.
+    pub fn chapters(songs: &[Song]) -> Vec<Chapter> {
+        let mut chapters: Vec<Chapter> = vec![];
+        for (idx, song) in songs.iter().enumerate() {
+            let song_ref = SongRef::new((idx, song));
+            match chapters.iter_mut().find(|ch| ch.name == song.chapter) {
+                Some(chapter) => chapter.songs.push(song_ref),
+                None => chapters.push(Chapter {
+                    name: song.chapter.clone(),
+                    songs: vec![song_ref],
+                }),
+            }
+        }
+        chapters
+    }
+
+    /// Table of contents entries of `songs`, in the same order.
+    pub fn toc(songs: &[Song]) -> Vec<TocEntry> {
+        songs
             .iter()
-            .flat_map(|s| s.blocks.iter())
-            .filter_map(Block::verse)
-            .flat_map(|v| v.inlines())
-            .filter_map(Inline::image)
+            .enumerate()
+            .map(|(idx, song)| TocEntry {
+                title: song.title.clone(),
+                slug: song.id.clone(),
+                tags: song.tags(),
+                first_line: song.first_line(),
+                idx,
+            })
+            .collect()
+    }
+
+    pub fn iter_images(&self) -> impl Iterator<Item = &Image> {
+        self.songs.iter().flat_map(Song::images)
     }
 
     pub fn iter_images_mut(&mut self) -> impl Iterator<Item = &mut Image> {
         self.songs
             .iter_mut()
             .flat_map(|s| s.blocks.iter_mut())
-            .filter_map(Block::verse_mut)
-            .flat_map(|v| v.inlines_mut())
-            .filter_map(Inline::image_mut)
+            .flat_map(Block::images_mut)
     }
 }
 
@@ -29,8 +29,13 @@ pub static AST_VERSION_LOG: &[AstVersion] = &[
     AstVersion::new(1, 0, "Initial version"),
     AstVersion::new(1, 1, "New style, added support for HTML snippets, TTF font files, and baseline chords"),
     AstVersion::new(1, 2, "Added scaling of images in HTML via the dpi setting, width and height are now provided in i-image elements"),
+    AstVersion::new(1, 3, "Added repeat_count to verses, chords_only to i-chord inlines, i-verse-ref, i-repeat-start, i-repeat-end, i-footnote and i-raw inlines, b-tab and b-image blocks, info to b-pre blocks, notation to verses, metadata, key, chapter, id and footnotes to songs, id to song references, chapters to the book, toc, and song, prev, next, split, split_dir and index to split HTML outputs, schema_version to the top level, and build_timestamp to program"),
+    AstVersion::new(1, 4, "Added the solfege notation"),
 ];
 
+/// Version of a template to assume if it doesn't declare one with `version_check`.
+pub const ASSUMED_FIRST_VERSION: Version = Version::new(1, 0, 0);
+
 pub fn current() -> &'static Version {
     AST_VERSION_LOG
         .iter()
@@ -40,42 +45,160 @@ pub fn current() -> &'static Version {
         .unwrap()
 }
 
+/// Versions newer than `since`, oldest first.
+pub fn changes_since(since: &Version) -> impl Iterator<Item = &'static AstVersion> + '_ {
+    AST_VERSION_LOG.iter().skip_while(move |v| &v.ver <= since)
+}
+
 fn log_changes(app: &App, since: &Version) {
     app.status("", format!("Changes since version {}:", since));
 
-    for ver in AST_VERSION_LOG.iter().skip_while(|v| &v.ver <= since) {
+    for ver in changes_since(since) {
         app.status("", ver);
     }
 }
 
-pub fn compat_check(app: &App, tpl_path: &Path, tpl_version: &Version) {
+/// How a template's declared AST version relates to the version this bard uses.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Compat {
+    /// The template is up to date.
+    Current,
+    /// The template doesn't declare its version, `ASSUMED_FIRST_VERSION` is assumed.
+    Undeclared,
+    /// The template is from a newer, incompatible bard.
+    NewerMajor,
+    /// The template is from a newer bard, but compatible.
+    NewerMinor,
+    /// The template is from an older, incompatible generation.
+    OlderMajor,
+    /// The template is older, but compatible.
+    OlderMinor,
+}
+
+impl Compat {
+    pub fn of(tpl_version: Option<&Version>) -> Self {
+        let current = current();
+        let tpl_version = match tpl_version {
+            Some(version) => version,
+            None => return Self::Undeclared,
+        };
+
+        if tpl_version.major > current.major {
+            Self::NewerMajor
+        } else if tpl_version > current {
+            Self::NewerMinor
+        } else if tpl_version.major < current.major {
+            Self::OlderMajor
+        } else if tpl_version < current {
+            Self::OlderMinor
+        } else {
+            Self::Current
+        }
+    }
+}
+
+/// Fails if the template's AST is from a newer, incompatible bard, rendering it would produce garbage.
+pub fn ensure_supported(tpl_path: &Path, tpl_version: Option<&Version>) -> Result<()> {
+    if Compat::of(tpl_version) == Compat::NewerMajor {
+        bail!(
+            "The version of template {:?} is {}, which is incompatible with what this bard uses ({}).
+The template was probably made for a newer bard, please upgrade bard to use it.",
+            tpl_path,
+            tpl_version.unwrap(),
+            current(),
+        );
+    }
+    Ok(())
+}
+
+/// Reports how the template's version relates to this bard's version.
+///
+/// `undeclared_notice` is whether to report a template without a `version_check`,
+/// this is done once per project build.
+pub fn compat_check(
+    app: &App,
+    tpl_path: &Path,
+    tpl_version: Option<&Version>,
+    undeclared_notice: bool,
+) {
     let current = current();
-    if current < tpl_version {
-        // Template's AST is newer than this bard's AST
-        app.warning(format!(
-            "The version of template {:?} is {}, which is newer than what this bard uses ({}).
+    match Compat::of(tpl_version) {
+        Compat::Current | Compat::NewerMajor => {}
+        Compat::Undeclared if undeclared_notice => app.warning(format!(
+            "The template {:?} doesn't declare its version with the version_check helper, version {} is assumed.
+Add {{{{~ version_check \"{}\" ~}}}} on top of custom templates once they're up to date with this bard.",
+            tpl_path, ASSUMED_FIRST_VERSION, current,
+        )),
+        Compat::Undeclared => {}
+        Compat::NewerMinor => {
+            // Template's AST is newer than this bard's AST
+            app.warning(format!(
+                "The version of template {:?} is {}, which is newer than what this bard uses ({}).
 Maybe this project was created with a newer bard version.
 This may cause errors while rendering...",
-            tpl_path, tpl_version, current,
-        ));
-    } else if current.major > tpl_version.major {
-        // Template's AST major version is older than this bard's AST, incompatibly
-        app.warning(
-            format!("The version of template {:?} is {}, which is from an older generation than what this bard uses ({}).
-This may cause errors while rendering. It may be needed to convert the template to the newer format.",
-            tpl_path, tpl_version, current,
-        ));
-        log_changes(app, tpl_version);
-    } else if current > tpl_version {
-        // Template's AST version is older than this bard's AST, compatibly
-        app.status(
-            "Notice",
-            format!(
-                "The version of template {:?} is {}. This version of bard supports {}.
-This is not a problem, but the new version may offer improvements.",
+                tpl_path,
+                tpl_version.unwrap(),
+                current,
+            ));
+        }
+        Compat::OlderMajor => {
+            // Template's AST major version is older than this bard's AST, incompatibly
+            let tpl_version = tpl_version.unwrap();
+            app.warning(
+                format!("The version of template {:?} is {}, which is from an older generation than what this bard uses ({}).
+This may cause errors while rendering. It may be needed to convert the template to the newer format,
+use 'bard util template-diff <output>' to compare it with the built-in template.",
                 tpl_path, tpl_version, current,
-            ),
+            ));
+            log_changes(app, tpl_version);
+        }
+        Compat::OlderMinor => {
+            // Template's AST version is older than this bard's AST, compatibly
+            let tpl_version = tpl_version.unwrap();
+            app.status(
+                "Notice",
+                format!(
+                    "The version of template {:?} is {}. This version of bard supports {}.
+This is not a problem, but the new version may offer improvements.",
+                    tpl_path, tpl_version, current,
+                ),
+            );
+            log_changes(app, tpl_version);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_compat() {
+        let current = current();
+        let version = |major, minor| Version::new(major, minor, 0);
+
+        assert_eq!(Compat::of(Some(current)), Compat::Current);
+        assert_eq!(Compat::of(None), Compat::Undeclared);
+        assert_eq!(
+            Compat::of(Some(&version(current.major + 1, 0))),
+            Compat::NewerMajor
+        );
+        assert_eq!(
+            Compat::of(Some(&version(current.major, current.minor + 1))),
+            Compat::NewerMinor
         );
-        log_changes(app, tpl_version);
+        assert_eq!(
+            Compat::of(Some(&version(current.major - 1, 9))),
+            Compat::OlderMajor
+        );
+        assert_eq!(
+            Compat::of(Some(&version(current.major, 0))),
+            Compat::OlderMinor
+        );
+
+        let path = Path::new("songbook.hbs");
+        assert!(ensure_supported(path, Some(&version(current.major + 1, 0))).is_err());
+        assert!(ensure_supported(path, Some(&version(current.major, current.minor + 1))).is_ok());
+        assert!(ensure_supported(path, None).is_ok());
     }
 }
@@ -11,12 +11,14 @@ xml_write!(struct Chord {
     backticks,
     baseline,
     inlines,
+    chords_only,
 } -> |w| {
     w.tag("chord")
         .attr(chord)
         .attr_opt("alt-chord", alt_chord.unwrap())
         .attr(backticks)
         .attr(baseline)
+        .attr(("chords-only", chords_only.unwrap()))
         .content()?
         .many(inlines)?
 });
@@ -59,6 +61,22 @@ xml_write!(struct ChorusRef {
         .attr(prefix_space)
 });
 
+xml_write!(struct VerseRef {
+    label,
+    prefix_space,
+} -> |w| {
+    w.tag("verse-ref")
+        .attr(label)
+        .attr(prefix_space)
+});
+
+xml_write!(struct RepeatEnd {
+    count,
+} -> |w| {
+    w.tag("repeat-end")
+        .attr_opt("count", &count.unwrap().map(|n| format!("{}", n)))
+});
+
 xml_write!(struct HtmlTag {
     name,
     attrs,
@@ -72,6 +90,16 @@ xml_write!(struct HtmlTag {
     }
 });
 
+xml_write!(struct Footnote {
+    num,
+    inlines,
+} -> |w| {
+    w.tag("footnote")
+        .attr(num)
+        .content()?
+        .many(inlines)?
+});
+
 xml_write!(enum Inline |w| {
     Text { text } => { w.write_text(text)?; },
     Chord(c) => { w.write_value(c)?; },
@@ -81,7 +109,12 @@ xml_write!(enum Inline |w| {
     Link(l) => { w.write_value(l)?; },
     Image(i) => { w.write_value(i)?; },
     ChorusRef(cr) => { w.write_value(cr)?; },
+    VerseRef(vr) => { w.write_value(vr)?; },
+    RepeatStart => { w.tag("repeat-start").finish()?; },
+    RepeatEnd(re) => { w.write_value(re)?; },
     HtmlTag(tag) => { w.write_value(tag)?; },
+    Raw { tex } => { w.tag("raw").content()?.text(tex)?.finish()?; },
+    Footnote(f) => { w.write_value(f)?; },
 
     Transpose(..) => { unreachable!() },
 });
@@ -89,6 +122,8 @@ xml_write!(enum Inline |w| {
 xml_write!(struct Verse {
     label,
     paragraphs,
+    repeat_count,
+    notation,
 } -> |w| {
     use VerseLabel::*;
     let label = label.unwrap();
@@ -105,9 +140,14 @@ xml_write!(struct Verse {
         _ => Option::None,
     };
 
+    let repeat_count = repeat_count.unwrap().map(|n| n.to_string());
+    let notation = notation.unwrap().map(|nt| nt.to_string());
+
     w.tag("verse")
         .attr(("label-type", label_type))
         .attr_opt("label", &label)
+        .attr_opt("repeat-count", &repeat_count)
+        .attr_opt("notation", &notation)
         .content()?
         .many_tags("p", paragraphs)?
 });
@@ -120,29 +160,65 @@ xml_write!(enum Block |w| {
     Verse(verse) => { w.write_value(verse)?; },
     BulletList(l) => { w.write_value(l)?; },
     HorizontalLine => { w.tag("hr").finish()?; },
-    Pre { text } => { w.tag("pre").content()?.text(text)?.finish()?; },
+    Pre { text, info } => { w.tag("pre").attr(("info", info)).content()?.text(text)?.finish()?; },
+    Tab { text } => { w.tag("tab").content()?.text(text)?.finish()?; },
+    Image { image, caption } => { w.tag("image-block").attr(("caption", caption)).content()?.value(image)?.finish()?; },
     HtmlBlock(i) => { w.tag("html-block").content()?.many(i)?.finish()?; },
 });
 
 xml_write!(struct Song {
     title,
+    id,
     subtitles,
+    metadata,
+    key,
+    chapter,
     blocks,
+    footnotes,
     notation,
 } -> |w| {
-    w.tag("song")
+    let footnotes = footnotes.unwrap();
+    let song = w.tag("song")
         .attr(title)
+        .attr(id)
         .attr(notation)
+        .attr_opt("key", key.unwrap())
+        .attr_opt("chapter", chapter.unwrap())
         .content()?
         .many_tags("subtitle", subtitles)?
-        .many(blocks)?
+        .field(metadata)?
+        .many(blocks)?;
+
+    if footnotes.is_empty() {
+        song
+    } else {
+        song.value_wrap("footnotes", &footnotes[..])?
+    }
 });
 
 xml_write!(struct SongRef {
     title,
+    id,
     idx,
 } -> |w| {
     w.tag("song-ref")
         .attr(title)
+        .attr(id)
+        .attr(idx)
+});
+
+xml_write!(struct TocEntry {
+    title,
+    slug,
+    tags,
+    first_line,
+    idx,
+} -> |w| {
+    w.tag("toc-entry")
+        .attr(title)
+        .attr(slug)
         .attr(idx)
+        .attr_opt("first-line", first_line.unwrap())
+        .content()?
+        .many_tags("tag", tags)?
 });
@@ -1,9 +1,98 @@
+use std::borrow::Cow;
+use std::fmt::Write as _;
 use std::fs;
-use std::path::MAIN_SEPARATOR;
+use std::path::{Component, MAIN_SEPARATOR};
 
 use crate::prelude::*;
+use crate::project::Format;
 use crate::util::PathBufExt as _;
 
+/// Options of `bard init`, the project file is generated based on these.
+#[derive(clap::Parser, Clone, Default, Debug)]
+pub struct InitOptions {
+    /// Songbook title
+    #[arg(long)]
+    pub title: Option<String>,
+    /// Songbook author
+    #[arg(long)]
+    pub author: Option<String>,
+    /// Output formats, comma-separated [default: pdf,html]
+    #[arg(long = "format", value_name = "FORMAT", value_delimiter = ',')]
+    pub formats: Vec<Format>,
+    /// Output directory, relative to the project directory [default: output]
+    #[arg(long)]
+    pub dir_output: Option<String>,
+}
+
+impl InitOptions {
+    fn formats(&self) -> Vec<Format> {
+        if self.formats.is_empty() {
+            return vec![Format::Pdf, Format::Html];
+        }
+
+        let mut formats = vec![];
+        for &format in &self.formats {
+            if !formats.contains(&format) {
+                formats.push(format);
+            }
+        }
+        formats
+    }
+}
+
+/// Output file name for a format in a new project.
+fn output_file(format: Format) -> &'static str {
+    match format {
+        Format::Pdf => "songbook.pdf",
+        Format::Html => "songbook.html",
+        Format::Hovorka => "hovorka.xml",
+        Format::Json => "songbook.json",
+        Format::Xml => "songbook.xml",
+        Format::Markdown => "songbook.md",
+        Format::MusicXml => "songbook.musicxml",
+        Format::Lilypond => "songbook.ly",
+        Format::Abc => "songbook.abc",
+    }
+}
+
+/// Generates the `bard.toml` file of a new project.
+fn project_file(opts: &InitOptions) -> String {
+    let string = |s: &str| toml::Value::from(s).to_string();
+
+    let mut res =
+        "version = 2\n\nsongs = [\n    \"example.md\",\n]\n\nnotation = \"english\"\n".to_string();
+    if let Some(dir_output) = opts.dir_output.as_ref() {
+        let _ = writeln!(res, "dir_output = {}", string(dir_output));
+    }
+
+    for format in opts.formats() {
+        let file = output_file(format);
+        let _ = write!(res, "\n[[output]]\nfile = {}\n", string(file));
+        if Format::try_from_ext(Path::new(file)).ok() != Some(format) {
+            let _ = writeln!(res, "format = {}", string(&format.to_string()));
+        }
+    }
+
+    res.push_str("\n[book]\n");
+    match opts.title.as_ref() {
+        Some(title) => {
+            let _ = writeln!(res, "title = {}", string(title));
+        }
+        None => {
+            res.push_str("title = \"My Songbook\"\nsubtitle = \"(You can edit that title!)\"\n")
+        }
+    }
+    if let Some(author) = opts.author.as_ref() {
+        let _ = writeln!(res, "author = {}", string(author));
+    }
+    res.push_str("chorus_label = \"Ch\"\n");
+    if opts.title.is_none() {
+        res.push_str("title_note = \"(And this note too...)\"\n");
+    }
+
+    res
+}
+
 /// A filesystem node, either a file (with content), or a directory.
 #[derive(Debug)]
 enum Node {
@@ -11,6 +100,8 @@ enum Node {
         path: &'static str,
         content: &'static [u8],
     },
+    /// The `bard.toml` file, generated by `project_file()`.
+    ProjectFile,
     Dir {
         path: &'static str,
     },
@@ -21,20 +112,36 @@ impl Node {
         Self::Dir { path }
     }
 
-    fn path_buf(&self) -> PathBuf {
-        match self {
-            Self::File { path, .. } => path,
-            Self::Dir { path } => path,
-        }
-        .replace('/', &String::from(MAIN_SEPARATOR))
-        .into() // MAIN_SEPARATOR_STR isn't stable :-|
+    fn path_buf(&self, opts: &InitOptions) -> PathBuf {
+        let path = match self {
+            Self::File { path, .. } => *path,
+            Self::ProjectFile => "bard.toml",
+            Self::Dir { path } => *path,
+        };
+
+        // Nodes in the output directory are moved to the configured one
+        let path = match (opts.dir_output.as_ref(), path.strip_prefix("output")) {
+            (Some(dir_output), Some(rest)) if rest.is_empty() || rest.starts_with('/') => {
+                Cow::Owned(format!("{}{}", dir_output.trim_end_matches('/'), rest))
+            }
+            _ => Cow::Borrowed(path),
+        };
+
+        path.replace('/', &String::from(MAIN_SEPARATOR)).into() // MAIN_SEPARATOR_STR isn't stable :-|
     }
 
-    fn resolve(&self, base: &Path) -> NodeResolved {
-        let mut path = self.path_buf();
+    fn resolve(&self, base: &Path, opts: &InitOptions) -> NodeResolved {
+        let mut path = self.path_buf(opts);
         path.resolve(base);
         match self {
-            Self::File { content, .. } => NodeResolved::File { path, content },
+            Self::File { content, .. } => NodeResolved::File {
+                path,
+                content: Cow::Borrowed(content),
+            },
+            Self::ProjectFile => NodeResolved::File {
+                path,
+                content: Cow::Owned(project_file(opts).into_bytes()),
+            },
             Self::Dir { .. } => NodeResolved::Dir { path },
         }
     }
@@ -43,7 +150,7 @@ impl Node {
 enum NodeResolved {
     File {
         path: PathBuf,
-        content: &'static [u8],
+        content: Cow<'static, [u8]>,
     },
     Dir {
         path: PathBuf,
@@ -93,17 +200,43 @@ pub struct DefaultProject {
 
 impl DefaultProject {
     pub fn resolve(&self, project_dir: &Path) -> DefaultProjectResolved {
-        let nodes = self.nodes.iter().map(|n| n.resolve(project_dir)).collect();
-        DefaultProjectResolved { nodes }
+        self.resolve_with(project_dir, &InitOptions::default())
+            .expect("Default init options are valid")
+    }
+
+    pub fn resolve_with(
+        &self,
+        project_dir: &Path,
+        opts: &InitOptions,
+    ) -> Result<DefaultProjectResolved> {
+        if let Some(dir_output) = opts.dir_output.as_ref() {
+            let path = Path::new(dir_output);
+            if dir_output.is_empty()
+                || !path.is_relative()
+                || path.components().any(|c| c == Component::ParentDir)
+            {
+                bail!(
+                    "The output directory must be a relative path within the project directory: {:?}",
+                    dir_output
+                );
+            }
+        }
+
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|n| n.resolve(project_dir, opts))
+            .collect();
+        Ok(DefaultProjectResolved { nodes })
     }
 }
 
 pub const DEFAULT_PROJECT: DefaultProject = DefaultProject {
     nodes: &[
         // Project file:
-        node_file!("bard.toml"),
+        Node::ProjectFile,
         // Song:
-        node_file!("songs/yippie.md"),
+        node_file!("songs/example.md"),
         // Output dir:
         Node::dir("output"),
         // Fonts:
@@ -0,0 +1,106 @@
+//! Import of songs written in other formats, converted to bard Markdown.
+//!
+//! The result is a Markdown file the user can keep editing, rather than AST directly,
+//! since imports are heuristic and usually need some touching up.
+//!
+//! XML formats of projection software (OpenLyrics, OpenSong) are an exception,
+//! these are structured well enough to be read into the AST directly, see the `xml` module.
+
+use std::fmt;
+use std::fs;
+
+use crate::app::App;
+use crate::prelude::*;
+
+mod openlyrics;
+mod opensong;
+pub mod ug;
+pub mod xml;
+
+#[derive(clap::Parser)]
+pub enum ImportCmd {
+    /// Converts a song with chords above the lyrics (Ultimate Guitar style) to bard Markdown
+    Ug {
+        /// The plain text file to convert
+        file: PathBuf,
+        /// Write the song to this file instead of the standard output
+        output: Option<PathBuf>,
+        /// Song title [default: the input file name]
+        #[arg(long)]
+        title: Option<String>,
+    },
+}
+
+impl ImportCmd {
+    pub fn run(self, app: &App) -> Result<()> {
+        use ImportCmd::*;
+
+        match self {
+            Ug {
+                file,
+                output,
+                title,
+            } => {
+                let md = import_ug(app, &file, title.as_deref())?;
+                match output {
+                    Some(output) => write_new(app, &output, &md),
+                    None => {
+                        print!("{}", md);
+                        Ok(())
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// A problem found during import, the line number is 1-indexed.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Warning {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Converts the chords-over-lyrics `file` to bard Markdown, warnings are reported to `app`.
+/// The song title defaults to the file name without extension.
+pub fn import_ug(app: &App, file: &Path, title: Option<&str>) -> Result<String> {
+    let input =
+        fs::read_to_string(file).with_context(|| format!("Could not read file {:?}", file))?;
+    let title = title.map(str::to_owned).unwrap_or_else(|| {
+        file.file_stem()
+            .map_or_else(String::new, |stem| stem.to_string_lossy().into())
+    });
+
+    app.status("Importing", format!("{:?}", file));
+    let (md, warnings) = ug::convert(&title, &input);
+    for warning in warnings {
+        app.warning(format!(
+            "{}:{}: {}",
+            file.display(),
+            warning.line,
+            warning.message
+        ));
+    }
+
+    Ok(md)
+}
+
+fn write_new(app: &App, path: &Path, content: &str) -> Result<()> {
+    if path.exists() {
+        bail!("File already exists: {:?}", path);
+    }
+
+    fs::write(path, content).with_context(|| format!("Could not write file {:?}", path))?;
+    app.indent(format!(
+        "Add {:?} to the songs in bard.toml to include it in the songbook.",
+        path.file_name().unwrap_or_default()
+    ));
+    app.success("Done!");
+    Ok(())
+}
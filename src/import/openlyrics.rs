@@ -0,0 +1,285 @@
+//! OpenLyrics songs, see <https://docs.openlyrics.org>.
+
+use super::xml::{self, Diags, Element, Node};
+use crate::book::{Block, Chord, Inline, Paragraph, Song, Verse};
+use crate::parser::ParserConfig;
+use crate::project::Metadata;
+use crate::util::BStr;
+
+pub(super) fn song(root: &Element, diags: &Diags, config: &ParserConfig) -> Song {
+    let mut titles = vec![];
+    let mut metadata = Metadata::new();
+    let mut blocks = vec![];
+
+    for element in root.elements() {
+        match element.name.as_str() {
+            "properties" => properties(element, diags, &mut titles, &mut metadata),
+            "lyrics" => lyrics(element, diags, &mut blocks),
+            _ => diags.unsupported(element),
+        }
+    }
+
+    let mut titles = titles.into_iter();
+    let title = titles.next().unwrap_or_default();
+    let subtitles = titles.map(Into::into).collect();
+    xml::finalize(title, subtitles, metadata, blocks, config)
+}
+
+fn properties(
+    properties: &Element,
+    diags: &Diags,
+    titles: &mut Vec<String>,
+    metadata: &mut Metadata,
+) {
+    let texts = |element: &Element, name: &str| {
+        element
+            .elements()
+            .filter(|child| child.name == name)
+            .map(Element::text)
+            .filter(|text| !text.is_empty())
+            .collect::<Vec<_>>()
+    };
+
+    for prop in properties.elements() {
+        match prop.name.as_str() {
+            "titles" => titles.extend(texts(prop, "title")),
+            "authors" => xml::insert_meta(metadata, "author", texts(prop, "author").join(", ")),
+            "copyright" => xml::insert_meta(metadata, "copyright", prop.text()),
+            "ccliNo" => xml::insert_meta(metadata, "ccli", prop.text()),
+            "key" => xml::insert_meta(metadata, "key", prop.text()),
+            "tempo" => xml::insert_meta(metadata, "tempo", prop.text()),
+            // Verses are kept in the order of the file
+            "verseOrder" => {}
+            _ => diags.unsupported(prop),
+        }
+    }
+}
+
+fn lyrics(lyrics: &Element, diags: &Diags, blocks: &mut Vec<Block>) {
+    let mut verse_num = 0;
+    let mut lang = None;
+
+    for verse in lyrics.elements() {
+        if verse.name != "verse" {
+            diags.unsupported(verse);
+            continue;
+        }
+
+        // Translations are given as verses in other languages, only the first language is used
+        if let Some(verse_lang) = verse.attr("lang") {
+            if *lang.get_or_insert(verse_lang) != verse_lang {
+                continue;
+            }
+        }
+
+        let label = xml::verse_label(verse.attr("name").unwrap_or(""), &mut verse_num);
+        let paragraphs = verse
+            .elements()
+            .filter_map(|lines| {
+                if lines.name == "lines" {
+                    Some(paragraph(lines, diags))
+                } else {
+                    diags.unsupported(lines);
+                    None
+                }
+            })
+            .collect();
+        blocks.push(Block::Verse(Verse::new(label, paragraphs, None)));
+    }
+}
+
+fn paragraph(lines: &Element, diags: &Diags) -> Paragraph {
+    let mut builder = LinesBuilder::new(diags);
+    builder.add_nodes(&lines.children);
+
+    let mut lines: Vec<_> = builder
+        .finish()
+        .split(Inline::is_break)
+        .map(|line| trim_line(line.to_vec()))
+        .collect();
+    while lines.last().map_or(false, Vec::is_empty) {
+        lines.pop();
+    }
+
+    lines.join(&Inline::Break).into()
+}
+
+struct LinesBuilder<'a> {
+    diags: &'a Diags<'a>,
+    inlines: Vec<Inline>,
+    /// Chord given by an empty `<chord/>` element, it applies to the text following it
+    /// up to the next chord or line break.
+    chord: Option<(BStr, Vec<Inline>)>,
+}
+
+impl<'a> LinesBuilder<'a> {
+    fn new(diags: &'a Diags<'a>) -> Self {
+        Self {
+            diags,
+            inlines: vec![],
+            chord: None,
+        }
+    }
+
+    fn push(&mut self, inline: Inline) {
+        match self.chord.as_mut() {
+            Some((_, inlines)) => inlines.push(inline),
+            None => self.inlines.push(inline),
+        }
+    }
+
+    fn flush_chord(&mut self) {
+        if let Some((chord, inlines)) = self.chord.take() {
+            let chord = Chord::new(chord, None, 1, false, inlines);
+            self.inlines.push(Inline::Chord(chord));
+        }
+    }
+
+    fn add_nodes(&mut self, nodes: &[Node]) {
+        for node in nodes {
+            match node {
+                Node::Text(text) => {
+                    // Line breaks are given by <br/>, whitespace is only formatting
+                    let text = collapse_whitespace(text);
+                    if !text.is_empty() {
+                        self.push(Inline::Text { text: text.into() });
+                    }
+                }
+                Node::Element(element) => self.add_element(element),
+            }
+        }
+    }
+
+    fn add_element(&mut self, element: &Element) {
+        match element.name.as_str() {
+            "br" => {
+                self.flush_chord();
+                self.inlines.push(Inline::Break);
+            }
+            // Lines given as elements, used by OpenLyrics before 0.8
+            "line" => {
+                self.add_nodes(&element.children);
+                self.flush_chord();
+                self.inlines.push(Inline::Break);
+            }
+            "chord" => {
+                self.flush_chord();
+                let name = match chord_name(element) {
+                    Some(name) => name,
+                    None => return self.add_nodes(&element.children),
+                };
+
+                if element.children.is_empty() {
+                    self.chord = Some((name, vec![]));
+                } else {
+                    // OpenLyrics 0.9 chords may enclose the text they apply to
+                    let mut inner = LinesBuilder::new(self.diags);
+                    inner.add_nodes(&element.children);
+                    let chord = Chord::new(name, None, 1, false, inner.finish());
+                    self.inlines.push(Inline::Chord(chord));
+                }
+            }
+            "comment" => self.diags.unsupported(element),
+            _ => {
+                self.diags.unsupported(element);
+                self.add_nodes(&element.children);
+            }
+        }
+    }
+
+    fn finish(mut self) -> Vec<Inline> {
+        self.flush_chord();
+        self.inlines
+    }
+}
+
+/// Chord name given either by the `name` attribute or, since OpenLyrics 0.9,
+/// by the `root`, `structure` and `bass` attributes.
+fn chord_name(chord: &Element) -> Option<BStr> {
+    if let Some(name) = chord.attr("name").map(str::trim).filter(|n| !n.is_empty()) {
+        return Some(name.into());
+    }
+
+    let mut name = chord.attr("root")?.trim().to_string();
+    let structure = match chord.attr("structure").unwrap_or("").trim() {
+        "maj" | "" => "",
+        "min" => "m",
+        "dom7" => "7",
+        "min7" => "m7",
+        other => other,
+    };
+    name.push_str(structure);
+    if let Some(bass) = chord.attr("bass") {
+        name.push('/');
+        name.push_str(bass.trim());
+    }
+    Some(name.into())
+}
+
+/// Replace each run of whitespace with a single space.
+fn collapse_whitespace(text: &str) -> String {
+    let mut res = String::with_capacity(text.len());
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+        } else {
+            if space {
+                res.push(' ');
+                space = false;
+            }
+            res.push(c);
+        }
+    }
+    if space {
+        res.push(' ');
+    }
+    res
+}
+
+/// Text at the start or end of an inline, looking inside chords.
+fn edge_text(inline: &mut Inline, start: bool) -> Option<&mut BStr> {
+    let inline = match inline {
+        Inline::Chord(chord) if start => chord.inlines.first_mut()?,
+        Inline::Chord(chord) => chord.inlines.last_mut()?,
+        other => other,
+    };
+    match inline {
+        Inline::Text { text } => Some(text),
+        _ => None,
+    }
+}
+
+/// Remove whitespace at the start and end of a line left over from XML formatting.
+fn trim_line(mut line: Vec<Inline>) -> Vec<Inline> {
+    while let Some(text) = line.first_mut().and_then(|i| edge_text(i, true)) {
+        *text = text.trim_start().into();
+        if text.is_empty() && matches!(line[0], Inline::Text { .. }) {
+            line.remove(0);
+        } else {
+            break;
+        }
+    }
+
+    while let Some(text) = line.last_mut().and_then(|i| edge_text(i, false)) {
+        *text = text.trim_end().into();
+        if text.is_empty() && matches!(line.last(), Some(Inline::Text { .. })) {
+            line.pop();
+        } else {
+            break;
+        }
+    }
+
+    for inline in line.iter_mut() {
+        if let Inline::Chord(chord) = inline {
+            chord.inlines = chord
+                .inlines
+                .iter()
+                .filter(|i| !matches!(i, Inline::Text { text } if text.is_empty()))
+                .cloned()
+                .collect();
+        }
+    }
+
+    line
+}
@@ -0,0 +1,190 @@
+//! OpenSong songs, see <http://www.opensong.org>.
+//!
+//! The lyrics are plain text where the first character of each line gives its kind:
+//! `[` starts a section, `.` is a line of chords, `;` a comment and ` ` a line of lyrics.
+
+use std::iter;
+use std::mem;
+
+use super::xml::{self, Diags, Element};
+use crate::book::{Block, Chord, Inline, Song, Verse, VerseLabel};
+use crate::parser::ParserConfig;
+use crate::project::Metadata;
+
+pub(super) fn song(root: &Element, diags: &Diags, config: &ParserConfig) -> Song {
+    let mut title = String::new();
+    let mut subtitles = vec![];
+    let mut metadata = Metadata::new();
+    let mut blocks = vec![];
+
+    for element in root.elements() {
+        match element.name.as_str() {
+            "title" => title = element.text(),
+            "aka" if !element.is_empty() => subtitles.push(element.text().into()),
+            "author" => xml::insert_meta(&mut metadata, "author", element.text()),
+            "copyright" => xml::insert_meta(&mut metadata, "copyright", element.text()),
+            "ccli" => xml::insert_meta(&mut metadata, "ccli", element.text()),
+            "key" => xml::insert_meta(&mut metadata, "key", element.text()),
+            "tempo" => xml::insert_meta(&mut metadata, "tempo", element.text()),
+            "lyrics" => blocks = lyrics(&element.text()),
+            // Verses are kept in the order of the file
+            "presentation" => {}
+            // OpenSong files contain all the fields, mostly empty
+            _ if element.is_empty() => {}
+            _ => diags.unsupported(element),
+        }
+    }
+
+    xml::finalize(title, subtitles, metadata, blocks, config)
+}
+
+fn lyrics(text: &str) -> Vec<Block> {
+    let mut builder = LyricsBuilder::default();
+    for line in text.lines().map(str::trim_end) {
+        match line.chars().next() {
+            None => {
+                builder.flush_chords();
+                builder.paragraph();
+            }
+            Some('[') => {
+                builder.flush_chords();
+                let name = line[1..].split(']').next().unwrap_or("");
+                builder.verse(name);
+            }
+            Some('.') => {
+                builder.flush_chords();
+                builder.chords = chords(&line[1..]);
+            }
+            Some(';') => {}
+            // Page and line break hints for projection
+            _ if line.trim() == "---" || line.trim() == "||" => {}
+            Some(_) => {
+                let lyrics = line.strip_prefix(' ').unwrap_or(line);
+                builder.lyrics(lyrics);
+            }
+        }
+    }
+    builder.finish()
+}
+
+#[derive(Default)]
+struct LyricsBuilder {
+    blocks: Vec<Block>,
+    label: Option<VerseLabel>,
+    verse_num: u32,
+    paragraphs: Vec<Vec<Inline>>,
+    para: Vec<Inline>,
+    /// Chords of a `.` line along with their columns, waiting for lyrics below them.
+    chords: Vec<(usize, String)>,
+}
+
+impl LyricsBuilder {
+    fn paragraph(&mut self) {
+        if !self.para.is_empty() {
+            self.paragraphs.push(mem::take(&mut self.para));
+        }
+    }
+
+    fn flush_verse(&mut self) {
+        self.paragraph();
+        if let Some(label) = self.label.take() {
+            let paragraphs = mem::take(&mut self.paragraphs)
+                .into_iter()
+                .map(Into::into)
+                .collect();
+            let verse = Verse::new(label, paragraphs, None);
+            self.blocks.push(Block::Verse(verse));
+        }
+    }
+
+    fn verse(&mut self, name: &str) {
+        self.flush_verse();
+        self.label = Some(xml::verse_label(name, &mut self.verse_num));
+    }
+
+    fn line(&mut self, inlines: Vec<Inline>) {
+        // Content before the first section goes in a verse
+        if self.label.is_none() {
+            self.verse("");
+        }
+        if !self.para.is_empty() {
+            self.para.push(Inline::Break);
+        }
+        self.para.extend(inlines);
+    }
+
+    /// Chords not followed by lyrics make a line on their own, eg. an intro.
+    fn flush_chords(&mut self) {
+        if self.chords.is_empty() {
+            return;
+        }
+
+        let num_chords = self.chords.len();
+        let inlines = mem::take(&mut self.chords)
+            .into_iter()
+            .enumerate()
+            .map(|(i, (_, chord))| {
+                let space = (i + 1 < num_chords).then(|| Inline::Text { text: " ".into() });
+                let mut chord =
+                    Chord::new(chord.into(), None, 1, false, space.into_iter().collect());
+                chord.chords_only = true;
+                Inline::Chord(chord)
+            })
+            .collect();
+        self.line(inlines);
+    }
+
+    /// Lyrics line, merged with the chords above it by column.
+    fn lyrics(&mut self, lyrics: &str) {
+        let chars: Vec<char> = lyrics.chars().collect();
+        // Underscores mark syllables stretched under chords
+        let segment = |from: usize, to: usize| -> String {
+            chars[from.min(chars.len())..to.min(chars.len())]
+                .iter()
+                .filter(|&&c| c != '_')
+                .collect()
+        };
+
+        let chords = mem::take(&mut self.chords);
+        let mut inlines = vec![];
+        let first_col = chords.first().map_or(chars.len(), |(col, _)| *col);
+        let text = segment(0, first_col);
+        if !text.is_empty() {
+            inlines.push(Inline::Text { text: text.into() });
+        }
+
+        for (i, (col, chord)) in chords.iter().enumerate() {
+            let end = chords.get(i + 1).map_or(chars.len(), |(col, _)| *col);
+            let text = segment(*col, end);
+            let chord_inlines = iter::once(text)
+                .filter(|text| !text.is_empty())
+                .map(|text| Inline::Text { text: text.into() })
+                .collect();
+            let chord = Chord::new(chord.as_str().into(), None, 1, false, chord_inlines);
+            inlines.push(Inline::Chord(chord));
+        }
+
+        self.line(inlines);
+    }
+
+    fn finish(mut self) -> Vec<Block> {
+        self.flush_chords();
+        self.flush_verse();
+        self.blocks
+    }
+}
+
+/// Chords of a chord line with their character columns.
+fn chords(line: &str) -> Vec<(usize, String)> {
+    let mut res = vec![];
+    let mut chord = String::new();
+    for (col, c) in line.chars().chain(iter::once(' ')).enumerate() {
+        if !c.is_whitespace() {
+            chord.push(c);
+        } else if !chord.is_empty() {
+            let start = col - chord.chars().count();
+            res.push((start, mem::take(&mut chord)));
+        }
+    }
+    res
+}
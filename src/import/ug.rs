@@ -0,0 +1,315 @@
+//! Import of the chords-over-lyrics plain text format, as found eg. on Ultimate Guitar:
+//!
+//! ```text
+//! [Verse 1]
+//! G            C
+//! Summer time has come
+//! ```
+//!
+//! Chord lines are detected heuristically, ie. a line is taken as chords when all of its words
+//! look like chords. Chords are then merged into the following lyrics line by their column.
+//! Section markers such as `[Chorus]` or `[Verse 2]` start bard verses.
+
+use std::fmt::Write as _;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::Warning;
+
+static CHORD: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(
+        r"^[A-H][#b♯♭]?(?:maj|min|dim|aug|sus|add|alt|m|M|\+|-|°|ø|Δ|\d|#|b|♯|♭|\(|\)|,)*(?:/[A-H][#b♯♭]?)?$",
+    )
+    .unwrap()
+});
+/// Words that can appear on chord lines besides chords, such as bars or repeats.
+static CHORD_LINE_EXTRA: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(?:\|+:?|:\|+|-+|/|[xX×]\d+|\([xX×]\d+\)|N\.?C\.?)$").unwrap());
+static SECTION: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\[([^\[\]]+)\]$").unwrap());
+static REPEAT: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\(?[xX×]\d+\)?$").unwrap());
+
+const TAB_WIDTH: usize = 8;
+
+#[derive(PartialEq, Eq, Debug)]
+enum Line<'a> {
+    Blank,
+    Section(&'a str),
+    /// Chord line, words along with their columns
+    Chords(Vec<(usize, &'a str)>),
+    Lyrics,
+}
+
+impl<'a> Line<'a> {
+    fn classify(line: &'a str) -> Self {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            return Self::Blank;
+        }
+        if let Some(caps) = SECTION.captures(trimmed) {
+            return Self::Section(caps.get(1).unwrap().as_str().trim());
+        }
+
+        let words = words(line);
+        let is_chord = |word: &&str| CHORD.is_match(word);
+        let all_chords = words
+            .iter()
+            .all(|(_, word)| is_chord(word) || CHORD_LINE_EXTRA.is_match(word));
+        if all_chords && words.iter().map(|(_, word)| word).any(is_chord) {
+            Self::Chords(words)
+        } else {
+            Self::Lyrics
+        }
+    }
+}
+
+/// Splits `line` into whitespace-separated words along with their columns (in characters).
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = vec![];
+    let mut start = None;
+    for (col, (i, c)) in line.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((col, i)),
+            (true, Some((start_col, start_i))) => {
+                words.push((start_col, &line[start_i..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some((start_col, start_i)) = start {
+        words.push((start_col, &line[start_i..]));
+    }
+    words
+}
+
+fn expand_tabs(line: &str) -> String {
+    let mut res = String::with_capacity(line.len());
+    let mut col = 0;
+    for c in line.chars() {
+        if c == '\t' {
+            let spaces = TAB_WIDTH - col % TAB_WIDTH;
+            res.extend(std::iter::repeat(' ').take(spaces));
+            col += spaces;
+        } else {
+            res.push(c);
+            col += 1;
+        }
+    }
+    res
+}
+
+/// Escapes characters that would otherwise start bard chords or Markdown blocks.
+fn escape(text: &str) -> String {
+    escape_start(text.replace('`', "\\`"))
+}
+
+/// Escapes the start of a line that would otherwise start a Markdown block, such as a heading or a list.
+fn escape_start(mut line: String) -> String {
+    if line.starts_with(&['#', '>', '-', '*', '+'][..]) {
+        line.insert(0, '\\');
+    } else if let Some(i) = line
+        .find(|c: char| !c.is_ascii_digit())
+        .filter(|&i| i > 0 && line[i..].starts_with(&['.', ')'][..]))
+    {
+        // Ordered list, ie. 1. or 1)
+        line.insert(i, '\\');
+    }
+    line
+}
+
+/// Merges `chords` into `lyrics` by their column,
+/// chords past the end of the lyrics are appended.
+fn merge(chords: &[(usize, &str)], lyrics: &str) -> String {
+    let mut res = String::with_capacity(lyrics.len() + chords.len() * 8);
+    let mut chords = chords
+        .iter()
+        .filter(|(_, chord)| CHORD.is_match(chord))
+        .peekable();
+
+    for (col, c) in lyrics.chars().enumerate() {
+        while let Some((_, chord)) = chords.next_if(|(chord_col, _)| *chord_col <= col) {
+            write!(res, "`{}`", chord).unwrap();
+        }
+        if c == '`' {
+            res.push('\\');
+        }
+        res.push(c);
+    }
+
+    let mut res = res.trim_end().to_string();
+    for (_, chord) in chords {
+        if !res.is_empty() {
+            res.push(' ');
+        }
+        write!(res, "`{}`", chord).unwrap();
+    }
+
+    escape_start(res.trim_start().to_string())
+}
+
+/// Renders a line of chords which isn't followed by lyrics, such as an intro.
+fn chords_only(words: &[(usize, &str)]) -> String {
+    let words: Vec<_> = words
+        .iter()
+        .map(|(_, word)| {
+            if CHORD.is_match(word) {
+                format!("`{}`", word)
+            } else {
+                escape(word)
+            }
+        })
+        .collect();
+    words.join(" ")
+}
+
+#[derive(PartialEq, Eq, Debug)]
+enum Kind {
+    Verse,
+    Chorus,
+    Custom(String),
+}
+
+#[derive(Debug)]
+struct Block {
+    kind: Kind,
+    repeat: Option<String>,
+    lines: Vec<String>,
+}
+
+impl Block {
+    fn new(kind: Kind) -> Self {
+        Self {
+            kind,
+            repeat: None,
+            lines: vec![],
+        }
+    }
+
+    /// Parse a section marker label, such as `Verse 2`, `Chorus x2`, or `Bridge`.
+    fn section(label: &str) -> Self {
+        let (label, repeat) = match label.rsplit_once(char::is_whitespace) {
+            Some((rest, marker)) if REPEAT.is_match(marker) => (rest.trim_end(), Some(marker)),
+            _ => (label, None),
+        };
+
+        let lower = label.to_lowercase();
+        let is_kind = |name: &str| {
+            lower.strip_prefix(name).map_or(false, |rest| {
+                rest.trim().chars().all(|c| c.is_ascii_digit())
+            })
+        };
+        let kind = if is_kind("verse") {
+            Kind::Verse
+        } else if is_kind("chorus") || is_kind("refrain") {
+            Kind::Chorus
+        } else {
+            Kind::Custom(label.to_string())
+        };
+
+        Self {
+            kind,
+            repeat: repeat.map(str::to_owned),
+            lines: vec![],
+        }
+    }
+
+    /// Write the block as a bard verse, the repeat marker (if any) goes on the first line.
+    fn write(&self, out: &mut String) {
+        let repeat = self.repeat.as_deref();
+        match (&self.kind, repeat) {
+            (Kind::Verse, _) => out.push_str("1. "),
+            (Kind::Chorus, _) => out.push_str("> "),
+            (Kind::Custom(label), None) => writeln!(out, "### {}", label).unwrap(),
+            (Kind::Custom(label), Some(repeat)) => {
+                writeln!(out, "### {} {}", label, repeat).unwrap()
+            }
+        }
+        if let (Kind::Verse | Kind::Chorus, Some(repeat)) = (&self.kind, repeat) {
+            writeln!(out, "{}", repeat).unwrap();
+        }
+
+        for line in self.lines.iter() {
+            writeln!(out, "{}", line).unwrap();
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
+
+/// Converts a chords-over-lyrics song to bard Markdown with the given `title`.
+///
+/// A line which looks like chords, but follows a chord line that hasn't been matched
+/// with lyrics yet, is taken as lyrics and a warning is returned.
+pub fn convert(title: &str, input: &str) -> (String, Vec<Warning>) {
+    let mut warnings = vec![];
+    let mut blocks = vec![];
+    let mut block: Option<Block> = None;
+    // Chord line waiting for lyrics
+    let mut pending: Option<Vec<(usize, &str)>> = None;
+
+    let lines: Vec<_> = input.lines().map(expand_tabs).collect();
+    for (i, line) in lines.iter().enumerate() {
+        let line = line.trim_end();
+        let mut kind = Line::classify(line);
+
+        if matches!(kind, Line::Chords(..)) && pending.is_some() {
+            warnings.push(Warning {
+                line: i + 1,
+                message: format!(
+                    "\"{}\" looks like chords, it is taken as lyrics of the chords above",
+                    line.trim()
+                ),
+            });
+            kind = Line::Lyrics;
+        }
+
+        if kind != Line::Lyrics {
+            if let Some(chords) = pending.take() {
+                let block = block.get_or_insert_with(|| Block::new(Kind::Verse));
+                block.lines.push(chords_only(&chords));
+            }
+        }
+
+        match kind {
+            Line::Blank => blocks.extend(block.take()),
+            Line::Section(label) => {
+                blocks.extend(block.take());
+                block = Some(Block::section(label));
+            }
+            Line::Chords(chords) => pending = Some(chords),
+            Line::Lyrics => {
+                let text = match pending.take() {
+                    Some(chords) => merge(&chords, line),
+                    None => escape(line.trim()),
+                };
+                let block = block.get_or_insert_with(|| Block::new(Kind::Verse));
+                block.lines.push(text);
+            }
+        }
+    }
+
+    if let Some(chords) = pending.take() {
+        let block = block.get_or_insert_with(|| Block::new(Kind::Verse));
+        block.lines.push(chords_only(&chords));
+    }
+    blocks.extend(block.take());
+
+    let mut out = format!("# {}\n", title);
+    for block in blocks.iter() {
+        // Verses need some content, custom labels can stand alone, eg. a solo
+        if block.is_empty() && !matches!(block.kind, Kind::Custom(..)) {
+            continue;
+        }
+
+        out.push('\n');
+        block.write(&mut out);
+    }
+
+    (out, warnings)
+}
+
+#[cfg(test)]
+mod tests;
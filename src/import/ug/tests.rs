@@ -0,0 +1,172 @@
+use super::*;
+use crate::book::{Block as BookBlock, VerseLabel};
+use crate::parser::{parse, ParserConfig};
+
+fn convert_ok(input: &str) -> String {
+    let (md, warnings) = convert("Song", input);
+    assert_eq!(warnings, vec![]);
+    md
+}
+
+fn verse_labels(md: &str) -> Vec<(VerseLabel, Option<u32>)> {
+    let songs = parse(md, ParserConfig::default()).unwrap();
+    assert_eq!(songs.len(), 1);
+    songs[0]
+        .blocks
+        .iter()
+        .filter_map(|block| match block {
+            BookBlock::Verse(verse) => Some((verse.label.clone(), verse.repeat_count)),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn ug_merge_columns() {
+    assert_eq!(
+        merge(&words("G       C"), "Hello there world"),
+        "`G`Hello th`C`ere world"
+    );
+    assert_eq!(merge(&words("  Am"), "Hi"), "Hi `Am`");
+    assert_eq!(merge(&words("D"), "  - dash"), "`D`  - dash");
+    assert_eq!(merge(&[], "- dash"), "\\- dash");
+    assert_eq!(merge(&words("   E"), "   `quoted`"), "`E`\\`quoted\\`");
+}
+
+#[test]
+fn ug_classify() {
+    assert_eq!(Line::classify("   "), Line::Blank);
+    assert_eq!(Line::classify("[Verse 2]"), Line::Section("Verse 2"));
+    assert_eq!(
+        Line::classify("Am7  C/G | F#m x2"),
+        Line::Chords(vec![
+            (0, "Am7"),
+            (5, "C/G"),
+            (9, "|"),
+            (11, "F#m"),
+            (15, "x2")
+        ])
+    );
+    assert_eq!(Line::classify("| N.C. |"), Line::Lyrics);
+    assert_eq!(Line::classify("A little song"), Line::Lyrics);
+}
+
+#[test]
+fn ug_escape() {
+    assert_eq!(escape("# not a heading"), "\\# not a heading");
+    assert_eq!(escape("> not a quote"), "\\> not a quote");
+    assert_eq!(escape("1. not a list"), "1\\. not a list");
+    assert_eq!(escape("2) not a list"), "2\\) not a list");
+    assert_eq!(escape("1999 was a year"), "1999 was a year");
+    assert_eq!(escape("a `tick`"), "a \\`tick\\`");
+}
+
+#[test]
+fn ug_convert_sections() {
+    let input = r#"
+[Verse 1]
+G            C
+Summer time has come
+[Chorus]
+Am       F
+And we'll all go
+
+[Verse 2]
+Another verse
+
+[Bridge]
+C
+Lalala
+[Chorus]
+[Solo x2]
+"#;
+
+    let md = convert_ok(input);
+    assert_eq!(
+        md,
+        r#"# Song
+
+1. `G`Summer time h`C`as come
+
+> `Am`And we'll`F` all go
+
+1. Another verse
+
+### Bridge
+`C`Lalala
+
+### Solo x2
+"#
+    );
+
+    assert_eq!(
+        verse_labels(&md),
+        vec![
+            (VerseLabel::Verse(1), None),
+            (VerseLabel::Chorus(None), None),
+            (VerseLabel::Verse(2), None),
+            (VerseLabel::Custom("Bridge".into()), None),
+            (VerseLabel::Custom("Solo".into()), Some(2)),
+        ]
+    );
+}
+
+#[test]
+fn ug_convert_chords_only() {
+    let input =
+        "[Intro]\nAm  F  |  C  G  x2\n\n[Bridge x2]\nC\tD\n# not a heading\n1. not a list\n";
+
+    let md = convert_ok(input);
+    assert_eq!(
+        md,
+        "# Song\n\n### Intro\n`Am` `F` | `C` `G` x2\n\n### Bridge x2\n`C`# not a `D`heading\n1\\. not a list\n"
+    );
+
+    assert_eq!(
+        verse_labels(&md),
+        vec![
+            (VerseLabel::Custom("Intro".into()), None),
+            (VerseLabel::Custom("Bridge".into()), Some(2)),
+        ]
+    );
+}
+
+#[test]
+fn ug_convert_repeats() {
+    let md = convert_ok("[Verse x2]\nC\nLine\n\n[Refrain (x3)]\nLine\n");
+    assert_eq!(md, "# Song\n\n1. x2\n`C`Line\n\n> (x3)\nLine\n");
+    assert_eq!(
+        verse_labels(&md),
+        vec![
+            (VerseLabel::Verse(1), Some(2)),
+            (VerseLabel::Chorus(None), Some(3)),
+        ]
+    );
+}
+
+#[test]
+fn ug_convert_no_sections() {
+    let md = convert_ok("C\nLine one\n\n\nLine two\nG");
+    assert_eq!(md, "# Song\n\n1. `C`Line one\n\n1. Line two\n`G`\n");
+    assert_eq!(
+        verse_labels(&md),
+        vec![(VerseLabel::Verse(1), None), (VerseLabel::Verse(2), None)]
+    );
+}
+
+#[test]
+fn ug_convert_ambiguous() {
+    let input = "Some lyrics\nG            C\nA B C D E F G\n";
+    let (md, warnings) = convert("Song", input);
+
+    assert_eq!(md, "# Song\n\n1. Some lyrics\n`G`A B C D E F G `C`\n");
+    assert_eq!(
+        warnings,
+        vec![Warning {
+            line: 3,
+            message:
+                "\"A B C D E F G\" looks like chords, it is taken as lyrics of the chords above"
+                    .into(),
+        }]
+    );
+}
@@ -0,0 +1,318 @@
+//! Reading songs from XML formats used by projection software, ie. OpenLyrics and OpenSong,
+//! directly into the bard AST.
+//!
+//! The XML source is first read into a minimal DOM (`Element`), which the format modules
+//! then walk. Problems are reported as parser diagnostics, so that they're shown
+//! the same way as with Markdown files.
+
+use std::cell::Cell;
+
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+use super::{openlyrics, opensong};
+use crate::book::{Block, Song, VerseLabel};
+use crate::parser::{DiagKind, DiagSink, Diagnostic, ParserConfig};
+use crate::prelude::*;
+use crate::project::{Metadata, Value};
+use crate::util::{slugify, BStr};
+
+/// Whether the file at `path` is to be read as an XML song rather than Markdown,
+/// based on its extension (`.xml` or `.opensong`).
+pub fn is_xml(path: &Path) -> bool {
+    path.extension().map_or(false, |ext| {
+        ext.eq_ignore_ascii_case("xml") || ext.eq_ignore_ascii_case("opensong")
+    })
+}
+
+/// Parse an OpenLyrics or OpenSong `source`, the format is detected from the content.
+///
+/// Diagnostics are reported to `diag_sink`, like with `Parser`. The result is an error
+/// if there's been an error diagnostic (or any diagnostic, if `config.warnings_strict` is set).
+#[allow(clippy::result_unit_err)]
+pub fn parse(
+    source: &str,
+    input_file: &Path,
+    config: &ParserConfig,
+    diag_sink: &dyn DiagSink,
+) -> Result<Vec<Song>, ()> {
+    let diags = Diags {
+        input_file,
+        diag_sink,
+        warnings_strict: config.warnings_strict,
+        error_seen: Cell::new(false),
+    };
+
+    let root = match Element::parse(source) {
+        Ok(root) => root,
+        Err((line, message)) => {
+            diags.report(line, DiagKind::Xml { message });
+            return Err(());
+        }
+    };
+
+    let song = match root.name.as_str() {
+        "song" if root.child("properties").is_some() => openlyrics::song(&root, &diags, config),
+        "song" => opensong::song(&root, &diags, config),
+        _ => {
+            let message = format!(
+                "Unknown song format with root element <{}>, expected OpenLyrics or OpenSong",
+                root.name
+            );
+            diags.report(
+                root.line,
+                DiagKind::Xml {
+                    message: message.into(),
+                },
+            );
+            return Err(());
+        }
+    };
+
+    if diags.error_seen.get() {
+        Err(())
+    } else {
+        Ok(vec![song])
+    }
+}
+
+pub(super) struct Diags<'a> {
+    input_file: &'a Path,
+    diag_sink: &'a dyn DiagSink,
+    warnings_strict: bool,
+    error_seen: Cell<bool>,
+}
+
+impl<'a> Diags<'a> {
+    pub(super) fn report(&self, line: usize, kind: DiagKind) {
+        if kind.is_error() || self.warnings_strict {
+            self.error_seen.set(true);
+        }
+
+        self.diag_sink
+            .report(Diagnostic::new(self.input_file, line, kind));
+    }
+
+    pub(super) fn unsupported(&self, element: &Element) {
+        let kind = DiagKind::UnsupportedElement {
+            element: element.name.as_str().into(),
+        };
+        self.report(element.line, kind);
+    }
+}
+
+#[derive(Debug)]
+pub(super) enum Node {
+    Element(Element),
+    Text(String),
+}
+
+/// A minimal XML DOM element, namespaces are dropped from names.
+#[derive(Debug)]
+pub(super) struct Element {
+    pub name: String,
+    pub attrs: Vec<(String, String)>,
+    pub children: Vec<Node>,
+    /// Source line of the start tag.
+    pub line: usize,
+}
+
+impl Element {
+    fn new(start: &BytesStart, line: usize) -> Result<Self, String> {
+        let name = String::from_utf8_lossy(start.local_name().as_ref()).into_owned();
+        let attrs = start
+            .attributes()
+            .map(|attr| {
+                let attr = attr.map_err(|err| err.to_string())?;
+                let key = String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned();
+                let value = attr.unescape_value().map_err(|err| err.to_string())?;
+                Ok((key, value.into_owned()))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            name,
+            attrs,
+            children: vec![],
+            line,
+        })
+    }
+
+    /// Parse `source` into the root element, on failure the line and message are returned.
+    fn parse(source: &str) -> Result<Self, (usize, BStr)> {
+        let line_at = |pos: usize| source[..pos.min(source.len())].matches('\n').count() + 1;
+
+        let mut reader = Reader::from_str(source);
+        let mut stack: Vec<Element> = vec![];
+        let mut root = None;
+        loop {
+            let event = reader
+                .read_event()
+                .map_err(|err| (line_at(reader.buffer_position()), err.to_string().into()))?;
+            let line = line_at(reader.buffer_position());
+            let error = |message: String| (line, BStr::from(message));
+
+            let node = match event {
+                Event::Start(start) => {
+                    stack.push(Self::new(&start, line).map_err(error)?);
+                    continue;
+                }
+                Event::Empty(start) => Node::Element(Self::new(&start, line).map_err(error)?),
+                Event::End(_) => match stack.pop() {
+                    Some(element) => Node::Element(element),
+                    None => return Err(error("Unexpected closing tag".into())),
+                },
+                Event::Text(text) => {
+                    let text = text.unescape().map_err(|err| error(err.to_string()))?;
+                    Node::Text(text.into_owned())
+                }
+                Event::CData(cdata) => {
+                    Node::Text(String::from_utf8_lossy(&cdata.into_inner()).into_owned())
+                }
+                Event::Eof => break,
+                _ => continue,
+            };
+
+            match (stack.last_mut(), node) {
+                (Some(parent), node) => parent.children.push(node),
+                (None, Node::Element(element)) if root.is_none() => root = Some(element),
+                (None, Node::Element(element)) => {
+                    let message = format!(
+                        "Unexpected element <{}> after the root element",
+                        element.name
+                    );
+                    return Err(error(message));
+                }
+                // Whitespace around the root element
+                (None, Node::Text(_)) => {}
+            }
+        }
+
+        if let Some(element) = stack.last() {
+            let message = format!("Element <{}> isn't closed", element.name);
+            return Err((element.line, message.into()));
+        }
+        root.ok_or_else(|| (1, "No root element found".into()))
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn elements(&self) -> impl Iterator<Item = &Element> {
+        self.children.iter().filter_map(|node| match node {
+            Node::Element(element) => Some(element),
+            Node::Text(_) => None,
+        })
+    }
+
+    pub fn child(&self, name: &str) -> Option<&Element> {
+        self.elements().find(|element| element.name == name)
+    }
+
+    /// Text content of the element and its descendants, trimmed.
+    pub fn text(&self) -> String {
+        fn collect(element: &Element, out: &mut String) {
+            for node in element.children.iter() {
+                match node {
+                    Node::Element(element) => collect(element, out),
+                    Node::Text(text) => out.push_str(text),
+                }
+            }
+        }
+
+        let mut text = String::new();
+        collect(self, &mut text);
+        text.trim().to_string()
+    }
+
+    /// Whether the element has neither text nor child elements.
+    pub fn is_empty(&self) -> bool {
+        self.elements().next().is_none() && self.text().is_empty()
+    }
+}
+
+/// Label of a verse given by a name such as `v1`, `c`, or `b`, as used by OpenLyrics
+/// and OpenSong. Unnumbered verses are numbered following the previous verse.
+pub(super) fn verse_label(name: &str, verse_num: &mut u32) -> VerseLabel {
+    let name = name.trim();
+    let mut chars = name.chars();
+    let kind = chars.next().map(|c| c.to_ascii_lowercase());
+    let rest = chars.as_str();
+    let num = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .map_or(rest, |end| &rest[..end])
+        .parse::<u32>()
+        .ok();
+
+    match kind {
+        Some('v') => {
+            *verse_num = num.unwrap_or(*verse_num + 1);
+            VerseLabel::Verse(*verse_num)
+        }
+        Some('c') => VerseLabel::Chorus(Some(num.unwrap_or(1))),
+        Some('b') => VerseLabel::Custom("Bridge".into()),
+        Some('p') => VerseLabel::Custom("Pre-chorus".into()),
+        Some('i') => VerseLabel::Custom("Intro".into()),
+        Some('e') => VerseLabel::Custom("Ending".into()),
+        Some('t') => VerseLabel::Custom("Tag".into()),
+        _ if name.is_empty() => {
+            *verse_num += 1;
+            VerseLabel::Verse(*verse_num)
+        }
+        _ => VerseLabel::Custom(name.into()),
+    }
+}
+
+/// Put together the resulting `Song`, an empty title is replaced by the fallback title.
+pub(super) fn finalize(
+    title: String,
+    subtitles: Vec<BStr>,
+    metadata: Metadata,
+    mut blocks: Vec<Block>,
+    config: &ParserConfig,
+) -> Song {
+    // As with Markdown, a sole chorus isn't numbered
+    let max_chorus = blocks
+        .iter()
+        .map(|b| b.chorus_num().unwrap_or(0))
+        .max()
+        .unwrap_or(0);
+    if max_chorus < 2 {
+        blocks.iter_mut().for_each(Block::remove_chorus_num);
+    }
+
+    let title = if title.is_empty() {
+        config.fallback_title.clone()
+    } else {
+        title
+    };
+    let id = Some(slugify(&title))
+        .filter(|slug| !slug.is_empty())
+        .unwrap_or_else(|| "song".to_string());
+
+    let mut song = Song {
+        title: title.into(),
+        id: id.into(),
+        subtitles: subtitles.into(),
+        metadata,
+        key: None,
+        chapter: None,
+        blocks,
+        footnotes: vec![],
+        notation: config.notation,
+    };
+    song.postprocess();
+    song.key = song.detect_key();
+    song
+}
+
+/// Inserts a metadata string `value` unless it's empty.
+pub(super) fn insert_meta(metadata: &mut Metadata, key: &str, value: String) {
+    if !value.is_empty() {
+        metadata.insert(key.into(), Value::String(value));
+    }
+}
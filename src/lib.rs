@@ -1,8 +1,29 @@
 //! `bard`, the Markdown-based songbook compiler.
 //!
-//! > ### <span style="font-variant: small-caps">**This is not a public API.** </span>
-//! This library is an implementation detail of the `bard` CLI tool.
+//! > ### <span style="font-variant: small-caps">**This is mostly not a public API.** </span>
+//! This library is mostly an implementation detail of the `bard` CLI tool.
 //! These APIs are internal and may break without notice.
+//!
+//! The exception is the parser API re-exported from the crate root:
+//! [`parse()`], [`parse_songs()`], [`ParserConfig`], [`ParseError`], [`Diagnostic`], [`DiagKind`], [`Span`],
+//! and the AST types [`Song`], [`Block`], [`Verse`], [`VerseLabel`], and [`Inline`].
+//!
+//! ```
+//! let input = "# Yippie\n\n1. `G`Yippie yea `D`oh!";
+//! let songs = bard::parse(input, bard::ParserConfig::default()).unwrap();
+//!
+//! assert_eq!(&*songs[0].title, "Yippie");
+//! let json = serde_json::to_string(&songs).unwrap();
+//! let songs2: Vec<bard::Song> = serde_json::from_str(&json).unwrap();
+//! assert_eq!(songs, songs2);
+//! ```
+//!
+//! ### AST stability
+//!
+//! The AST follows the AST version (see `book::version`), which is also what templates declare
+//! via `version_check`. Minor version bumps only add new fields, types, or variants,
+//! a major version bump means a breaking change. Consequently, when matching on `Block` or `Inline`
+//! a wildcard arm should be used.
 
 #![allow(clippy::new_ret_no_self)]
 #![allow(clippy::comparison_chain)]
@@ -10,6 +31,7 @@
 
 use std::env;
 use std::ffi::OsString;
+use std::fs;
 
 use app::{App, InterruptFlag, MakeOpts, StdioOpts};
 use clap::{CommandFactory as _, Parser as _};
@@ -18,6 +40,8 @@ use serde::Serialize;
 pub mod app;
 pub mod book;
 pub mod default_project;
+pub mod import;
+pub mod lint;
 pub mod music;
 pub mod parser;
 pub mod prelude;
@@ -29,6 +53,11 @@ pub mod util;
 pub mod util_cmd;
 pub mod watch;
 
+pub use crate::book::{Block, Inline, Song, Verse, VerseLabel};
+pub use crate::parser::{parse, parse_songs, DiagKind, Diagnostic, ParseError, ParserConfig, Span};
+
+use crate::default_project::InitOptions;
+use crate::import::ImportCmd;
 use crate::prelude::*;
 use crate::project::{Project, Settings};
 use crate::util_cmd::UtilCmd;
@@ -41,6 +70,9 @@ pub struct ProgramMeta {
     pub description: &'static str,
     pub homepage: &'static str,
     pub authors: &'static str,
+    /// Build time as a Unix timestamp, only set in `RenderContext`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub build_timestamp: Option<i64>,
 }
 
 pub const PROGRAM_META: ProgramMeta = ProgramMeta {
@@ -49,6 +81,7 @@ pub const PROGRAM_META: ProgramMeta = ProgramMeta {
     description: env!("CARGO_PKG_DESCRIPTION"),
     homepage: env!("CARGO_PKG_HOMEPAGE"),
     authors: env!("CARGO_PKG_AUTHORS"),
+    build_timestamp: None,
 };
 
 #[derive(clap::Parser)]
@@ -93,6 +126,8 @@ impl Cli {
 enum Command {
     /// Initialize a new bard project skeleton in this directory
     Init {
+        #[clap(flatten)]
+        init: InitOptions,
         #[clap(flatten)]
         opts: StdioOpts,
     },
@@ -106,6 +141,26 @@ enum Command {
         #[clap(flatten)]
         opts: MakeOpts,
     },
+    /// Check songs against the rules in the [lint] section of bard.toml
+    Lint {
+        /// Exit with an error if there are any warnings
+        #[arg(long)]
+        fail_on_warning: bool,
+        #[clap(flatten)]
+        opts: StdioOpts,
+    },
+    /// Write a built-in template to a file so that it can be customized
+    ExportTemplate {
+        /// Template to export: pdf, html, or hovorka
+        format: String,
+        /// Destination file [default: templates/<format>.hbs]
+        dest: Option<PathBuf>,
+        #[clap(flatten)]
+        opts: StdioOpts,
+    },
+    /// Convert songs from other formats to bard Markdown
+    #[command(subcommand)]
+    Import(ImportCmd),
     /// CLI utilities for postprocessing
     #[command(subcommand)]
     Util(UtilCmd),
@@ -120,9 +175,14 @@ impl Command {
         use Command::*;
 
         match self {
-            Init { .. } => bard_init(app),
+            Init { init, .. } => bard_init(app, &init),
             Make { .. } => bard_make(app),
             Watch { .. } => bard_watch(app),
+            Lint {
+                fail_on_warning, ..
+            } => bard_lint(app, fail_on_warning),
+            ExportTemplate { format, dest, .. } => bard_export_template(app, &format, dest),
+            Import(cmd) => cmd.run(app),
             Util(cmd) => cmd.run(app),
 
             #[cfg(feature = "tectonic")]
@@ -136,17 +196,21 @@ fn get_cwd() -> Result<PathBuf> {
 }
 
 pub fn bard_init_at<P: AsRef<Path>>(app: &App, path: P) -> Result<()> {
+    bard_init_with_at(app, path, &InitOptions::default())
+}
+
+pub fn bard_init_with_at<P: AsRef<Path>>(app: &App, path: P, opts: &InitOptions) -> Result<()> {
     let path = path.as_ref();
 
     app.status("Initialize", format!("new project at {:?}", path));
-    Project::init(path).context("Could not initialize a new project")?;
+    Project::init(path, opts).context("Could not initialize a new project")?;
     app.success("Done!");
     Ok(())
 }
 
-pub fn bard_init(app: &App) -> Result<()> {
+pub fn bard_init(app: &App, opts: &InitOptions) -> Result<()> {
     let cwd = get_cwd()?;
-    bard_init_at(app, cwd)
+    bard_init_with_at(app, cwd, opts)
 }
 
 pub fn bard_make_at<P: AsRef<Path>>(app: &App, path: P) -> Result<Project> {
@@ -190,6 +254,75 @@ pub fn bard_watch(app: &App) -> Result<()> {
     bard_watch_at(app, cwd, watch)
 }
 
+/// Returns the lint warnings found, see `Project::lint()`.
+/// With `fail_on_warning`, any warning is an error.
+pub fn bard_lint_at<P: AsRef<Path>>(
+    app: &App,
+    path: P,
+    fail_on_warning: bool,
+) -> Result<Vec<Diagnostic>> {
+    let project = Project::new(app, path.as_ref()).context("Could not load project")?;
+    app.status("Linting", "songs");
+    let warnings = project.lint(app)?;
+    if fail_on_warning && !warnings.is_empty() {
+        bail!("Lint failed with {} warning(s)", warnings.len());
+    }
+    Ok(warnings)
+}
+
+pub fn bard_lint(app: &App, fail_on_warning: bool) -> Result<()> {
+    let cwd = get_cwd()?;
+
+    let warnings = bard_lint_at(app, cwd, fail_on_warning)?;
+    if warnings.is_empty() {
+        app.success("No lint warnings");
+    } else {
+        app.success(format!("Done with {} lint warning(s)", warnings.len()));
+    }
+    Ok(())
+}
+
+pub fn bard_export_template_at<P: AsRef<Path>>(app: &App, format: &str, dest: P) -> Result<()> {
+    let dest = dest.as_ref();
+    let template = render::DEFAULT_TEMPLATES
+        .iter()
+        .find(|t| t.filename.strip_suffix(".hbs") == Some(format))
+        .ok_or_else(|| {
+            anyhow!(
+                "Unknown template format '{}', built-in templates are: pdf, html, hovorka",
+                format
+            )
+        })?;
+
+    if dest.exists() {
+        bail!("File already exists: {:?}", dest);
+    }
+
+    app.status("Exporting", format!("{} template to {:?}", format, dest));
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Could not create directory {:?}", parent))?;
+    }
+    fs::write(dest, template.content.as_bytes())
+        .with_context(|| format!("Could not write template file {:?}", dest))?;
+
+    app.indent(format!(
+        "To use the template, reference it in an [[output]] section in bard.toml, eg.: template = \"{}\"\n\
+        The path is relative to the templates directory.",
+        dest.file_name().unwrap_or_default().to_string_lossy(),
+    ));
+    app.success("Done!");
+    Ok(())
+}
+
+pub fn bard_export_template(app: &App, format: &str, dest: Option<PathBuf>) -> Result<()> {
+    let dest = match dest {
+        Some(dest) => dest,
+        None => get_cwd()?.join("templates").join(format!("{}.hbs", format)),
+    };
+    bard_export_template_at(app, format, dest)
+}
+
 pub fn bard(args: &[OsString], interrupt: InterruptFlag) -> i32 {
     let cli = Cli::parse_from(args);
     if cli.print_version() {
@@ -204,9 +337,12 @@ pub fn bard(args: &[OsString], interrupt: InterruptFlag) -> i32 {
     };
 
     let app = match &cmd {
-        Command::Init { opts } => App::new(&opts.clone().into(), interrupt),
+        Command::Init { opts, .. } => App::new(&opts.clone().into(), interrupt),
         Command::Make { opts } => App::new(opts, interrupt),
         Command::Watch { opts } => App::new(opts, interrupt),
+        Command::Lint { opts, .. } => App::new(&opts.clone().into(), interrupt),
+        Command::ExportTemplate { opts, .. } => App::new(&opts.clone().into(), interrupt),
+        Command::Import(_) => App::new(&Default::default(), interrupt),
         Command::Util(_) => App::new(&Default::default(), interrupt),
 
         #[cfg(feature = "tectonic")]
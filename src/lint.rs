@@ -0,0 +1,50 @@
+//! Stylistic checks of songs done by `bard lint`, configured in the `[lint]` section of `bard.toml`.
+//!
+//! Rules concerning a single song are checked by the parser, which knows the source lines,
+//! and reported as `DiagKind::Lint` diagnostics. Duplicate titles, within a file
+//! as well as across files, are checked in `Project::lint()`.
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::util::BStr;
+
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
+pub struct LintConfig {
+    /// Maximum number of lines of a verse, unlimited if `None`.
+    pub max_verse_lines: Option<usize>,
+    /// Whether songs need to have a subtitle.
+    pub require_subtitle: bool,
+    /// Whether songs need to have at least one chord.
+    pub require_chords: bool,
+    /// Whether songs with the same title are reported.
+    pub warn_duplicate_titles: bool,
+}
+
+impl Default for LintConfig {
+    fn default() -> Self {
+        Self {
+            max_verse_lines: None,
+            require_subtitle: false,
+            require_chords: false,
+            warn_duplicate_titles: true,
+        }
+    }
+}
+
+#[derive(Error, PartialEq, Eq, Clone, Debug)]
+pub enum LintKind {
+    #[error("Song \"{title}\" has no chords.")]
+    NoChords { title: BStr },
+    #[error("Song \"{title}\" has no subtitle.")]
+    NoSubtitle { title: BStr },
+    #[error("Verse has {lines} lines, the maximum is {max}.")]
+    VerseLines { lines: usize, max: usize },
+    #[error("Duplicate song title: \"{title}\"\nA song with the same title was already found in {first_file}:{first_line}.")]
+    DuplicateTitle {
+        title: BStr,
+        first_file: BStr,
+        first_line: usize,
+    },
+}
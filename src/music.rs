@@ -1,9 +1,19 @@
+use std::cmp::Ordering;
 use std::fmt;
 use std::ops;
 use std::str::FromStr;
 
+use once_cell::sync::Lazy;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
+/// Recognized chord suffixes, such as `m7`, `maj7`, `sus4`, or `7(b9)`.
+/// German-style accidentals such as in `Fis` or `Es` are accepted as well.
+static CHORD_SUFFIX: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"^(?:maj|min|dim|aug|sus|add|alt|omit|no|is|es|s|m|M|o|\+|-|°|ø|Δ|\d|#|b|♯|♭|\(|\)|\*|\.)*$")
+        .unwrap()
+});
+
 #[derive(Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Debug)]
 #[serde(rename_all = "lowercase")]
 /// Musical note notation convention
@@ -16,6 +26,49 @@ pub enum Notation {
     German,
     Nashville,
     Roman,
+    /// Do, Ré, Mi, ... as used in Romance languages.
+    #[serde(alias = "solfège")]
+    Solfege,
+}
+
+/// Spelling of the tones between natural notes, ie. whether eg. `A#` or `Bb` is used.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Accidentals {
+    /// Sharps and flats as usual in C major: `C#`, `Eb`, `F#`, `Ab`, `Bb`.
+    #[default]
+    Mixed,
+    Sharps,
+    Flats,
+}
+
+impl Accidentals {
+    /// Accidentals conventionally used in a key given by its tonic, ie. sharps in sharp keys
+    /// (eg. G major or E minor), flats in flat keys (eg. F major or D minor),
+    /// and the mixed spelling in C major and A minor. G♭ major and D♭ major are taken as flat keys.
+    pub fn for_key(tonic: Chromatic, minor: bool) -> Self {
+        // The relative major key decides
+        let major = if minor {
+            tonic + Chromatic::new(3)
+        } else {
+            tonic
+        };
+        match major.num() {
+            0 => Self::Mixed,
+            2 | 4 | 7 | 9 | 11 => Self::Sharps,
+            _ => Self::Flats,
+        }
+    }
+
+    /// Accidentals for a transposition by `delta` half-tones when the key isn't known,
+    /// ie. sharps when transposing up, flats when transposing down, and the mixed spelling otherwise.
+    pub fn for_direction(delta: i32) -> Self {
+        match delta.cmp(&0) {
+            Ordering::Greater => Self::Sharps,
+            Ordering::Less => Self::Flats,
+            Ordering::Equal => Self::Mixed,
+        }
+    }
 }
 
 #[allow(clippy::derivable_impls)] // Due to MSRV
@@ -25,6 +78,13 @@ impl Default for Notation {
     }
 }
 
+impl Notation {
+    /// Whether chords are written as scale degrees, ie. Nashville and Roman numerals.
+    pub fn is_degree(&self) -> bool {
+        matches!(self, Notation::Nashville | Notation::Roman)
+    }
+}
+
 impl FromStr for Notation {
     type Err = ();
 
@@ -37,6 +97,7 @@ impl FromStr for Notation {
             "german" | "czech" => Ok(German),
             "nashville" => Ok(Nashville),
             "roman" => Ok(Roman),
+            "solfege" | "solfège" => Ok(Solfege),
             _ => Err(()),
         }
     }
@@ -49,6 +110,7 @@ impl fmt::Display for Notation {
             Notation::German => "german",
             Notation::Nashville => "nashville",
             Notation::Roman => "roman",
+            Notation::Solfege => "solfege",
         };
         write!(f, "{}", name)
     }
@@ -166,8 +228,30 @@ impl Chromatic {
         (base + delta.into(), base_size + size)
     }
 
+    /// Parses the German `-is` and `-es` accidentals, eg. `Cis` or `Hes`, and `-s` in `As` and `Es`.
+    /// The `-s` isn't taken as an accidental when it starts `sus`, eg. in `Asus4`.
+    fn parse_german_accidental(base: char, rest: &str) -> Option<(i32, usize)> {
+        if rest.starts_with("is") {
+            Some((1, 2))
+        } else if rest.starts_with("es") {
+            Some((-1, 2))
+        } else if matches!(base, 'A' | 'a' | 'E' | 'e')
+            && rest.starts_with('s')
+            && !rest.starts_with("sus")
+        {
+            Some((-1, 1))
+        } else {
+            None
+        }
+    }
+
+    /// English and German note names. In German notation `B` is B flat and `H` is B,
+    /// accidentals may also be written as `-is` and `-es`, eg. `Fis` or `Hes`.
+    /// `B` with an accidental (eg. `Bb`, `B#` or `Bes`) isn't valid in German notation,
+    /// German note names with `-is` or `-es` as well as `H` aren't valid in English notation.
     fn parse_western(from: &str, german: bool) -> Option<(Chromatic, usize)> {
-        let base = match from.chars().next().unwrap() {
+        let first = from.chars().next().unwrap();
+        let base = match first {
             'C' | 'c' => 0,
             'D' | 'd' => 2,
             'E' | 'e' => 4,
@@ -180,7 +264,47 @@ impl Chromatic {
             _ => return None,
         };
 
-        Some(Self::parse_halftone(from, base.into(), 1))
+        let german_acc = Self::parse_german_accidental(first, &from[1..]);
+        let (chromatic, size) = match german_acc {
+            Some(_) if !german => return None,
+            Some((delta, size)) => (Chromatic::from(base) + delta.into(), 1 + size),
+            None => Self::parse_halftone(from, base.into(), 1),
+        };
+
+        // German B is already flattened H
+        if german && base == 10 && size > 1 {
+            return None;
+        }
+
+        Some((chromatic, size))
+    }
+
+    /// Solfège syllables, case-insensitive, `Ré` may also be written as `Re`.
+    fn parse_solfege(from: &str) -> Option<(Chromatic, usize)> {
+        const SYLLABLES: &[(&str, i32)] = &[
+            ("do", 0),
+            ("ré", 2),
+            ("re", 2),
+            ("mi", 4),
+            ("fa", 5),
+            ("sol", 7),
+            ("la", 9),
+            ("si", 11),
+        ];
+
+        SYLLABLES.iter().find_map(|&(syllable, base)| {
+            let num_chars = syllable.chars().count();
+            let size = from
+                .char_indices()
+                .nth(num_chars)
+                .map_or(from.len(), |(i, _)| i);
+            let prefix = &from[..size];
+            if prefix.chars().count() == num_chars && prefix.to_lowercase() == syllable {
+                Some(Self::parse_halftone(from, base.into(), size))
+            } else {
+                None
+            }
+        })
     }
 
     fn parse_nashvile(from: &str) -> Option<(Chromatic, usize)> {
@@ -232,6 +356,7 @@ impl Chromatic {
                 German => Self::parse_western(from, true),
                 Nashville => Self::parse_nashvile(from),
                 Roman => Self::parse_roman(from),
+                Solfege => Self::parse_solfege(from),
             }
         }
     }
@@ -240,70 +365,96 @@ impl Chromatic {
         Self::parse_span(from, notation).map(|(chromatic, _)| chromatic)
     }
 
-    fn as_str_western(&self, german: bool, uppercase: bool) -> &'static str {
-        const TONES_UPPER: &[&str] = &[
+    fn as_str_western(&self, german: bool, accidentals: Accidentals) -> &'static str {
+        const TONES_MIXED: &[&str] = &[
             "C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
         ];
-        const TONES_UPPER_G: &[&str] = &[
-            "C", "C#", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "B", "H",
-        ];
-        const TONES_LOWER: &[&str] = &[
-            "c", "c#", "d", "eb", "e", "f", "f#", "g", "ab", "a", "bb", "b",
+        const TONES_SHARPS: &[&str] = &[
+            "C", "C#", "D", "D#", "E", "F", "F#", "G", "G#", "A", "A#", "B",
         ];
-        const TONES_LOWER_G: &[&str] = &[
-            "c", "c#", "d", "eb", "e", "f", "f#", "g", "ab", "a", "b", "h",
+        const TONES_FLATS: &[&str] = &[
+            "C", "Db", "D", "Eb", "E", "F", "Gb", "G", "Ab", "A", "Bb", "B",
         ];
 
+        // In German notation B is B flat and H is B, regardless of the accidentals
         let i = self.0 as usize;
-        match (german, uppercase) {
-            (false, true) => TONES_UPPER[i],
-            (true, true) => TONES_UPPER_G[i],
-            (false, false) => TONES_LOWER[i],
-            (true, false) => TONES_LOWER_G[i],
+        match (german, i) {
+            (true, 10) => "B",
+            (true, 11) => "H",
+            _ => match accidentals {
+                Accidentals::Mixed => TONES_MIXED[i],
+                Accidentals::Sharps => TONES_SHARPS[i],
+                Accidentals::Flats => TONES_FLATS[i],
+            },
         }
     }
 
-    fn as_str_nashville(&self) -> &'static str {
-        match self.0 {
-            0 => "1",
-            1 => "1#",
-            2 => "2",
-            3 => "3b",
-            4 => "3",
-            5 => "4",
-            6 => "4#",
-            7 => "5",
-            8 => "6b",
-            9 => "6",
-            10 => "7b",
-            11 => "7",
-            _ => unreachable!(),
+    fn as_str_nashville(&self, accidentals: Accidentals) -> &'static str {
+        const TONES_MIXED: &[&str] = &[
+            "1", "1#", "2", "3b", "3", "4", "4#", "5", "6b", "6", "7b", "7",
+        ];
+        const TONES_SHARPS: &[&str] = &[
+            "1", "1#", "2", "2#", "3", "4", "4#", "5", "5#", "6", "6#", "7",
+        ];
+        const TONES_FLATS: &[&str] = &[
+            "1", "2b", "2", "3b", "3", "4", "5b", "5", "6b", "6", "7b", "7",
+        ];
+
+        let i = self.0 as usize;
+        match accidentals {
+            Accidentals::Mixed => TONES_MIXED[i],
+            Accidentals::Sharps => TONES_SHARPS[i],
+            Accidentals::Flats => TONES_FLATS[i],
         }
     }
 
-    fn as_str_roman(&self, uppercase: bool) -> &'static str {
-        const TONES_UPPER: &[&str] = &[
+    fn as_str_roman(&self, accidentals: Accidentals) -> &'static str {
+        const TONES_MIXED: &[&str] = &[
             "I", "I#", "II", "IIIb", "III", "IV", "IV#", "V", "VIb", "VI", "VIIb", "VII",
         ];
-        const TONES_LOWER: &[&str] = &[
-            "i", "i#", "ii", "iiib", "iii", "iv", "iv#", "v", "vib", "vi", "viib", "vii",
+        const TONES_SHARPS: &[&str] = &[
+            "I", "I#", "II", "II#", "III", "IV", "IV#", "V", "V#", "VI", "VI#", "VII",
+        ];
+        const TONES_FLATS: &[&str] = &[
+            "I", "IIb", "II", "IIIb", "III", "IV", "Vb", "V", "VIb", "VI", "VIIb", "VII",
         ];
 
         let i = self.0 as usize;
-        if uppercase {
-            TONES_UPPER[i]
-        } else {
-            TONES_LOWER[i]
+        match accidentals {
+            Accidentals::Mixed => TONES_MIXED[i],
+            Accidentals::Sharps => TONES_SHARPS[i],
+            Accidentals::Flats => TONES_FLATS[i],
         }
     }
 
-    fn as_str(&self, notation: Notation, uppercase: bool) -> &'static str {
+    fn as_str_solfege(&self, accidentals: Accidentals) -> &'static str {
+        const TONES_MIXED: &[&str] = &[
+            "Do", "Do#", "Ré", "Mib", "Mi", "Fa", "Fa#", "Sol", "Lab", "La", "Sib", "Si",
+        ];
+        const TONES_SHARPS: &[&str] = &[
+            "Do", "Do#", "Ré", "Ré#", "Mi", "Fa", "Fa#", "Sol", "Sol#", "La", "La#", "Si",
+        ];
+        const TONES_FLATS: &[&str] = &[
+            "Do", "Réb", "Ré", "Mib", "Mi", "Fa", "Solb", "Sol", "Lab", "La", "Sib", "Si",
+        ];
+
+        let i = self.0 as usize;
+        match accidentals {
+            Accidentals::Mixed => TONES_MIXED[i],
+            Accidentals::Sharps => TONES_SHARPS[i],
+            Accidentals::Flats => TONES_FLATS[i],
+        }
+    }
+
+    /// The tone in uppercase, lowercase chords are written with this lowercased.
+    fn as_str(&self, notation: Notation, accidentals: Accidentals) -> &'static str {
         use self::Notation::*;
         match notation {
-            English => self.as_str_western(false, uppercase),
-            German => self.as_str_western(true, uppercase),
-            Nashville => self.as_str_nashville(),
-            Roman => self.as_str_roman(uppercase),
+            English => self.as_str_western(false, accidentals),
+            German => self.as_str_western(true, accidentals),
+            Nashville => self.as_str_nashville(accidentals),
+            Roman => self.as_str_roman(accidentals),
+            Solfege => self.as_str_solfege(accidentals),
         }
     }
 
@@ -317,12 +468,13 @@ impl Chromatic {
 
 impl fmt::Display for Chromatic {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.as_str(Notation::English, true))
+        write!(f, "{}", self.as_str(Notation::English, Accidentals::Mixed))
     }
 }
 
 #[derive(Debug)]
 struct Chord<'s> {
+    src: &'s str,
     base: Chromatic,
     uppercase: bool,
     suffix: &'s str,
@@ -333,6 +485,7 @@ impl<'s> Chord<'s> {
         let (base, base_size) = Chromatic::parse_span(src, notation).ok_or(src)?;
 
         Ok(Self {
+            src,
             base,
             uppercase: src.chars().next().unwrap().is_uppercase(),
             suffix: &src[base_size..],
@@ -341,19 +494,24 @@ impl<'s> Chord<'s> {
 
     fn transposed(self, by: impl Into<Chromatic>) -> Self {
         Self {
+            src: self.src,
             base: self.base.transposed(by),
             uppercase: self.uppercase,
             suffix: self.suffix,
         }
     }
 
-    fn str_len(&self, notation: Notation) -> usize {
-        self.base.as_str(notation, self.uppercase).len() + self.suffix.len()
+    fn str_len(&self, notation: Notation, accidentals: Accidentals) -> usize {
+        self.base.as_str(notation, accidentals).len() + self.suffix.len()
     }
 
-    fn write_string(&self, mut to: String, notation: Notation) -> String {
-        let base = self.base.as_str(notation, self.uppercase);
-        to.push_str(base);
+    fn write_string(&self, mut to: String, notation: Notation, accidentals: Accidentals) -> String {
+        let base = self.base.as_str(notation, accidentals);
+        if self.uppercase {
+            to.push_str(base);
+        } else {
+            to.extend(base.chars().map(|c| c.to_ascii_lowercase()));
+        }
         to.push_str(self.suffix);
         to
     }
@@ -417,6 +575,17 @@ pub fn transpose(
     by: impl Into<Chromatic>,
     src_notation: Notation,
     to_notation: Notation,
+) -> Result<String, &str> {
+    transpose_with(chord_set, by, src_notation, to_notation, Accidentals::Mixed)
+}
+
+/// Like `transpose()`, with the tones between natural notes spelled according to `accidentals`.
+pub fn transpose_with(
+    chord_set: &str,
+    by: impl Into<Chromatic>,
+    src_notation: Notation,
+    to_notation: Notation,
+    accidentals: Accidentals,
 ) -> Result<String, &str> {
     let by = by.into();
 
@@ -429,17 +598,44 @@ pub fn transpose(
     // Compute the resulting string's length
     let mut transposed_len = prefix.len();
     for chord in ChordIter::new(rest, src_notation) {
-        transposed_len += chord?.transposed(by).str_len(to_notation);
+        transposed_len += chord?.transposed(by).str_len(to_notation, accidentals);
     }
 
     // Render the resulting string
     let mut res = String::with_capacity(transposed_len);
     res.push_str(prefix);
     Ok(ChordIter::new(rest, src_notation).fold(res, |res, chord| {
-        chord.unwrap().transposed(by).write_string(res, to_notation)
+        chord
+            .unwrap()
+            .transposed(by)
+            .write_string(res, to_notation, accidentals)
     }))
 }
 
+/// Replaces `Ré` with `Re` in chords in the solfège notation, for when accents aren't wanted.
+pub fn solfege_unaccented(chord_set: String) -> String {
+    if chord_set.contains(['é', 'É']) {
+        chord_set.replace('é', "e").replace('É', "E")
+    } else {
+        chord_set
+    }
+}
+
+/// Checks that all chords in `chord_set` are recognized, ie. the base note as well as the suffix.
+/// On failure returns the first chord that isn't recognized.
+pub fn validate(chord_set: &str, notation: Notation) -> Result<(), &str> {
+    let rest = chord_set.trim_start_matches(is_chord_separator);
+    for chord in ChordIter::new(rest, notation) {
+        let chord = chord.map_err(|src| src.trim_end_matches(is_chord_separator))?;
+        let suffix = chord.suffix.trim_end_matches(is_chord_separator);
+        if !CHORD_SUFFIX.is_match(suffix) {
+            return Err(chord.src.trim_end_matches(is_chord_separator));
+        }
+    }
+
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -455,8 +651,12 @@ mod tests {
             "german",
             "nashville",
             "roman",
+            "solfege",
+            "solfège",
+        ];
+        let expected = vec![
+            English, English, English, German, Nashville, Roman, Solfege, Solfege,
         ];
-        let expected = vec![English, English, English, German, Nashville, Roman];
 
         // Test from_str:
         let parsed: Vec<_> = names
@@ -479,7 +679,8 @@ mod tests {
         assert_eq!(&parsed, &expected);
 
         // Test serde serialization:
-        let json_expected = r#"["english","english","english","german","nashville","roman"]"#;
+        let json_expected =
+            r#"["english","english","english","german","nashville","roman","solfege","solfege"]"#;
         let serialized = serde_json::to_string(&parsed).unwrap();
         assert_eq!(serialized, json_expected);
     }
@@ -537,6 +738,106 @@ mod tests {
         assert_eq!(Chromatic::parse("X", German), None);
     }
 
+    #[test]
+    fn transpose_german_to_english() {
+        #[rustfmt::skip]
+        let table = [
+            ("C", "C"), ("His", "C"), ("H#", "C"),
+            ("C#", "C#"), ("Cis", "C#"), ("Des", "C#"), ("Db", "C#"),
+            ("D", "D"),
+            ("D#", "Eb"), ("Dis", "Eb"), ("Es", "Eb"), ("Eb", "Eb"),
+            ("E", "E"), ("Fes", "E"),
+            ("F", "F"), ("Eis", "F"),
+            ("F#", "F#"), ("Fis", "F#"), ("Ges", "F#"), ("Gb", "F#"),
+            ("G", "G"),
+            ("G#", "Ab"), ("Gis", "Ab"), ("As", "Ab"), ("Ab", "Ab"),
+            ("A", "A"),
+            ("A#", "Bb"), ("Ais", "Bb"), ("B", "Bb"), ("Hes", "Bb"), ("Hb", "Bb"),
+            ("H", "B"), ("Ces", "B"), ("Cb", "B"),
+        ];
+        for (german, english) in table {
+            assert_eq!(
+                transpose(german, 0, German, English),
+                Ok(english.into()),
+                "{}",
+                german
+            );
+        }
+
+        // Minor and extended chords
+        let t = transpose(
+            "h,hes,b,Hm7,Hes7,Bmaj7,His/Fis,Cism,Esus4,Asus2,es",
+            0,
+            German,
+            English,
+        );
+        assert_eq!(t.unwrap(), "b,bb,bb,Bm7,Bb7,Bbmaj7,C/F#,C#m,Esus4,Asus2,eb");
+
+        // B is already flattened, it can't take an accidental in German notation
+        for chord in ["Bb", "B♭", "B#", "B♯", "Bes", "Bis", "bb", "Bbm7"] {
+            assert_eq!(
+                transpose(chord, 0, German, English),
+                Err(chord),
+                "{}",
+                chord
+            );
+        }
+    }
+
+    #[test]
+    fn transpose_english_to_german() {
+        #[rustfmt::skip]
+        let table = [
+            ("C", "C"), ("B#", "C"),
+            ("C#", "C#"), ("Db", "C#"),
+            ("D", "D"),
+            ("D#", "Eb"), ("Eb", "Eb"),
+            ("E", "E"), ("Fb", "E"),
+            ("F", "F"), ("E#", "F"),
+            ("F#", "F#"), ("Gb", "F#"),
+            ("G", "G"),
+            ("G#", "Ab"), ("Ab", "Ab"),
+            ("A", "A"),
+            ("A#", "B"), ("Bb", "B"), ("B♭", "B"),
+            ("B", "H"), ("Cb", "H"),
+        ];
+        for (english, german) in table {
+            assert_eq!(
+                transpose(english, 0, English, German),
+                Ok(german.into()),
+                "{}",
+                english
+            );
+        }
+
+        // Minor and extended chords
+        let t = transpose(
+            "b,bb,Bm7,Bb7,Bbmaj7,B/F#,Bsus4,Esus4,Asus2",
+            0,
+            English,
+            German,
+        );
+        assert_eq!(t.unwrap(), "h,b,Hm7,B7,Bmaj7,H/F#,Hsus4,Esus4,Asus2");
+
+        // H and the -is and -es accidentals are only valid in German notation
+        for chord in [
+            "H", "h", "Hm7", "Hes", "His", "Cis", "Fis", "Es", "As", "Des7",
+        ] {
+            assert_eq!(
+                transpose(chord, 0, English, English),
+                Err(chord),
+                "{}",
+                chord
+            );
+            assert_eq!(
+                transpose(chord, 0, English, German),
+                Err(chord),
+                "{}",
+                chord
+            );
+        }
+    }
+
     #[test]
     fn chromatic_nashville() {
         let c: Chromatic = 0.into();
@@ -572,6 +873,42 @@ mod tests {
         assert_eq!(Chromatic::parse("X", Roman), None);
     }
 
+    #[test]
+    fn chromatic_solfege() {
+        let english = |tone| Chromatic::parse(tone, English).unwrap();
+        #[rustfmt::skip]
+        let table = [
+            ("Do", "C"), ("Do#", "C#"), ("Réb", "Db"), ("Ré", "D"), ("Re", "D"), ("Ré#", "D#"),
+            ("Mib", "Eb"), ("Mi", "E"), ("Fa", "F"), ("Fa#", "F#"), ("Solb", "Gb"), ("Sol", "G"),
+            ("Sol#", "G#"), ("Lab", "Ab"), ("La", "A"), ("La#", "A#"), ("Sib", "Bb"), ("Si", "B"),
+        ];
+        for (solfege, tone) in table {
+            assert_eq!(
+                Chromatic::parse(solfege, Solfege),
+                Some(english(tone)),
+                "{}",
+                solfege
+            );
+        }
+
+        // Case and accent insensitive
+        for re in ["ré", "re", "RÉ", "RE", "rÉ", "Re♭"] {
+            assert!(Chromatic::parse(re, Solfege).is_some(), "{}", re);
+        }
+        assert_eq!(
+            Chromatic::parse_span("Sol7", Solfege),
+            Some((english("G"), 3))
+        );
+        assert_eq!(
+            Chromatic::parse_span("Ré#m", Solfege),
+            Some((english("D#"), 4))
+        );
+        assert_eq!(Chromatic::parse("C", Solfege), None);
+        assert_eq!(Chromatic::parse("D", Solfege), None);
+        assert_eq!(Chromatic::parse("S", Solfege), None);
+        assert_eq!(Chromatic::parse("Ut", Solfege), None);
+    }
+
     #[test]
     fn chromatic_span() {
         assert_eq!(Chromatic::parse_span("A", English).unwrap().1, 1);
@@ -640,6 +977,32 @@ mod tests {
         assert_eq!(t, "1/2,3");
     }
 
+    #[test]
+    fn transpose_solfege() {
+        let t = transpose("Do/Ré,Mi Fa Sol La Si", 0, Solfege, English).unwrap();
+        assert_eq!(t, "C/D,E F G A B");
+        let t = transpose("C/D,E F G A B", 0, English, Solfege).unwrap();
+        assert_eq!(t, "Do/Ré,Mi Fa Sol La Si");
+        let t = transpose("B,H,F#", 0, German, Solfege).unwrap();
+        assert_eq!(t, "Sib,Si,Fa#");
+
+        // Natively in solfège
+        let t = transpose("Re,Sol7,lam,Do#m/Sol#", 2, Solfege, Solfege).unwrap();
+        assert_eq!(t, "Mi,La7,sim,Mibm/Sib");
+        let t = transpose_with("Ré,La", 1, Solfege, Solfege, Accidentals::Flats).unwrap();
+        assert_eq!(t, "Mib,Sib");
+        let t = transpose_with("Ré,La", 1, Solfege, Solfege, Accidentals::Sharps).unwrap();
+        assert_eq!(t, "Ré#,La#");
+        let t = transpose("Do,Fa,Sol", 5, Solfege, Nashville).unwrap();
+        assert_eq!(t, "4,7b,1");
+
+        assert_eq!(solfege_unaccented("Ré/Fa#,rém".into()), "Re/Fa#,rem");
+
+        // Chords in other notations aren't recognized
+        assert_eq!(transpose("Do,G", 2, Solfege, Solfege), Err("G"));
+        assert_eq!(transpose("Mi,Re", 2, English, English), Err("Mi,"));
+    }
+
     #[test]
     fn transpose_lowercase() {
         let t = transpose("c", 2, English, Roman).unwrap();
@@ -648,4 +1011,69 @@ mod tests {
         let t = transpose("c,d,e,", 2, English, Roman).unwrap();
         assert_eq!(t, "ii,iii,iv#,");
     }
+
+    #[test]
+    fn transpose_accidentals() {
+        let xpose = |chord, by, acc| transpose_with(chord, by, English, English, acc).unwrap();
+        assert_eq!(xpose("A/C#", 1, Accidentals::Mixed), "Bb/D");
+        assert_eq!(xpose("A/C#", 1, Accidentals::Sharps), "A#/D");
+        assert_eq!(xpose("G,F#m7", -1, Accidentals::Flats), "Gb,Fm7");
+        assert_eq!(xpose("c,d", 1, Accidentals::Flats), "db,eb");
+        assert_eq!(xpose("c,d", 1, Accidentals::Sharps), "c#,d#");
+
+        // The German B and H are kept
+        let t = transpose_with("A,Hm,C", 1, German, German, Accidentals::Sharps).unwrap();
+        assert_eq!(t, "B,Cm,C#");
+        let t = transpose_with("Ab,G", 4, German, German, Accidentals::Flats).unwrap();
+        assert_eq!(t, "C,H");
+
+        let t = transpose_with("C,D", 1, English, Roman, Accidentals::Flats).unwrap();
+        assert_eq!(t, "IIb,IIIb");
+        let t = transpose_with("C,D", 1, English, Nashville, Accidentals::Sharps).unwrap();
+        assert_eq!(t, "1#,2#");
+    }
+
+    #[test]
+    fn accidentals_for_key() {
+        let key = |key| Chromatic::parse(key, English).unwrap();
+        assert_eq!(Accidentals::for_key(key("C"), false), Accidentals::Mixed);
+        assert_eq!(Accidentals::for_key(key("A"), true), Accidentals::Mixed);
+        assert_eq!(Accidentals::for_key(key("E"), false), Accidentals::Sharps);
+        assert_eq!(Accidentals::for_key(key("F#"), true), Accidentals::Sharps);
+        assert_eq!(Accidentals::for_key(key("Bb"), false), Accidentals::Flats);
+        assert_eq!(Accidentals::for_key(key("G"), true), Accidentals::Flats);
+        assert_eq!(Accidentals::for_key(key("Gb"), false), Accidentals::Flats);
+    }
+
+    #[test]
+    fn accidentals_for_direction() {
+        assert_eq!(Accidentals::for_direction(1), Accidentals::Sharps);
+        assert_eq!(Accidentals::for_direction(-2), Accidentals::Flats);
+        assert_eq!(Accidentals::for_direction(0), Accidentals::Mixed);
+    }
+
+    #[test]
+    fn chord_validate() {
+        for chord in [
+            "C",
+            "Am7",
+            "F#m7b5",
+            "Bbmaj9",
+            "Gsus4",
+            "Cadd9",
+            "E7(b9)",
+            "D/F#",
+            " G C, Am ",
+            "C°",
+        ] {
+            assert_eq!(validate(chord, English), Ok(()), "{}", chord);
+        }
+        assert_eq!(validate("Hmi7/Fis Es", German), Ok(()));
+        assert_eq!(validate("IVmaj7", Roman), Ok(()));
+
+        assert_eq!(validate("Gg", English), Err("Gg"));
+        assert_eq!(validate("Hmaj9x", English), Err("Hmaj9x"));
+        assert_eq!(validate("Am Cmaj9x/G", English), Err("Cmaj9x"));
+        assert_eq!(validate("Am X", English), Err("X"));
+    }
 }
@@ -7,20 +7,24 @@
 
 use std::cell::{Cell, Ref, RefCell, RefMut};
 use std::fmt;
+use std::iter;
 use std::mem;
 use std::str;
 use std::sync::mpsc;
 
-use comrak::nodes::{Ast, AstNode, ListType, NodeCode, NodeValue};
+use comrak::nodes::{Ast, AstNode, ListType, NodeCode, NodeCodeBlock, NodeValue};
 use comrak::{ComrakExtensionOptions, ComrakOptions, ComrakParseOptions, ComrakRenderOptions};
 use once_cell::sync::Lazy;
 use regex::{Captures, Regex};
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::book::*;
-use crate::music::{self, Notation};
+use crate::lint::{LintConfig, LintKind};
+use crate::music::{self, Accidentals, Chromatic, Notation};
 use crate::prelude::*;
-use crate::util::{BStr, StrExt};
+use crate::project::{Metadata, Value};
+use crate::util::{slugify, BStr, StrExt};
 
 pub mod html;
 
@@ -29,7 +33,17 @@ type Arena<'a> = comrak::Arena<AstNode<'a>>;
 
 const FALLBACK_TITLE: &str = "[Untitled]";
 
-static EXTENSION: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|\s)(!+)(\S+)").unwrap());
+/// File name reported in diagnostics by `parse()`, which has no actual source file.
+pub const INPUT_NAME: &str = "<input>";
+
+static EXTENSION: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(^|\s)(!+)(>["“][^"”]*["”]|\S+)"#).unwrap());
+static REPEAT_MARKER: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\(?[xX×](\d+)\)?$").unwrap());
+static REPEAT_SIGN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"\|:|:\|(?:\s*(\(?[xX×]\d+\)?))?").unwrap());
+static METADATA_LINE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^!([A-Za-z][A-Za-z0-9_-]*):(.*)$").unwrap());
+static LINE_REPEAT: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|\s)\|(\S+)\s*$").unwrap());
 
 #[derive(Error, PartialEq, Eq, Clone, Debug)]
 pub enum DiagKind {
@@ -39,8 +53,64 @@ pub enum DiagKind {
     Transposition { chord: BStr },
     #[error("The HTML tag <{tag}> is reserved for future use.")]
     HtmlReservedTag { tag: BStr },
+    #[error("Raw TeX tag without code: The <tex> tag needs the code in an attribute, eg. <tex code=\"\\ding{{51}}\"/>")]
+    RawTexCode,
     #[error("Text in HTML block ignored: \"{text}\"\nYou may need a blank line between the HTML block and the following text.")]
     HtmlIgnoredText { text: BStr },
+    #[error("Invalid repeat marker ignored: \"{marker}\"\nThe repeat count needs to be a number greater than zero, eg. x2 or (x2).")]
+    RepeatMarker { marker: BStr },
+    #[error("Reference to verse \"{label}\", but there's no verse with that label in the song.")]
+    VerseRef { label: BStr },
+    #[error("{}, but there's no such chorus in the song.", chorus_ref_desc(.num))]
+    ChorusRef { num: Option<u32> },
+    #[error("Unmatched repeat sign \"{sign}\"\nRepeat signs need to come in pairs, eg. |: ... :|")]
+    RepeatUnmatched { sign: BStr },
+    #[error("Repeat sign \"|:\" found inside another repeated section, nested repeats are not supported.")]
+    RepeatNested,
+    #[error(
+        "Invalid song metadata line: \"{text}\"\nMetadata lines need to have the form !key: value"
+    )]
+    Metadata { text: BStr },
+    #[error("Duplicate song metadata key: \"{key}\"")]
+    MetadataKey { key: BStr },
+    /// Reported by the project once all the files are parsed, see `Project::new()`.
+    #[error("Duplicate song title: \"{title}\"\nA song with the same title was already found in {first_file}:{first_line}.")]
+    DuplicateTitle {
+        title: BStr,
+        first_file: BStr,
+        first_line: usize,
+        strict: bool,
+    },
+    #[error("Subtitle \"{text}\" ignored: Subtitles need to come right after the song title.")]
+    SubtitleIgnored { text: BStr },
+    #[error("Empty {}: There's no text after the {} marker.", verse_kind(.chorus), verse_kind(.chorus))]
+    EmptyVerse { chorus: bool },
+    #[error("Unrecognized extension \"{text}\", it is kept as plain text.")]
+    UnknownExtension { text: BStr },
+    #[error("Unrecognized chord: {chord}\nChords need to start with a note name, optionally followed by a suffix such as m7, maj7, or sus4.")]
+    ChordInvalid { chord: BStr },
+    #[error("Invalid XML: {message}")]
+    Xml { message: BStr },
+    #[error("Unsupported element <{element}> ignored.")]
+    UnsupportedElement { element: BStr },
+    /// Lint rule violation, only reported when linting, see the `lint` module.
+    #[error(transparent)]
+    Lint(LintKind),
+}
+
+fn verse_kind(chorus: &bool) -> &'static str {
+    if *chorus {
+        "chorus"
+    } else {
+        "verse"
+    }
+}
+
+fn chorus_ref_desc(num: &Option<u32>) -> String {
+    match num {
+        Some(num) => format!("Reference to chorus {}", num),
+        None => "Chorus reference".to_string(),
+    }
 }
 
 impl DiagKind {
@@ -49,7 +119,23 @@ impl DiagKind {
             Self::ControlChar { .. } => true,
             Self::Transposition { .. } => true,
             Self::HtmlReservedTag { .. } => true,
+            Self::RawTexCode => true,
             Self::HtmlIgnoredText { .. } => false,
+            Self::RepeatMarker { .. } => false,
+            Self::VerseRef { .. } => true,
+            Self::ChorusRef { .. } => false,
+            Self::RepeatUnmatched { .. } => false,
+            Self::RepeatNested => false,
+            Self::Metadata { .. } => true,
+            Self::MetadataKey { .. } => true,
+            Self::DuplicateTitle { strict, .. } => *strict,
+            Self::SubtitleIgnored { .. } => false,
+            Self::EmptyVerse { .. } => false,
+            Self::UnknownExtension { .. } => false,
+            Self::ChordInvalid { .. } => false,
+            Self::Xml { .. } => true,
+            Self::UnsupportedElement { .. } => false,
+            Self::Lint(..) => false,
         }
     }
 
@@ -67,23 +153,93 @@ impl DiagKind {
     }
 }
 
+/// Position of the offending token within its source line.
+///
+/// The column is 1-indexed, both the column and length are counted in characters.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub struct Span {
+    pub column: usize,
+    pub len: usize,
+}
+
+impl Span {
+    /// Span of `token` within `text`, which is the text covered by this span.
+    fn within(self, text: &str, token: &str) -> Option<Self> {
+        let start = text.find(token)?;
+        Some(Self {
+            column: self.column + text[..start].chars().count(),
+            len: token.chars().count(),
+        })
+    }
+}
+
 /// Parser diagnostic report type.
 ///
 /// Reports kind of diagnostic (error or warning), filename, line number and containts the specific error/warning.
-/// The line number is 1-indexed.
+/// The line number is 1-indexed. The span of the offending token is given where known,
+/// along with the source line it refers to.
 #[derive(Error, PartialEq, Eq, Clone, Debug)]
 #[error("{file}:{line}: {kind}")]
+#[non_exhaustive]
 pub struct Diagnostic {
     pub file: PathBuf,
     pub line: usize,
+    pub span: Option<Span>,
+    pub source_line: Option<BStr>,
     pub kind: DiagKind,
 }
 
 impl Diagnostic {
+    pub fn new(file: impl Into<PathBuf>, line: usize, kind: DiagKind) -> Self {
+        Self {
+            file: file.into(),
+            line,
+            span: None,
+            source_line: None,
+            kind,
+        }
+    }
+
+    /// Set the span of the offending token in `source_line`, which is used for the excerpt.
+    pub fn with_span(mut self, span: Span, source_line: impl Into<BStr>) -> Self {
+        self.span = Some(span);
+        self.source_line = Some(source_line.into());
+        self
+    }
+
     #[inline]
     pub fn is_error(&self) -> bool {
         self.kind.is_error()
     }
+
+    /// Render the offending source line with the token underlined, similar to rustc, eg.:
+    ///
+    /// ```text
+    ///   |
+    /// 7 | Yippie yea `X`yay!
+    ///   |             ^
+    /// ```
+    ///
+    /// Returns `None` if the span isn't known.
+    pub fn excerpt(&self) -> Option<String> {
+        let span = self.span?;
+        let line = self.source_line.as_deref()?;
+
+        // Tabs are kept in the padding so that the underline is aligned with the line
+        let padding: String = line
+            .chars()
+            .take(span.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let underline = format!("^{}", "~".repeat(span.len.saturating_sub(1)));
+
+        let num = self.line.to_string();
+        let gutter = " ".repeat(num.len());
+        Some(format!(
+            "{} |\n{} | {}\n{} | {}{}",
+            gutter, num, line, gutter, padding, underline
+        ))
+    }
 }
 
 pub trait DiagSink {
@@ -105,6 +261,35 @@ impl DiagSink for mpsc::Sender<Diagnostic> {
     }
 }
 
+/// Error returned by `parse()`.
+///
+/// Carries all diagnostics reported during parsing, including warnings,
+/// at least one of them is an error.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct ParseError {
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ParseError {
+    pub fn errors(&self) -> impl Iterator<Item = &Diagnostic> {
+        self.diagnostics.iter().filter(|diag| diag.is_error())
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, diag) in self.errors().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", diag)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
 type Result<T, E = ()> = std::result::Result<T, E>;
 
 /// Parser for a candidate bard MD extension
@@ -154,6 +339,24 @@ impl Extension {
             return Some(Transpose::AltNone);
         }
 
+        if self.num_excls == 1 {
+            match self.content.as_str() {
+                "#" | "♯" => return Some(Transpose::Accidentals(Accidentals::Sharps)),
+                "b" | "♭" => return Some(Transpose::Accidentals(Accidentals::Flats)),
+                _ => {}
+            }
+        }
+
+        if let Some(notation) = self.content.strip_prefix('=') {
+            if self.num_excls == 1 {
+                if notation == "default" {
+                    return Some(Transpose::SrcNotation(None));
+                } else if let Ok(notation) = notation.parse::<Notation>() {
+                    return Some(Transpose::SrcNotation(Some(notation)));
+                }
+            }
+        }
+
         None
     }
 
@@ -166,22 +369,79 @@ impl Extension {
         }
     }
 
+    /// Parse reference to a custom-labelled verse, ie. `!>Bridge` or `!>"Middle eight"`.
+    fn try_parse_verse_ref(&self) -> Option<VerseRef> {
+        if self.num_excls != 1 {
+            return None;
+        }
+
+        let label = self.content.strip_prefix('>')?;
+        let label = label
+            .strip_prefix(&['"', '“'][..])
+            .and_then(|l| l.strip_suffix(&['"', '”'][..]))
+            .unwrap_or(label)
+            .trim();
+
+        if label.is_empty() || label.starts_with('>') {
+            None
+        } else {
+            Some(VerseRef::new(label, self.prefix_space))
+        }
+    }
+
+    /// Parse the key directive, ie. `!key: G` or `!key:G`.
+    /// The key may be a separate word following the extension, which ends at `end` in `text`.
+    /// Returns the key and the end of the directive in `text`.
+    fn try_parse_key<'t>(&'t self, text: &'t str, end: usize) -> Option<(&'t str, usize)> {
+        if self.num_excls != 1 {
+            return None;
+        }
+
+        let key = self.content.strip_prefix("key:")?;
+        if !key.is_empty() {
+            return Some((key, end));
+        }
+
+        let rest = &text[end..];
+        let key = rest.trim_start().split(char::is_whitespace).next()?;
+        if key.is_empty() {
+            None
+        } else {
+            let key_end = end + rest.len() - rest.trim_start().len() + key.len();
+            Some((key, key_end))
+        }
+    }
+
     fn try_parse(&self) -> Option<Inline> {
         if let Some(xpose) = self.try_parse_xpose() {
             // Transposition extension recognized
             Some(Inline::Transpose(xpose))
         } else {
-            // Try parsing chorus reference,
+            // Try parsing chorus or verse reference,
             // push as regular text if not recognized
-            self.try_parse_chorus_ref().map(Inline::ChorusRef)
+            self.try_parse_chorus_ref()
+                .map(Inline::ChorusRef)
+                .or_else(|| self.try_parse_verse_ref().map(Inline::VerseRef))
         }
     }
 }
 
+/// Parse a song metadata value as a TOML value, such as a number or a quoted string,
+/// anything that isn't valid TOML is taken as a plain string.
+pub(crate) fn metadata_value(value: &str) -> Value {
+    toml::from_str::<toml::Table>(&format!("value = {}", value))
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.into()))
+}
+
 /// Parser transposition state
 #[derive(Clone, Default, Debug)]
 pub struct Transposition {
-    /// Source notation of the song
+    /// Notation of the song, as configured
+    song_notation: Notation,
+    /// Source notation of the chords being parsed,
+    /// the same as `song_notation` unless switched by a `!=notation` marker
     src_notation: Notation,
     /// Transposition of chords
     xpose: Option<i32>,
@@ -191,6 +451,16 @@ pub struct Transposition {
     alt_xpose: Option<i32>,
     /// Notation conversion of alt chords (2nd row)
     alt_notation: Option<Notation>,
+    /// Declared key of the song, degree-based notations are relative to it
+    key: Option<Chromatic>,
+    /// Whether the declared key is a minor one
+    key_minor: bool,
+    /// Spelling of accidentals set by a `!#` or `!b` marker,
+    /// if not set, it's given by the target key (see `Accidentals::for_key()`)
+    /// or by the direction of the transposition
+    accidentals: Option<Accidentals>,
+    /// Whether `Ré` is written with the accent when converting to the solfège notation
+    solfege_accents: bool,
 
     /// Option to disable transposition for unit testing,
     /// ie. leave `Inline::Transpose` in the AST so they can be checked.
@@ -198,14 +468,92 @@ pub struct Transposition {
 }
 
 impl Transposition {
-    fn new(src_notation: Notation, disabled: bool) -> Self {
+    fn new(song_notation: Notation, solfege_accents: bool, disabled: bool) -> Self {
         Self {
-            src_notation,
+            song_notation,
+            src_notation: song_notation,
+            solfege_accents,
             disabled,
             ..Default::default()
         }
     }
 
+    /// Source notation switches, the declared key and the spelling of accidentals
+    /// only apply until the end of the song.
+    fn song_start(&mut self) {
+        self.src_notation = self.song_notation;
+        self.key = None;
+        self.key_minor = false;
+        self.accidentals = None;
+    }
+
+    /// Notation the song key is written in, degree-based song notations use English note names.
+    fn key_notation(&self) -> Notation {
+        if self.song_notation.is_degree() {
+            Notation::English
+        } else {
+            self.song_notation
+        }
+    }
+
+    /// Set the declared key, a minor key is given by an `m` suffix (eg. `Am`)
+    /// or by a lowercase tone (eg. `a`).
+    fn set_key(&mut self, key: &str) {
+        let parsed = Chromatic::parse_span(key, self.key_notation());
+        self.key = parsed.map(|(key, _)| key);
+        self.key_minor = parsed.map_or(false, |(_, size)| {
+            let suffix = key[size..].trim_start();
+            key.starts_with(char::is_lowercase)
+                || (suffix.starts_with('m') && !suffix.starts_with("maj"))
+        });
+    }
+
+    /// Accidentals to use for chords transposed by `xpose`, as set by a marker,
+    /// otherwise as usual in the target key. Without a declared key,
+    /// the spelling follows the direction of the transposition.
+    fn accidentals(&self, xpose: Option<i32>) -> Accidentals {
+        let xpose = xpose.unwrap_or(0);
+        match (self.accidentals, self.key) {
+            (Some(accidentals), _) => accidentals,
+            (None, Some(key)) => Accidentals::for_key(key.transposed(xpose), self.key_minor),
+            (None, None) => Accidentals::for_direction(xpose),
+        }
+    }
+
+    /// Final touches of chords converted to `to_nt`, ie. removing solfège accents if not wanted.
+    fn spell(&self, chord_set: String, to_nt: Notation) -> String {
+        if to_nt == Notation::Solfege && !self.solfege_accents {
+            music::solfege_unaccented(chord_set)
+        } else {
+            chord_set
+        }
+    }
+
+    /// Transposition delta for converting chords to `to_nt`.
+    ///
+    /// Chords in degree-based notations are relative to the declared key (if any)
+    /// rather than to C, the target key is the declared one after transposition.
+    fn chord_delta(&self, xpose: Option<i32>, to_nt: Notation) -> i32 {
+        let xpose = xpose.unwrap_or(0);
+        let key = match self.key {
+            Some(key) => key.num() as i32,
+            None => return xpose,
+        };
+
+        let src_key = if self.src_notation.is_degree() {
+            key
+        } else {
+            0
+        };
+        let to_key = if to_nt.is_degree() { key + xpose } else { 0 };
+        xpose + src_key - to_key
+    }
+
+    /// Notation of chords as they end up in the AST, ie. after conversion (if any).
+    fn chords_notation(&self) -> Notation {
+        self.notation.unwrap_or(self.src_notation)
+    }
+
     fn update(&mut self, xpose: Transpose) {
         if self.disabled {
             return;
@@ -220,6 +568,8 @@ impl Transposition {
                 self.alt_xpose = None;
                 self.alt_notation = None;
             }
+            Transpose::SrcNotation(nt) => self.src_notation = nt.unwrap_or(self.song_notation),
+            Transpose::Accidentals(acc) => self.accidentals = Some(acc),
         }
     }
 
@@ -229,6 +579,23 @@ impl Transposition {
             || self.alt_xpose.is_some()
             || self.alt_notation.is_some()
     }
+
+    /// Transposes the song `key` like the chords in the song (not the alt chords),
+    /// returns `None` if there's no transposition or the key isn't recognized.
+    ///
+    /// The key stays a note name even if the chords are converted to a degree-based notation.
+    fn transpose_key(&self, key: &str) -> Option<String> {
+        if self.disabled || (self.xpose.is_none() && self.notation.is_none()) {
+            return None;
+        }
+
+        let delta = self.xpose.unwrap_or(0);
+        let key_nt = self.key_notation();
+        let to_nt = self.notation.filter(|nt| !nt.is_degree()).unwrap_or(key_nt);
+        music::transpose_with(key, delta, key_nt, to_nt, self.accidentals(self.xpose))
+            .ok()
+            .map(|key| self.spell(key, to_nt))
+    }
 }
 
 /// Custom operations on Comrak AST nodes
@@ -239,6 +606,8 @@ trait NodeExt<'a> {
     fn is_p(&self) -> bool;
     fn is_code(&self) -> bool;
     fn is_break(&self) -> bool;
+    /// Whether this is an `Emph` or `Strong` node.
+    fn is_formatting(&self) -> bool;
     fn is_link(&self) -> bool;
     fn is_item(&self) -> bool;
     fn is_bq(&self) -> bool;
@@ -266,9 +635,16 @@ trait NodeExt<'a> {
     ///   within another inline element, the element is split and the code is brought up.
     ///   This happens recursively. This is done so that inline code spans can be easily
     ///   collected into Chord spans with the content that follows until the next inline code
-    ///   or linebreak.
+    ///   or linebreak. Formatting elements left empty by the split are removed.
     fn preprocess(&'a self, arena: &'a Arena<'a>);
 
+    /// Joins lines of paragraphs which end with a backslash, ie. a line break
+    /// after a backslash is replaced by a space rather than producing `Inline::Break`.
+    ///
+    /// Comrak doesn't tell a backslash line break from one made by trailing spaces,
+    /// so the breaks are matched up with `source` lines of the paragraph.
+    fn join_continued_lines(&'a self, source: &[&str]);
+
     /// Parse the html snippet using a 3rd party HTML parser,
     /// convert HTML elements into `Inline::HtmlTag`s and append to `target`.
     fn parse_html(&self, target: &mut Vec<Inline>, ctx: &ParserCtx);
@@ -316,6 +692,14 @@ impl<'a> NodeExt<'a> for AstNode<'a> {
         )
     }
 
+    #[inline]
+    fn is_formatting(&self) -> bool {
+        matches!(
+            self.data.borrow().value,
+            NodeValue::Emph | NodeValue::Strong
+        )
+    }
+
     #[inline]
     fn is_link(&self) -> bool {
         matches!(self.data.borrow().value, NodeValue::Link(..))
@@ -431,6 +815,57 @@ impl<'a> NodeExt<'a> for AstNode<'a> {
                 node.insert_after(child);
                 start_node = Some(node2);
             }
+
+            // Splitting leaves an empty element behind if the child was first or last,
+            // eg. with a chord at the start of emphasis, this would make an empty inline.
+            if node.is_formatting() && node.first_child().is_none() {
+                node.detach();
+            }
+        }
+    }
+
+    fn join_continued_lines(&'a self, source: &[&str]) {
+        if !self.is_p() {
+            self.children().for_each(|c| c.join_continued_lines(source));
+            return;
+        }
+
+        let (start, end) = {
+            let pos = self.data.borrow().sourcepos;
+            (pos.start.line, pos.end.line)
+        };
+        let breaks: Vec<_> = self.descendants().filter(|n| n.is_break()).collect();
+        if breaks.len() != end - start {
+            // Some line ends don't make a break, eg. in a multi-line code span,
+            // so breaks can't be matched up with lines.
+            return;
+        }
+
+        for (i, node) in breaks.into_iter().enumerate() {
+            let is_line_break = matches!(node.data.borrow().value, NodeValue::LineBreak);
+            let line = source.get(start - 1 + i).copied().unwrap_or("");
+            // A backslash alone on a line is an explicit break, eg. after a verse label.
+            if !is_line_break || !line.ends_with('\\') || node.previous_sibling().is_none() {
+                continue;
+            }
+
+            let text = |n: &AstRef<'a>| match &n.data.borrow().value {
+                NodeValue::Text(text) => Some(text.clone()),
+                _ => None,
+            };
+            let prev = node.previous_sibling().filter(|n| text(n).is_some());
+            let next_text = node.next_sibling().and_then(|n| text(&n).map(|t| (n, t)));
+            match (prev, next_text) {
+                (Some(prev), Some((next, next_text))) => {
+                    if let NodeValue::Text(text) = &mut prev.data.borrow_mut().value {
+                        text.push(' ');
+                        text.push_str(&next_text);
+                    }
+                    next.detach();
+                    node.detach();
+                }
+                _ => node.data.borrow_mut().value = NodeValue::Text(" ".into()),
+            }
         }
     }
 
@@ -517,6 +952,11 @@ impl ChordBuilder {
         &mut self.inlines
     }
 
+    /// Check the chord is recognized, as it would be by transposition.
+    fn validate(&self, notation: Notation) -> Result<(), BStr> {
+        music::validate(&self.chord, notation).map_err(BStr::from)
+    }
+
     fn transpose(&mut self, xp: &Transposition) -> Result<(), BStr> {
         if xp.disabled {
             return Ok(());
@@ -526,15 +966,19 @@ impl ChordBuilder {
 
         // alt_xpose needs to be done first, because self.chord may be overwritten
         if xp.alt_xpose.is_some() || xp.alt_notation.is_some() {
-            let delta = xp.alt_xpose.unwrap_or(0);
             let to_nt = xp.alt_notation.unwrap_or(src_nt);
-            self.alt_chord = Some(music::transpose(&self.chord, delta, src_nt, to_nt)?.into());
+            let delta = xp.chord_delta(xp.alt_xpose, to_nt);
+            let acc = xp.accidentals(xp.alt_xpose);
+            let alt_chord = music::transpose_with(&self.chord, delta, src_nt, to_nt, acc)?;
+            self.alt_chord = Some(xp.spell(alt_chord, to_nt).into());
         }
 
         if xp.xpose.is_some() || xp.notation.is_some() {
-            let delta = xp.xpose.unwrap_or(0);
             let to_nt = xp.notation.unwrap_or(src_nt);
-            self.chord = music::transpose(&self.chord, delta, src_nt, to_nt)?.into();
+            let delta = xp.chord_delta(xp.xpose, to_nt);
+            let acc = xp.accidentals(xp.xpose);
+            let chord = music::transpose_with(&self.chord, delta, src_nt, to_nt, acc)?;
+            self.chord = xp.spell(chord, to_nt).into();
         }
 
         Ok(())
@@ -552,10 +996,35 @@ impl ChordBuilder {
     }
 }
 
+/// Verse repeat marker, such as `x2` or `(x3)`.
+enum RepeatMarker {
+    /// Repeat count, `None` in case of `x1`.
+    Count(Option<u32>),
+    /// The count is zero (or too large), eg. `x0`.
+    Invalid,
+}
+
+impl RepeatMarker {
+    fn parse(text: &str) -> Option<Self> {
+        let caps = REPEAT_MARKER.captures(text.trim())?;
+        let marker = match caps[1].parse::<u32>() {
+            Ok(0) | Err(_) => Self::Invalid,
+            Ok(1) => Self::Count(None),
+            Ok(n) => Self::Count(Some(n)),
+        };
+        Some(marker)
+    }
+}
+
 #[derive(Debug)]
 struct VerseBuilder<'a> {
     label: VerseLabel,
     paragraphs: Vec<Paragraph>,
+    repeat_count: Option<u32>,
+    /// Notation of the first chord in the verse
+    notation: Option<Notation>,
+    /// Source line of the first paragraph
+    line: Option<usize>,
     ctx: &'a ParserCtx<'a>,
 }
 
@@ -564,18 +1033,39 @@ impl<'a> VerseBuilder<'a> {
         Self {
             label,
             paragraphs: vec![],
+            repeat_count: None,
+            notation: None,
+            line: None,
             ctx,
         }
     }
 
-    fn with_p_nodes<I>(label: VerseLabel, ctx: &'a ParserCtx<'a>, nodes: I) -> Self
-    where
-        I: Iterator<Item = AstRef<'a>>,
-    {
-        nodes.fold(Self::new(label, ctx), |mut this, node| {
-            this.add_p_node(node);
-            this
-        })
+    /// Create a custom-labeled verse out of a heading.
+    /// The heading text may end with a repeat marker.
+    fn with_heading(node: AstRef, ctx: &'a ParserCtx<'a>) -> Self {
+        let mut label = node.as_plaintext();
+        let mut repeat_count = None;
+
+        if let Some((text, marker)) = label.trim_end().rsplit_once(char::is_whitespace) {
+            match RepeatMarker::parse(marker) {
+                Some(RepeatMarker::Count(count)) => {
+                    repeat_count = count;
+                    label = text.trim_end().to_string();
+                }
+                Some(RepeatMarker::Invalid) => {
+                    let kind = DiagKind::RepeatMarker {
+                        marker: marker.into(),
+                    };
+                    let line = node.source_line();
+                    ctx.report_diag_span(line, ctx.locate(line, "", marker), kind);
+                }
+                None => {}
+            }
+        }
+
+        let mut this = Self::new(VerseLabel::Custom(label.into()), ctx);
+        this.repeat_count = repeat_count;
+        this
     }
 
     /// Parse a text node. It may parse into a series of `Inline`s
@@ -593,7 +1083,21 @@ impl<'a> VerseBuilder<'a> {
 
             // Try parsing an extension
             let ext = Extension::from(caps);
-            if let Some(inline) = ext.try_parse() {
+            if let Some((key, end)) = ext.try_parse_key(text, hit.end()) {
+                let preceding = &text[pos..hit.start()];
+                if !preceding.is_empty() {
+                    target.push(Inline::text(preceding));
+                }
+
+                // The directive isn't kept in the AST, the key ends up in song metadata,
+                // following whitespace is consumed the same way as with transposition.
+                self.ctx.declare_key(key, node.source_line());
+                if !ext.prefix_space && end < text.len() {
+                    pos = end + 1;
+                } else {
+                    pos = end;
+                }
+            } else if let Some(inline) = ext.try_parse() {
                 // First see if there's regular text preceding the extension
                 let preceding = &text[pos..hit.start()];
                 if !preceding.is_empty() {
@@ -615,9 +1119,29 @@ impl<'a> VerseBuilder<'a> {
                     }
                 } else {
                     // inline not xpose or xp disabled
+                    // Verse and chorus references are verified once the whole song is parsed
+                    match &inline {
+                        Inline::VerseRef(vr) => self
+                            .ctx
+                            .verse_refs
+                            .borrow_mut()
+                            .push((vr.label.clone(), node.source_line())),
+                        Inline::ChorusRef(cr) => self
+                            .ctx
+                            .chorus_refs
+                            .borrow_mut()
+                            .push((cr.num, node.source_line())),
+                        _ => {}
+                    }
                     target.push(inline);
                     pos = hit.end();
                 }
+            } else {
+                let text = hit.as_str().trim_start();
+                let kind = DiagKind::UnknownExtension { text: text.into() };
+                let line = node.source_line();
+                self.ctx
+                    .report_diag_span(line, self.ctx.locate(line, "", text), kind);
             }
         }
 
@@ -694,13 +1218,38 @@ impl<'a> VerseBuilder<'a> {
                 }
 
                 let mut new_cb = ChordBuilder::new(code);
+                // Every chord is located so that the following ones are found at their own position
+                let line = c.source_line();
+                let code_span = self.ctx.locate(line, "`", &code.literal);
+                let chord_span = |chord: &str| {
+                    code_span.map(|span| span.within(&code.literal, chord).unwrap_or(span))
+                };
                 let xp = self.ctx.xp();
+                self.ctx
+                    .first_chord_xp
+                    .borrow_mut()
+                    .get_or_insert_with(|| xp.clone());
+                self.notation.get_or_insert(xp.chords_notation());
+                let invalid = if self.ctx.validate_chords {
+                    new_cb.validate(xp.src_notation).err()
+                } else {
+                    None
+                };
+                let mut xp_failed = false;
                 if xp.is_some() {
                     if let Err(chord) = new_cb.transpose(&xp) {
-                        self.ctx
-                            .report_diag(c.source_line(), DiagKind::Transposition { chord });
+                        let span = chord_span(&chord);
+                        let kind = DiagKind::Transposition { chord };
+                        self.ctx.report_diag_span(line, span, kind);
+                        xp_failed = true;
                     }
                 }
+                // The chord is only reported once, transposition errors take precedence
+                if let Some(chord) = invalid.filter(|_| !xp_failed) {
+                    let span = chord_span(&chord);
+                    let kind = DiagKind::ChordInvalid { chord };
+                    self.ctx.report_diag_span(line, span, kind);
+                }
 
                 if new_cb.baseline {
                     // Baseline chords don't take any inlines, finalize right away...
@@ -731,11 +1280,366 @@ impl<'a> VerseBuilder<'a> {
             cb.finalize(&mut para);
         }
 
+        let mut para = self.parse_footnotes(para);
+        if self.paragraphs.is_empty() && self.repeat_count.is_none() {
+            self.take_repeat_marker(&mut para, node.source_line());
+        }
+
+        let mut para = self.parse_repeat_signs(para, node.source_line());
+        Self::mark_chords_only(&mut para);
+
         if !para.is_empty() {
             self.paragraphs.push(para.into());
         }
     }
 
+    /// Parse `^[text]` footnotes in the inlines, recursively. A footnote needs to start
+    /// and end within the same element, its text may contain formatting.
+    /// Footnotes are numbered in the order of appearance within the song.
+    fn parse_footnotes(&self, inlines: Vec<Inline>) -> Vec<Inline> {
+        let has_candidate = inlines
+            .iter()
+            .any(|inline| matches!(inline, Inline::Text { text } if text.contains('^')));
+
+        let mut res: Vec<Inline> = Vec::with_capacity(inlines.len());
+        // Content of the footnote being currently parsed, if any
+        let mut open: Option<Vec<Inline>> = None;
+
+        for inline in inlines {
+            match inline {
+                Inline::Text { text } if has_candidate => {
+                    // Adjacent text is joined so that the delimiters may be found across text nodes
+                    let target = open.as_mut().unwrap_or(&mut res);
+                    if let Some(Inline::Text { text: prev }) = target.last_mut() {
+                        *prev = format!("{}{}", prev, text).into();
+                    } else {
+                        target.push(Inline::Text { text });
+                    }
+
+                    self.split_footnotes(&mut res, &mut open);
+                }
+                Inline::Chord(mut chord) => {
+                    Self::close_footnote_unfinished(&mut res, &mut open);
+                    chord.inlines = self.parse_footnotes(chord.inlines.into()).into();
+                    res.push(Inline::Chord(chord));
+                }
+                Inline::Break => {
+                    Self::close_footnote_unfinished(&mut res, &mut open);
+                    res.push(inline);
+                }
+                Inline::Emph(i) => {
+                    let inlines = self.parse_footnotes(i.inlines.into());
+                    open.as_mut()
+                        .unwrap_or(&mut res)
+                        .push(Inline::Emph(inlines.into()));
+                }
+                Inline::Strong(i) => {
+                    let inlines = self.parse_footnotes(i.inlines.into());
+                    open.as_mut()
+                        .unwrap_or(&mut res)
+                        .push(Inline::Strong(inlines.into()));
+                }
+                other => open.as_mut().unwrap_or(&mut res).push(other),
+            }
+        }
+
+        Self::close_footnote_unfinished(&mut res, &mut open);
+        res
+    }
+
+    /// Look for footnote delimiters in the last text inline, either in `res`,
+    /// or in `open` if a footnote is being parsed.
+    fn split_footnotes(&self, res: &mut Vec<Inline>, open: &mut Option<Vec<Inline>>) {
+        loop {
+            let target = open.as_mut().unwrap_or(&mut *res);
+            let text = match target.last() {
+                Some(Inline::Text { text }) => text.clone(),
+                _ => return,
+            };
+
+            if let Some(content) = open.as_mut() {
+                let end = match text.find(']') {
+                    Some(end) => end,
+                    None => return,
+                };
+
+                content.pop();
+                if end > 0 {
+                    content.push(Inline::text(&text[..end]));
+                }
+                let mut footnotes = self.ctx.footnotes.borrow_mut();
+                let footnote = Footnote::new(footnotes.len() as u32 + 1, open.take().unwrap());
+                footnotes.push(footnote.clone());
+                res.push(Inline::Footnote(footnote));
+
+                if end + 1 < text.len() {
+                    res.push(Inline::text(&text[end + 1..]));
+                }
+            } else {
+                let start = match text.find("^[") {
+                    Some(start) => start,
+                    None => return,
+                };
+
+                res.pop();
+                if start > 0 {
+                    res.push(Inline::text(&text[..start]));
+                }
+
+                let mut content = vec![];
+                if start + 2 < text.len() {
+                    content.push(Inline::text(&text[start + 2..]));
+                }
+                *open = Some(content);
+            }
+        }
+    }
+
+    /// A footnote that isn't closed by the end of the element is left as regular text.
+    fn close_footnote_unfinished(res: &mut Vec<Inline>, open: &mut Option<Vec<Inline>>) {
+        if let Some(content) = open.take() {
+            for inline in iter::once(Inline::text("^[")).chain(content) {
+                match (res.last_mut(), inline) {
+                    (Some(Inline::Text { text: prev }), Inline::Text { text }) => {
+                        *prev = format!("{}{}", prev, text).into();
+                    }
+                    (_, inline) => res.push(inline),
+                }
+            }
+        }
+    }
+
+    /// Look for a repeat marker on the first line of the verse, ie. right after the label,
+    /// as in `1. x2` or `> (x3)`. A valid marker is removed from the paragraph.
+    fn take_repeat_marker(&mut self, para: &mut Vec<Inline>, line: usize) {
+        let text = match para.first() {
+            Some(Inline::Text { text }) if para.get(1).map_or(true, Inline::is_break) => text,
+            _ => return,
+        };
+
+        match RepeatMarker::parse(text) {
+            Some(RepeatMarker::Count(count)) => {
+                self.repeat_count = count;
+                let end = para.len().min(2);
+                para.drain(..end);
+            }
+            Some(RepeatMarker::Invalid) => {
+                let marker = text.trim().into();
+                self.ctx
+                    .report_diag(line, DiagKind::RepeatMarker { marker });
+            }
+            None => {}
+        }
+    }
+
+    /// Parse repeat signs in the paragraph: `|:` and `:|` delimiting a repeated section,
+    /// optionally with a count, as in `:|x2` or `:| (x2)`, and `|x2` at the end of a line,
+    /// which repeats the whole line. Signs need to be separated by whitespace from the text.
+    fn parse_repeat_signs(&self, para: Vec<Inline>, line: usize) -> Vec<Inline> {
+        let mut res = Vec::with_capacity(para.len());
+        for inline in para {
+            match inline {
+                Inline::Text { text } => self.split_repeat_signs(&text, &mut res, line),
+                Inline::Chord(mut chord) => {
+                    let mut inlines = vec![];
+                    for inline in Vec::from(chord.inlines) {
+                        match inline {
+                            Inline::Text { text } => {
+                                self.split_repeat_signs(&text, &mut inlines, line)
+                            }
+                            other => inlines.push(other),
+                        }
+                    }
+
+                    // Repeat ends at the end of chord text are moved out of the chord
+                    let trailing = inlines
+                        .iter()
+                        .rev()
+                        .take_while(|i| matches!(i, Inline::RepeatEnd(..)))
+                        .count();
+                    let ends = inlines.split_off(inlines.len() - trailing);
+                    chord.inlines = inlines.into();
+                    res.push(Inline::Chord(chord));
+                    res.extend(ends);
+                }
+                other => res.push(other),
+            }
+        }
+
+        self.take_line_repeats(&mut res, line);
+        self.check_repeat_signs(&res, line);
+        res
+    }
+
+    /// Split `text` around `|:` and `:|` repeat signs and push the result to `target`.
+    /// Whitespace around the signs is dropped.
+    fn split_repeat_signs(&self, text: &str, target: &mut Vec<Inline>, line: usize) {
+        let mut pos = 0;
+        let mut after_sign = false;
+        for caps in REPEAT_SIGN.captures_iter(text) {
+            let hit = caps.get(0).unwrap();
+            let before = text[..hit.start()].chars().next_back();
+            let after = text[hit.end()..].chars().next();
+            if !before.map_or(true, char::is_whitespace) || !after.map_or(true, char::is_whitespace)
+            {
+                continue;
+            }
+
+            let preceding = &text[pos..hit.start()];
+            let preceding = if after_sign {
+                preceding.trim()
+            } else {
+                preceding.trim_end()
+            };
+            if !preceding.is_empty() {
+                target.push(Inline::text(preceding));
+            }
+
+            if hit.as_str() == "|:" {
+                target.push(Inline::RepeatStart);
+            } else {
+                let count = caps
+                    .get(1)
+                    .and_then(|count| self.repeat_count(count.as_str(), line))
+                    .flatten();
+                target.push(Inline::RepeatEnd(RepeatEnd::new(count)));
+            }
+
+            pos = hit.end();
+            after_sign = true;
+        }
+
+        let rest = &text[pos..];
+        let rest = if after_sign { rest.trim_start() } else { rest };
+        if !rest.is_empty() {
+            target.push(Inline::text(rest));
+        }
+    }
+
+    /// Look for `|x2` at the end of each line, if found, the line is wrapped in a repeated section.
+    fn take_line_repeats(&self, para: &mut Vec<Inline>, line: usize) {
+        let mut line_end = para.len();
+        while line_end > 0 {
+            let line_start = para[..line_end]
+                .iter()
+                .rposition(Inline::is_break)
+                .map_or(0, |i| i + 1);
+
+            if let Some(count) = self.take_line_repeat(&mut para[line_end - 1], line) {
+                if matches!(&para[line_end - 1], Inline::Text { text } if text.is_empty()) {
+                    para.remove(line_end - 1);
+                    line_end -= 1;
+                }
+
+                para.insert(line_end, Inline::RepeatEnd(RepeatEnd::new(count)));
+                if !matches!(para.get(line_start), Some(Inline::RepeatStart)) {
+                    para.insert(line_start, Inline::RepeatStart);
+                }
+            }
+
+            line_end = line_start.saturating_sub(1);
+        }
+    }
+
+    /// Remove a `|x2` sign from the end of the inline's text, returns the repeat count if found.
+    fn take_line_repeat(&self, inline: &mut Inline, line: usize) -> Option<Option<u32>> {
+        let text = match inline {
+            Inline::Text { text } => text,
+            Inline::Chord(chord) => match chord.inlines.last_mut() {
+                Some(Inline::Text { text }) => text,
+                _ => return None,
+            },
+            _ => return None,
+        };
+
+        let caps = LINE_REPEAT.captures(text)?;
+        let count = self.repeat_count(&caps[2], line)?;
+        let start = caps.get(0).unwrap().start();
+        *text = text[..start].trim_end().into();
+
+        if let Inline::Chord(chord) = inline {
+            if matches!(chord.inlines.last(), Some(Inline::Text { text }) if text.is_empty()) {
+                let mut inlines = Vec::from(mem::take(&mut chord.inlines));
+                inlines.pop();
+                chord.inlines = inlines.into();
+            }
+        }
+
+        Some(count)
+    }
+
+    /// Check that repeat signs in the paragraph are paired, reports a warning if not.
+    fn check_repeat_signs(&self, para: &[Inline], line: usize) {
+        let mut open = false;
+        let inlines = para.iter().flat_map(|inline| {
+            let nested: &[Inline] = match inline {
+                Inline::Chord(chord) => &chord.inlines,
+                _ => &[],
+            };
+            iter::once(inline).chain(nested)
+        });
+
+        for inline in inlines {
+            match inline {
+                Inline::RepeatStart if open => self.ctx.report_diag(line, DiagKind::RepeatNested),
+                Inline::RepeatStart => open = true,
+                Inline::RepeatEnd(..) if !open => {
+                    let sign = ":|".into();
+                    self.ctx
+                        .report_diag(line, DiagKind::RepeatUnmatched { sign });
+                }
+                Inline::RepeatEnd(..) => open = false,
+                _ => {}
+            }
+        }
+
+        if open {
+            let sign = "|:".into();
+            self.ctx
+                .report_diag(line, DiagKind::RepeatUnmatched { sign });
+        }
+    }
+
+    /// Parse the count of a repeat sign, such as `x2`. Returns `None` if `marker` isn't a count.
+    /// An invalid count, such as `x0`, is reported and ignored.
+    fn repeat_count(&self, marker: &str, line: usize) -> Option<Option<u32>> {
+        match RepeatMarker::parse(marker)? {
+            RepeatMarker::Count(count) => Some(count),
+            RepeatMarker::Invalid => {
+                let marker = marker.into();
+                self.ctx
+                    .report_diag(line, DiagKind::RepeatMarker { marker });
+                Some(None)
+            }
+        }
+    }
+
+    /// Mark chords on lines which contain nothing but chords, whitespace and repeat signs,
+    /// such as instrumental intros, so that templates can lay these out on their own.
+    fn mark_chords_only(para: &mut [Inline]) {
+        let is_blank = |inline: &Inline| match inline {
+            Inline::Text { text } => text.trim().is_empty(),
+            Inline::Transpose(..) | Inline::RepeatStart | Inline::RepeatEnd(..) => true,
+            _ => false,
+        };
+
+        for line in para.split_mut(Inline::is_break) {
+            let chords_only = line.iter().any(|i| matches!(i, Inline::Chord(..)))
+                && line.iter().all(|inline| match inline {
+                    Inline::Chord(chord) => chord.inlines.iter().all(is_blank),
+                    other => is_blank(other),
+                });
+
+            if chords_only {
+                line.iter_mut().for_each(|inline| {
+                    if let Inline::Chord(chord) = inline {
+                        chord.chords_only = true;
+                    }
+                });
+            }
+        }
+    }
+
     /// Add node containing a paragraph (or multiple ones in case of nested lists)
     fn add_p_node(&mut self, node: AstRef) {
         // This is called from SongBuilder, ie. if we come across a List
@@ -743,6 +1647,7 @@ impl<'a> VerseBuilder<'a> {
         // as top-level ones are handled in SongBuilder.
         // These nested lists/bqs are undefined by bard MD,
         // ATM we just ignore them as such, but parse the paragraphs within.
+        self.line.get_or_insert_with(|| node.source_line());
         match &node.data.borrow().value {
             NodeValue::Paragraph => self.add_p_inner(node),
             NodeValue::BlockQuote | NodeValue::List(..) | NodeValue::Item(..) => {
@@ -762,7 +1667,23 @@ impl<'a> VerseBuilder<'a> {
     }
 
     fn finalize(self) -> Verse {
-        Verse::new(self.label, self.paragraphs)
+        if let Some(max) = self.ctx.lint.as_ref().and_then(|lint| lint.max_verse_lines) {
+            let lines = self
+                .paragraphs
+                .iter()
+                .filter(|para| para.iter().any(|inline| !inline.is_break()))
+                .map(|para| para.iter().filter(|inline| inline.is_break()).count() + 1)
+                .sum();
+            if lines > max {
+                let kind = DiagKind::Lint(LintKind::VerseLines { lines, max });
+                self.ctx.report_diag(self.line.unwrap_or(1), kind);
+            }
+        }
+
+        let song_notation = self.ctx.xp().song_notation;
+        let mut verse = Verse::new(self.label, self.paragraphs, self.repeat_count);
+        verse.notation = self.notation.filter(|&nt| nt != song_notation);
+        verse
     }
 }
 
@@ -770,7 +1691,10 @@ impl<'a> VerseBuilder<'a> {
 struct SongBuilder<'a> {
     nodes: &'a [AstRef<'a>],
     title: String,
+    /// Source line of the title
+    line: usize,
     subtitles: Vec<BStr>,
+    metadata: Metadata,
     verse: Option<VerseBuilder<'a>>,
     blocks: Vec<Block>,
     verse_num: u32,
@@ -778,12 +1702,14 @@ struct SongBuilder<'a> {
 }
 
 impl<'a> SongBuilder<'a> {
-    fn new(nodes: &'a [AstRef<'a>], ctx: &'a ParserCtx<'a>) -> Self {
+    fn new(nodes: &'a [AstRef<'a>], source: &[&str], ctx: &'a ParserCtx<'a>) -> Self {
         // Read song title or use fallback
+        let line = nodes.first().map_or(1, |n| n.source_line());
         let (title, nodes) = match nodes.first() {
             Some(n) if n.is_h(1) => (n.as_plaintext(), &nodes[1..]),
             _ => (ctx.fallback_title.clone(), nodes),
         };
+        ctx.song_lines.borrow_mut().push(line);
 
         // Collect subtitles - H2s following the title (if any)
         let subtitles: Vec<_> = nodes
@@ -792,13 +1718,31 @@ impl<'a> SongBuilder<'a> {
             .map(|node| node.as_plaintext().into())
             .collect();
 
-        // Shift nodes to the song content
+        // Shift nodes past subtitles
         let nodes = &nodes[subtitles.len()..];
 
+        // Song metadata - a paragraph of !key: value lines (if any)
+        let (metadata, nodes) = match nodes.first() {
+            Some(n) if Self::is_metadata(n, source) => {
+                (Self::parse_metadata(n, source, ctx), &nodes[1..])
+            }
+            _ => (Metadata::new(), nodes),
+        };
+
+        ctx.xp_mut().song_start();
+        ctx.first_chord_xp.take();
+        ctx.key_directive.take();
+        ctx.key_declared.set(metadata.contains_key("key"));
+        if let Some(Value::String(key)) = metadata.get("key") {
+            ctx.xp_mut().set_key(key);
+        }
+
         Self {
             nodes,
             title,
+            line,
             subtitles,
+            metadata,
             verse: None,
             blocks: vec![],
             // xp: Transposition::new(ctx.config.notation, ctx.config.xp_disabled),
@@ -807,6 +1751,63 @@ impl<'a> SongBuilder<'a> {
         }
     }
 
+    /// Check the song against lint rules concerning the whole song, if linting.
+    fn lint(song: &Song, line: usize, ctx: &ParserCtx) {
+        let lint = match ctx.lint.as_ref() {
+            Some(lint) => lint,
+            None => return,
+        };
+
+        let span = ctx.locate(line, "# ", &song.title);
+        let report = |kind| ctx.report_diag_span(line, span, DiagKind::Lint(kind));
+        if lint.require_chords && !song.has_chords() {
+            report(LintKind::NoChords {
+                title: song.title.clone(),
+            });
+        }
+        if lint.require_subtitle && song.subtitles.is_empty() {
+            report(LintKind::NoSubtitle {
+                title: song.title.clone(),
+            });
+        }
+    }
+
+    /// A paragraph is considered song metadata if its first line is a `!key: value` line.
+    fn is_metadata(node: AstRef, source: &[&str]) -> bool {
+        node.is_p()
+            && source
+                .get(node.source_line() - 1)
+                .map_or(false, |line| METADATA_LINE.is_match(line.trim()))
+    }
+
+    /// Parse song metadata from source lines of the paragraph,
+    /// values are parsed as TOML values where possible, otherwise they're kept as strings.
+    fn parse_metadata(node: AstRef, source: &[&str], ctx: &ParserCtx) -> Metadata {
+        let pos = node.data.borrow().sourcepos;
+        let mut metadata = Metadata::new();
+
+        for num in pos.start.line..=pos.end.line {
+            let line = source.get(num - 1).map_or("", |line| line.trim());
+            let caps = match METADATA_LINE.captures(line) {
+                Some(caps) => caps,
+                None => {
+                    let text = line.into();
+                    ctx.report_diag(num, DiagKind::Metadata { text });
+                    continue;
+                }
+            };
+
+            let value = metadata_value(caps[2].trim());
+            if metadata.insert(caps[1].into(), value).is_some() {
+                let key = caps[1].into();
+                let span = ctx.locate(num, "!", &caps[1]);
+                ctx.report_diag_span(num, span, DiagKind::MetadataKey { key });
+            }
+        }
+
+        metadata
+    }
+
     fn next_verse_num(&mut self) -> u32 {
         self.verse_num += 1;
         self.verse_num
@@ -826,15 +1827,96 @@ impl<'a> SongBuilder<'a> {
         }
     }
 
+    /// Preformatted blocks are added between verses. When one is found within a verse,
+    /// the verse is finished and the rest of it continues as an unlabelled verse.
+    fn add_code_block(&mut self, node: AstRef) -> bool {
+        if let NodeValue::CodeBlock(cb) = &node.data.borrow().value {
+            self.verse_finalize();
+            self.blocks.push(Self::code_block(cb));
+            true
+        } else {
+            false
+        }
+    }
+
+    /// A paragraph whose first line is nothing but an image followed by more lines
+    /// is a standalone image, the rest of the paragraph is its caption.
+    /// An image without a caption is left as an inline one.
+    fn image_block(node: AstRef) -> Option<Block> {
+        let is_blank = |node: &AstRef| {
+            node.data
+                .borrow()
+                .value
+                .text()
+                .map_or(false, |text| text.trim().is_empty())
+        };
+
+        let mut children = node.children().skip_while(is_blank);
+        let img = children.next().filter(|c| c.is_img())?;
+        let mut rest = children.skip_while(is_blank);
+        if !rest.next()?.is_break() {
+            return None;
+        }
+
+        let caption = rest.fold(String::new(), |mut caption, c| {
+            if c.is_break() {
+                caption.push(' ');
+            } else {
+                caption.push_str(&c.as_plaintext());
+            }
+            caption
+        });
+
+        let image = match &img.data.borrow().value {
+            NodeValue::Image(link) => Image::new(
+                link.url.clone_bstr(),
+                img.as_plaintext().into(),
+                link.title.clone_bstr(),
+            ),
+            _ => unreachable!(),
+        };
+
+        let caption = caption.trim();
+        if caption.is_empty() {
+            return None;
+        }
+
+        Some(Block::Image {
+            image,
+            caption: caption.into(),
+        })
+    }
+
+    fn code_block(cb: &NodeCodeBlock) -> Block {
+        let text = cb.literal.clone_bstr();
+        let info = cb.info.trim();
+        if cb.fenced && info.split_whitespace().next() == Some("tab") {
+            Block::Tab { text }
+        } else {
+            Block::Pre {
+                text,
+                info: info.into(),
+            }
+        }
+    }
+
     fn parse_bq(&mut self, bq: AstRef, level: u32) {
         assert!(bq.is_bq());
 
+        if bq.children().next().is_none() {
+            self.ctx
+                .report_diag(bq.source_line(), DiagKind::EmptyVerse { chorus: true });
+        }
+
         let mut prev_bq = false;
+        let mut split = false;
         for c in bq.children() {
             if c.is_bq() {
                 self.verse_finalize();
                 self.parse_bq(c, level + 1);
                 prev_bq = true;
+            } else if self.add_code_block(c) {
+                split = true;
             } else {
                 if prev_bq {
                     self.verse_finalize();
@@ -842,7 +1924,12 @@ impl<'a> SongBuilder<'a> {
                 }
 
                 if self.verse.is_none() {
-                    let label = VerseLabel::Chorus(Some(level));
+                    // Don't count the rest of a chorus split by a preformatted block as another chorus
+                    let label = if split {
+                        VerseLabel::None {}
+                    } else {
+                        VerseLabel::Chorus(Some(level))
+                    };
                     let verse = VerseBuilder::new(label, self.ctx);
                     self.verse = Some(verse);
                 }
@@ -859,16 +1946,32 @@ impl<'a> SongBuilder<'a> {
             }
 
             match &node.data.borrow().value {
-                NodeValue::Paragraph => self.verse_mut().add_p_node(node),
+                NodeValue::Paragraph => match Self::image_block(node) {
+                    Some(block) => {
+                        self.verse_finalize();
+                        self.blocks.push(block);
+                    }
+                    None => self.verse_mut().add_p_node(node),
+                },
 
                 NodeValue::List(list) if matches!(list.list_type, ListType::Ordered) => {
                     for item in node.children() {
                         assert!(item.is_item());
                         self.verse_finalize();
 
+                        if item.children().next().is_none() {
+                            self.ctx.report_diag(
+                                item.source_line(),
+                                DiagKind::EmptyVerse { chorus: false },
+                            );
+                        }
                         let label = VerseLabel::Verse(self.next_verse_num());
-                        let verse = VerseBuilder::with_p_nodes(label, self.ctx, item.children());
-                        self.verse = Some(verse);
+                        self.verse = Some(VerseBuilder::new(label, self.ctx));
+                        for c in item.children() {
+                            if !self.add_code_block(c) {
+                                self.verse_mut().add_p_node(c);
+                            }
+                        }
                     }
                 }
 
@@ -886,17 +1989,20 @@ impl<'a> SongBuilder<'a> {
                 NodeValue::BlockQuote => self.parse_bq(node, 1),
 
                 NodeValue::Heading(h) if h.level >= 3 => {
-                    let label = VerseLabel::Custom(node.as_plaintext().into());
-                    self.verse = Some(VerseBuilder::new(label, self.ctx));
+                    self.verse = Some(VerseBuilder::with_heading(node, self.ctx));
+                }
+
+                NodeValue::Heading(..) => {
+                    let text = node.as_plaintext().into();
+                    self.ctx
+                        .report_diag(node.source_line(), DiagKind::SubtitleIgnored { text });
                 }
 
                 NodeValue::ThematicBreak => {
                     self.blocks.push(Block::HorizontalLine);
                 }
 
-                NodeValue::CodeBlock(cb) => self.blocks.push(Block::Pre {
-                    text: cb.literal.clone_bstr(),
-                }),
+                NodeValue::CodeBlock(cb) => self.blocks.push(Self::code_block(cb)),
 
                 NodeValue::HtmlBlock(..) => {
                     let mut inlines = vec![];
@@ -929,14 +2035,67 @@ impl<'a> SongBuilder<'a> {
             self.blocks.iter_mut().for_each(Block::remove_chorus_num);
         }
 
+        // Verify chorus references point to existing choruses
+        for (num, line) in self.ctx.chorus_refs.take() {
+            let num = num.filter(|_| max_chorus >= 2);
+            let exists = self.blocks.iter().any(|b| {
+                matches!(b, Block::Verse(Verse { label: VerseLabel::Chorus(n), .. }) if *n == num)
+            });
+            if !exists {
+                self.ctx.report_diag(line, DiagKind::ChorusRef { num });
+            }
+        }
+
+        // Verify verse references point to existing custom-labelled verses
+        for (label, line) in self.ctx.verse_refs.take() {
+            let exists = self.blocks.iter().any(|b| {
+                matches!(b, Block::Verse(Verse { label: VerseLabel::Custom(l), .. }) if *l == label)
+            });
+            if !exists {
+                self.ctx.report_diag(line, DiagKind::VerseRef { label });
+            }
+        }
+
+        // IDs are only made unique across the whole book in `Book::postprocess()`
+        let id = match self.metadata.get("id") {
+            Some(Value::String(id)) if !id.is_empty() => id.clone(),
+            Some(Value::String(..)) | None => Some(slugify(&self.title))
+                .filter(|slug| !slug.is_empty())
+                .unwrap_or_else(|| "song".to_string()),
+            Some(other) => other.to_string(),
+        };
+
+        if let Some(key) = self.ctx.key_directive.take() {
+            self.metadata
+                .insert("key".into(), Value::String(key.into()));
+        }
+
         let mut song = Song {
             title: self.title.into(),
+            id: id.into(),
             subtitles: self.subtitles.into(),
+            metadata: self.metadata,
+            key: None,
+            chapter: None,
             blocks: self.blocks,
-            notation: self.ctx.xp().src_notation,
+            footnotes: self.ctx.footnotes.take(),
+            notation: self.ctx.xp().song_notation,
         };
 
         song.postprocess();
+        song.key = song.detect_key();
+        Self::lint(&song, self.line, self.ctx);
+
+        // A key given in metadata is transposed along with the chords,
+        // a key inferred from the first chord already is.
+        let key_declared =
+            matches!(song.metadata.get("key"), Some(Value::String(key)) if !key.is_empty());
+        if let Some(xp) = self.ctx.first_chord_xp.take().filter(|_| key_declared) {
+            if let Some(key) = song.key.as_deref().and_then(|key| xp.transpose_key(key)) {
+                song.key = Some(key.into());
+            }
+        }
+
         song
     }
 }
@@ -982,12 +2141,21 @@ impl<'s, 'a> Iterator for SongsIter<'s, 'a> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq, Debug)]
+#[serde(default)]
 pub struct ParserConfig {
     pub notation: Notation,
     pub fallback_title: String,
     pub xp_disabled: bool,
     pub smart_punctuation: bool,
+    /// Whether warnings fail the parse as well as errors.
+    pub warnings_strict: bool,
+    /// Whether chords are checked to be recognized even when they're not transposed.
+    pub validate_chords: bool,
+    /// Lint rules to check, no linting is done if `None`.
+    pub lint: Option<LintConfig>,
+    /// Whether chords converted to the solfège notation are written with accents, ie. `Ré` rather than `Re`.
+    pub solfege_accents: bool,
 }
 
 impl ParserConfig {
@@ -997,6 +2165,10 @@ impl ParserConfig {
             fallback_title: FALLBACK_TITLE.into(),
             xp_disabled: false,
             smart_punctuation,
+            warnings_strict: false,
+            validate_chords: false,
+            lint: None,
+            solfege_accents: true,
         }
     }
 
@@ -1004,6 +2176,26 @@ impl ParserConfig {
         self.xp_disabled = xp_disabled;
         self
     }
+
+    pub fn warnings_strict(mut self, warnings_strict: bool) -> Self {
+        self.warnings_strict = warnings_strict;
+        self
+    }
+
+    pub fn validate_chords(mut self, validate_chords: bool) -> Self {
+        self.validate_chords = validate_chords;
+        self
+    }
+
+    pub fn lint(mut self, lint: LintConfig) -> Self {
+        self.lint = Some(lint);
+        self
+    }
+
+    pub fn solfege_accents(mut self, solfege_accents: bool) -> Self {
+        self.solfege_accents = solfege_accents;
+        self
+    }
 }
 
 impl Default for ParserConfig {
@@ -1013,6 +2205,10 @@ impl Default for ParserConfig {
             fallback_title: FALLBACK_TITLE.into(),
             xp_disabled: false,
             smart_punctuation: true,
+            warnings_strict: false,
+            validate_chords: false,
+            lint: None,
+            solfege_accents: true,
         }
     }
 }
@@ -1024,17 +2220,59 @@ struct ParserCtx<'d> {
     diag_sink: Box<dyn DiagSink + 'd>,
     error_seen: Cell<bool>,
     smart_punctuation: bool,
+    warnings_strict: bool,
+    validate_chords: bool,
+    lint: Option<LintConfig>,
+    /// Verse references in the current song along with their line numbers.
+    verse_refs: RefCell<Vec<(BStr, usize)>>,
+    /// Chorus references in the current song along with their line numbers.
+    chorus_refs: RefCell<Vec<(Option<u32>, usize)>>,
+    /// Footnotes in the current song.
+    footnotes: RefCell<Vec<Footnote>>,
+    /// Transposition in effect at the first chord of the current song, if any.
+    first_chord_xp: RefCell<Option<Transposition>>,
+    /// Whether the current song's key was declared, either in metadata or by a `!key:` directive.
+    key_declared: Cell<bool>,
+    /// Key declared by a `!key:` directive in the current song, if any.
+    key_directive: RefCell<Option<BStr>>,
+    /// Source lines, used to locate offending tokens in diagnostics.
+    source: Vec<String>,
+    /// Line and byte offset past the token located last by `locate()`.
+    cursor: Cell<(usize, usize)>,
+    /// Source lines of the titles of the songs parsed so far.
+    song_lines: RefCell<Vec<usize>>,
 }
 
 impl<'d> ParserCtx<'d> {
-    fn new(config: ParserConfig, input_file: &Path, diag_sink: Box<dyn DiagSink + 'd>) -> Self {
+    fn new(
+        config: ParserConfig,
+        input: &str,
+        input_file: &Path,
+        diag_sink: Box<dyn DiagSink + 'd>,
+    ) -> Self {
         Self {
             fallback_title: config.fallback_title,
-            xp: RefCell::new(Transposition::new(config.notation, config.xp_disabled)),
+            xp: RefCell::new(Transposition::new(
+                config.notation,
+                config.solfege_accents,
+                config.xp_disabled,
+            )),
             input_file: input_file.to_owned(),
             diag_sink,
             error_seen: Cell::new(false),
             smart_punctuation: config.smart_punctuation,
+            warnings_strict: config.warnings_strict,
+            validate_chords: config.validate_chords,
+            lint: config.lint,
+            verse_refs: RefCell::new(vec![]),
+            chorus_refs: RefCell::new(vec![]),
+            footnotes: RefCell::new(vec![]),
+            first_chord_xp: RefCell::new(None),
+            key_declared: Cell::new(false),
+            key_directive: RefCell::new(None),
+            source: input.lines().map(str::to_owned).collect(),
+            cursor: Cell::new((0, 0)),
+            song_lines: RefCell::new(vec![]),
         }
     }
 
@@ -1047,15 +2285,62 @@ impl<'d> ParserCtx<'d> {
     }
 
     fn report_diag(&self, line: usize, kind: DiagKind) {
-        if kind.is_error() {
+        self.report_diag_span(line, None, kind);
+    }
+
+    /// Declare the current song's key by a `!key:` directive,
+    /// the key may only be declared once per song.
+    fn declare_key(&self, key: &str, line: usize) {
+        if self.key_declared.replace(true) {
+            let kind = DiagKind::MetadataKey { key: "key".into() };
+            self.report_diag_span(line, self.locate(line, "!", "key"), kind);
+            return;
+        }
+
+        self.xp_mut().set_key(key);
+        *self.key_directive.borrow_mut() = Some(key.into());
+    }
+
+    /// Locate `token` in the source `line`, preferably where it follows `prefix`,
+    /// eg. a chord in backticks. The span covers only the `token`.
+    ///
+    /// Tokens are located in the order they're parsed: the search starts past the token located last
+    /// on the same line, so that eg. the second of two identical chords is found at its own position.
+    fn locate(&self, line: usize, prefix: &str, token: &str) -> Option<Span> {
+        if token.is_empty() {
+            return None;
+        }
+
+        let source = self.source.get(line.checked_sub(1)?)?;
+        let from = match self.cursor.get() {
+            (cursor_line, offset) if cursor_line == line => offset,
+            _ => 0,
+        };
+        let rest = &source[from..];
+        let start = from
+            + rest
+                .find(&format!("{}{}", prefix, token))
+                .map(|i| i + prefix.len())
+                .or_else(|| rest.find(token))?;
+        self.cursor.set((line, start + token.len()));
+
+        Some(Span {
+            column: source[..start].chars().count() + 1,
+            len: token.chars().count(),
+        })
+    }
+
+    fn report_diag_span(&self, line: usize, span: Option<Span>, kind: DiagKind) {
+        if kind.is_error() || self.warnings_strict {
             self.error_seen.set(true);
         }
 
-        self.diag_sink.report(Diagnostic {
-            file: self.input_file.clone(),
-            line,
-            kind,
-        });
+        let mut diag = Diagnostic::new(self.input_file.clone(), line, kind);
+        let source_line = line.checked_sub(1).and_then(|i| self.source.get(i));
+        if let (Some(span), Some(source_line)) = (span, source_line) {
+            diag = diag.with_span(span, source_line.as_str());
+        }
+        self.diag_sink.report(diag);
     }
 
     fn diag_result<T>(&self, value: T) -> Result<T, ()> {
@@ -1091,7 +2376,7 @@ impl<'i, 'd> Parser<'i, 'd> {
         config: ParserConfig,
         diagnostic_sink: impl DiagSink + 'd,
     ) -> Self {
-        let ctx = ParserCtx::new(config, input_file, Box::new(diagnostic_sink));
+        let ctx = ParserCtx::new(config, input, input_file, Box::new(diagnostic_sink));
         Self { input, ctx }
     }
 
@@ -1108,19 +2393,23 @@ impl<'i, 'd> Parser<'i, 'd> {
 
     /// Verify input doesn't contain disallowed control chars,
     /// which are all of them except LF, TAB, and CR.
-    fn check_control_chars(&mut self) -> Result<()> {
+    /// Reports control characters as errors. Parsing carries on afterwards,
+    /// so that the other errors in the input are reported too.
+    fn check_control_chars(&mut self) {
         for (num, line) in self.input.lines().enumerate() {
-            for c in line.chars() {
+            for (i, c) in line.chars().enumerate() {
                 // The Lines iterator already takes care of \n and \r,
                 // only need to check for \t here:
                 if c.is_control() && c != '\t' {
-                    self.ctx
-                        .report_diag(num + 1, DiagKind::ControlChar { char: c as u32 });
+                    let span = Span {
+                        column: i + 1,
+                        len: 1,
+                    };
+                    let kind = DiagKind::ControlChar { char: c as u32 };
+                    self.ctx.report_diag_span(num + 1, Some(span), kind);
                 }
             }
         }
-
-        self.ctx.diag_result(())
     }
 
     /// Parsing is done in four steps:
@@ -1147,24 +2436,90 @@ impl<'i, 'd> Parser<'i, 'd> {
     /// See the `book` module where the bard AST is defined.
     #[allow(clippy::result_unit_err)]
     pub fn parse(&mut self) -> Result<Vec<Song>> {
-        self.check_control_chars()?;
+        self.check_control_chars();
 
         let arena = Arena::new();
         let config = Self::comrak_config(self.ctx.smart_punctuation);
         let root = comrak::parse_document(&arena, self.input, &config);
         let root_elems: Vec<_> = root.children().collect();
+        let source: Vec<_> = self.input.lines().collect();
         let songs_iter = SongsIter::new(&root_elems);
         let songs = Vec::with_capacity(songs_iter.size_hint().0);
         let songs = songs_iter.fold(songs, |mut songs, nodes| {
-            nodes.iter().for_each(|node| node.preprocess(&arena));
+            nodes.iter().for_each(|node| {
+                node.join_continued_lines(&source);
+                node.preprocess(&arena);
+            });
 
-            let song = SongBuilder::new(nodes, &self.ctx);
+            let song = SongBuilder::new(nodes, &source, &self.ctx);
             songs.push(song.parse().finalize());
             songs
         });
 
         self.ctx.diag_result(songs)
     }
+
+    /// Source lines of the titles of the songs parsed, in the same order as the songs.
+    /// For a song without a title, this is the line where the song starts.
+    pub fn song_lines(&self) -> Vec<usize> {
+        self.ctx.song_lines.borrow().clone()
+    }
+}
+
+/// Parse a bard Markdown `input` into songs.
+///
+/// This is a convenience wrapper around `Parser` for use as a library.
+/// Diagnostics are reported with the [`INPUT_NAME`] placeholder as the file name.
+/// Warnings are only returned as part of `ParseError`, use `Parser` directly
+/// with a custom `DiagSink` to also receive warnings on success.
+///
+/// ```
+/// use bard::{Block, ParserConfig, VerseLabel};
+///
+/// let songs = bard::parse("# Song\n\n1. First verse.", ParserConfig::default()).unwrap();
+/// assert_eq!(&*songs[0].title, "Song");
+///
+/// let Block::Verse(verse) = &songs[0].blocks[0] else {
+///     panic!("Expected a verse");
+/// };
+/// assert_eq!(verse.label, VerseLabel::Verse(1));
+/// ```
+///
+/// Errors carry source location:
+///
+/// ```
+/// use bard::ParserConfig;
+///
+/// let err = bard::parse("# Song\n\nBad \u{7}char", ParserConfig::default()).unwrap_err();
+/// let diag = err.errors().next().unwrap();
+/// assert_eq!(diag.line, 3);
+/// ```
+pub fn parse(input: &str, config: ParserConfig) -> Result<Vec<Song>, ParseError> {
+    parse_songs(input, INPUT_NAME, config)
+}
+
+/// Like [`parse()`], but diagnostics carry the `filename` passed in.
+///
+/// ```
+/// use bard::ParserConfig;
+///
+/// let err = bard::parse_songs("# Song\n\nBad \u{7}char", "song.md", ParserConfig::default())
+///     .unwrap_err();
+/// let diag = err.errors().next().unwrap();
+/// assert_eq!(diag.file.to_str(), Some("song.md"));
+/// assert_eq!(diag.line, 3);
+/// assert_eq!(diag.kind, bard::DiagKind::ControlChar { char: 7 });
+/// ```
+pub fn parse_songs(
+    input: &str,
+    filename: impl AsRef<Path>,
+    config: ParserConfig,
+) -> Result<Vec<Song>, ParseError> {
+    let (tx, rx) = mpsc::channel();
+    let mut parser = Parser::new(input, filename.as_ref(), config, tx);
+    let res = parser.parse();
+    let diagnostics = rx.try_iter().collect();
+    res.map_err(|_| ParseError { diagnostics })
 }
 
 #[cfg(test)]
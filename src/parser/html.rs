@@ -6,6 +6,9 @@
 //!
 //! The HTML isn't validate at all, not even matching of tags,
 //! the tags are really just a way to call inlines.
+//!
+//! The exception is the `<tex code="..."/>` tag, which carries raw TeX code
+//! and is turned into an `Inline::Raw`.
 
 use html5ever::buffer_queue::BufferQueue;
 use html5ever::tokenizer::{
@@ -16,10 +19,10 @@ use super::{DiagKind, ParserCtx};
 use crate::book::{HtmlTag, Inline};
 use crate::util::BStr;
 
-pub static RESERVED_TAGS: &[&str] = &["html", "tex"];
+pub static RESERVED_TAGS: &[&str] = &["html"];
 
 struct Sink<'c> {
-    inlines: Vec<HtmlTag>,
+    inlines: Vec<Inline>,
     start_line: usize,
     text_buffer: String,
     text_start_line: usize,
@@ -39,12 +42,17 @@ impl<'c> Sink<'c> {
 
     fn append_tag(&mut self, tag: Tag, line_num: u64) {
         if RESERVED_TAGS.contains(&tag.name.to_ascii_lowercase().as_ref()) {
-            self.ctx.report_diag(
-                line_num as _,
-                DiagKind::HtmlReservedTag {
-                    tag: tag.name.to_string().into(),
-                },
-            );
+            let kind = DiagKind::HtmlReservedTag {
+                tag: tag.name.to_string().into(),
+            };
+            let line = line_num as _;
+            self.ctx
+                .report_diag_span(line, self.ctx.locate(line, "<", &tag.name), kind);
+            return;
+        }
+
+        if tag.name.eq_ignore_ascii_case("tex") {
+            self.append_raw_tex(tag, line_num);
             return;
         }
 
@@ -66,7 +74,21 @@ impl<'c> Sink<'c> {
             .collect();
 
         let tag = HtmlTag { name, attrs };
-        self.inlines.push(tag);
+        self.inlines.push(Inline::HtmlTag(tag));
+    }
+
+    fn append_raw_tex(&mut self, tag: Tag, line_num: u64) {
+        if tag.kind == TagKind::EndTag {
+            // The code is in the attribute, end tags have no meaning
+            return;
+        }
+
+        match tag.attrs.iter().find(|attr| &*attr.name.local == "code") {
+            Some(code) => self.inlines.push(Inline::Raw {
+                tex: code.value.to_string().into(),
+            }),
+            None => self.ctx.report_diag(line_num as _, DiagKind::RawTexCode),
+        }
     }
 
     fn append_text(&mut self, text: &str, line_num: usize) {
@@ -97,8 +119,7 @@ impl<'c> Sink<'c> {
 
     fn finalize(mut self, target: &mut Vec<Inline>) {
         self.ignored_text_warn();
-        target.reserve(self.inlines.len());
-        target.extend(self.inlines.drain(..).map(Inline::HtmlTag));
+        target.append(&mut self.inlines);
     }
 }
 
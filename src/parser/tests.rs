@@ -89,12 +89,27 @@ fn song(
         .into_iter()
         .map(|s| Json::String(s.into()))
         .collect();
+    let blocks: Vec<_> = blocks.into_iter().collect();
+
+    // The key is inferred from the first chord of the first verse with chords
+    let key = blocks
+        .iter()
+        .filter(|b| b["type"] == "b-verse")
+        .flat_map(|b| b["paragraphs"].as_array().unwrap())
+        .flat_map(|p| p.as_array().unwrap())
+        .find(|i| i["type"] == "i-chord")
+        .map_or(Null, |i| i["chord"].clone());
 
     json!({
         "title": title.as_ref(),
+        "id": slugify(title.as_ref()),
         "subtitles": subtitles,
+        "metadata": {},
+        "key": key,
+        "chapter": null,
         "notation": notation.as_ref(),
-        "blocks": blocks.into_iter().collect::<Vec<_>>(),
+        "blocks": blocks,
+        "footnotes": [],
     })
 }
 
@@ -103,9 +118,21 @@ fn b_verse(typ: &str, label: impl Serialize, paras: impl IntoIterator<Item = Jso
         "type": "b-verse",
         "label": { typ: label },
         "paragraphs": paras.into_iter().collect::<Vec<_>>(),
+        "repeat_count": Null,
+        "notation": Null,
     })
 }
 
+fn repeat(mut verse: Json, count: u32) -> Json {
+    verse["repeat_count"] = count.into();
+    verse
+}
+
+fn notation(mut verse: Json, notation: &str) -> Json {
+    verse["notation"] = notation.into();
+    verse
+}
+
 fn ver_verse(label: u32, paras: impl IntoIterator<Item = Json>) -> Json {
     b_verse("verse", label, paras)
 }
@@ -137,13 +164,27 @@ fn b_hr() -> Json {
     json!({"type": "b-horizontal-line"})
 }
 
-fn b_pre(text: &str) -> Json {
+fn b_pre(text: &str, info: &str) -> Json {
     json!({
         "type": "b-pre",
         "text": text,
+        "info": info,
+    })
+}
+
+fn b_tab(text: &str) -> Json {
+    json!({
+        "type": "b-tab",
+        "text": text,
     })
 }
 
+fn b_image(path: &str, title: &str, class: &str, caption: &str) -> Json {
+    let mut image = i_image(path, title, class);
+    image.as_object_mut().unwrap().remove("type");
+    json!({ "type": "b-image", "image": image, "caption": caption })
+}
+
 fn b_html(inlines: impl IntoIterator<Item = Json>) -> Json {
     json!({
         "type": "b-html-block",
@@ -202,9 +243,15 @@ fn i_chord(
         "backticks": backticks,
         "baseline": inlines.baseline(),
         "inlines": inlines.inlines(),
+        "chords_only": false,
     })
 }
 
+fn chords_only(mut chord: Json) -> Json {
+    chord["chords_only"] = true.into();
+    chord
+}
+
 fn i_strong(inlines: impl IntoIterator<Item = Json>) -> Json {
     json!({ "type": "i-strong", "inlines": inlines.into_iter().collect::<Vec<_>>() })
 }
@@ -217,10 +264,26 @@ fn i_xpose(typ: &str, value: impl Serialize) -> Json {
     json!({ "type": "i-transpose", typ: value })
 }
 
+fn i_footnote(num: u32, inlines: impl IntoIterator<Item = Json>) -> Json {
+    json!({ "type": "i-footnote", "num": num, "inlines": inlines.into_iter().collect::<Vec<_>>() })
+}
+
 fn i_chorus_ref(num: impl Serialize, prefix_space: &str) -> Json {
     json!({ "type": "i-chorus-ref", "num": num, "prefix_space": prefix_space })
 }
 
+fn i_verse_ref(label: &str, prefix_space: &str) -> Json {
+    json!({ "type": "i-verse-ref", "label": label, "prefix_space": prefix_space })
+}
+
+fn i_repeat_start() -> Json {
+    json!({ "type": "i-repeat-start" })
+}
+
+fn i_repeat_end(count: impl Serialize) -> Json {
+    json!({ "type": "i-repeat-end", "count": count })
+}
+
 fn i_link(text: &str, url: &str, title: &str) -> Json {
     json!({
         "type": "i-link",
@@ -451,6 +514,36 @@ Sailing round the ``` D ```sea.
     ]));
 }
 
+#[test]
+fn parse_line_continuation() {
+    let input = r#"
+# Song
+1. Some word\
+next line
+"#;
+    parse_one_para(input).assert_json_eq(json!([i_text("Some word next line")]));
+
+    let input = r#"
+# Song
+1. `G`Sailing round\
+the `D`sea.
+Sailing round the sea.
+"#;
+    parse_one_para(input).assert_json_eq(json!([
+        i_chord("G", Null, 1, [i_text("Sailing round the ")]),
+        i_chord("D", Null, 1, [i_text("sea.")]),
+        i_break(),
+        i_text("Sailing round the sea."),
+    ]));
+
+    let input = r#"
+# Song
+1. \
+Lyrics
+"#;
+    parse_one_para(input).assert_json_eq(json!([i_break(), i_text("Lyrics")]));
+}
+
 #[test]
 fn parse_chords_baseline() {
     let input = r#"
@@ -471,6 +564,95 @@ fn parse_chords_baseline() {
     ]));
 }
 
+#[test]
+fn parse_chords_only() {
+    let input = r#"
+# Song
+1. `Am` `F` `C_` ``G``
+Lyrics `Am`here
+`D` `E`
+`D` text
+"#;
+    parse_one_para(input).assert_json_eq(json!([
+        chords_only(i_chord("Am", Null, 1, [i_text(" ")])),
+        chords_only(i_chord("F", Null, 1, [i_text(" ")])),
+        chords_only(i_chord("C", Null, 1, Baseline)),
+        i_text(" "),
+        chords_only(i_chord("G", Null, 2, Vec::<Json>::new())),
+        i_break(),
+        i_text("Lyrics "),
+        i_chord("Am", Null, 1, [i_text("here")]),
+        i_break(),
+        chords_only(i_chord("D", Null, 1, [i_text(" ")])),
+        chords_only(i_chord("E", Null, 1, Vec::<Json>::new())),
+        i_break(),
+        i_chord("D", Null, 1, [i_text(" text")]),
+    ]));
+}
+
+#[test]
+fn parse_repeat_signs() {
+    let input = r#"
+# Song
+1. |: `G`Sailing round the `D`sea :|
+Oh my darling |x2
+|: `Am` `C` :| x3
+Not a sign: a|b x|2 |:| and x2 |x2 here
+"#;
+    parse_one_para(input).assert_json_eq(json!([
+        i_repeat_start(),
+        i_chord("G", Null, 1, [i_text("Sailing round the ")]),
+        i_chord("D", Null, 1, [i_text("sea")]),
+        i_repeat_end(Null),
+        i_break(),
+        i_repeat_start(),
+        i_text("Oh my darling"),
+        i_repeat_end(2),
+        i_break(),
+        i_repeat_start(),
+        chords_only(i_chord("Am", Null, 1, [i_text(" ")])),
+        chords_only(i_chord("C", Null, 1, Vec::<Json>::new())),
+        i_repeat_end(3),
+        i_break(),
+        i_text("Not a sign: a|b x|2 |:| and x2 |x2 here"),
+    ]));
+}
+
+#[test]
+fn parse_repeat_signs_mismatched() {
+    let input = r#"
+# Song
+1. |: one |: two :| three :|
+
+2. four :|
+five |:
+"#;
+
+    let (res, diag) = try_parse(input, false);
+    let songs = res.unwrap();
+    songs[0].get_verse(0).paragraphs[0].assert_json_eq(json!([
+        i_repeat_start(),
+        i_text("one"),
+        i_repeat_start(),
+        i_text("two"),
+        i_repeat_end(Null),
+        i_text("three"),
+        i_repeat_end(Null),
+    ]));
+
+    assert!(diag.iter().all(|d| !d.is_error()));
+    let kinds: Vec<_> = diag.iter().map(|d| (d.line, d.kind.clone())).collect();
+    assert_eq!(
+        kinds,
+        [
+            (3, DiagKind::RepeatNested),
+            (3, DiagKind::RepeatUnmatched { sign: ":|".into() }),
+            (5, DiagKind::RepeatUnmatched { sign: ":|".into() }),
+            (5, DiagKind::RepeatUnmatched { sign: "|:".into() }),
+        ]
+    );
+}
+
 #[test]
 fn parse_inlines() {
     let input = r#"
@@ -493,6 +675,48 @@ Sailing_ round the `D`sea.**
     ]));
 }
 
+#[test]
+fn parse_nested_formatting() {
+    let input = "# Song\n1. ***bold italic*** text\n";
+    parse_one_para(input).assert_json_eq(json!([
+        i_emph([i_strong([i_text("bold italic")])]),
+        i_text(" text"),
+    ]));
+
+    // Chords at the start or at the end of formatting don't leave empty inlines behind
+    let input = "# Song\n1. ***`G`bold italic*** and ***more `C`***\n";
+    parse_one_para(input).assert_json_eq(json!([
+        i_chord(
+            "G",
+            Null,
+            1,
+            [
+                i_emph([i_strong([i_text("bold italic")])]),
+                i_text(" and "),
+                i_emph([i_strong([i_text("more ")])]),
+            ]
+        ),
+        i_chord("C", Null, 1, Vec::<Json>::new()),
+    ]));
+
+    // Emphasis spanning a chord and a line break
+    let input = "# Song\n1. _a `C`b\nc `D`d_\n";
+    parse_one_para(input).assert_json_eq(json!([
+        i_emph([i_text("a ")]),
+        i_chord("C", Null, 1, [i_emph([i_text("b")])]),
+        i_break(),
+        i_emph([i_text("c ")]),
+        i_chord("D", Null, 1, [i_emph([i_text("d")])]),
+    ]));
+
+    // Emphasis can't span paragraphs, the delimiters are kept as text
+    let input = "# Song\n> _open\n>\n> close_ text\n";
+    parse_one(input).blocks.assert_json_eq(json!([ver_chorus(
+        Null,
+        [p([i_text("_open")]), p([i_text("close_ text")])]
+    )]));
+}
+
 #[test]
 fn parse_extensions() {
     let input = r#"
@@ -516,6 +740,18 @@ fn parse_extensions() {
 !> First on the line.
 Mixed !>> in text.
 
+# Song three
+
+1. Go to !>Bridge
+!>"Middle eight" and !>“Middle eight” again.
+Not references: !!>Bridge !>>Bridge
+
+### Bridge
+Bridge lyrics.
+
+### Middle eight
+Middle eight lyrics.
+
 "#;
 
     let songs = parse(input, true);
@@ -560,6 +796,80 @@ Mixed !>> in text.
             ])]
         ),
     ]));
+
+    songs[2].blocks.assert_json_eq(json!([
+        ver_verse(
+            1,
+            [p([
+                i_text("Go to"),
+                i_verse_ref("Bridge", " "),
+                i_break(),
+                i_verse_ref("Middle eight", ""),
+                i_text(" and"),
+                i_verse_ref("Middle eight", " "),
+                i_text(" again."),
+                i_break(),
+                i_text("Not references: !!>Bridge !>>Bridge"),
+            ])]
+        ),
+        ver_custom("Bridge", [p([i_text("Bridge lyrics.")])]),
+        ver_custom("Middle eight", [p([i_text("Middle eight lyrics.")])]),
+    ]));
+}
+
+#[test]
+fn verse_ref_error() {
+    let input = r#"
+# Song
+
+### Bridge
+Bridge lyrics.
+
+1. Go to !>Bridge
+Go to !>Chorus
+"#;
+
+    let (res, diag) = try_parse(input, false);
+    res.unwrap_err();
+
+    let [diag]: [_; 1] = diag.try_into().unwrap();
+    assert!(diag.is_error());
+    assert_eq!(diag.file.as_os_str(), "<test>");
+    assert_eq!(diag.line, 8);
+    assert_eq!(
+        diag.kind,
+        DiagKind::VerseRef {
+            label: "Chorus".into()
+        }
+    );
+}
+
+#[test]
+fn chorus_ref_warning() {
+    let input = r#"
+# Song
+
+> Chorus one.
+
+>> Chorus two.
+
+1. Go to !>>
+Go to !>>>
+
+# Song two
+
+1. Go to !>
+"#;
+
+    let (res, diag) = try_parse(input, false);
+    res.unwrap();
+
+    let [diag1, diag2]: [_; 2] = diag.try_into().unwrap();
+    assert!(!diag1.is_error());
+    assert_eq!(diag1.line, 9);
+    assert_eq!(diag1.kind, DiagKind::ChorusRef { num: Some(3) });
+    assert_eq!(diag2.line, 13);
+    assert_eq!(diag2.kind, DiagKind::ChorusRef { num: None });
 }
 
 #[test]
@@ -606,103 +916,515 @@ Yippie yea `Bm`yay!
 }
 
 #[test]
-fn transposition_error() {
+fn transposition_solfege() {
     let input = r#"
 # Song
 
 !+5
+!!english
+
+> `Sim`Yippie yea `Ré`oh! !+0
+!+0 Yippie yea `Sim`yay!
+
+!!none
+
+1. `Sim`Yippie yea `Re`oh! !+0
+Yippie yea `sim`yay!
 
-> 1. `Bm`Yippie yea `D`oh!
-Yippie yea `X`yay!
-Yippie yea `Y`yay!
 "#;
 
-    let (res, diag) = try_parse(input, false);
-    res.unwrap_err();
+    let config = ParserConfig::new(Notation::Solfege, true);
+    let song = TetsParser::new(input, config).parse_one();
+    assert_eq!(song.notation, Notation::Solfege);
+    song.blocks.assert_json_eq(json!([
+        ver_chorus(
+            Null,
+            [p([
+                i_chord("Mim", "Bm", 1, [i_text("Yippie yea ")]),
+                i_chord("Sol", "D", 1, [i_text("oh!")]),
+                i_break(),
+                i_text("Yippie yea "),
+                i_chord("Sim", "Bm", 1, [i_text("yay!")]),
+            ])]
+        ),
+        ver_verse(
+            1,
+            [p([
+                i_chord("Sim", Null, 1, [i_text("Yippie yea ")]),
+                i_chord("Ré", Null, 1, [i_text("oh!")]),
+                i_break(),
+                i_text("Yippie yea "),
+                i_chord("sim", Null, 1, [i_text("yay!")]),
+            ])]
+        )
+    ]));
 
-    assert!(diag[0].is_error());
-    assert_eq!(diag[0].file.as_os_str(), "<test>");
-    assert_eq!(diag[0].line, 7);
-    assert_eq!(diag[0].kind, DiagKind::Transposition { chord: "X".into() });
+    // Conversion to solfège, without accents
+    let input = "# Song\n\n!solfege\n\n1. `D`One `Am`two.\n";
+    let config = ParserConfig::new(Notation::English, true).solfege_accents(false);
+    let song = TetsParser::new(input, config).parse_one();
+    song.blocks.assert_json_eq(json!([notation(
+        ver_verse(
+            1,
+            [p([
+                i_chord("Re", Null, 1, [i_text("One ")]),
+                i_chord("Lam", Null, 1, [i_text("two.")]),
+            ])]
+        ),
+        "solfege"
+    )]));
 
-    assert!(diag[1].is_error());
-    assert_eq!(diag[1].file.as_os_str(), "<test>");
-    assert_eq!(diag[1].line, 8);
-    assert_eq!(diag[1].kind, DiagKind::Transposition { chord: "Y".into() });
+    // Chords in another notation are an error
+    let input = "# Song\n\n!+2\n\n1. `Do`One `G`two.\n";
+    let config = ParserConfig::new(Notation::Solfege, true);
+    let (res, diag) = TetsParser::new(input, config).parse();
+    res.unwrap_err();
+    assert_eq!(diag.len(), 1);
+    assert_eq!(diag[0].kind, DiagKind::Transposition { chord: "G".into() });
 }
 
 #[test]
-fn parse_verse_numbering() {
-    let input = r#"
-# Song 1
+fn transposition_accidentals() {
+    let song_chords = |song: &Song| -> Vec<(String, Option<String>)> {
+        song.blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Verse(verse) => Some(verse),
+                _ => None,
+            })
+            .flat_map(|verse| verse.paragraphs.iter().flat_map(|p| p.iter()))
+            .filter_map(|inline| match inline {
+                Inline::Chord(chord) => Some((
+                    chord.chord.to_string(),
+                    chord.alt_chord.as_ref().map(|alt| alt.to_string()),
+                )),
+                _ => None,
+            })
+            .collect()
+    };
+    let chords = |input: &str| song_chords(&parse_one(input));
+    let main = |input: &str| -> Vec<String> {
+        chords(input).into_iter().map(|(chord, _)| chord).collect()
+    };
+
+    // Without a key the spelling follows the direction, unless set by a marker, for the chords following it
+    let input = "# Song\n\n!+1\n\n1. `A`One `C#m`two `F`three.\n\n!b\n\n2. `A`One `F`two.\n\n!#\n\n3. `C`One `F#`two.\n";
+    assert_eq!(main(input), ["A#", "Dm", "F#", "Bb", "Gb", "C#", "G"]);
+    assert_eq!(
+        main("# Song\n\n!-1\n\n1. `A`One `G`two `C`three.\n"),
+        ["Ab", "Gb", "B"]
+    );
+    assert_eq!(
+        main("# Song\n\n!german\n\n1. `C#`One `Eb`two.\n"),
+        ["C#", "Eb"]
+    );
 
-1. Verse 1.
-> Chorus 1.
-1. Verse 2.
->> Chorus 2.
-1. Verse 3.
+    // The marker only applies until the end of the song
+    let input = "# One\n\n!-1 !#\n\n1. `A`One.\n\n# Two\n\n1. `A`One.\n";
+    let songs = parse(input, false);
+    assert_eq!(song_chords(&songs[0]), [("G#".to_string(), None)]);
+    assert_eq!(song_chords(&songs[1]), [("Ab".to_string(), None)]);
 
-# Song 2
+    // Flat target keys use flats, sharp target keys use sharps, C major and A minor the mixed spelling
+    assert_eq!(
+        main("# Song\n!key: D\n\n!-1\n\n1. `D`One `F#m`two `A`three.\n"),
+        ["Db", "Fm", "Ab"]
+    );
+    assert_eq!(
+        main("# Song\n!key: Em\n\n!+1\n\n1. `Em`One `C`two `B7`three.\n"),
+        ["Fm", "Db", "C7"]
+    );
+    assert_eq!(
+        main("# Song\n!key: C\n\n!-1\n\n1. `C`One `A`two `D`three.\n"),
+        ["B", "G#", "C#"]
+    );
+    assert_eq!(
+        main("# Song\n!key: Bb\n\n!-1\n\n1. `Bb`One `Eb`two `F`three.\n"),
+        ["A", "D", "E"]
+    );
+    assert_eq!(
+        main("# Song\n!key: G\n\n!+2\n\n1. `G`One `C#`two.\n"),
+        ["A", "D#"]
+    );
+    assert_eq!(
+        main("# Song\n!key: Dm\n\n!+2\n\n1. `Dm`One `C#`two.\n"),
+        ["Em", "D#"]
+    );
 
-1. Verse 1.
-2. Verse 2.
-> Chorus.
->> Chorus two.
-3. Verse 3.
-3. Verse 3.
-"#;
+    // A marker takes precedence over the key
+    assert_eq!(
+        main("# Song\n!key: D\n\n!-1 !#\n\n1. `D`One `F#m`two `A`three.\n"),
+        ["C#", "Fm", "G#"]
+    );
 
-    let songs = parse(input, true);
+    // Alt chords follow their own target key, or the marker
+    assert_eq!(
+        chords("# Song\n!key: C\n\n!!-1\n\n1. `A`One `Eb`two.\n"),
+        [
+            ("A".to_string(), Some("G#".to_string())),
+            ("Eb".to_string(), Some("D".to_string())),
+        ]
+    );
+    assert_eq!(
+        chords("# Song\n!key: C\n\n!!-1 !b\n\n1. `A`One.\n"),
+        [("A".to_string(), Some("Ab".to_string()))]
+    );
 
-    assert_eq!(songs[0].get_verse(0).label, VerseLabel::Verse(1));
-    assert_eq!(songs[0].get_verse(2).label, VerseLabel::Verse(2));
-    assert_eq!(songs[0].get_verse(4).label, VerseLabel::Verse(3));
+    // German B and H are kept
+    assert_eq!(
+        main("# Song\n!key: D\n\n!-1 !german\n\n1. `D`One `Bm`two `C`three `Eb`four.\n"),
+        ["Db", "Bm", "H", "D"]
+    );
 
-    assert_eq!(songs[1].get_verse(0).label, VerseLabel::Verse(1));
-    assert_eq!(songs[1].get_verse(1).label, VerseLabel::Verse(2));
-    assert_eq!(songs[1].get_verse(4).label, VerseLabel::Verse(3));
-    assert_eq!(songs[1].get_verse(5).label, VerseLabel::Verse(4));
+    // The transposed key follows the spelling too
+    let song = parse_one("# Song\n!key: Eb\n\n!-1\n\n1. `Eb`One.\n");
+    assert_eq!(song.key.as_deref(), Some("D"));
+    let song = parse_one("# Song\n!key: A\n\n!+1\n\n1. `A`One.\n");
+    assert_eq!(song.key.as_deref(), Some("Bb"));
+    let song = parse_one("# Song\n!key: E\n\n!+2 !#\n\n1. `E`One.\n");
+    assert_eq!(song.key.as_deref(), Some("F#"));
 }
 
 #[test]
-fn parse_bullet_list() {
+fn transposition_src_notation() {
     let input = r#"
 # Song
 
-- Item 1
-- Item 2
+!+2
 
-1. First verse.
+1. `G`Verse one.
 
-* Item 3
-* Item 4
-"#;
+!=nashville
 
-    parse_one(input).assert_json_eq(song(
-        "Song",
-        [],
-        "english",
-        [
-            b_bullet_list(["Item 1", "Item 2"]),
-            ver_verse(1, [p([i_text("First verse.")])]),
-            b_bullet_list(["Item 3", "Item 4"]),
-        ],
-    ));
-}
+### Instrumental
+`1`One `4`four `5`five.
 
-#[test]
-fn parse_hr() {
-    let input = r#"
-# Song
+!=default
 
-1. First verse.
+2. `C`Verse two.
 
----
+# Song two
 
-2. Second verse.
-"#;
+!english
+!=german
 
-    parse_one(input).assert_json_eq(song(
+1. `H`Verse one.
+
+# Song three
+
+1. `C`Back to the song's notation.
+"#;
+
+    let songs = parse(input, false);
+
+    songs[0].blocks.assert_json_eq(json!([
+        ver_verse(1, [p([i_chord("A", Null, 1, [i_text("Verse one.")])])]),
+        notation(
+            ver_custom(
+                "Instrumental",
+                [p([
+                    i_chord("2", Null, 1, [i_text("One ")]),
+                    i_chord("5", Null, 1, [i_text("four ")]),
+                    i_chord("6", Null, 1, [i_text("five.")]),
+                ])]
+            ),
+            "nashville"
+        ),
+        ver_verse(2, [p([i_chord("D", Null, 1, [i_text("Verse two.")])])]),
+    ]));
+
+    // Output notation is english, so the verse notation doesn't differ from the song's
+    songs[1].blocks.assert_json_eq(json!([ver_verse(
+        1,
+        [p([i_chord("C#", Null, 1, [i_text("Verse one.")])])]
+    )]));
+
+    // Source notation is reset at the start of each song
+    songs[2].blocks.assert_json_eq(json!([ver_verse(
+        1,
+        [p([i_chord(
+            "D",
+            Null,
+            1,
+            [i_text("Back to the song's notation.")]
+        )])]
+    )]));
+
+    let songs = parse(input, true);
+    songs[0].blocks.assert_json_eq(json!([
+        ver_verse(
+            1,
+            [
+                p([i_chord("G", Null, 1, [i_text("Verse one.")])]),
+                p([i_xpose("t-src-notation", "nashville")]),
+            ]
+        ),
+        ver_custom(
+            "Instrumental",
+            [
+                p([
+                    i_chord("1", Null, 1, [i_text("One ")]),
+                    i_chord("4", Null, 1, [i_text("four ")]),
+                    i_chord("5", Null, 1, [i_text("five.")]),
+                ]),
+                p([i_xpose("t-src-notation", Null)]),
+            ]
+        ),
+        ver_verse(2, [p([i_chord("C", Null, 1, [i_text("Verse two.")])])]),
+    ]));
+}
+
+#[test]
+fn transposition_error() {
+    let input = r#"
+# Song
+
+!+5
+
+> 1. `Bm`Yippie yea `D`oh!
+Yippie yea `X`yay!
+Yippie yea `Y`yay!
+"#;
+
+    let (res, diag) = try_parse(input, false);
+    res.unwrap_err();
+
+    assert!(diag[0].is_error());
+    assert_eq!(diag[0].file.as_os_str(), "<test>");
+    assert_eq!(diag[0].line, 7);
+    assert_eq!(diag[0].kind, DiagKind::Transposition { chord: "X".into() });
+
+    assert!(diag[1].is_error());
+    assert_eq!(diag[1].file.as_os_str(), "<test>");
+    assert_eq!(diag[1].line, 8);
+    assert_eq!(diag[1].kind, DiagKind::Transposition { chord: "Y".into() });
+}
+
+#[test]
+fn transposition_error_span() {
+    let input = r#"
+# Song
+
+!+5
+
+> 1. `Bm`Yippie yea `D`oh!
+Yippie yea `X`yay!
+Yippie yea `Y`yay!
+"#;
+
+    let (res, diag) = try_parse(input, false);
+    res.unwrap_err();
+
+    assert_eq!(diag[0].span, Some(Span { column: 13, len: 1 }));
+    assert_eq!(diag[1].span, Some(Span { column: 13, len: 1 }));
+    assert_eq!(
+        diag[0].excerpt().unwrap(),
+        "  |\n7 | Yippie yea `X`yay!\n  |             ^"
+    );
+}
+
+#[test]
+fn transposition_german_source() {
+    let input = "# Song\n\n!!english\n\n1. `Hes`One `Fis`two `h`three `B`four.\n";
+    let config = ParserConfig::new(Notation::German, true);
+    let song = TetsParser::new(input, config).parse_one();
+    let verse = song
+        .blocks
+        .iter()
+        .find_map(|block| match block {
+            Block::Verse(verse) if verse.label == VerseLabel::Verse(1) => Some(verse),
+            _ => None,
+        })
+        .unwrap();
+    let chords: Vec<_> = verse
+        .paragraphs
+        .iter()
+        .flat_map(|p| p.iter())
+        .filter_map(|i| match i {
+            Inline::Chord(c) => Some(c.chord.to_string()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(chords, ["Bb", "F#", "b", "Bb"]);
+    assert_eq!(song.notation, Notation::German);
+    assert_eq!(verse.notation, Some(Notation::English));
+
+    // Bb is English, B is already flat in German notation
+    let input = "# Song\n\n!!english\n\n1. `H`One `Bb`two.\n";
+    let config = ParserConfig::new(Notation::German, true);
+    let (res, diag) = TetsParser::new(input, config).parse();
+    res.unwrap_err();
+    assert_eq!(diag.len(), 1);
+    assert_eq!(diag[0].kind, DiagKind::Transposition { chord: "Bb".into() });
+}
+
+#[test]
+fn chord_validation() {
+    let input = r#"
+# Song
+
+1. `Am`Yippie yea `Gg`oh!
+Yippie yea `Hmaj9x`yay!
+`C/G`Yippie `X`yea `D7(b9)`yay!
+"#;
+
+    // Without validation malformed chords pass silently, as long as there's no transposition
+    let (res, diag) = try_parse(input, false);
+    res.unwrap();
+    assert_eq!(diag, vec![]);
+
+    let config = ParserConfig::default().validate_chords(true);
+    let (res, diag) = TetsParser::new(input, config).parse();
+    res.unwrap();
+
+    let diag: Vec<_> = diag.iter().map(|d| (d.line, d.span, &d.kind)).collect();
+    assert_eq!(
+        diag,
+        vec![
+            (
+                4,
+                Some(Span { column: 20, len: 2 }),
+                &DiagKind::ChordInvalid { chord: "Gg".into() }
+            ),
+            (
+                5,
+                Some(Span { column: 13, len: 6 }),
+                &DiagKind::ChordInvalid {
+                    chord: "Hmaj9x".into()
+                }
+            ),
+            (
+                6,
+                Some(Span { column: 14, len: 1 }),
+                &DiagKind::ChordInvalid { chord: "X".into() }
+            ),
+        ]
+    );
+
+    // With transposition, only the transposition error is reported
+    let input = "# Song\n\n!+2\n\n1. `X`Yippie yea `Gg`oh!\n";
+    let config = ParserConfig::default().validate_chords(true);
+    let (res, diag) = TetsParser::new(input, config).parse();
+    res.unwrap_err();
+    let kinds: Vec<_> = diag.iter().map(|d| &d.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            &DiagKind::Transposition { chord: "X".into() },
+            &DiagKind::ChordInvalid { chord: "Gg".into() },
+        ]
+    );
+}
+
+#[test]
+fn diag_span() {
+    let input = "# Song\n\n!+2\n\n1. \tYippie `Am`yea `Xmaj7`oh! !foo\n";
+    let (res, diag) = try_parse(input, false);
+    res.unwrap_err();
+
+    assert_eq!(diag.len(), 2);
+    let xp = diag.iter().find(|d| d.is_error()).unwrap();
+    let ext = diag.iter().find(|d| !d.is_error()).unwrap();
+    assert_eq!(xp.line, 5);
+    assert_eq!(xp.span, Some(Span { column: 21, len: 5 }));
+    assert_eq!(
+        xp.excerpt().unwrap(),
+        "  |\n5 | 1. \tYippie `Am`yea `Xmaj7`oh! !foo\n  |    \t                ^~~~~"
+    );
+    assert_eq!(ext.span, Some(Span { column: 31, len: 4 }));
+
+    // No excerpt without a span
+    let no_span = Diagnostic::new(&ext.file, ext.line, ext.kind.clone());
+    assert_eq!(no_span.excerpt(), None);
+
+    // Repeated chords are located at their own position
+    let input = "# Song\n\n!+2\n\n1. `X`Yippie `G`yea `X`oh `G/X`yay!\n";
+    let (res, diag) = try_parse(input, false);
+    res.unwrap_err();
+
+    let spans: Vec<_> = diag.iter().map(|d| d.span.unwrap()).collect();
+    assert_eq!(
+        spans,
+        [
+            Span { column: 5, len: 1 },
+            Span { column: 22, len: 1 },
+            Span { column: 30, len: 1 },
+        ]
+    );
+}
+
+#[test]
+fn parse_verse_numbering() {
+    let input = r#"
+# Song 1
+
+1. Verse 1.
+> Chorus 1.
+1. Verse 2.
+>> Chorus 2.
+1. Verse 3.
+
+# Song 2
+
+1. Verse 1.
+2. Verse 2.
+> Chorus.
+>> Chorus two.
+3. Verse 3.
+3. Verse 3.
+"#;
+
+    let songs = parse(input, true);
+
+    assert_eq!(songs[0].get_verse(0).label, VerseLabel::Verse(1));
+    assert_eq!(songs[0].get_verse(2).label, VerseLabel::Verse(2));
+    assert_eq!(songs[0].get_verse(4).label, VerseLabel::Verse(3));
+
+    assert_eq!(songs[1].get_verse(0).label, VerseLabel::Verse(1));
+    assert_eq!(songs[1].get_verse(1).label, VerseLabel::Verse(2));
+    assert_eq!(songs[1].get_verse(4).label, VerseLabel::Verse(3));
+    assert_eq!(songs[1].get_verse(5).label, VerseLabel::Verse(4));
+}
+
+#[test]
+fn parse_bullet_list() {
+    let input = r#"
+# Song
+
+- Item 1
+- Item 2
+
+1. First verse.
+
+* Item 3
+* Item 4
+"#;
+
+    parse_one(input).assert_json_eq(song(
+        "Song",
+        [],
+        "english",
+        [
+            b_bullet_list(["Item 1", "Item 2"]),
+            ver_verse(1, [p([i_text("First verse.")])]),
+            b_bullet_list(["Item 3", "Item 4"]),
+        ],
+    ));
+}
+
+#[test]
+fn parse_hr() {
+    let input = r#"
+# Song
+
+1. First verse.
+
+---
+
+2. Second verse.
+"#;
+
+    parse_one(input).assert_json_eq(song(
         "Song",
         [],
         "english",
@@ -741,6 +1463,23 @@ fn parse_link() {
     ));
 }
 
+#[test]
+fn parse_link_nested() {
+    // Links within emphasis and chords are kept, formatting within link text is dropped
+    let input = "# Song\n1. `G`Words by _[John](http://example.com/john)_, [**source**](http://example.com)\n";
+    parse_one_para(input).assert_json_eq(json!([i_chord(
+        "G",
+        Null,
+        1,
+        [
+            i_text("Words by "),
+            i_emph([i_link("John", "http://example.com/john", "")]),
+            i_text(", "),
+            i_link("source", "http://example.com", ""),
+        ]
+    )]));
+}
+
 #[test]
 fn parse_image() {
     let input = r#"
@@ -765,6 +1504,72 @@ fn parse_image() {
     ));
 }
 
+#[test]
+fn parse_image_block() {
+    let input = r#"
+# Song
+
+1. First verse.
+
+![Chart](chart.png "center")
+Intro chord
+*chart*
+
+![Baz](baz.jpg) and lyrics.
+"#;
+
+    parse_one(input).assert_json_eq(song(
+        "Song",
+        [],
+        "english",
+        [
+            ver_verse(1, [p([i_text("First verse.")])]),
+            b_image("chart.png", "Chart", "center", "Intro chord chart"),
+            ver_none([p([i_image("baz.jpg", "Baz", ""), i_text(" and lyrics.")])]),
+        ],
+    ));
+}
+
+fn i_raw(tex: &str) -> Json {
+    json!({ "type": "i-raw", "tex": tex })
+}
+
+#[test]
+fn parse_raw_tex() {
+    let input = r#"
+# Song
+
+<tex code="\newpage"/>
+
+1. Verse <tex code="\ding{51}"/> with <TEX code='"\&"'></tex>raw & escaped.
+"#;
+
+    parse(input, false)[0].assert_json_eq(song(
+        "Song",
+        [],
+        "english",
+        [
+            b_html([i_raw("\\newpage")]),
+            ver_verse(
+                1,
+                [p([
+                    i_text("Verse "),
+                    i_raw("\\ding{51}"),
+                    i_text(" with "),
+                    i_raw("\"\\&\""),
+                    i_text("raw & escaped."),
+                ])],
+            ),
+        ],
+    ));
+
+    let (res, diag) = try_parse("# Song\n\n1. Missing <tex>code.\n", false);
+    assert!(res.is_err());
+    let [diag]: [_; 1] = diag.try_into().unwrap();
+    assert_eq!(diag.line, 3);
+    assert_eq!(diag.kind, DiagKind::RawTexCode);
+}
+
 #[test]
 fn parse_html() {
     let input = r#"
@@ -842,6 +1647,285 @@ Trailing text.
     );
 }
 
+#[test]
+fn parse_metadata() {
+    let input = r#"
+# Song
+## Subtitle
+!author: Robert Tannahill
+!key: G
+!tempo: 96
+!copyright: "1810"
+!source: https://example.com/songs?id=12&lang=en
+
+1. Verse.
+"#;
+
+    let song = parse_one(input);
+    song.metadata.assert_json_eq(json!({
+        "author": "Robert Tannahill",
+        "copyright": "1810",
+        "key": "G",
+        "source": "https://example.com/songs?id=12&lang=en",
+        "tempo": 96,
+    }));
+    song.blocks[..].assert_json_eq(json!([ver_verse(1, [p([i_text("Verse.")])])]));
+
+    // Not metadata unless right after the title and subtitles
+    let input = r#"
+# Song
+!+2
+!author: Nobody
+"#;
+    let song = parse_one(input);
+    assert!(song.metadata.is_empty());
+}
+
+#[test]
+fn parse_song_key() {
+    // Explicit key in metadata
+    let song = parse_one("# Song\n!key: Em\n\n1. `G`Verse.\n");
+    assert_eq!(song.key.as_deref(), Some("Em"));
+
+    // Inferred from the first chord, verses without chords are skipped
+    let song = parse_one("# Song\n\n1. Spoken intro.\n\n2. `Am`Verse `C`two.\n\n> `G`Chorus.\n");
+    assert_eq!(song.key.as_deref(), Some("Am"));
+
+    // Inferred after transposition
+    let song = parse_one("# Song\n\n!+2\n\n1. `G`Verse.\n");
+    assert_eq!(song.key.as_deref(), Some("A"));
+
+    // No chords and no metadata
+    let song = parse_one("# Song\n\n1. Verse.\n");
+    assert_eq!(song.key, None);
+
+    // Explicit key is transposed along with the chords, the metadata is kept as written
+    let song = parse_one("# Song\n!key: G\n\n!+2\n\n1. `G`Verse.\n");
+    assert_eq!(song.key.as_deref(), Some("A"));
+    assert_eq!(song.metadata["key"], Value::String("G".into()));
+    let song = parse_one("# Song\n!key: Em\n\n!+2 !german\n\n1. `G`Verse.\n");
+    assert_eq!(song.key.as_deref(), Some("F#m"));
+
+    // Unrecognized keys are kept as they are
+    let song = parse_one("# Song\n!key: G major\n\n!+2\n\n1. `G`Verse.\n");
+    assert_eq!(song.key.as_deref(), Some("G major"));
+
+    // Transposition from a previous song carries over, disabled transposition leaves the key alone
+    let input = "# One\n\n!+2\n\n1. `C`Verse.\n\n# Two\n!key: D\n\n1. `D`Verse.\n";
+    assert_eq!(parse(input, false)[1].key.as_deref(), Some("E"));
+    assert_eq!(parse(input, true)[1].key.as_deref(), Some("D"));
+}
+
+#[test]
+fn parse_key_directive() {
+    let chords = |song: &Song| -> Vec<String> {
+        song.blocks
+            .iter()
+            .filter_map(|block| match block {
+                Block::Verse(verse) => Some(verse),
+                _ => None,
+            })
+            .flat_map(|verse| verse.paragraphs.iter().flat_map(|p| p.iter()))
+            .filter_map(|inline| match inline {
+                Inline::Chord(chord) => Some(chord.chord.to_string()),
+                _ => None,
+            })
+            .collect()
+    };
+
+    // Directive between verses, it doesn't stay in the AST
+    let song = parse_one("# Song\n\n1. `G`Verse.\n\n!key: C\n\n2. `C`Two.\n");
+    assert_eq!(song.key.as_deref(), Some("C"));
+    assert_eq!(song.metadata["key"], Value::String("C".into()));
+    assert_eq!(chords(&song), ["G", "C"]);
+    assert!(!serde_json::to_string(&song.blocks).unwrap().contains("key"));
+
+    // Directive within a verse, without a space
+    let song = parse_one("# Song\n\n1. !key:F#m `Em`Verse.\n");
+    assert_eq!(song.key.as_deref(), Some("F#m"));
+    song.blocks.assert_json_eq(json!([ver_verse(
+        1,
+        [p([i_chord("Em", Null, 1, [i_text("Verse.")])])]
+    )]));
+
+    // The key doesn't affect transposition of chords
+    let with_key = parse_one("# Song\n\n!+2\n\n!key: F#m\n\n1. `Em`Verse `G`two.\n");
+    let without_key = parse_one("# Song\n\n!+2\n\n1. `Em`Verse `G`two.\n");
+    assert_eq!(chords(&with_key), ["F#m", "A"]);
+    assert_eq!(chords(&with_key), chords(&without_key));
+
+    // The key may only be declared once
+    let (res, diag) = try_parse("# Song\n!key: C\n\n1. `C`Verse.\n\n!key: G\n", false);
+    res.unwrap_err();
+    let [diag]: [_; 1] = diag.try_into().unwrap();
+    assert_eq!(diag.line, 6);
+    assert_eq!(diag.kind, DiagKind::MetadataKey { key: "key".into() });
+
+    // Degree-based notations are relative to the declared key
+    let song =
+        parse_one("# Song\n!key: G\n\n!=nashville !english\n\n1. `1`One `4`four `5m`five.\n");
+    assert_eq!(chords(&song), ["G", "C", "Dm"]);
+    let song = parse_one("# Song\n!key: G\n\n!roman\n\n1. `G`One `Em`six.\n");
+    assert_eq!(chords(&song), ["I", "VIm"]);
+    assert_eq!(song.key.as_deref(), Some("G"));
+    let song = parse_one("# Song\n!key: G\n\n!+2 !nashville\n\n1. `G`One `C`four.\n");
+    assert_eq!(chords(&song), ["1", "4"]);
+    assert_eq!(song.key.as_deref(), Some("A"));
+    let song = parse_one("# Song\n\n!nashville\n\n1. `G`One `C`four.\n");
+    assert_eq!(chords(&song), ["5", "1"]);
+}
+
+#[test]
+fn parse_song_id() {
+    let input = r#"
+# Žluťoučký kůň -- Úpěl (Ďábelské ódy)!
+
+# Song
+!id: my-song
+
+# ???
+"#;
+
+    let ids: Vec<_> = parse(input, false)
+        .iter()
+        .map(|song| song.id.to_string())
+        .collect();
+    assert_eq!(ids, ["zlutoucky-kun-upel-dabelske-ody", "my-song", "song"]);
+}
+
+#[test]
+fn parse_footnotes() {
+    let input = r#"
+# Song
+
+1. Word^[This is a *footnote*] and `G`more^[Second]
+> Chorus^[Third
+unterminated]
+
+# Song two
+
+1. _Again^[First **again**]_
+"#;
+
+    let songs = parse(input, false);
+
+    let footnote_1 = i_footnote(1, [i_text("This is a "), i_emph([i_text("footnote")])]);
+    let footnote_2 = i_footnote(2, [i_text("Second")]);
+    songs[0].blocks.assert_json_eq(json!([
+        ver_verse(
+            1,
+            [p([
+                i_text("Word"),
+                footnote_1.clone(),
+                i_text(" and "),
+                i_chord("G", Null, 1, [i_text("more"), footnote_2.clone()]),
+            ])]
+        ),
+        ver_chorus(
+            Null,
+            [p([
+                i_text("Chorus^[Third"),
+                i_break(),
+                i_text("unterminated]"),
+            ])]
+        ),
+    ]));
+    songs[0].footnotes.assert_json_eq(json!([
+        { "num": 1, "inlines": footnote_1["inlines"] },
+        { "num": 2, "inlines": footnote_2["inlines"] },
+    ]));
+
+    // Numbering starts over in each song
+    let footnote = i_footnote(1, [i_text("First "), i_strong([i_text("again")])]);
+    songs[1].blocks.assert_json_eq(json!([ver_verse(
+        1,
+        [p([i_emph([i_text("Again"), footnote])])]
+    )]));
+    assert_eq!(songs[1].footnotes.len(), 1);
+}
+
+#[test]
+fn parse_metadata_errors() {
+    let input = r#"
+# Song
+!author: Robert Tannahill
+this is not metadata
+!author: Robert Burns
+
+1. Verse.
+"#;
+
+    let (res, diag) = try_parse(input, false);
+    assert!(res.is_err());
+
+    let kinds: Vec<_> = diag.iter().map(|d| (d.line, d.kind.clone())).collect();
+    assert_eq!(
+        kinds,
+        [
+            (
+                4,
+                DiagKind::Metadata {
+                    text: "this is not metadata".into()
+                }
+            ),
+            (
+                5,
+                DiagKind::MetadataKey {
+                    key: "author".into()
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn parse_warnings() {
+    let input = r#"# Song
+## Subtitle
+
+1. Verse !foo lyrics.
+
+2.
+
+>
+
+## Late subtitle
+
+3. Verse.
+"#;
+
+    let expected = vec![
+        (
+            4,
+            DiagKind::UnknownExtension {
+                text: "!foo".into(),
+            },
+        ),
+        (6, DiagKind::EmptyVerse { chorus: false }),
+        (8, DiagKind::EmptyVerse { chorus: true }),
+        (
+            10,
+            DiagKind::SubtitleIgnored {
+                text: "Late subtitle".into(),
+            },
+        ),
+    ];
+
+    let (res, diag) = try_parse(input, false);
+    let song = &res.unwrap()[0];
+    assert_eq!(song.subtitles.len(), 1);
+    assert_eq!(&*song.subtitles[0], "Subtitle");
+    assert!(diag.iter().all(|d| !d.is_error()));
+    let kinds: Vec<_> = diag.iter().map(|d| (d.line, d.kind.clone())).collect();
+    assert_eq!(kinds, expected);
+
+    let config = ParserConfig::default().warnings_strict(true);
+    let (res, diag) = TetsParser::new(input, config).parse();
+    assert!(res.is_err());
+    let kinds: Vec<_> = diag.iter().map(|d| (d.line, d.kind.clone())).collect();
+    assert_eq!(kinds, expected);
+}
+
 #[test]
 fn parse_punctuation() {
     let input = r#"# Song
@@ -866,7 +1950,73 @@ fn parse_crlf() {
         "english",
         [
             ver_verse(1, [p([i_text("First verse.")])]),
-            b_pre("pre1\npre2\n"),
+            b_pre("pre1\npre2\n", ""),
+        ],
+    ));
+}
+
+#[test]
+fn parse_tab() {
+    let input = r#"# Song
+
+```tab
+e|-----0---3---|
+B|---1---0-----|
+G|-0-----------|
+```
+
+```text
+Not a tab
+```
+"#;
+
+    parse_one(input).assert_json_eq(song(
+        "Song",
+        [],
+        "english",
+        [
+            b_tab("e|-----0---3---|\nB|---1---0-----|\nG|-0-----------|\n"),
+            b_pre("Not a tab\n", "text"),
+        ],
+    ));
+}
+
+#[test]
+fn parse_pre() {
+    let input = r#"# Song
+
+1. First verse.
+
+```
+e|--0--|
+```
+
+2. Second `G`verse.
+
+> Chorus lyrics.
+> ```text
+> G: 320003
+> ```
+> More chorus.
+"#;
+
+    parse_one(input).assert_json_eq(song(
+        "Song",
+        [],
+        "english",
+        [
+            ver_verse(1, [p([i_text("First verse.")])]),
+            b_pre("e|--0--|\n", ""),
+            ver_verse(
+                2,
+                [p([
+                    i_text("Second "),
+                    i_chord("G", Null, 1, [i_text("verse.")]),
+                ])],
+            ),
+            ver_chorus(Null, [p([i_text("Chorus lyrics.")])]),
+            b_pre("G: 320003\n", "text"),
+            ver_none([p([i_text("More chorus.")])]),
         ],
     ));
 }
@@ -884,6 +2034,127 @@ fn parse_crlf_html() {
     ));
 }
 
+#[test]
+fn parse_repeat_markers() {
+    let input = r#"
+# Song
+
+1. x1
+Verse one.
+
+2. x2
+Verse two.
+
+3. (x3)
+Verse three.
+
+> x2
+> Chorus.
+
+### Bridge x2
+Bridge lyrics.
+
+### Outro (x3)
+
+Outro lyrics.
+
+4. Verse four.
+x2
+"#;
+
+    parse_one(input).assert_json_eq(song(
+        "Song",
+        [],
+        "english",
+        [
+            ver_verse(1, [p([i_text("Verse one.")])]),
+            repeat(ver_verse(2, [p([i_text("Verse two.")])]), 2),
+            repeat(ver_verse(3, [p([i_text("Verse three.")])]), 3),
+            repeat(ver_chorus(Null, [p([i_text("Chorus.")])]), 2),
+            repeat(ver_custom("Bridge", [p([i_text("Bridge lyrics.")])]), 2),
+            repeat(ver_custom("Outro", [p([i_text("Outro lyrics.")])]), 3),
+            ver_verse(4, [p([i_text("Verse four."), i_break(), i_text("x2")])]),
+        ],
+    ));
+}
+
+#[test]
+fn parse_repeat_marker_label() {
+    // The repeat count is kept next to the label rather than in it,
+    // so that the label stays the same as that of a verse without a marker
+    let input = r#"
+# Song
+
+### Chorus x2
+Sing it twice.
+
+### Chorus
+Sing it once.
+"#;
+
+    let song = parse_one(input);
+    assert_eq!(song.get_verse(0).label, VerseLabel::Custom("Chorus".into()));
+    assert_eq!(song.get_verse(0).repeat_count, Some(2));
+    assert_eq!(song.get_verse(1).label, VerseLabel::Custom("Chorus".into()));
+    assert_eq!(song.get_verse(1).repeat_count, None);
+
+    let json = serde_json::to_value(song.get_verse(0)).unwrap();
+    assert_eq!(json["label"], json!({ "custom": "Chorus" }));
+    assert_eq!(json["repeat_count"], 2);
+}
+
+#[test]
+fn parse_repeat_marker_invalid() {
+    let input = r#"
+# Song
+
+1. x0
+Verse one.
+
+### Bridge (x0)
+Bridge lyrics.
+
+### Merry Xmas
+Xmas lyrics.
+
+2. Xanadu
+Verse two.
+"#;
+
+    let (res, diag) = try_parse(input, false);
+    let [parsed]: [_; 1] = res.unwrap().try_into().unwrap();
+    parsed.assert_json_eq(song(
+        "Song",
+        [],
+        "english",
+        [
+            ver_verse(1, [p([i_text("x0"), i_break(), i_text("Verse one.")])]),
+            ver_custom("Bridge (x0)", [p([i_text("Bridge lyrics.")])]),
+            ver_custom("Merry Xmas", [p([i_text("Xmas lyrics.")])]),
+            ver_verse(2, [p([i_text("Xanadu"), i_break(), i_text("Verse two.")])]),
+        ],
+    ));
+
+    // Words starting with an x aren't markers and aren't reported
+    let [diag1, diag2]: [_; 2] = diag.try_into().unwrap();
+    assert!(!diag1.is_error());
+    assert_eq!(diag1.line, 4);
+    assert_eq!(
+        diag1.kind,
+        DiagKind::RepeatMarker {
+            marker: "x0".into()
+        }
+    );
+    assert!(!diag2.is_error());
+    assert_eq!(diag2.line, 7);
+    assert_eq!(
+        diag2.kind,
+        DiagKind::RepeatMarker {
+            marker: "(x0)".into()
+        }
+    );
+}
+
 #[test]
 fn control_chars_error() {
     let input = "# Song
@@ -908,6 +2179,34 @@ fn control_chars_error() {
     assert_eq!(diag[0].kind, DiagKind::ControlChar { char: 159 });
 }
 
+#[test]
+fn control_char_span() {
+    let input = "# Song\n\n1. First verse.\n2. Second verse.\0\n";
+    let (res, diag) = try_parse(input, false);
+    res.unwrap_err();
+    assert_eq!(diag[0].line, 4);
+    assert_eq!(diag[0].span, Some(Span { column: 17, len: 1 }));
+}
+
+#[test]
+fn multiple_errors() {
+    // Parsing carries on after errors so that all of them are reported
+    let input = "# Song
+
+1. First \u{7}verse.
+2. Second verse. <tex>
+";
+
+    let (res, diag) = try_parse(input, false);
+    res.unwrap_err();
+
+    let [diag1, diag2]: [_; 2] = diag.try_into().unwrap();
+    assert_eq!(diag1.line, 3);
+    assert_eq!(diag1.kind, DiagKind::ControlChar { char: 7 });
+    assert_eq!(diag2.line, 4);
+    assert_eq!(diag2.kind, DiagKind::RawTexCode);
+}
+
 #[test]
 fn bom() {
     let input = "\u{feff}# Song";
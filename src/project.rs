@@ -1,32 +1,46 @@
-use std::collections::BTreeMap;
+use std::cell::RefCell;
+use std::collections::hash_map::Entry;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::env;
 use std::fs;
 use std::iter;
+use std::mem;
 use std::process::Command;
 use std::process::Stdio;
 use std::str;
+use std::time::Duration;
 
 use serde::de::Error as _;
 use serde::{Deserialize, Deserializer};
 
-use crate::app::App;
+use crate::app::{App, InterruptError};
 use crate::book::{self, Book, Song, SongRef};
-use crate::default_project::DEFAULT_PROJECT;
+use crate::default_project::{InitOptions, DEFAULT_PROJECT};
+use crate::import::xml;
+use crate::lint::{LintConfig, LintKind};
 use crate::music::Notation;
-use crate::parser::Diagnostic;
 use crate::parser::Parser;
 use crate::parser::ParserConfig;
+use crate::parser::{DiagKind, DiagSink, Diagnostic};
 use crate::prelude::*;
+use crate::render::template;
+use crate::render::tex_tools::TectonicSettings;
 use crate::render::tex_tools::TexConfig;
 use crate::render::tex_tools::TexTools;
 use crate::render::Renderer;
-use crate::util::ExitStatusExt;
+use crate::util::{kill_tree, new_process_group, ExitStatusExt};
 
 pub use toml::Value;
 
+mod assets;
+mod cache;
+pub use cache::{Fingerprint, RenderCache};
 mod input;
-use input::{InputSet, SongsGlobs};
+use input::{ChapterInput, InputSet, Inputs, SongsGlobs};
 mod output;
-pub use output::{Format, Output};
+pub use output::{Format, JsonStyle, LabelNumbering, OnFailure, Output, Script, Split};
+mod setlist;
+pub use setlist::Setlist;
 
 pub type Metadata = BTreeMap<Box<str>, Value>;
 
@@ -75,9 +89,18 @@ fn default_smart_punctuation() -> bool {
     true
 }
 
+fn default_solfege_accents() -> bool {
+    true
+}
+
 #[derive(Deserialize, Debug)]
 pub struct Settings {
-    songs: SongsGlobs,
+    songs: Option<SongsGlobs>,
+    #[serde(default)]
+    chapter: Vec<ChapterInput>,
+    /// Whether patterns in `songs` that don't match any files are an error rather than a warning.
+    #[serde(default)]
+    songs_strict: bool,
 
     #[serde(default = "dir_songs", deserialize_with = "pathbuf_relative_only")]
     dir_songs: PathBuf,
@@ -90,7 +113,24 @@ pub struct Settings {
     pub notation: Notation,
     #[serde(default = "default_smart_punctuation")]
     pub smart_punctuation: bool,
+    /// Whether chords converted to the solfège notation are written with accents, ie. `Ré` rather than `Re`.
+    #[serde(default = "default_solfege_accents")]
+    pub solfege_accents: bool,
+    /// Whether songs with the same title are an error rather than a warning.
+    #[serde(default)]
+    pub titles_strict: bool,
+    /// Whether chords are checked to be recognized even when not transposed.
+    #[serde(default)]
+    pub validate_chords: bool,
+    /// Whether warnings about song files fail the build.
+    #[serde(default)]
+    pub strict: bool,
+    /// Rules checked by `bard lint`.
+    #[serde(default)]
+    pub lint: LintConfig,
     tex: Option<TexConfig>,
+    #[serde(default)]
+    tectonic: TectonicSettings,
 
     pub output: Vec<Output>,
     #[serde(deserialize_with = "meta_default_chorus_label")]
@@ -126,6 +166,10 @@ impl Settings {
         }
 
         let mut settings: Settings = toml::from_str(&contents).with_context(parse_err)?;
+        if settings.songs.is_none() && settings.chapter.is_empty() {
+            return Err(anyhow!("Missing 'songs' field or '[[chapter]]' sections"))
+                .with_context(parse_err);
+        }
 
         settings.resolve(project_dir)?;
         Ok(settings)
@@ -135,17 +179,32 @@ impl Settings {
         self.dir_songs.as_ref()
     }
 
+    pub fn dir_templates(&self) -> &Path {
+        self.dir_templates.as_ref()
+    }
+
     pub fn dir_output(&self) -> &Path {
         self.dir_output.as_ref()
     }
 
+    pub fn tex(&self) -> Option<&TexConfig> {
+        self.tex.as_ref()
+    }
+
+    pub fn tectonic(&self) -> &TectonicSettings {
+        &self.tectonic
+    }
+
     fn resolve(&mut self, project_dir: &Path) -> Result<()> {
         self.dir_songs.resolve(project_dir);
         self.dir_templates.resolve(project_dir);
         self.dir_output.resolve(project_dir);
+        if let Some(cache_dir) = self.tectonic.cache_dir.as_mut() {
+            cache_dir.resolve(project_dir);
+        }
 
         for output in self.output.iter_mut() {
-            output.resolve(&self.dir_templates, &self.dir_output)?;
+            output.resolve(project_dir, &self.dir_templates, &self.dir_output)?;
         }
 
         Ok(())
@@ -157,14 +216,91 @@ static SCRIPT_EXT: &str = "sh";
 #[cfg(windows)]
 static SCRIPT_EXT: &str = "bat";
 
+/// Script filenames to look for, in order of precedence:
+/// the platform-specific `<name>.<os>.<ext>` first, then the generic `<name>.<ext>`.
+/// `os` is a value as in `std::env::consts::OS`, eg. `"linux"`, `"macos"` or `"windows"`.
+fn script_filenames(name: &str, os: &str, ext: &str) -> [String; 2] {
+    [
+        format!("{}.{}.{}", name, os, ext),
+        format!("{}.{}", name, ext),
+    ]
+}
+
+/// Expand `${VAR}` references to bard's environment variables in `value`,
+/// undefined variables expand to an empty string.
+fn expand_env_vars(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find("${") {
+        res.push_str(&rest[..start]);
+        let var = &rest[start + 2..];
+        match var.find('}') {
+            Some(end) => {
+                res.push_str(&env::var(&var[..end]).unwrap_or_default());
+                rest = &var[end + 1..];
+            }
+            None => {
+                rest = &rest[start..];
+                break;
+            }
+        }
+    }
+    res.push_str(rest);
+    res
+}
+
 #[derive(Debug)]
 pub struct Project {
     pub project_dir: PathBuf,
     pub settings: Settings,
     pub book: Book,
+    /// The book parsed with transposition disabled,
+    /// only present if some output has `transpose = false`.
+    pub book_original: Option<Book>,
 
     project_file: PathBuf,
     input_paths: Vec<PathBuf>,
+    input_dirs: Vec<PathBuf>,
+    /// Template partials and per-song templates used by the outputs.
+    template_files: Vec<PathBuf>,
+}
+
+/// Parse a songs file, either Markdown or, based on the extension, an XML song format.
+/// The songs are returned along with the source lines of their titles.
+fn parse_songs(
+    source: &str,
+    rel_path: &Path,
+    config: ParserConfig,
+    diag_sink: impl DiagSink,
+) -> Result<(Vec<Song>, Vec<usize>), ()> {
+    if xml::is_xml(rel_path) {
+        let songs = xml::parse(source, rel_path, &config, &diag_sink)?;
+        let lines = vec![1; songs.len()];
+        Ok((songs, lines))
+    } else {
+        let mut parser = Parser::new(source, rel_path, config, diag_sink);
+        let songs = parser.parse()?;
+        Ok((songs, parser.song_lines()))
+    }
+}
+
+/// Song titles seen so far, used to find songs with the same title.
+/// Titles are compared case-insensitively.
+#[derive(Default)]
+struct TitleIndex(HashMap<String, (PathBuf, usize)>);
+
+impl TitleIndex {
+    /// Record the title of a song found at `file` and `line`. If there already was a song
+    /// with the same title, its file and line are returned instead.
+    fn check(&mut self, title: &str, file: &Path, line: usize) -> Option<(PathBuf, usize)> {
+        match self.0.entry(title.trim().to_lowercase()) {
+            Entry::Occupied(first) => Some(first.get().clone()),
+            Entry::Vacant(entry) => {
+                entry.insert((file.to_owned(), line));
+                None
+            }
+        }
+    }
 }
 
 impl Project {
@@ -181,22 +317,82 @@ impl Project {
 
         let settings = Settings::from_file(&project_file, &project_dir)?;
         let book = Book::new(&settings);
+        let book_original = settings
+            .output
+            .iter()
+            .any(|output| !output.transpose)
+            .then(|| Book::new(&settings));
 
         let mut project = Project {
             project_file,
             project_dir,
             settings,
             input_paths: vec![],
+            input_dirs: vec![],
+            template_files: vec![],
             book,
+            book_original,
         };
 
         project
             .load_md_files(app)
             .context("Failed to load input files")?;
+        project.expand_per_song_outputs();
+        project.find_template_files();
 
         Ok(project)
     }
 
+    /// Collects the partials and song templates used by the outputs, so that they can be watched.
+    /// Files that can't be found are left out, they're reported when rendering.
+    fn find_template_files(&mut self) {
+        let mut files = vec![];
+        let mut dirs = BTreeSet::new();
+        for output in self.settings.output.iter() {
+            let template = match output.template_path() {
+                Some(template) => template,
+                None => continue,
+            };
+            dirs.extend(template.parent().map(Path::to_owned));
+
+            let song_templates = self
+                .book
+                .songs
+                .iter()
+                .filter_map(|song| song.template(output.format()))
+                .map(|name| self.settings.dir_templates().join(name))
+                .filter(|path| path.exists());
+            for template in song_templates {
+                dirs.extend(template.parent().map(Path::to_owned));
+                files.push(template);
+            }
+        }
+
+        for dir in dirs {
+            let partials = template::partial_paths(&dir).unwrap_or_default();
+            files.extend(partials.into_iter().map(|(_, path)| path));
+        }
+        files.sort();
+        files.dedup();
+        self.template_files = files;
+    }
+
+    /// Replaces outputs with the song placeholder in their file name by one output per song.
+    fn expand_per_song_outputs(&mut self) {
+        let songs = &self.book.songs;
+        let outputs = mem::take(&mut self.settings.output);
+        self.settings.output = outputs
+            .into_iter()
+            .flat_map(|output| {
+                if output.is_per_song() {
+                    songs.iter().map(|song| output.for_song(&song.id)).collect()
+                } else {
+                    vec![output]
+                }
+            })
+            .collect();
+    }
+
     fn find_in_parents(start_dir: &Path) -> Option<(PathBuf, PathBuf)> {
         assert!(start_dir.is_dir());
 
@@ -212,38 +408,160 @@ impl Project {
     }
 
     fn load_md_files(&mut self, app: &App) -> Result<()> {
-        let input_set = InputSet::new(&self.settings.dir_songs)?;
-        self.input_paths = self
-            .settings
-            .songs
-            .iter()
-            .try_fold(input_set, InputSet::apply_glob)?
-            .finalize()?;
+        // Songs given in the top-level `songs` field go first, followed by each chapter,
+        // for each input path we remember which chapter it belongs to.
+        let groups = self.settings.songs.iter().map(|songs| (None, songs)).chain(
+            self.settings
+                .chapter
+                .iter()
+                .map(|ch| (Some(ch.name.as_str()), &ch.songs)),
+        );
+        let mut input_set =
+            InputSet::new(app, &self.settings.dir_songs, self.settings.songs_strict)?;
+        let mut chapters = vec![];
+        for (chapter, songs) in groups {
+            input_set = songs.iter().try_fold(input_set, InputSet::apply_glob)?;
+            chapters.resize(input_set.num_matched(), chapter);
+        }
+        let Inputs { files, dirs } = input_set.finalize()?;
+        self.input_paths = files;
+        // Don't watch the output directory in case it's located in the songs directory
+        let dir_output = &self.settings.dir_output;
+        self.input_dirs = dirs
+            .into_iter()
+            .filter(|dir| !dir.starts_with(dir_output))
+            .collect();
+
+        // All files are parsed even if some fail, so that all the errors are reported at once
+        let mut failed = vec![];
+        let mut titles = TitleIndex::default();
+        for (path, chapter) in self.input_paths.iter().zip(chapters) {
+            app.check_interrupted()?;
+            let source = fs::read_to_string(path)?;
+            let config = ParserConfig::new(self.settings.notation, self.settings.smart_punctuation)
+                .validate_chords(self.settings.validate_chords)
+                .solfege_accents(self.settings.solfege_accents)
+                .warnings_strict(self.settings.strict || app.deny_warnings());
+            let rel_path = path.strip_prefix(&self.project_dir).unwrap_or(path);
+            let diag_sink = |diag: Diagnostic| app.parser_diag(diag);
+            let (mut songs, lines) = match parse_songs(&source, rel_path, config.clone(), diag_sink)
+            {
+                Ok(res) => res,
+                Err(_) => {
+                    failed.push(path);
+                    continue;
+                }
+            };
 
-        let diag_sink = move |diag: Diagnostic| {
-            app.parser_diag(diag);
-        };
+            // Duplicate titles are checked across the whole project, usually a song was pasted twice
+            let mut duplicate_failed = false;
+            for (song, &line) in songs.iter().zip(lines.iter()) {
+                if let Some((first_file, first_line)) = titles.check(&song.title, rel_path, line) {
+                    let kind = DiagKind::DuplicateTitle {
+                        title: song.title.clone(),
+                        first_file: first_file.display().to_string().into(),
+                        first_line,
+                        strict: self.settings.titles_strict,
+                    };
+                    let diag = Diagnostic::new(rel_path, line, kind);
+                    duplicate_failed |= diag.is_error() || config.warnings_strict;
+                    app.parser_diag(diag);
+                }
+            }
+            if duplicate_failed {
+                failed.push(path);
+                continue;
+            }
+
+            for song in songs.iter_mut() {
+                song.chapter = chapter.map(Into::into);
+            }
+            self.book.add_songs(songs);
+
+            if let Some(book) = self.book_original.as_mut() {
+                // Diagnostics have already been reported by the first pass
+                let config = config.xp_disabled(true);
+                let (mut songs, _) = parse_songs(&source, rel_path, config, |_: Diagnostic| {})
+                    .map_err(|_| anyhow!("Could not parse file {:?}", path))?;
+                for song in songs.iter_mut() {
+                    song.chapter = chapter.map(Into::into);
+                }
+                book.add_songs(songs);
+            }
+        }
+        match &failed[..] {
+            [] => {}
+            [path] => bail!("Could not parse file {:?}", path),
+            paths => bail!("Could not parse files {:?}", paths),
+        }
+
+        self.book
+            .postprocess(&self.settings.dir_output, app.img_cache())?;
+        if let Some(book) = self.book_original.as_mut() {
+            book.postprocess(&self.settings.dir_output, app.img_cache())?;
+        }
+
+        Ok(())
+    }
+
+    /// Check the songs against the `[lint]` rules. Violations are reported to `app` as warnings
+    /// and returned. Other diagnostics are left out, they've already been reported when loading.
+    pub fn lint(&self, app: &App) -> Result<Vec<Diagnostic>> {
+        let mut warnings = vec![];
+        let mut titles = TitleIndex::default();
 
         for path in self.input_paths.iter() {
+            // Lint rules are about the Markdown source, imported XML songs are left out
+            if xml::is_xml(path) {
+                continue;
+            }
+
             app.check_interrupted()?;
             let source = fs::read_to_string(path)?;
-            let config = ParserConfig::new(self.settings.notation, self.settings.smart_punctuation);
+            let config = ParserConfig::new(self.settings.notation, self.settings.smart_punctuation)
+                .lint(self.settings.lint.clone());
             let rel_path = path.strip_prefix(&self.project_dir).unwrap_or(path);
+            let lints = RefCell::new(vec![]);
+            let diag_sink = |diag: Diagnostic| {
+                if matches!(diag.kind, DiagKind::Lint(..)) {
+                    lints.borrow_mut().push(diag);
+                }
+            };
             let mut parser = Parser::new(&source, rel_path, config, diag_sink);
             let songs = parser
                 .parse()
                 .map_err(|_| anyhow!("Could not parse file {:?}", path))?;
-            self.book.add_songs(songs);
-        }
+            let mut lints = lints.take();
+
+            if self.settings.lint.warn_duplicate_titles {
+                for (song, line) in songs.iter().zip(parser.song_lines()) {
+                    if let Some((first_file, first_line)) =
+                        titles.check(&song.title, rel_path, line)
+                    {
+                        let kind = DiagKind::Lint(LintKind::DuplicateTitle {
+                            title: song.title.clone(),
+                            first_file: first_file.display().to_string().into(),
+                            first_line,
+                        });
+                        lints.push(Diagnostic::new(rel_path, line, kind));
+                    }
+                }
+            }
 
-        self.book
-            .postprocess(&self.settings.dir_output, app.img_cache())?;
+            lints.sort_by_key(|diag| diag.line);
+            for diag in lints {
+                app.parser_diag(diag.clone());
+                warnings.push(diag);
+            }
+        }
 
-        Ok(())
+        Ok(warnings)
     }
 
-    pub fn init<P: AsRef<Path>>(project_dir: P) -> Result<()> {
-        DEFAULT_PROJECT.resolve(project_dir.as_ref()).create()
+    pub fn init<P: AsRef<Path>>(project_dir: P, opts: &InitOptions) -> Result<()> {
+        DEFAULT_PROJECT
+            .resolve_with(project_dir.as_ref(), opts)?
+            .create()
     }
 
     pub fn book_section(&self) -> &Metadata {
@@ -258,22 +576,67 @@ impl Project {
         &self.book.songs_sorted
     }
 
+    /// The book to render `output` from, ie. the original one if the output has `transpose = false`.
+    pub fn book_for(&self, output: &Output) -> &Book {
+        match self.book_original.as_ref() {
+            Some(book) if !output.transpose => book,
+            _ => &self.book,
+        }
+    }
+
+    /// Path of the post-processing script for the `output`, if it has one configured.
+    pub fn script_path(&self, output: &Output) -> Option<PathBuf> {
+        let script = output.script.as_ref()?;
+        let dir_output = self.settings.dir_output();
+        let [specific, generic] = script_filenames(&script.name, env::consts::OS, SCRIPT_EXT);
+        let specific = dir_output.join(specific);
+        if specific.exists() {
+            Some(specific)
+        } else {
+            Some(dir_output.join(generic))
+        }
+    }
+
+    /// Run the script of the `output`, if any, and handle failure according to its `on_failure` setting.
     fn run_script(&self, app: &App, output: &Output) -> Result<()> {
-        let script_fn = match output.script.as_deref() {
-            Some(s) => format!("{}.{}", s, SCRIPT_EXT),
+        let script = match output.script.as_ref() {
+            Some(script) => script,
             None => return Ok(()),
         };
 
-        let script_path = self.settings.dir_output().join(&script_fn);
+        match self.run_script_inner(app, output, script) {
+            Err(err) if !err.is::<InterruptError>() => match script.on_failure {
+                OnFailure::Error => Err(err),
+                OnFailure::Warn => {
+                    app.warning(format!(
+                        "Script for output {} failed: {:#}",
+                        output.label(),
+                        err
+                    ));
+                    Ok(())
+                }
+                OnFailure::Ignore => Ok(()),
+            },
+            res => res,
+        }
+    }
+
+    fn run_script_inner(&self, app: &App, output: &Output, script: &Script) -> Result<()> {
+        let script_path = self.script_path(output).unwrap();
+        let script_fn = script_path.file_name().unwrap().to_string_lossy();
+
         if !script_path.exists() {
+            let [specific, _] = script_filenames(&script.name, env::consts::OS, SCRIPT_EXT);
             bail!(
-                "Could not find script file '{}' in the output directory.",
-                script_fn
+                "Could not find script file '{}' or '{}' in the output directory.",
+                script_fn,
+                specific
             );
         }
 
         app.status("Running", format!("script '{}'", script_fn));
-        let mut child = Command::new(script_path)
+        let mut command = Command::new(script_path);
+        command
             .current_dir(self.settings.dir_output())
             .stdin(Stdio::null())
             .stdout(Stdio::inherit())
@@ -282,43 +645,148 @@ impl Project {
             .env("OUTPUT", output.file.as_os_str())
             .env("OUTPUT_STEM", output.file.file_stem().unwrap()) // NB. unwrap is fine here, there's always a stem
             .env("PROJECT_DIR", self.project_dir.as_os_str())
-            .env("OUTPUT_DIR", self.settings.dir_output().as_os_str())
-            .spawn()?;
-        app.child_wait(&mut child)?.into_result()?;
+            .env("OUTPUT_DIR", self.settings.dir_output().as_os_str());
+        if let Some(split_dir) = output.split_dir() {
+            command.env("OUTPUT_SPLIT_DIR", split_dir);
+        }
+        for (key, value) in script.env.iter() {
+            command.env(key, expand_env_vars(value));
+        }
 
-        Ok(())
+        let timeout_secs = match script.timeout_secs {
+            Some(secs) => secs,
+            None => {
+                let mut child = command.spawn()?;
+                return app.child_wait(&mut child)?.into_result();
+            }
+        };
+
+        // The script runs in its own process group so that it can be killed
+        // along with any programs it started.
+        new_process_group(&mut command);
+        let mut child = command.spawn()?;
+        match app.child_wait_timeout(&mut child, Duration::from_secs(timeout_secs)) {
+            Ok(Some(status)) => status.into_result(),
+            Ok(None) => {
+                kill_tree(&mut child)?;
+                bail!(
+                    "Script '{}' timed out after {} seconds and was killed.",
+                    script_fn,
+                    timeout_secs
+                );
+            }
+            Err(err) => {
+                // Being in a separate process group, the script doesn't get the interrupt signal
+                let _ = kill_tree(&mut child);
+                Err(err)
+            }
+        }
+    }
+
+    /// Outputs selected by name on the command line, or all enabled outputs if there's no selection.
+    /// Explicitly selecting a disabled output is an error.
+    fn selected_outputs(&self, app: &App) -> Result<Vec<&Output>> {
+        let outputs = &self.settings.output;
+        let selection = app.selected_outputs();
+        if selection.is_empty() {
+            return Ok(outputs
+                .iter()
+                .filter(|output| match output.disabled_reason() {
+                    Some(reason) => {
+                        app.status("Skipping", format!("{}: {}", output.label(), reason));
+                        false
+                    }
+                    None => true,
+                })
+                .collect());
+        }
+
+        if let Some(unknown) = selection
+            .iter()
+            .find(|s| !outputs.iter().any(|o| o.is_selected_by(s)))
+        {
+            let mut available: Vec<_> = outputs.iter().map(|o| o.name()).collect();
+            available.sort();
+            available.dedup();
+            bail!(
+                "Unknown output '{}', available outputs: {}",
+                unknown,
+                available.join(", ")
+            );
+        }
+
+        let selected: Vec<_> = outputs
+            .iter()
+            .filter(|o| selection.iter().any(|s| o.is_selected_by(s)))
+            .collect();
+        if let Some((output, reason)) = selected
+            .iter()
+            .find_map(|o| o.disabled_reason().map(|reason| (o, reason)))
+        {
+            bail!("Output {} can't be built, {}.", output.label(), reason);
+        }
+
+        Ok(selected)
     }
 
     pub fn render(&self, app: &App) -> Result<()> {
         fs::create_dir_all(&self.settings.dir_output)?;
 
-        if self.settings.output.iter().any(|o| o.is_pdf()) {
+        let outputs = self.selected_outputs(app)?;
+
+        if outputs.iter().any(|o| o.is_pdf()) {
             // Initialize Tex tools ahead of actual rendering so that
             // errors are reported early...
-            TexTools::initialize(app, self.settings.tex.as_ref())
+            TexTools::initialize(app, self.settings.tex.as_ref(), &self.settings.tectonic)
                 .context("Could not initialize TeX tools.")?;
         }
 
-        self.settings.output.iter().try_for_each(|output| {
+        let mut cache = RenderCache::load(&self.settings.dir_output);
+        // Templates used by more outputs are only reported once if they don't declare a version
+        let mut checked_templates = HashSet::new();
+        let res = outputs.iter().try_for_each(|output| {
             app.check_interrupted()?;
-            app.status("Rendering", output.output_filename());
-            let context = || {
-                format!(
-                    "Could not render output file {:?}",
-                    output.file.file_name().unwrap()
-                )
-            };
+            let context = || format!("Could not render output {}", output.label());
+
+            if let Some(dir) = output.file.parent() {
+                fs::create_dir_all(dir)
+                    .with_context(|| format!("Could not create directory {:?}", dir))
+                    .with_context(context)?;
+            }
+
+            assets::copy_assets(
+                &output.assets,
+                &self.settings.dir_templates,
+                &self.project_dir,
+                &self.settings.dir_output,
+            )
+            .with_context(context)?;
 
             let renderer = Renderer::new(self, output, app.img_cache()).with_context(context)?;
             let tpl_version = renderer.version();
+            if let Some(tpl_path) = output.template_path() {
+                book::version::ensure_supported(tpl_path, tpl_version.as_ref())
+                    .with_context(context)?;
+            }
+            for (tpl_path, tpl_version) in renderer.song_templates() {
+                book::version::ensure_supported(tpl_path, tpl_version.as_ref())
+                    .with_context(context)?;
+            }
 
+            let fingerprint = renderer.fingerprint(app).with_context(context)?;
+            let pages = renderer.split_pages().with_context(context)?;
+            if !app.force_rebuild() && cache.is_fresh(&output.file, &pages, &fingerprint) {
+                app.status("Unchanged", output.label());
+                return Ok(());
+            }
+
+            // Remove the entry in case rendering fails, it's re-added on success
+            cache.remove(&output.file);
+            app.status("Rendering", output.label());
             let res = renderer.render(app).with_context(context).and_then(|_| {
                 if app.post_process() {
                     self.run_script(app, output).with_context(|| {
-                        format!(
-                            "Could not run script for output file {:?}",
-                            output.file.file_name().unwrap()
-                        )
+                        format!("Could not run script for output {}", output.label())
                     })
                 } else {
                     Ok(())
@@ -328,12 +796,25 @@ impl Project {
             // Perform version check of the template (if the Render supports it and there is a template file).
             // This is done after rendering and preprocessing so that the CLI messages are at the bottom of the log.
             // Otherwise they tend to be far behind eg. TeX output etc.
-            if let Some((tpl_version, tpl_path)) = tpl_version.zip(output.template.as_ref()) {
-                book::version::compat_check(app, tpl_path, &tpl_version);
+            let templates = output
+                .template_path()
+                .map(|path| (path, tpl_version))
+                .into_iter()
+                .chain(renderer.song_templates());
+            for (tpl_path, tpl_version) in templates {
+                let first = checked_templates.insert(tpl_path.to_owned());
+                book::version::compat_check(app, tpl_path, tpl_version.as_ref(), first);
+            }
+
+            if res.is_ok() {
+                cache.insert(&output.file, fingerprint);
             }
 
             res
-        })
+        });
+
+        cache.save()?;
+        res
     }
 
     pub fn input_paths(&self) -> &Vec<PathBuf> {
@@ -345,9 +826,12 @@ impl Project {
     }
 
     pub fn watch_paths(&self) -> impl Iterator<Item = &Path> {
-        // Input MD files:
-        // TODO: this won't work for wildcards
-        let inputs = self.input_paths.iter().map(PathBuf::as_ref);
+        // Input MD files and directories where new input files may appear:
+        let inputs = self
+            .input_paths
+            .iter()
+            .chain(self.input_dirs.iter())
+            .map(PathBuf::as_ref);
 
         // Templates:
         let templates = self
@@ -356,6 +840,16 @@ impl Project {
             .iter()
             .filter_map(Output::template_path);
 
+        // Partials and song templates:
+        let template_files = self.template_files.iter().map(PathBuf::as_ref);
+
+        // Setlists:
+        let setlists = self
+            .settings
+            .output
+            .iter()
+            .filter_map(|o| o.setlist.as_deref());
+
         // Images:
         let images = self.book.iter_images().map(|i| i.full_path());
 
@@ -363,6 +857,29 @@ impl Project {
         iter::once(self.project_file.as_path())
             .chain(inputs)
             .chain(templates)
+            .chain(template_files)
+            .chain(setlists)
             .chain(images)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn script_filenames_per_platform() {
+        assert_eq!(
+            script_filenames("foo", "macos", "sh"),
+            ["foo.macos.sh", "foo.sh"]
+        );
+        assert_eq!(
+            script_filenames("foo", "linux", "sh"),
+            ["foo.linux.sh", "foo.sh"]
+        );
+        assert_eq!(
+            script_filenames("foo", "windows", "bat"),
+            ["foo.windows.bat", "foo.bat"]
+        );
+    }
+}
@@ -0,0 +1,62 @@
+//! Copying of static files used by an output, see the `assets` output setting.
+
+use std::fs;
+
+use crate::prelude::*;
+use crate::util::read_dir_all;
+
+/// Copy `assets` into `dir_output`, preserving their relative paths.
+///
+/// Each asset is looked up in `dir_templates` first and then in `project_dir`.
+/// Directories are copied recursively. A file is only copied if its copy in the output directory
+/// is missing or older than the source.
+pub fn copy_assets(
+    assets: &[PathBuf],
+    dir_templates: &Path,
+    project_dir: &Path,
+    dir_output: &Path,
+) -> Result<()> {
+    for asset in assets {
+        let src = [dir_templates, project_dir]
+            .iter()
+            .map(|dir| dir.join(asset))
+            .find(|path| path.exists())
+            .ok_or_else(|| {
+                anyhow!(
+                    "Asset file not found: {:?}, also tried {:?}",
+                    dir_templates.join(asset),
+                    project_dir.join(asset)
+                )
+            })?;
+        let dest = dir_output.join(asset);
+
+        if src.is_dir() {
+            for file in read_dir_all(&src)
+                .with_context(|| format!("Could not read asset directory {:?}", src))?
+            {
+                let rel_path = file.strip_prefix(&src).unwrap();
+                copy_if_newer(&file, &dest.join(rel_path))?;
+            }
+        } else {
+            copy_if_newer(&src, &dest)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn copy_if_newer(src: &Path, dest: &Path) -> Result<()> {
+    let modified = |path: &Path| fs::metadata(path).and_then(|meta| meta.modified()).ok();
+    if let (Some(src_time), Some(dest_time)) = (modified(src), modified(dest)) {
+        if dest_time >= src_time {
+            return Ok(());
+        }
+    }
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Could not create directory {:?}", parent))?;
+    }
+    fs::copy(src, dest).with_context(|| format!("Could not copy asset {:?} to {:?}", src, dest))?;
+    Ok(())
+}
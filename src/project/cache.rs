@@ -0,0 +1,121 @@
+//! Render cache, used to skip rendering of outputs whose inputs haven't changed since the last build.
+//!
+//! For each output a `Fingerprint` is computed out of everything that goes into rendering it,
+//! see `Renderer::fingerprint()`. Fingerprints of successfully rendered outputs
+//! are stored in a small manifest file in the output directory.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::BTreeMap;
+use std::fs;
+use std::hash::Hasher;
+use std::io;
+
+use crate::prelude::*;
+
+/// Name of the manifest file in the output directory.
+pub const MANIFEST_FILENAME: &str = ".bard-cache";
+
+/// Hash of render inputs.
+///
+/// This is not a cryptographic hash nor is it guaranteed to be stable
+/// between bard builds, a mismatch merely causes a rebuild.
+#[derive(Default)]
+pub struct Fingerprint(DefaultHasher);
+
+impl Fingerprint {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, data: impl AsRef<[u8]>) {
+        let data = data.as_ref();
+        self.0.write_usize(data.len());
+        self.0.write(data);
+    }
+
+    /// Add contents of a file. A missing file is not an error, it's hashed as such.
+    pub fn add_file(&mut self, path: &Path) -> Result<()> {
+        match fs::read(path) {
+            Ok(content) => self.add(content),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => self.0.write_u8(0),
+            Err(err) => {
+                return Err(Error::from(err).context(format!("Could not read file {:?}", path)))
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn finish(&self) -> String {
+        format!("{:016x}", self.0.finish())
+    }
+}
+
+impl io::Write for Fingerprint {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The cache manifest, maps output files to fingerprints they were rendered with.
+#[derive(Debug)]
+pub struct RenderCache {
+    dir_output: PathBuf,
+    entries: BTreeMap<String, String>,
+}
+
+impl RenderCache {
+    /// Load the manifest from the output directory.
+    /// If the manifest is missing or can't be read, the cache is empty, ie. everything gets rendered.
+    pub fn load(dir_output: &Path) -> Self {
+        let entries = fs::read(dir_output.join(MANIFEST_FILENAME))
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+
+        Self {
+            dir_output: dir_output.to_owned(),
+            entries,
+        }
+    }
+
+    fn key(&self, output: &Path) -> String {
+        output
+            .strip_prefix(&self.dir_output)
+            .unwrap_or(output)
+            .to_string_lossy()
+            .into()
+    }
+
+    /// Returns `true` if the `output` file along with the split output `pages`, if any, exist
+    /// and the output was last rendered with the same `fingerprint`.
+    pub fn is_fresh(&self, output: &Path, pages: &[PathBuf], fingerprint: &str) -> bool {
+        output.exists()
+            && pages.iter().all(|page| page.exists())
+            && self
+                .entries
+                .get(&self.key(output))
+                .map_or(false, |fp| fp == fingerprint)
+    }
+
+    pub fn insert(&mut self, output: &Path, fingerprint: String) {
+        self.entries.insert(self.key(output), fingerprint);
+    }
+
+    pub fn remove(&mut self, output: &Path) {
+        self.entries.remove(&self.key(output));
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = self.dir_output.join(MANIFEST_FILENAME);
+        serde_json::to_vec_pretty(&self.entries)
+            .map_err(Error::from)
+            .and_then(|data| fs::write(&path, data).map_err(Error::from))
+            .with_context(|| format!("Could not write render cache manifest {:?}", path))
+    }
+}
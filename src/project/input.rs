@@ -1,10 +1,13 @@
+use std::collections::HashSet;
+use std::iter;
 use std::slice;
 
 use globset::Glob;
 use serde::Deserialize;
 
+use crate::app::App;
 use crate::prelude::*;
-use crate::util::{read_dir_all, sort_paths_lexical};
+use crate::util::{read_dir_all_with_dirs, sort_paths_lexical};
 
 #[derive(Deserialize, Debug)]
 #[serde(untagged)]
@@ -30,22 +33,50 @@ impl Default for SongsGlobs {
     }
 }
 
+/// A `[[chapter]]` section in `bard.toml`.
+#[derive(Deserialize, Debug)]
+pub struct ChapterInput {
+    pub name: String,
+    pub songs: SongsGlobs,
+}
+
+/// Files matched by the inputs along with directories to watch for new files.
+#[derive(Debug)]
+pub struct Inputs {
+    pub files: Vec<PathBuf>,
+    /// Directories in which a newly created file might match some of the inputs,
+    /// empty if all the inputs are plain filenames.
+    pub dirs: Vec<PathBuf>,
+}
+
 #[derive(Debug)]
 pub struct InputSet<'a> {
+    app: &'a App,
     dir_songs: &'a Path,
+    strict: bool,
     all_files: Vec<PathBuf>,
+    all_dirs: Vec<PathBuf>,
     match_set: Vec<PathBuf>,
+    matched: HashSet<PathBuf>,
+    dynamic: bool,
 }
 
 impl<'a> InputSet<'a> {
-    pub fn new(dir_songs: &'a Path) -> Result<Self> {
-        let all_files = read_dir_all(dir_songs)
+    /// With `strict` set, patterns which don't match any files are reported as errors
+    /// rather than warnings.
+    pub fn new(app: &'a App, dir_songs: &'a Path, strict: bool) -> Result<Self> {
+        let (all_files, all_dirs) = read_dir_all_with_dirs(dir_songs)
             .with_context(|| format!("Could not read directory {:?}", dir_songs))?;
 
         Ok(Self {
+            app,
             dir_songs,
+            strict,
             all_files,
+            all_dirs,
             match_set: vec![],
+            matched: HashSet::new(),
+            dynamic: false,
         })
     }
 
@@ -53,56 +84,96 @@ impl<'a> InputSet<'a> {
         s.as_ref().contains(&['*', '?', '{', '}'][..])
     }
 
-    fn apply_glob_inner<'s>(&'s mut self, glob: &str) -> Result<&'s mut [PathBuf]> {
-        let orig_len = self.match_set.len();
-        let glob = Glob::new(glob)
-            .with_context(|| format!("Invalid glob pattern: '{}'", glob))?
-            .compile_matcher();
-        let dir_songs = self.dir_songs;
-        let match_set = &mut self.match_set;
-
-        for matched in self
-            .all_files
-            .iter()
-            // NB. Unwrap should be ok here as the paths will all be prefixed by dir_songs
-            .filter(|path| glob.is_match(path.strip_prefix(dir_songs).unwrap()))
-        {
-            match_set.push(matched.clone());
+    fn is_md(path: &Path) -> bool {
+        path.extension()
+            .map_or(false, |ext| ext.eq_ignore_ascii_case("md"))
+    }
+
+    /// Adds newly matched files sorted lexically, files matched previously are skipped
+    /// so that only the first occurrence counts.
+    fn add_matched(&mut self, mut matched: Vec<PathBuf>) {
+        sort_paths_lexical(&mut matched);
+        for path in matched {
+            if self.matched.insert(path.clone()) {
+                self.match_set.push(path);
+            }
+        }
+    }
+
+    fn no_match(&self, input: &str) -> Result<()> {
+        let msg = format!(
+            "No files matched pattern '{}' in directory {:?}",
+            input, self.dir_songs,
+        );
+        if self.strict {
+            bail!(msg);
         }
 
-        Ok(&mut match_set[orig_len..])
+        self.app.warning(msg);
+        Ok(())
     }
 
     pub fn apply_glob(mut self, glob: &str) -> Result<Self> {
-        if Self::is_globlike(glob) {
-            // This might be a glob
-            let added = self.apply_glob_inner(glob)?;
-            if added.is_empty() {
-                bail!(
-                    "No files matched pattern '{}' in diectory {:?}",
-                    glob,
-                    self.dir_songs,
-                );
-            }
+        let path = self.dir_songs.join(glob);
 
-            // Sort the entries collected for this glob.
-            // This way, paths from one glob pattern are sorted alphabetically,
-            // but order of globs as given in the input array is preserved.
-            sort_paths_lexical(added);
+        let matched: Vec<_> = if Self::is_globlike(glob) {
+            // This might be a glob
+            let glob = Glob::new(glob)
+                .with_context(|| format!("Invalid glob pattern: '{}'", glob))?
+                .compile_matcher();
+            let dir_songs = self.dir_songs;
+            self.all_files
+                .iter()
+                // NB. Unwrap should be ok here as the paths will all be prefixed by dir_songs
+                .filter(|path| glob.is_match(path.strip_prefix(dir_songs).unwrap()))
+                .cloned()
+                .collect()
+        } else if path.is_dir() {
+            // A directory, all .md files within are added
+            self.all_files
+                .iter()
+                .filter(|file| file.starts_with(&path) && Self::is_md(file))
+                .cloned()
+                .collect()
         } else {
             // This is a plain filename
-            let path = self.dir_songs.join(glob);
             if !path.exists() {
                 bail!("File not found: {:?}", path);
             }
 
-            self.match_set.push(path);
+            self.add_matched(vec![path]);
+            return Ok(self);
+        };
+
+        // Paths from one glob pattern or directory are sorted alphabetically (in add_matched()),
+        // but order of inputs as given in the input array is preserved.
+        // New files may match later on, which needs to be picked up in watch mode.
+        self.dynamic = true;
+        if matched.is_empty() {
+            self.no_match(glob)?;
         }
+        self.add_matched(matched);
 
         Ok(self)
     }
 
-    pub fn finalize(self) -> Result<Vec<PathBuf>> {
-        Ok(self.match_set)
+    /// Number of files matched so far.
+    pub fn num_matched(&self) -> usize {
+        self.match_set.len()
+    }
+
+    pub fn finalize(self) -> Result<Inputs> {
+        let dirs = if self.dynamic {
+            iter::once(self.dir_songs.to_owned())
+                .chain(self.all_dirs)
+                .collect()
+        } else {
+            vec![]
+        };
+
+        Ok(Inputs {
+            files: self.match_set,
+            dirs,
+        })
     }
 }
@@ -1,13 +1,18 @@
 use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::env;
+use std::path::Component;
 
 use serde::{Deserialize, Serialize};
-use strum::{Display, EnumVariantNames, VariantNames};
+use strum::{Display, EnumString, EnumVariantNames, VariantNames};
 
 use crate::prelude::*;
 use crate::project::Metadata;
 use crate::util::PathBufExt;
 
-#[derive(Serialize, Deserialize, Display, EnumVariantNames, PartialEq, Eq, Clone, Copy, Debug)]
+#[derive(
+    Serialize, Deserialize, Display, EnumString, EnumVariantNames, PartialEq, Eq, Clone, Copy, Debug,
+)]
 #[serde(rename_all = "lowercase")]
 #[strum(serialize_all = "lowercase")]
 pub enum Format {
@@ -16,6 +21,109 @@ pub enum Format {
     Hovorka,
     Json,
     Xml,
+    Markdown,
+    MusicXml,
+    Lilypond,
+    Abc,
+}
+
+/// What to do when an output's script fails or times out.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum OnFailure {
+    /// Fail the build.
+    #[default]
+    Error,
+    /// Report a warning and carry on.
+    Warn,
+    /// Carry on silently.
+    Ignore,
+}
+
+/// Post-processing script of an output.
+///
+/// In `bard.toml`, this is either just the script name, or a table with the name and further options.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(from = "ScriptSpec")]
+pub struct Script {
+    pub name: String,
+    /// Additional environment variables, values may refer to the environment of bard with `${VAR}`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timeout_secs: Option<u64>,
+    pub on_failure: OnFailure,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScriptSpec {
+    Name(String),
+    Full {
+        name: String,
+        #[serde(default)]
+        env: BTreeMap<String, String>,
+        timeout_secs: Option<u64>,
+        #[serde(default)]
+        on_failure: OnFailure,
+    },
+}
+
+impl From<ScriptSpec> for Script {
+    fn from(spec: ScriptSpec) -> Self {
+        match spec {
+            ScriptSpec::Name(name) => Self {
+                name,
+                env: BTreeMap::new(),
+                timeout_secs: None,
+                on_failure: OnFailure::default(),
+            },
+            ScriptSpec::Full {
+                name,
+                env,
+                timeout_secs,
+                on_failure,
+            } => Self {
+                name,
+                env,
+                timeout_secs,
+                on_failure,
+            },
+        }
+    }
+}
+
+/// How an output is split into several files, only supported for HTML.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum Split {
+    /// One page per song plus an index page.
+    Song,
+}
+
+/// Numbering of verse and chorus labels.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum LabelNumbering {
+    /// Verses and choruses are always numbered.
+    Always,
+    /// Verses and choruses are never numbered.
+    Never,
+    /// Verses or choruses are only numbered if a song has more than one of them.
+    Auto,
+}
+
+/// Formatting of JSON outputs.
+#[derive(Serialize, Deserialize, PartialEq, Eq, Clone, Copy, Default, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum JsonStyle {
+    /// Indented JSON document.
+    #[default]
+    Pretty,
+    /// JSON document on a single line.
+    Compact,
+    /// Newline-delimited JSON: a header object without songs followed by one song per line.
+    Ndjson,
 }
 
 impl Format {
@@ -43,6 +151,10 @@ impl Format {
             "html" => Self::Html,
             "json" => Self::Json,
             "xml" => Self::Xml,
+            "md" => Self::Markdown,
+            "musicxml" => Self::MusicXml,
+            "ly" => Self::Lilypond,
+            "abc" => Self::Abc,
             _ => bail!(
                 "Could not detect format based file on extension for: {:?}\n{}",
                 path,
@@ -71,12 +183,39 @@ fn default_tex_runs() -> u32 {
     3
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_transpose() -> bool {
+    true
+}
+
+/// Placeholder in an output file name which expands the output into one output per song,
+/// replaced by the song's ID, eg. `file = "songs/{{slug}}.pdf"`.
+pub const SONG_PLACEHOLDER: &str = "{{slug}}";
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
 pub struct Output {
     #[serde(skip_serializing)]
     pub file: PathBuf,
     #[serde(skip_serializing)]
     pub template: Option<PathBuf>,
+    /// Name for selecting the output on the command line, see `name()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Whether the output is built, see `disabled_reason()`.
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    /// Operating systems to build the output on, as in `std::env::consts::OS`, all if empty.
+    #[serde(default)]
+    pub only_on: Vec<String>,
+    /// Why the output isn't built on this machine, evaluated in `resolve()`.
+    #[serde(skip)]
+    pub(crate) disabled: Option<String>,
+    /// ID of the only song in this output, set for outputs expanded by `for_song()`.
+    #[serde(skip)]
+    pub(crate) song: Option<String>,
     pub format: Option<Format>,
     #[serde(default)]
     pub sans_font: bool,
@@ -88,33 +227,154 @@ pub struct Output {
     pub toc_sort_key: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dpi: Option<f32>,
+    /// Lowest resolution images in PDF outputs may be printed at before a warning is printed, see `image_dpi_warning()`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_dpi_min: Option<f32>,
+    /// Highest resolution images in PDF outputs may be printed at before a warning is printed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub image_dpi_max: Option<f32>,
     #[serde(default = "default_tex_runs")]
     pub tex_runs: u32,
+    /// Whether the TeX file is kept next to the PDF, like with `bard make -k`.
+    #[serde(default)]
+    pub keep_intermediate: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub script: Option<Script>,
+    #[serde(default)]
+    pub expand_chorus_refs: bool,
+    /// Whether chords are transposed as marked in songs, otherwise the original chords are rendered.
+    #[serde(default = "default_transpose")]
+    pub transpose: bool,
+    #[serde(default)]
+    pub alt_chords_primary: bool,
+    /// Numbering of verse and chorus labels, if not set, the labels are kept as parsed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_numbering: Option<LabelNumbering>,
+    /// Path to a setlist file, which gives the order of songs in this output.
+    #[serde(skip_serializing)]
+    pub setlist: Option<PathBuf>,
+    /// Whether songs missing from the setlist are an error, otherwise they're appended.
+    #[serde(default)]
+    pub setlist_strict: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub script: Option<String>,
+    pub split: Option<Split>,
+    /// Whether the template fails on missing fields instead of rendering them empty.
+    #[serde(default)]
+    pub template_strict: bool,
+    /// Formatting of JSON outputs.
+    #[serde(default)]
+    pub json_style: JsonStyle,
+    /// Static files copied into the output directory, relative to the template directory or the project directory.
+    #[serde(default)]
+    pub assets: Vec<PathBuf>,
 
     #[serde(rename = "book", default, skip_serializing)]
     pub book_overrides: Metadata,
 }
 
 impl Output {
-    pub fn resolve(&mut self, dir_templates: &Path, dir_output: &Path) -> Result<()> {
+    pub fn resolve(
+        &mut self,
+        project_dir: &Path,
+        dir_templates: &Path,
+        dir_output: &Path,
+    ) -> Result<()> {
         if let Some(template) = self.template.as_mut() {
             template.resolve(dir_templates);
         }
+        if let Some(setlist) = self.setlist.as_mut() {
+            setlist.resolve(project_dir);
+        }
 
         if self.format.is_none() {
             self.format = Some(Format::try_from_ext(&self.file)?);
         }
+        if let Some(asset) = self.assets.iter().find(|asset| {
+            !asset.is_relative() || asset.components().any(|c| c == Component::ParentDir)
+        }) {
+            bail!(
+                "Output file {:?}: Asset paths must be relative and stay within the output directory. Path: {:?}",
+                self.file,
+                asset
+            );
+        }
+        if self.split.is_some() && self.format() != Format::Html {
+            bail!(
+                "Output file {:?}: The 'split' setting is only supported for HTML outputs.",
+                self.file
+            );
+        }
+
+        if self
+            .name
+            .as_deref()
+            .map_or(false, |name| name.trim().is_empty())
+        {
+            bail!(
+                "Output file {:?}: The 'name' setting can't be empty.",
+                self.file
+            );
+        }
+
+        if self.only_on.iter().any(|os| os.trim().is_empty()) {
+            bail!(
+                "Output file {:?}: The 'only_on' setting can't contain empty names.",
+                self.file
+            );
+        }
+        self.disabled = self.disabled_reason_on(env::consts::OS);
 
         self.file.resolve(dir_output);
         Ok(())
     }
 
+    /// Why the output wouldn't be built on the operating system `os`, if it wouldn't.
+    fn disabled_reason_on(&self, os: &str) -> Option<String> {
+        if !self.enabled {
+            Some("it is disabled with 'enabled = false'".into())
+        } else if !self.only_on.is_empty() && !self.only_on.iter().any(|o| o == os) {
+            Some(format!(
+                "it is only built on {} and this system is {}",
+                self.only_on.join(", "),
+                os
+            ))
+        } else {
+            None
+        }
+    }
+
+    /// Why the output isn't built on this machine, or `None` if it is built.
+    /// Only valid once the output is resolved.
+    pub fn disabled_reason(&self) -> Option<&str> {
+        self.disabled.as_deref()
+    }
+
     pub fn format(&self) -> Format {
         self.format.unwrap()
     }
 
+    /// Name of the output for selecting it on the command line, the file stem unless set explicitly.
+    pub fn name(&self) -> Cow<str> {
+        match self.name.as_deref() {
+            Some(name) => name.into(),
+            None => self.file.file_stem().unwrap_or_default().to_string_lossy(),
+        }
+    }
+
+    /// Whether the output is selected by `selector`, which can be the output's name or file name.
+    pub fn is_selected_by(&self, selector: &str) -> bool {
+        self.name() == selector || self.output_filename() == selector
+    }
+
+    /// Description of the output for status and error messages:
+    /// The file name, preceded by the name if set explicitly.
+    pub fn label(&self) -> String {
+        match self.name.as_deref() {
+            Some(name) => format!("'{}' ({})", name, self.output_filename()),
+            None => self.output_filename().into_owned(),
+        }
+    }
+
     pub fn output_filename(&self) -> Cow<str> {
         self.file
             .file_name()
@@ -125,10 +385,36 @@ impl Output {
     pub fn template_path(&self) -> Option<&Path> {
         match self.format() {
             Format::Pdf | Format::Html | Format::Hovorka => self.template.as_deref(),
-            Format::Json | Format::Xml => None,
+            Format::Json
+            | Format::Xml
+            | Format::Markdown
+            | Format::MusicXml
+            | Format::Lilypond
+            | Format::Abc => None,
+        }
+    }
+
+    /// Whether the file name contains `SONG_PLACEHOLDER`, ie. the output is expanded
+    /// into one output per song.
+    pub fn is_per_song(&self) -> bool {
+        self.song.is_none() && self.file.to_string_lossy().contains(SONG_PLACEHOLDER)
+    }
+
+    /// A copy of this output with only the song `id`, its file name has the placeholder replaced by `id`.
+    pub fn for_song(&self, id: &str) -> Self {
+        let file = self.file.to_string_lossy().replace(SONG_PLACEHOLDER, id);
+        Self {
+            file: file.into(),
+            song: Some(id.into()),
+            ..self.clone()
         }
     }
 
+    /// Directory for the per-song pages of a split output, named after the output file.
+    pub fn split_dir(&self) -> Option<PathBuf> {
+        self.split.map(|_| self.file.with_extension(""))
+    }
+
     pub fn is_pdf(&self) -> bool {
         self.format() == Format::Pdf
     }
@@ -152,3 +438,39 @@ impl Output {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(toml: &str) -> Output {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn output_disabled_reason() {
+        let out = output(r#"file = "songbook.pdf""#);
+        assert_eq!(out.disabled_reason_on("linux"), None);
+
+        let out = output(
+            r#"
+            file = "songbook.pdf"
+            enabled = false
+            "#,
+        );
+        assert!(out.disabled_reason_on("linux").is_some());
+
+        let out = output(
+            r#"
+            file = "songbook.pdf"
+            only_on = ["linux", "macos"]
+            "#,
+        );
+        assert_eq!(out.disabled_reason_on("linux"), None);
+        assert_eq!(out.disabled_reason_on("macos"), None);
+        assert_eq!(
+            out.disabled_reason_on("windows").unwrap(),
+            "it is only built on linux, macos and this system is windows"
+        );
+    }
+}
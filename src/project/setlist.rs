@@ -0,0 +1,154 @@
+//! Setlists, used to render an output with songs in a custom running order.
+//!
+//! A setlist file lists one song per line, either by title or by slug
+//! (ie. the title lowercased and transliterated to ASCII with words joined by dashes).
+//! Empty lines and lines starting with `#` are ignored.
+
+use std::fs;
+
+use crate::book::Song;
+use crate::prelude::*;
+use crate::util::slugify;
+
+#[derive(Debug)]
+pub struct Setlist {
+    path: PathBuf,
+    /// Listed songs along with their line numbers.
+    entries: Vec<(usize, String)>,
+}
+
+impl Setlist {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Could not read setlist file {:?}", path))?;
+        Ok(Self::parse(path, &content))
+    }
+
+    pub fn parse(path: &Path, content: &str) -> Self {
+        let entries = content
+            .lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim()))
+            .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+            .map(|(num, line)| (num, line.to_string()))
+            .collect();
+
+        Self {
+            path: path.to_owned(),
+            entries,
+        }
+    }
+
+    /// Returns indices into `songs` in the setlist order.
+    ///
+    /// A title listed more than once is only included at its first position,
+    /// unless there are several songs of that title. Songs not in the setlist are appended
+    /// in their original order, or, if `strict` is `true`, reported as an error.
+    pub fn order(&self, songs: &[Song], strict: bool) -> Result<Vec<usize>> {
+        let slugs: Vec<_> = songs.iter().map(|song| slugify(&song.title)).collect();
+        let mut placed = vec![false; songs.len()];
+        let mut order = Vec::with_capacity(songs.len());
+
+        for (line, entry) in self.entries.iter() {
+            let entry_slug = slugify(entry);
+            let mut matching = (0..songs.len())
+                .filter(|&i| slugs[i] == entry_slug)
+                .peekable();
+            if matching.peek().is_none() {
+                bail!(
+                    "{}:{}: Song not found: '{}'",
+                    self.path.display(),
+                    line,
+                    entry
+                );
+            }
+
+            if let Some(idx) = matching.find(|&i| !placed[i]) {
+                placed[idx] = true;
+                order.push(idx);
+            }
+        }
+
+        let unlisted: Vec<_> = (0..songs.len()).filter(|&i| !placed[i]).collect();
+        if strict && !unlisted.is_empty() {
+            let titles: Vec<_> = unlisted
+                .iter()
+                .map(|&i| format!("'{}'", songs[i].title))
+                .collect();
+            bail!(
+                "Songs missing from setlist {:?}: {}",
+                self.path,
+                titles.join(", ")
+            );
+        }
+
+        order.extend(unlisted);
+        Ok(order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::music::Notation;
+
+    fn songs(titles: &[&str]) -> Vec<Song> {
+        titles
+            .iter()
+            .map(|title| Song {
+                id: slugify(title).into(),
+                title: (*title).into(),
+                subtitles: vec![].into(),
+                metadata: Default::default(),
+                key: None,
+                chapter: None,
+                blocks: vec![],
+                footnotes: vec![],
+                notation: Notation::default(),
+            })
+            .collect()
+    }
+
+    fn setlist(content: &str) -> Setlist {
+        Setlist::parse(Path::new("setlist.txt"), content)
+    }
+
+    #[test]
+    fn setlist_order() {
+        let songs = songs(&["Danny Boy", "Wild Mountain Thyme", "Whiskey in the Jar"]);
+
+        let order = setlist("whiskey-in-the-jar\n\n# Opener above\nDanny Boy\n")
+            .order(&songs, false)
+            .unwrap();
+        assert_eq!(order, [2, 0, 1]);
+
+        let err = setlist("Danny Boy\nWhiskey in the Jar\n")
+            .order(&songs, true)
+            .unwrap_err();
+        assert!(err.to_string().contains("'Wild Mountain Thyme'"));
+    }
+
+    #[test]
+    fn setlist_duplicates() {
+        let songs = songs(&["Intro", "Danny Boy", "Intro"]);
+
+        // A duplicate entry picks the next song of the same title, if any
+        let order = setlist("Danny Boy\nIntro\nDanny Boy\nIntro\nIntro\n")
+            .order(&songs, true)
+            .unwrap();
+        assert_eq!(order, [1, 0, 2]);
+    }
+
+    #[test]
+    fn setlist_missing() {
+        let songs = songs(&["Danny Boy"]);
+
+        let err = setlist("Danny Boy\n\nNo Such Song\n")
+            .order(&songs, false)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "setlist.txt:3: Song not found: 'No Such Song'"
+        );
+    }
+}
@@ -1,28 +1,39 @@
 use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::env;
+use std::io::Write;
 
 use semver::Version;
 use serde::Serialize;
 
 use crate::app::App;
-use crate::book::{Song, SongRef};
+use crate::book::{version, Book, Song, SongRef, TocEntry};
 use crate::music::Notation;
 use crate::prelude::*;
-use crate::project::{Format, Metadata, Output, Project};
-use crate::util::ImgCache;
+use crate::project::{Fingerprint, Format, Metadata, Output, Project, Setlist, Value};
+use crate::util::{build_timestamp, ImgCache, SOURCE_DATE_EPOCH};
 use crate::{ProgramMeta, PROGRAM_META};
 
 #[macro_use]
 pub mod template;
+pub mod abc;
 pub mod hovorka;
 pub mod html;
 pub mod json;
+pub mod lilypond;
+pub mod markdown;
+pub mod musicxml;
 pub mod pdf;
 pub mod tex_tools;
 pub mod xml;
 
+pub use self::abc::RAbc;
 pub use self::hovorka::RHovorka;
 pub use self::html::RHtml;
 pub use self::json::RJson;
+pub use self::lilypond::RLilypond;
+pub use self::markdown::RMarkdown;
+pub use self::musicxml::RMusicXml;
 pub use self::pdf::RPdf;
 use self::template::DefaultTemaplate;
 pub use self::xml::RXml;
@@ -33,26 +44,84 @@ pub static DEFAULT_TEMPLATES: &[&DefaultTemaplate] = &[
     &hovorka::DEFAULT_TEMPLATE,
 ];
 
+/// The built-in template of a templated output format, if any.
+pub fn default_template(format: Format) -> Option<&'static DefaultTemaplate> {
+    match format {
+        Format::Pdf => Some(&pdf::DEFAULT_TEMPLATE),
+        Format::Html => Some(&html::DEFAULT_TEMPLATE),
+        Format::Hovorka => Some(&hovorka::DEFAULT_TEMPLATE),
+        Format::Json
+        | Format::Xml
+        | Format::Markdown
+        | Format::MusicXml
+        | Format::Lilypond
+        | Format::Abc => None,
+    }
+}
+
 #[derive(Serialize, Debug)]
 pub struct RenderContext<'a> {
     book: Cow<'a, Metadata>,
-    songs: &'a [Song],
-    songs_sorted: &'a [SongRef],
+    songs: Cow<'a, [Song]>,
+    songs_sorted: Cow<'a, [SongRef]>,
+    toc: Vec<TocEntry>,
     notation: Notation,
     output: &'a Output,
-    program: &'static ProgramMeta,
+    program: ProgramMeta,
+    /// Version of the data format, ie. the AST version, see `bard.schema.json`.
+    schema_version: String,
 }
 
 impl<'a> RenderContext<'a> {
-    fn new(project: &'a Project, output: &'a Output) -> Self {
-        RenderContext {
-            book: output.override_book_section(project.book_section()),
-            songs: project.songs(),
-            songs_sorted: project.songs_sorted(),
+    fn new(project: &'a Project, output: &'a Output) -> Result<Self> {
+        let project_book = project.book_for(output);
+        let mut songs = Cow::Borrowed(&project_book.songs[..]);
+        if output.expand_chorus_refs {
+            songs.to_mut().iter_mut().for_each(Song::expand_chorus_refs);
+        }
+        if let Some(numbering) = output.label_numbering {
+            songs
+                .to_mut()
+                .iter_mut()
+                .for_each(|song| song.number_labels(numbering));
+        }
+
+        let mut songs_sorted = Cow::Borrowed(&project_book.songs_sorted[..]);
+        if let Some(path) = output.setlist.as_deref() {
+            let order = Setlist::load(path)?.order(&songs, output.setlist_strict)?;
+            songs = order.into_iter().map(|i| songs[i].clone()).collect();
+            songs_sorted = Cow::Owned(Book::sort_songs(&songs));
+        }
+
+        // Outputs expanded per song only contain their song
+        if let Some(id) = output.song.as_deref() {
+            songs = songs
+                .iter()
+                .filter(|song| &*song.id == id)
+                .cloned()
+                .collect();
+            songs_sorted = Cow::Owned(Book::sort_songs(&songs));
+        }
+
+        // Chapters are passed as book.chapters, computed after songs are possibly reordered by a setlist
+        let mut book = output.override_book_section(project.book_section());
+        let chapters = Value::try_from(Book::chapters(&songs))?;
+        book.to_mut().insert("chapters".into(), chapters);
+        let toc = Book::toc(&songs);
+
+        Ok(RenderContext {
+            book,
+            songs,
+            songs_sorted,
+            toc,
             notation: project.settings.notation,
             output,
-            program: &PROGRAM_META,
-        }
+            program: ProgramMeta {
+                build_timestamp: Some(build_timestamp()?),
+                ..PROGRAM_META
+            },
+            schema_version: version::current().to_string(),
+        })
     }
 }
 
@@ -60,10 +129,36 @@ trait Render {
     /// Render the output file based on `project` and `output`.
     fn render(&self, app: &App, output: &Path, context: RenderContext) -> Result<()>;
 
+    /// Render the output into `w` instead of a file, only supported by templated formats.
+    fn render_to_writer(
+        &self,
+        _app: &App,
+        _w: &mut dyn Write,
+        context: RenderContext,
+    ) -> Result<()> {
+        bail!(
+            "Output format {} can't be rendered to a writer",
+            context.output.format()
+        );
+    }
+
+    /// Render the final output file content, only supported by the PDF format.
+    fn render_bytes(&self, _app: &App, context: RenderContext) -> Result<Vec<u8>> {
+        bail!(
+            "Output format {} can't be rendered to bytes",
+            context.output.format()
+        );
+    }
+
     /// Returns the AST version specified in the template, if any.
     fn version(&self) -> Option<Version> {
         None
     }
+
+    /// Returns paths and AST versions (if specified) of per-song template overrides, if any.
+    fn song_templates(&self) -> Vec<(&Path, Option<Version>)> {
+        vec![]
+    }
 }
 
 pub struct Renderer<'a> {
@@ -80,6 +175,10 @@ impl<'a> Renderer<'a> {
             Format::Hovorka => Box::new(RHovorka::new(project, output, img_cache)?),
             Format::Json => Box::new(RJson::new()),
             Format::Xml => Box::new(RXml::new()),
+            Format::Markdown => Box::new(RMarkdown::new(project)),
+            Format::MusicXml => Box::new(RMusicXml::new()),
+            Format::Lilypond => Box::new(RLilypond::new()),
+            Format::Abc => Box::new(RAbc::new()),
         };
 
         Ok(Self {
@@ -93,8 +192,95 @@ impl<'a> Renderer<'a> {
         self.render.version()
     }
 
+    pub fn song_templates(&self) -> Vec<(&Path, Option<Version>)> {
+        self.render.song_templates()
+    }
+
+    /// Compute a fingerprint of everything that goes into rendering the output,
+    /// this is used by `RenderCache` to skip outputs that haven't changed.
+    pub fn fingerprint(&self, app: &App) -> Result<String> {
+        let mut fp = Fingerprint::new();
+        fp.add(PROGRAM_META.version);
+        fp.add([app.post_process() as u8, app.keep_interm()]);
+
+        // The build time is left out so that outputs aren't re-rendered each time,
+        // only the reproducible build override is considered.
+        let mut context = RenderContext::new(self.project, self.output)?;
+        context.program.build_timestamp = None;
+        serde_json::to_writer(&mut fp, &context)?;
+        fp.add(env::var(SOURCE_DATE_EPOCH).unwrap_or_default());
+
+        if let Some(template) = self.output.template_path() {
+            fp.add_file(template)?;
+        }
+        let song_templates = self.render.song_templates();
+        for (template, _) in song_templates.iter() {
+            fp.add_file(template)?;
+        }
+        // Partials are registered from the directories of the templates
+        let template_dirs: BTreeSet<_> = self
+            .output
+            .template_path()
+            .into_iter()
+            .chain(song_templates.iter().map(|(template, _)| *template))
+            .filter_map(Path::parent)
+            .collect();
+        for dir in template_dirs {
+            for (name, partial) in template::partial_paths(dir)? {
+                fp.add(name);
+                fp.add_file(&partial)?;
+            }
+        }
+        if let Some(script) = self.project.script_path(self.output) {
+            fp.add_file(&script)?;
+        }
+        for image in self.project.book.iter_images() {
+            fp.add_file(image.full_path())?;
+        }
+        if self.output.is_pdf() {
+            fp.add(format!("{:?}", self.project.settings.tex()));
+            fp.add(format!("{:?}", self.project.settings.tectonic()));
+        }
+
+        Ok(fp.finish())
+    }
+
+    /// Paths of the song pages written along with the output file by a split output,
+    /// see `Output::split_dir()`.
+    pub fn split_pages(&self) -> Result<Vec<PathBuf>> {
+        let dir = match self.output.split_dir() {
+            Some(dir) => dir,
+            None => return Ok(vec![]),
+        };
+
+        let context = RenderContext::new(self.project, self.output)?;
+        let pages = context
+            .songs
+            .iter()
+            .map(|song| template::split_page(&dir, &song.id))
+            .collect();
+        Ok(pages)
+    }
+
     pub fn render(&self, app: &App) -> Result<()> {
-        let context = RenderContext::new(self.project, self.output);
+        let context = RenderContext::new(self.project, self.output)?;
         self.render.render(app, &self.output.file, context)
     }
+
+    /// Render the output into `w` instead of the output file.
+    ///
+    /// This is supported by the templated formats, ie. HTML, Hovorka and PDF,
+    /// where the latter yields the TeX source.
+    pub fn render_to_writer(&self, app: &App, mut w: impl Write) -> Result<()> {
+        let context = RenderContext::new(self.project, self.output)?;
+        self.render.render_to_writer(app, &mut w, context)
+    }
+
+    /// Render a PDF output and return its content instead of saving it to the output file.
+    ///
+    /// TeX still runs in a temporary directory, which is removed afterwards.
+    pub fn render_bytes(&self, app: &App) -> Result<Vec<u8>> {
+        let context = RenderContext::new(self.project, self.output)?;
+        self.render.render_bytes(app, context)
+    }
 }
@@ -0,0 +1,180 @@
+//! ABC notation Renderer.
+//!
+//! Exports chord charts in the [ABC notation](https://abcnotation.com/wiki/abc:standard:v2.1):
+//! each song is a tune with headers taken from song metadata. Since there's no melody,
+//! every chord is set above a whole-note `C,` placeholder, one per bar,
+//! with the text sung under the chord as lyrics.
+
+use std::fmt::Write as _;
+use std::fs;
+use std::slice;
+
+use super::lilypond::{collect_text, verse_label};
+use super::{Render, RenderContext};
+use crate::app::App;
+use crate::book::{Block, Inline, Song, Verse};
+use crate::music::{self, Chromatic, Notation};
+use crate::prelude::*;
+use crate::project::Value;
+
+/// The melody placeholder note, a whole note with `L:1/1`.
+const PLACEHOLDER: &str = "C,";
+
+#[derive(Debug, Default)]
+pub struct RAbc;
+
+impl RAbc {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Render for RAbc {
+    fn render(&self, _app: &App, output: &Path, context: RenderContext) -> Result<()> {
+        let title = context.book.get("title").and_then(|title| title.as_str());
+        let abc = render_songs(title, &context.songs);
+        fs::write(output, abc).with_context(|| format!("Error writing output file: {:?}", output))
+    }
+}
+
+/// Render `songs` as an ABC file, each song is a separate tune.
+pub fn render_songs(title: Option<&str>, songs: &[Song]) -> String {
+    let mut out = String::from("%abc-2.1\n");
+    if let Some(title) = title {
+        writeln!(out, "% {}", title).unwrap();
+    }
+
+    for (i, song) in songs.iter().enumerate() {
+        out.push('\n');
+        write_song(&mut out, i + 1, song);
+    }
+
+    out
+}
+
+fn write_song(out: &mut String, num: usize, song: &Song) {
+    writeln!(out, "X:{}", num).unwrap();
+    writeln!(out, "T:{}", text(&song.title)).unwrap();
+    for subtitle in song.subtitles.iter() {
+        writeln!(out, "T:{}", text(subtitle)).unwrap();
+    }
+    match song.metadata.get("author") {
+        Some(Value::String(author)) => writeln!(out, "C:{}", text(author)).unwrap(),
+        Some(other) => writeln!(out, "C:{}", text(&other.to_string())).unwrap(),
+        None => {}
+    }
+    out.push_str("M:4/4\nL:1/1\n");
+    let key = song
+        .key
+        .as_deref()
+        .and_then(|key| abc_key(key, song.notation));
+    writeln!(out, "K:{}", key.as_deref().unwrap_or("none")).unwrap();
+
+    for block in song.blocks.iter() {
+        if let Block::Verse(verse) = block {
+            write_verse(out, verse, verse.notation.unwrap_or(song.notation));
+        }
+    }
+}
+
+fn write_verse(out: &mut String, verse: &Verse, notation: Notation) {
+    let lines: Vec<_> = verse
+        .paragraphs
+        .iter()
+        .flat_map(|para| para.split(Inline::is_break))
+        .map(chord_groups)
+        .filter(|groups| !groups.is_empty())
+        .collect();
+    if lines.is_empty() {
+        return;
+    }
+
+    if let Some(label) = verse_label(&verse.label) {
+        writeln!(out, "T:{}", text(&label)).unwrap();
+    }
+
+    for groups in lines.iter() {
+        let mut notes = String::new();
+        let mut lyrics = String::new();
+        for (chord, lyric) in groups {
+            if let Some(chord) = chord {
+                write!(notes, "\"{}\"", abc_chord(chord, notation)).unwrap();
+            }
+            write!(notes, "{} | ", PLACEHOLDER).unwrap();
+
+            if !lyrics.is_empty() {
+                lyrics.push(' ');
+            }
+            lyrics.push_str(&lyric_word(lyric));
+        }
+
+        writeln!(out, "{}", notes.trim_end_matches(' ')).unwrap();
+        writeln!(out, "w:{}", lyrics).unwrap();
+    }
+}
+
+/// Split a line into groups of a chord and the lyrics under it,
+/// lyrics before the first chord form a group without a chord.
+fn chord_groups(line: &[Inline]) -> Vec<(Option<&str>, String)> {
+    let mut groups = vec![(None, String::new())];
+
+    for inline in line {
+        if let Inline::Chord(chord) = inline {
+            let mut lyric = String::new();
+            collect_text(&chord.inlines, &mut lyric);
+            groups.push((Some(&*chord.chord), lyric));
+        } else {
+            let (_, lyric) = groups.last_mut().unwrap();
+            collect_text(slice::from_ref(inline), lyric);
+        }
+    }
+
+    groups.retain(|(chord, lyric)| chord.is_some() || !lyric.trim().is_empty());
+    groups
+}
+
+/// A chord symbol for chords in the English and German notation,
+/// other chords are exported as an annotation above the note.
+fn abc_chord(chord: &str, notation: Notation) -> String {
+    let chord = chord.replace('"', "'");
+    if matches!(notation, Notation::English | Notation::German) {
+        if let Ok(english) = music::transpose(&chord, 0, notation, Notation::English) {
+            return english;
+        }
+    }
+    format!("^{}", chord)
+}
+
+/// The `K:` field for a song key, only major and minor keys in the English and German notation are understood.
+fn abc_key(key: &str, notation: Notation) -> Option<String> {
+    if !matches!(notation, Notation::English | Notation::German) {
+        return None;
+    }
+    let (root, root_size) = Chromatic::parse_span(key, notation)?;
+    let mode = match &key[root_size..] {
+        "" => "",
+        "m" | "mi" | "min" | "moll" => "m",
+        _ => return None,
+    };
+
+    Some(format!("{}{}", root, mode))
+}
+
+/// Lyrics under one note, words are joined with `~`, `*` stands for no lyrics.
+fn lyric_word(lyric: &str) -> String {
+    let lyric = lyric.replace(['_', '*', '|', '~'], " ");
+    let words: Vec<_> = lyric.split_whitespace().map(text).collect();
+    if words.is_empty() {
+        "*".to_string()
+    } else {
+        words.join("~").replace('-', "\\-")
+    }
+}
+
+/// Text in a header or lyrics, `%` would start a comment.
+fn text(s: &str) -> String {
+    s.replace('%', "\\%")
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,75 @@
+use super::*;
+use crate::parser::{parse, ParserConfig};
+
+#[test]
+fn abc_key_field() {
+    assert_eq!(abc_key("G", Notation::English).unwrap(), "G");
+    assert_eq!(abc_key("Bbm", Notation::English).unwrap(), "Bbm");
+    assert_eq!(abc_key("F#mi", Notation::English).unwrap(), "F#m");
+    assert_eq!(abc_key("Hm", Notation::German).unwrap(), "Bm");
+    assert_eq!(abc_key("B", Notation::German).unwrap(), "Bb");
+    assert!(abc_key("G7", Notation::English).is_none());
+    assert!(abc_key("X", Notation::English).is_none());
+    assert!(abc_key("IV", Notation::Roman).is_none());
+}
+
+#[test]
+fn abc_chord_symbols() {
+    assert_eq!(abc_chord("C/G", Notation::English), "C/G");
+    assert_eq!(abc_chord("Hm7", Notation::German), "Bm7");
+    assert_eq!(abc_chord("B", Notation::German), "Bb");
+    assert_eq!(abc_chord("IV", Notation::Roman), "^IV");
+    assert_eq!(abc_chord("N.C.", Notation::English), "^N.C.");
+    assert_eq!(abc_chord("\"X\"", Notation::Nashville), "^'X'");
+}
+
+#[test]
+fn abc_song() {
+    let src = r#"
+# Song
+## Subtitle
+!author: Jane 100% Doe
+!key: Am
+
+1. `Am`Oh Danny `C/G`Boy,
+the pipes, the `G7`pipes-are
+`Dm`
+
+> Cho`E`rus.
+
+# Empty song
+"#;
+
+    let songs = parse(src, ParserConfig::new(Notation::English, false)).unwrap();
+    let abc = render_songs(Some("Songbook"), &songs);
+
+    assert!(abc.starts_with("%abc-2.1\n% Songbook\n\nX:1\n"));
+    assert!(abc.contains(
+        r#"
+X:1
+T:Song
+T:Subtitle
+C:Jane 100\% Doe
+M:4/4
+L:1/1
+K:Am
+T:1.
+"Am"C, | "C/G"C, |
+w:Oh~Danny Boy,
+C, | "G7"C, |
+w:the~pipes,~the pipes\-are
+"Dm"C, |
+w:*
+T:Ch.
+C, | "E"C, |
+w:Cho rus.
+
+X:2
+T:Empty song
+M:4/4
+L:1/1
+K:none
+"#
+    ));
+    assert!(abc.ends_with("K:none\n"));
+}
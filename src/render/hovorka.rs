@@ -1,3 +1,5 @@
+use std::io::Write;
+
 use semver::Version;
 
 use super::template::HbRender;
@@ -9,16 +11,30 @@ use crate::util::ImgCache;
 
 default_template!(DEFAULT_TEMPLATE, "hovorka.hbs");
 
+/// Escapes XML special characters, used instead of the default HTML escaping of handlebars.
+fn xml_escape(input: &str) -> String {
+    let mut res = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => res.push_str("&amp;"),
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&apos;"),
+            c => res.push(c),
+        }
+    }
+
+    res
+}
+
 pub struct RHovorka(HbRender);
 
 impl RHovorka {
     pub fn new(project: &Project, output: &Output, img_cache: &ImgCache) -> Result<Self> {
-        Ok(Self(HbRender::new(
-            project,
-            output,
-            &DEFAULT_TEMPLATE,
-            img_cache,
-        )?))
+        let mut hb = HbRender::new(project, output, &DEFAULT_TEMPLATE, img_cache)?;
+        hb.setup(|hb| hb.register_escape_fn(xml_escape));
+        Ok(Self(hb))
     }
 }
 
@@ -27,7 +43,34 @@ impl Render for RHovorka {
         self.0.render(output, context)
     }
 
+    fn render_to_writer(
+        &self,
+        _app: &App,
+        w: &mut dyn Write,
+        context: RenderContext,
+    ) -> Result<()> {
+        self.0.render_to_writer(w, context)
+    }
+
     fn version(&self) -> Option<Version> {
         self.0.version()
     }
+
+    fn song_templates(&self) -> Vec<(&Path, Option<Version>)> {
+        self.0.song_templates()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn xml_escape_special_chars() {
+        assert_eq!(
+            xml_escape(r#"Rock & Roll <live> "it's""#),
+            "Rock &amp; Roll &lt;live&gt; &quot;it&apos;s&quot;"
+        );
+        assert_eq!(xml_escape("Žluťoučký kůň"), "Žluťoučký kůň");
+    }
 }
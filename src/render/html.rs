@@ -1,3 +1,5 @@
+use std::io::Write;
+
 use semver::Version;
 
 use super::template::{DpiHelper, HbRender};
@@ -16,8 +18,9 @@ impl RHtml {
         let mut hb = HbRender::new(project, output, &DEFAULT_TEMPLATE, img_cache)?;
 
         // Setup HTML-specific helpers
-        hb.hb
-            .register_helper("scale", DpiHelper::new(output, "scale"));
+        hb.setup(|hb| {
+            hb.register_helper("scale", DpiHelper::new(output, "scale"));
+        });
 
         Ok(Self(hb))
     }
@@ -25,10 +28,29 @@ impl RHtml {
 
 impl Render for RHtml {
     fn render(&self, _app: &App, output: &Path, context: RenderContext) -> Result<()> {
-        self.0.render(output, context)
+        match context.output.split_dir() {
+            Some(dir) => self.0.render_split(output, &dir, context),
+            None => self.0.render(output, context),
+        }
+    }
+
+    fn render_to_writer(
+        &self,
+        _app: &App,
+        w: &mut dyn Write,
+        context: RenderContext,
+    ) -> Result<()> {
+        if context.output.split_dir().is_some() {
+            bail!("Split HTML output can't be rendered to a writer");
+        }
+        self.0.render_to_writer(w, context)
     }
 
     fn version(&self) -> Option<Version> {
         self.0.version()
     }
+
+    fn song_templates(&self) -> Vec<(&Path, Option<Version>)> {
+        self.0.song_templates()
+    }
 }
@@ -1,8 +1,44 @@
 use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use serde::Serialize;
 
 use super::{Render, RenderContext};
 use crate::app::App;
+use crate::book::{SongRef, TocEntry};
+use crate::music::Notation;
 use crate::prelude::*;
+use crate::project::{JsonStyle, Metadata, Output};
+use crate::ProgramMeta;
+
+/// JSON Schema of the output, it needs to be updated along with the AST version.
+pub const SCHEMA: &str = include_str!("bard.schema.json");
+
+/// First line of NDJSON output, ie. the `RenderContext` without songs.
+#[derive(Serialize)]
+struct NdjsonHeader<'a> {
+    book: &'a Metadata,
+    songs_sorted: &'a [SongRef],
+    toc: &'a [TocEntry],
+    notation: Notation,
+    output: &'a Output,
+    program: &'a ProgramMeta,
+    schema_version: &'a str,
+}
+
+impl<'a> NdjsonHeader<'a> {
+    fn new(context: &'a RenderContext) -> Self {
+        Self {
+            book: &context.book,
+            songs_sorted: &context.songs_sorted,
+            toc: &context.toc,
+            notation: context.notation,
+            output: context.output,
+            program: &context.program,
+            schema_version: &context.schema_version,
+        }
+    }
+}
 
 #[derive(Debug, Default)]
 pub struct RJson;
@@ -11,13 +47,31 @@ impl RJson {
     pub fn new() -> Self {
         Self
     }
+
+    fn write(mut w: impl Write, context: &RenderContext) -> Result<()> {
+        match context.output.json_style {
+            JsonStyle::Pretty => serde_json::to_writer_pretty(&mut w, context)?,
+            JsonStyle::Compact => serde_json::to_writer(&mut w, context)?,
+            JsonStyle::Ndjson => {
+                serde_json::to_writer(&mut w, &NdjsonHeader::new(context))?;
+                for song in context.songs.iter() {
+                    w.write_all(b"\n")?;
+                    serde_json::to_writer(&mut w, song)?;
+                }
+                w.write_all(b"\n")?;
+            }
+        }
+
+        w.flush()?;
+        Ok(())
+    }
 }
 
 impl Render for RJson {
     fn render(&self, _app: &App, output: &Path, context: RenderContext) -> Result<()> {
         File::create(output)
             .map_err(Error::from)
-            .and_then(|mut f| serde_json::to_writer_pretty(&mut f, &context).map_err(Error::from))
+            .and_then(|f| Self::write(BufWriter::new(f), &context))
             .with_context(|| format!("Error writing output file: {:?}", output))
     }
 }
@@ -0,0 +1,272 @@
+//! LilyPond Renderer.
+//!
+//! Exports chord charts for engraving with LilyPond: each verse becomes a score
+//! with a `ChordNames` context holding the chord sequence and a `Lyrics` context
+//! holding the text sung under each chord, there's no melody.
+//! Every chord and its lyrics take up one whole note so that they line up.
+
+use std::fmt::{self, Write as _};
+use std::fs;
+use std::slice;
+
+use super::{Render, RenderContext};
+use crate::app::App;
+use crate::book::{Block, Inline, Song, Verse, VerseLabel};
+use crate::music::{Chromatic, Notation};
+use crate::prelude::*;
+
+const LILYPOND_VERSION: &str = "2.24.0";
+
+#[derive(Debug, Default)]
+pub struct RLilypond;
+
+impl RLilypond {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Render for RLilypond {
+    fn render(&self, _app: &App, output: &Path, context: RenderContext) -> Result<()> {
+        let title = context.book.get("title").and_then(|title| title.as_str());
+        let ly = render_songs(title, &context.songs);
+        fs::write(output, ly).with_context(|| format!("Error writing output file: {:?}", output))
+    }
+}
+
+/// Render `songs` as a LilyPond file, each song is a separate `\bookpart`.
+pub fn render_songs(title: Option<&str>, songs: &[Song]) -> String {
+    let mut out = String::new();
+
+    writeln!(out, "\\version \"{}\"", LILYPOND_VERSION).unwrap();
+    out.push_str("\n\\header {\n");
+    if let Some(title) = title {
+        writeln!(out, "  title = {}", string(title)).unwrap();
+    }
+    out.push_str("  tagline = ##f\n}\n");
+
+    for song in songs {
+        write_song(&mut out, song);
+    }
+
+    out
+}
+
+fn write_song(out: &mut String, song: &Song) {
+    writeln!(
+        out,
+        "\n\\bookpart {{\n  \\header {{ title = {} }}",
+        string(&song.title)
+    )
+    .unwrap();
+
+    let mut empty = true;
+    for block in song.blocks.iter() {
+        if let Block::Verse(verse) = block {
+            empty &= !write_verse(out, verse, verse.notation.unwrap_or(song.notation));
+        }
+    }
+
+    if empty {
+        // LilyPond needs some content for the title to be typeset
+        out.push_str("  \\markup \\null\n");
+    }
+    out.push_str("}\n");
+}
+
+/// Write `verse` as a `\score`, returns `false` if the verse is empty and nothing was written.
+fn write_verse(out: &mut String, verse: &Verse, notation: Notation) -> bool {
+    let lines: Vec<_> = verse
+        .paragraphs
+        .iter()
+        .flat_map(|para| para.split(Inline::is_break))
+        .map(|line| chord_groups(line, notation))
+        .filter(|groups| !groups.is_empty())
+        .collect();
+    if lines.is_empty() {
+        return false;
+    }
+
+    out.push_str("  \\score {\n    <<\n      \\new ChordNames \\chordmode {\n");
+    for (i, groups) in lines.iter().enumerate() {
+        out.push_str("       ");
+        for group in groups {
+            out.push(' ');
+            match group.chord.as_ref() {
+                Some(chord) => write!(out, "{}1{}", chord.root, chord.modifiers).unwrap(),
+                None => out.push_str("s1"),
+            }
+        }
+        if i + 1 < lines.len() {
+            out.push_str(" \\break");
+        }
+        out.push('\n');
+    }
+
+    out.push_str("      }\n      \\new Lyrics \\lyricmode {\n");
+    for groups in lines.iter() {
+        out.push_str("       ");
+        for group in groups {
+            out.push(' ');
+            let lyric = group.lyric.trim();
+            if lyric.is_empty() {
+                out.push_str("\\skip 1");
+            } else {
+                write!(out, "{}1", string(lyric)).unwrap();
+            }
+        }
+        out.push('\n');
+    }
+    out.push_str("      }\n    >>\n");
+
+    if let Some(label) = verse_label(&verse.label) {
+        writeln!(out, "    \\header {{ piece = {} }}", string(&label)).unwrap();
+    }
+    out.push_str("  }\n");
+    true
+}
+
+pub(super) fn verse_label(label: &VerseLabel) -> Option<String> {
+    match label {
+        VerseLabel::Verse(num) => Some(format!("{}.", num)),
+        VerseLabel::Chorus(None) => Some("Ch.".to_string()),
+        VerseLabel::Chorus(Some(num)) => Some(format!("Ch. {}", num)),
+        VerseLabel::Custom(label) => Some(label.to_string()),
+        VerseLabel::None {} => None,
+    }
+}
+
+/// A LilyPond string literal.
+fn string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[derive(Default)]
+struct ChordGroup {
+    chord: Option<LyChord>,
+    lyric: String,
+}
+
+/// Split a line into groups of a chord and the lyrics under it,
+/// lyrics before the first chord form a group without a chord.
+fn chord_groups(line: &[Inline], notation: Notation) -> Vec<ChordGroup> {
+    let mut groups = vec![ChordGroup::default()];
+
+    for inline in line {
+        if let Inline::Chord(chord) = inline {
+            let mut lyric = String::new();
+            collect_text(&chord.inlines, &mut lyric);
+            groups.push(ChordGroup {
+                chord: Some(LyChord::parse(&chord.chord, notation)),
+                lyric,
+            });
+        } else {
+            let group = groups.last_mut().unwrap();
+            collect_text(slice::from_ref(inline), &mut group.lyric);
+        }
+    }
+
+    groups.retain(|group| group.chord.is_some() || !group.lyric.trim().is_empty());
+    groups
+}
+
+pub(super) fn collect_text(inlines: &[Inline], target: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Text { text } => target.push_str(text),
+            Inline::Emph(i) | Inline::Strong(i) => collect_text(&i.inlines, target),
+            Inline::Link(link) => target.push_str(&link.text),
+            _ => {}
+        }
+    }
+}
+
+/// A chord in LilyPond's `\chordmode` syntax, split around where the duration goes,
+/// eg. `Bm7/F#` is `b` and `:m7/fis`.
+///
+/// Chords are only understood in the English and German notation, chords which can't be parsed
+/// are exported as a rest, which LilyPond displays as _N.C._
+#[derive(PartialEq, Eq, Debug)]
+struct LyChord {
+    root: &'static str,
+    modifiers: String,
+}
+
+impl LyChord {
+    fn parse(chord: &str, notation: Notation) -> Self {
+        let rest = Self {
+            root: "r",
+            modifiers: String::new(),
+        };
+
+        if !matches!(notation, Notation::English | Notation::German) {
+            return rest;
+        }
+        let (root, root_size) = match Chromatic::parse_span(chord, notation) {
+            Some(root) => root,
+            None => return rest,
+        };
+
+        let mut suffix = &chord[root_size..];
+        let mut bass = None;
+
+        // Slash chords, such as C/G
+        if let Some((s, b)) = suffix.rsplit_once('/') {
+            if let Some((b, _)) =
+                Chromatic::parse_span(b, notation).filter(|&(_, size)| size == b.len())
+            {
+                suffix = s;
+                bass = Some(b);
+            }
+        }
+
+        let mut modifiers = Self::modifiers(suffix).to_string();
+        if let Some(bass) = bass {
+            modifiers.push('/');
+            modifiers.push_str(Self::note_name(bass));
+        }
+
+        Self {
+            root: Self::note_name(root),
+            modifiers,
+        }
+    }
+
+    /// Note name in LilyPond's default (Dutch) note names.
+    fn note_name(note: Chromatic) -> &'static str {
+        const NAMES: [&str; 12] = [
+            "c", "cis", "d", "es", "e", "f", "fis", "g", "as", "a", "bes", "b",
+        ];
+        NAMES[note.num() as usize]
+    }
+
+    /// Chord modifiers for a chord suffix. Suffixes that aren't understood
+    /// are dropped, leaving a major chord.
+    fn modifiers(suffix: &str) -> &'static str {
+        match suffix {
+            "m" | "mi" | "min" => ":m",
+            "7" => ":7",
+            "maj7" | "M7" => ":maj7",
+            "m7" | "mi7" | "min7" => ":m7",
+            "6" => ":6",
+            "m6" | "mi6" => ":m6",
+            "9" => ":9",
+            "dim" => ":dim",
+            "dim7" => ":dim7",
+            "m7b5" | "mi7b5" => ":m7.5-",
+            "aug" | "+" => ":aug",
+            "sus2" => ":sus2",
+            "sus4" | "sus" => ":sus4",
+            _ => "",
+        }
+    }
+}
+
+impl fmt::Display for LyChord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.root, self.modifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests;
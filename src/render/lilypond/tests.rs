@@ -0,0 +1,85 @@
+use super::*;
+use crate::parser::{parse, ParserConfig};
+
+fn chord(chord: &str) -> String {
+    LyChord::parse(chord, Notation::English).to_string()
+}
+
+#[test]
+fn lilypond_chord_names() {
+    // Major
+    assert_eq!(chord("C"), "c");
+    assert_eq!(chord("F#"), "fis");
+    assert_eq!(chord("Bb"), "bes");
+    // Minor
+    assert_eq!(chord("Bm"), "b:m");
+    assert_eq!(chord("Ebmi"), "es:m");
+    // Seventh
+    assert_eq!(chord("G7"), "g:7");
+    assert_eq!(chord("Cmaj7"), "c:maj7");
+    assert_eq!(chord("Am7"), "a:m7");
+    // Diminished
+    assert_eq!(chord("Bdim"), "b:dim");
+    assert_eq!(chord("C#dim7"), "cis:dim7");
+    // Slash chords, unknown suffixes and chords that can't be parsed
+    assert_eq!(chord("C/G"), "c/g");
+    assert_eq!(chord("Dm/F#"), "d:m/fis");
+    assert_eq!(chord("Cadd9"), "c");
+    assert_eq!(chord("N.C."), "r");
+
+    assert_eq!(LyChord::parse("Hm", Notation::German).to_string(), "b:m");
+    assert_eq!(LyChord::parse("B", Notation::German).to_string(), "bes");
+    assert_eq!(LyChord::parse("I", Notation::Roman).to_string(), "r");
+}
+
+#[test]
+fn lilypond_song() {
+    let src = r#"
+# Song
+
+1. `G7`Oh Danny `C/G`Boy,
+the pipes `Bm`
+
+> "Chorus".
+
+# Empty song
+"#;
+
+    let songs = parse(src, ParserConfig::new(Notation::English, false)).unwrap();
+    let ly = render_songs(Some("Songbook"), &songs);
+
+    assert!(ly.starts_with("\\version \"2.24.0\"\n\n\\header {\n  title = \"Songbook\"\n"));
+    assert!(ly.contains(
+        r#"
+\bookpart {
+  \header { title = "Song" }
+  \score {
+    <<
+      \new ChordNames \chordmode {
+        g1:7 c1/g \break
+        s1 b1:m
+      }
+      \new Lyrics \lyricmode {
+        "Oh Danny"1 "Boy,"1
+        "the pipes"1 \skip 1
+      }
+    >>
+    \header { piece = "1." }
+  }
+"#
+    ));
+    assert!(ly.contains(
+        r#"\lyricmode {
+        "\"Chorus\"."1
+      }"#
+    ));
+    assert!(ly.contains("\\header { piece = \"Ch.\" }"));
+    assert!(ly.ends_with(
+        r#"
+\bookpart {
+  \header { title = "Empty song" }
+  \markup \null
+}
+"#
+    ));
+}
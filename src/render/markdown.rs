@@ -0,0 +1,523 @@
+//! Markdown Renderer.
+//!
+//! Renders songs back into bard Markdown such that parsing the output
+//! yields the same AST. The exceptions are alternative chords,
+//! which are the result of transposition extensions and have no Markdown form,
+//! and HTML, which is only rendered approximately.
+
+use std::fs;
+
+use super::{Render, RenderContext};
+use crate::app::App;
+use crate::book::{Block, Inline, Paragraph, Song, Verse, VerseLabel};
+use crate::parser::metadata_value;
+use crate::prelude::*;
+use crate::project::{Project, Value};
+
+#[derive(Debug)]
+pub struct RMarkdown {
+    smart_punctuation: bool,
+}
+
+impl RMarkdown {
+    pub fn new(project: &Project) -> Self {
+        Self {
+            smart_punctuation: project.settings.smart_punctuation,
+        }
+    }
+}
+
+impl Render for RMarkdown {
+    fn render(&self, _app: &App, output: &Path, context: RenderContext) -> Result<()> {
+        let md = render_songs(&context.songs, self.smart_punctuation);
+        fs::write(output, md).with_context(|| format!("Error writing output file: {:?}", output))
+    }
+}
+
+/// Render `songs` as bard Markdown.
+///
+/// `smart_punctuation` should match the setting the output is going to be parsed with,
+/// if it's on, quotes, dashes and ellipses in text need to be escaped.
+pub fn render_songs(songs: &[Song], smart_punctuation: bool) -> String {
+    let mut writer = MdWriter {
+        out: String::new(),
+        smart_punctuation,
+    };
+
+    for (i, song) in songs.iter().enumerate() {
+        if i > 0 {
+            writer.out.push('\n');
+        }
+        writer.write_song(song);
+    }
+
+    writer.out
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Style {
+    Emph,
+    Strong,
+}
+
+/// Paragraph inlines flattened into a sequence.
+///
+/// The parser splits emphasis and strong elements around chords, breaks, images and HTML tags,
+/// here the formatting is flattened into style stacks so that the pieces can be joined back together.
+enum Piece<'a> {
+    /// A text-like inline and the styles it's nested in.
+    /// `None` stands for an empty formatting element.
+    Content(Option<&'a Inline>, Vec<Style>),
+    /// A chord, break, image, HTML tag or raw TeX.
+    Split(&'a Inline),
+}
+
+fn flatten<'a>(inlines: &'a [Inline], styles: &mut Vec<Style>, pieces: &mut Vec<Piece<'a>>) {
+    for inline in inlines {
+        match inline {
+            Inline::Emph(i) | Inline::Strong(i) => {
+                let style = if matches!(inline, Inline::Emph(..)) {
+                    Style::Emph
+                } else {
+                    Style::Strong
+                };
+
+                styles.push(style);
+                if i.inlines.is_empty() {
+                    pieces.push(Piece::Content(None, styles.clone()));
+                }
+                flatten(&i.inlines, styles, pieces);
+                styles.pop();
+            }
+            Inline::Chord(chord) => {
+                pieces.push(Piece::Split(inline));
+                flatten(&chord.inlines, styles, pieces);
+            }
+            Inline::Break | Inline::Image(..) | Inline::HtmlTag(..) | Inline::Raw { .. } => {
+                pieces.push(Piece::Split(inline))
+            }
+            Inline::Transpose(..) => {}
+            _ => pieces.push(Piece::Content(Some(inline), styles.clone())),
+        }
+    }
+}
+
+enum Token {
+    Md(String),
+    Open(Style),
+    Close(Style),
+}
+
+impl Token {
+    /// Returns the first or last character of the token,
+    /// delimiters count as punctuation regardless of which character is eventually used.
+    fn edge_char(&self, last: bool) -> Option<char> {
+        match self {
+            Self::Md(s) if last => s.chars().last(),
+            Self::Md(s) => s.chars().next(),
+            _ => Some('*'),
+        }
+    }
+}
+
+/// Join paragraph tokens into a string.
+///
+/// Emphasis is written with underscores, except where the delimiter
+/// is adjacent to a word character, underscores don't work inside words.
+fn join_tokens(tokens: &[Token]) -> String {
+    let is_alnum = |c: Option<char>| c.map_or(false, char::is_alphanumeric);
+
+    let mut delims = vec![""; tokens.len()];
+    let mut open_emphs = vec![];
+    for (i, token) in tokens.iter().enumerate() {
+        match token {
+            Token::Open(Style::Strong) | Token::Close(Style::Strong) => delims[i] = "**",
+            Token::Open(Style::Emph) => open_emphs.push(i),
+            Token::Close(Style::Emph) => {
+                let open = open_emphs.pop().expect("Unbalanced emphasis tokens");
+                let before = tokens[..open].iter().rev().find_map(|t| t.edge_char(true));
+                let after = tokens[i + 1..].iter().find_map(|t| t.edge_char(false));
+                let delim = if is_alnum(before) || is_alnum(after) {
+                    "*"
+                } else {
+                    "_"
+                };
+                delims[open] = delim;
+                delims[i] = delim;
+            }
+            Token::Md(..) => {}
+        }
+    }
+
+    tokens
+        .iter()
+        .zip(delims)
+        .fold(String::new(), |mut res, (token, delim)| {
+            match token {
+                Token::Md(s) => res.push_str(s),
+                _ => res.push_str(delim),
+            }
+            res
+        })
+}
+
+fn common_prefix(a: &[Style], b: &[Style]) -> usize {
+    a.iter().zip(b.iter()).take_while(|(a, b)| a == b).count()
+}
+
+struct MdWriter {
+    out: String,
+    smart_punctuation: bool,
+}
+
+impl MdWriter {
+    fn write_song(&mut self, song: &Song) {
+        self.out.push_str("# ");
+        self.out.push_str(&self.escape(&song.title, true));
+        self.out.push('\n');
+        for subtitle in song.subtitles.iter() {
+            self.out.push_str("## ");
+            self.out.push_str(&self.escape(subtitle, true));
+            self.out.push('\n');
+        }
+        for (key, value) in song.metadata.iter() {
+            let value = match value {
+                // Strings are written as-is, unless they'd be read back as another value
+                Value::String(s) if metadata_value(s.trim()) == *value => s.clone(),
+                other => other.to_string(),
+            };
+            self.out.push_str(&format!("!{}: {}\n", key, value));
+        }
+
+        // Consecutive bullet lists need to use different bullet characters,
+        // otherwise they would be parsed as one list.
+        let mut bullet = '*';
+        let mut prev_list = false;
+        // Notation of verse chords as switched by `!=notation`, `None` for the song notation
+        let mut notation = None;
+
+        for block in song.blocks.iter() {
+            if let Block::Verse(verse) = block {
+                if verse.notation != notation {
+                    let name = verse.notation.map_or("default".into(), |nt| nt.to_string());
+                    self.out.push_str(&format!("\n!={}\n", name));
+                    notation = verse.notation;
+                }
+            }
+
+            self.out.push('\n');
+
+            match block {
+                Block::Verse(verse) => self.write_verse(verse),
+                Block::BulletList(list) => {
+                    bullet = match (prev_list, bullet) {
+                        (true, '-') => '*',
+                        _ => '-',
+                    };
+                    for item in list.items.iter() {
+                        self.out.push(bullet);
+                        self.out.push(' ');
+                        self.out.push_str(&self.escape(item, true));
+                        self.out.push('\n');
+                    }
+                }
+                Block::HorizontalLine => self.out.push_str("---\n"),
+                Block::Pre { text, info } => self.write_code_block(text, info),
+                Block::Tab { text } => self.write_code_block(text, "tab"),
+                Block::Image { image, caption } => {
+                    self.out
+                        .push_str(&self.split_md(&Inline::Image(image.clone())));
+                    self.out.push('\n');
+                    if !caption.is_empty() {
+                        self.out.push_str(&self.escape(caption, true));
+                        self.out.push('\n');
+                    }
+                }
+                Block::HtmlBlock(inlines) => {
+                    for inline in inlines.inlines.iter() {
+                        match inline {
+                            Inline::HtmlTag(..) | Inline::Raw { .. } => {
+                                self.out.push_str(&self.split_md(inline));
+                                self.out.push('\n');
+                            }
+                            Inline::Text { text } => self.out.push_str(text),
+                            _ => {}
+                        }
+                    }
+                    if !self.out.ends_with('\n') {
+                        self.out.push('\n');
+                    }
+                }
+            }
+
+            prev_list = matches!(block, Block::BulletList(..));
+        }
+    }
+
+    fn write_code_block(&mut self, text: &str, info: &str) {
+        let longest_run = text
+            .split(|c: char| c != '`')
+            .map(str::len)
+            .max()
+            .unwrap_or(0);
+        let fence = "`".repeat(3.max(longest_run + 1));
+        self.out.push_str(&fence);
+        self.out.push_str(info);
+        self.out.push('\n');
+        self.out.push_str(text);
+        if !text.is_empty() && !text.ends_with('\n') {
+            self.out.push('\n');
+        }
+        self.out.push_str(&fence);
+        self.out.push('\n');
+    }
+
+    fn write_verse(&mut self, verse: &Verse) {
+        let repeat = verse.repeat_count.map(|n| format!("x{}", n));
+
+        match &verse.label {
+            VerseLabel::Verse(num) => {
+                let marker = format!("{}. ", num);
+                self.write_paragraphs(&verse.paragraphs, &marker, "", repeat);
+            }
+            VerseLabel::Chorus(num) => {
+                let prefix = format!("{} ", ">".repeat(num.unwrap_or(1) as usize));
+                self.write_paragraphs(&verse.paragraphs, &prefix, &prefix, repeat);
+            }
+            VerseLabel::Custom(label) => {
+                self.out.push_str("### ");
+                self.out.push_str(&self.escape(label, true));
+                if let Some(repeat) = repeat {
+                    self.out.push(' ');
+                    self.out.push_str(&repeat);
+                }
+                self.out.push('\n');
+                self.write_paragraphs(&verse.paragraphs, "", "", None);
+            }
+            VerseLabel::None {} => self.write_paragraphs(&verse.paragraphs, "", "", repeat),
+        }
+    }
+
+    /// Writes verse paragraphs. The `first_prefix` is written in front of the first line
+    /// (ie. the verse label) and `line_prefix` in front of every other line.
+    fn write_paragraphs(
+        &mut self,
+        paragraphs: &[Paragraph],
+        first_prefix: &str,
+        line_prefix: &str,
+        repeat: Option<String>,
+    ) {
+        if paragraphs.is_empty() && repeat.is_none() {
+            self.out.push_str(first_prefix.trim_end());
+            self.out.push('\n');
+            return;
+        }
+
+        self.out.push_str(first_prefix);
+        if let Some(repeat) = repeat {
+            // The repeat marker is placed on its own line
+            self.out.push_str(&repeat);
+            self.out.push('\n');
+            if !paragraphs.is_empty() {
+                self.out.push_str(line_prefix);
+            }
+        }
+
+        for (i, para) in paragraphs.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(line_prefix.trim_end());
+                self.out.push('\n');
+                self.out.push_str(line_prefix);
+            }
+            self.write_paragraph(para, line_prefix);
+            self.out.push('\n');
+        }
+    }
+
+    fn write_paragraph(&mut self, inlines: &[Inline], line_prefix: &str) {
+        let mut pieces = vec![];
+        flatten(inlines, &mut vec![], &mut pieces);
+
+        let mut tokens = vec![];
+        let mut stack: Vec<Style> = vec![];
+        let mut line_start = true;
+
+        let close_to = |stack: &mut Vec<Style>, len: usize, tokens: &mut Vec<Token>| {
+            while stack.len() > len {
+                tokens.push(Token::Close(stack.pop().unwrap()));
+            }
+        };
+
+        for (i, piece) in pieces.iter().enumerate() {
+            match piece {
+                Piece::Content(inline, styles) => {
+                    let common = common_prefix(&stack, styles);
+                    close_to(&mut stack, common, &mut tokens);
+                    for style in &styles[common..] {
+                        tokens.push(Token::Open(*style));
+                        stack.push(*style);
+                        line_start = false;
+                    }
+
+                    if let Some(inline) = inline {
+                        let md = self.content_md(inline, line_start);
+                        line_start = line_start && md.is_empty();
+                        tokens.push(Token::Md(md));
+                    }
+                }
+                Piece::Split(inline) => {
+                    // Only keep formatting open across the split if the piece right after it
+                    // is formatted too, otherwise the parser would see an additional empty element.
+                    let next_styles = match pieces.get(i + 1) {
+                        Some(Piece::Content(_, styles)) => styles.as_slice(),
+                        _ => &[],
+                    };
+                    let common = common_prefix(&stack, next_styles);
+                    close_to(&mut stack, common, &mut tokens);
+
+                    if let Inline::Break = inline {
+                        // A break on an otherwise empty line needs to be a hard break,
+                        // a soft one would yield a blank line.
+                        let newline = if line_start { "\\\n" } else { "\n" };
+                        tokens.push(Token::Md(format!("{}{}", newline, line_prefix)));
+                        line_start = true;
+                    } else {
+                        tokens.push(Token::Md(self.split_md(inline)));
+                        line_start = false;
+                    }
+                }
+            }
+        }
+
+        close_to(&mut stack, 0, &mut tokens);
+        self.out.push_str(&join_tokens(&tokens));
+    }
+
+    fn content_md(&self, inline: &Inline, line_start: bool) -> String {
+        match inline {
+            Inline::Text { text } => self.escape(text, line_start),
+            Inline::Link(link) => format!(
+                "[{}]({}{})",
+                self.escape(&link.text, false),
+                link_destination(&link.url),
+                link_title(&link.title),
+            ),
+            Inline::ChorusRef(cr) => format!(
+                "{}!{}",
+                cr.prefix_space,
+                ">".repeat(cr.num.unwrap_or(1) as usize)
+            ),
+            Inline::VerseRef(vr) if vr.label.contains(char::is_whitespace) => {
+                format!("{}!>\"{}\"", vr.prefix_space, vr.label)
+            }
+            Inline::VerseRef(vr) => format!("{}!>{}", vr.prefix_space, vr.label),
+            // Whitespace around repeat signs is dropped by the parser, so it's safe to add some
+            Inline::RepeatStart if line_start => "|: ".to_string(),
+            Inline::RepeatStart => " |: ".to_string(),
+            Inline::RepeatEnd(re) => match re.count {
+                Some(count) => format!(" :|x{} ", count),
+                None => " :| ".to_string(),
+            },
+            Inline::Footnote(footnote) => {
+                let mut writer = MdWriter {
+                    out: String::new(),
+                    smart_punctuation: self.smart_punctuation,
+                };
+                writer.write_paragraph(&footnote.inlines, "");
+                format!("^[{}]", writer.out)
+            }
+            _ => String::new(),
+        }
+    }
+
+    fn split_md(&self, inline: &Inline) -> String {
+        match inline {
+            Inline::Chord(chord) => {
+                let ticks = "`".repeat(chord.backticks);
+                let text = if chord.baseline && chord.chord.contains(' ') {
+                    chord.chord.replace(' ', "_")
+                } else if chord.baseline {
+                    format!("{}_", chord.chord)
+                } else {
+                    chord.chord.to_string()
+                };
+                format!("{}{}{}", ticks, text, ticks)
+            }
+            Inline::Image(img) => format!(
+                "![{}]({}{})",
+                self.escape(&img.title, false),
+                link_destination(&img.path),
+                link_title(&img.class),
+            ),
+            Inline::HtmlTag(tag) => {
+                let (name, self_closing) = match tag.name.strip_suffix('/') {
+                    Some(name) => (name, "/"),
+                    None => (&tag.name[..], ""),
+                };
+                let attrs = tag
+                    .attrs
+                    .iter()
+                    .fold(String::new(), |mut res, (attr, value)| {
+                        res.push_str(&format!(" {}=\"{}\"", attr, value.replace('"', "&quot;")));
+                        res
+                    });
+                format!("<{}{}{}>", name, attrs, self_closing)
+            }
+            Inline::Raw { tex } => format!(
+                "<tex code=\"{}\"/>",
+                tex.replace('&', "&amp;").replace('"', "&quot;")
+            ),
+            _ => String::new(),
+        }
+    }
+
+    /// Escape text so that it's not interpreted as Markdown syntax.
+    /// If `line_start` is `true`, block-level syntax, such as list markers, is escaped as well.
+    fn escape(&self, text: &str, line_start: bool) -> String {
+        let mut res = String::with_capacity(text.len());
+        let mut chars = text.chars().peekable();
+        let mut first = line_start;
+        let mut digits_only = line_start;
+
+        while let Some(c) = chars.next() {
+            let next = chars.peek().copied();
+            let escape = match c {
+                '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '&' | '#' => true,
+                '-' | '+' | '=' if first => true,
+                '.' | ')' if digits_only && !first => true,
+                '"' | '\'' => self.smart_punctuation,
+                '.' | '-' => self.smart_punctuation && next == Some(c),
+                _ => false,
+            };
+
+            if escape {
+                res.push('\\');
+            }
+            res.push(c);
+
+            digits_only = digits_only && c.is_ascii_digit();
+            first = false;
+        }
+
+        res
+    }
+}
+
+fn link_destination(url: &str) -> String {
+    if url.is_empty() || url.contains(|c: char| c.is_whitespace() || c == '(' || c == ')') {
+        format!("<{}>", url)
+    } else {
+        url.to_string()
+    }
+}
+
+fn link_title(title: &str) -> String {
+    if title.is_empty() {
+        String::new()
+    } else {
+        format!(" \"{}\"", title.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,252 @@
+use fastrand::Rng;
+
+use super::*;
+use crate::parser::{parse, ParserConfig};
+
+const WORDS: &[&str] = &[
+    "la",
+    "dam",
+    "song",
+    "river",
+    "night,",
+    "home.",
+    "oh!",
+    "don't",
+    "\"hey\"",
+    "(yes)",
+    "well...",
+    "so--",
+    "1\\.",
+    "\\*star\\*",
+    "C\\#",
+    "a\\_b",
+    "50%",
+    "€",
+];
+const CHORDS: &[&str] = &["G", "Am", "D7", "Emi", "C/G", "``G``", "C_D", "F_"];
+const LABELS: &[&str] = &["Bridge", "Intro", "Middle eight", "Outro"];
+const NOTATIONS: &[&str] = &["german", "nashville", "solfege", "default"];
+
+fn pick<'a>(rng: &mut Rng, items: &[&'a str]) -> &'a str {
+    items[rng.usize(..items.len())]
+}
+
+fn gen_chord(rng: &mut Rng) -> String {
+    let chord = pick(rng, CHORDS);
+    if chord.starts_with('`') {
+        chord.to_string()
+    } else {
+        format!("`{}`", chord)
+    }
+}
+
+fn gen_token(rng: &mut Rng, labels: &[&str]) -> String {
+    match rng.u32(..15) {
+        0 | 1 => format!("{}{}", gen_chord(rng), pick(rng, WORDS)),
+        2 => gen_chord(rng),
+        3 => format!("_{} {}_", pick(rng, WORDS), pick(rng, WORDS)),
+        4 => format!("**{}**", pick(rng, WORDS)),
+        5 => format!("_{}{}_", gen_chord(rng), pick(rng, WORDS)),
+        6 => format!(
+            "**{} {}{}**",
+            pick(rng, WORDS),
+            gen_chord(rng),
+            pick(rng, WORDS)
+        ),
+        7 => "!>".to_string(),
+        8 if !labels.is_empty() => format!("!>\"{}\"", pick(rng, labels)),
+        9 => "|:".to_string(),
+        10 => ":|".to_string(),
+        11 => ":|x2".to_string(),
+        _ => pick(rng, WORDS).to_string(),
+    }
+}
+
+fn gen_lines(rng: &mut Rng, prefix: &str, labels: &[&str]) -> String {
+    let mut res = String::new();
+    for i in 0..rng.usize(1..4) {
+        if i > 0 {
+            res.push('\n');
+            res.push_str(prefix);
+        }
+        let tokens: Vec<_> = (0..rng.usize(1..6))
+            .map(|_| gen_token(rng, labels))
+            .collect();
+        res.push_str(&tokens.join(" "));
+        if rng.u32(..6) == 0 {
+            res.push_str(" |x3");
+        }
+    }
+    res
+}
+
+fn gen_repeat(rng: &mut Rng, prefix: &str) -> String {
+    if rng.u32(..4) == 0 {
+        format!("x{}\n{}", rng.u32(2..5), prefix)
+    } else {
+        String::new()
+    }
+}
+
+/// Generates a random bard Markdown source with a few songs.
+fn gen_source(rng: &mut Rng) -> String {
+    let mut src = String::new();
+
+    for song in 0..rng.usize(1..3) {
+        src.push_str(&format!("# Song {} {}\n", song, pick(rng, WORDS)));
+        if rng.bool() {
+            src.push_str(&format!("## {}\n", pick(rng, WORDS)));
+        }
+        if rng.u32(..3) == 0 {
+            src.push_str(&format!("!author: {}\n!tempo: 120\n", pick(rng, WORDS)));
+        }
+
+        let mut labels = vec![];
+        for _ in 0..rng.usize(1..7) {
+            src.push('\n');
+            if rng.u32(..6) == 0 {
+                src.push_str(&format!("!={}\n\n", pick(rng, NOTATIONS)));
+            }
+            let block = match rng.u32(..10) {
+                0 | 1 => {
+                    let repeat = gen_repeat(rng, "");
+                    format!("1. {}{}", repeat, gen_lines(rng, "", &labels))
+                }
+                2 | 3 => {
+                    let prefix = format!("{} ", ">".repeat(rng.usize(1..4)));
+                    let repeat = gen_repeat(rng, &prefix);
+                    let lines = gen_lines(rng, &prefix, &labels);
+                    format!("{}{}{}", prefix, repeat, lines)
+                }
+                4 => {
+                    let label = pick(rng, LABELS);
+                    labels.push(label);
+                    let repeat = if rng.bool() { " x2" } else { "" };
+                    let lines = gen_lines(rng, "", &labels);
+                    format!("### {}{}\n{}", label, repeat, lines)
+                }
+                5 => format!("- {}\n- {}", pick(rng, WORDS), pick(rng, WORDS)),
+                6 => "---".to_string(),
+                7 => format!("```\n{}\n  {}\n```", pick(rng, WORDS), pick(rng, WORDS)),
+                8 => "```tab\ne|--0--3--|\nB|-1---3-|\n```".to_string(),
+                _ => gen_lines(rng, "", &labels),
+            };
+            src.push_str(&block);
+            src.push('\n');
+        }
+
+        src.push('\n');
+    }
+
+    src
+}
+
+#[test]
+fn markdown_roundtrip() {
+    for smart_punctuation in [true, false] {
+        for seed in 0..500 {
+            let mut rng = Rng::with_seed(seed);
+            let src = gen_source(&mut rng);
+
+            let config = || ParserConfig::new(Default::default(), smart_punctuation);
+            let songs = parse(&src, config()).unwrap_or_else(|err| {
+                panic!("Generated source failed to parse:\n{}\n{}", err, src)
+            });
+
+            let md = render_songs(&songs, smart_punctuation);
+            let songs2 = parse(&md, config())
+                .unwrap_or_else(|err| panic!("Rendered source failed to parse:\n{}\n{}", err, md));
+
+            assert_eq!(
+                songs, songs2,
+                "Round-trip mismatch, seed: {}, source:\n{}\nrendered:\n{}",
+                seed, src, md
+            );
+        }
+    }
+}
+
+#[test]
+fn markdown_render_basic() {
+    let src = r#"# Song
+## Subtitle
+
+1. `G`Lyrics _with `Am`emph_
+and **strong**.
+
+> x2
+> Chorus !>
+
+### Bridge
+Bridge _`C_D`la_
+
+- item
+"#;
+
+    let songs = parse(src, ParserConfig::default()).unwrap();
+    let md = render_songs(&songs, true);
+    assert_eq!(
+        md,
+        r#"# Song
+## Subtitle
+
+1. `G`Lyrics _with `Am`emph_
+and **strong**.
+
+> x2
+> Chorus !>
+
+### Bridge
+Bridge _`C_D`la_
+
+- item
+"#
+    );
+}
+
+#[test]
+fn markdown_render_notation() {
+    let src = r#"# Song
+
+1. `G`One.
+
+!=solfege
+
+2. `Sol`Two.
+
+3. Three.
+
+> `Do`Chorus.
+
+!=default
+
+4. `C`Four.
+"#;
+
+    let songs = parse(src, ParserConfig::default()).unwrap();
+    let md = render_songs(&songs, true);
+    assert_eq!(
+        md,
+        r#"# Song
+
+1. `G`One.
+
+!=solfege
+
+2. `Sol`Two.
+
+!=default
+
+3. Three.
+
+!=solfege
+
+> `Do`Chorus.
+
+!=default
+
+4. `C`Four.
+"#
+    );
+    assert_eq!(parse(&md, ParserConfig::default()).unwrap(), songs);
+}
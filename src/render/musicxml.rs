@@ -0,0 +1,491 @@
+//! MusicXML Renderer.
+//!
+//! Exports chord charts for notation software: chord symbols as `<harmony>` elements
+//! and lyrics as `<lyric>` text, there's no melody. Every line of lyrics becomes a measure
+//! and every chord becomes an invisible placeholder note carrying the text sung under the chord.
+
+use std::fs::File;
+use std::io;
+use std::io::Write;
+use std::slice;
+
+use quick_xml::events::{BytesDecl, BytesText, Event};
+use quick_xml::Result as XmlResult;
+
+use super::{Render, RenderContext};
+use crate::app::App;
+use crate::book::{Block, Inline, Song, VerseLabel};
+use crate::music::{Chromatic, Notation};
+use crate::prelude::*;
+use crate::util::xml_support::*;
+
+const DOCTYPE: &str = r#"score-partwise PUBLIC "-//Recordare//DTD MusicXML 4.0 Partwise//EN" "http://www.musicxml.org/dtds/partwise.dtd""#;
+
+#[derive(Debug, Default)]
+pub struct RMusicXml;
+
+impl RMusicXml {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Render for RMusicXml {
+    fn render(&self, _app: &App, output: &Path, context: RenderContext) -> Result<()> {
+        let title = context.book.get("title").and_then(|title| title.as_str());
+
+        File::create(output)
+            .map_err(Error::from)
+            .and_then(|f| {
+                let mut writer = Writer::new_with_indent(f, b' ', 2);
+                write_score(&mut writer, title, &context.songs)?;
+
+                let mut f = writer.into_inner();
+                f.write_all(b"\n")?;
+                Ok(())
+            })
+            .with_context(|| format!("Error writing output file: {:?}", output))
+    }
+}
+
+/// Write `songs` as a single-part MusicXML score.
+///
+/// Each song starts on a new page with its title as a text direction,
+/// verse labels are written as rehearsal marks.
+pub fn write_score<W>(writer: &mut Writer<W>, title: Option<&str>, songs: &[Song]) -> XmlResult<()>
+where
+    W: io::Write,
+{
+    writer.write_event(Event::Decl(BytesDecl::new(
+        "1.0",
+        Some("UTF-8"),
+        Some("no"),
+    )))?;
+    writer.write_event(Event::DocType(BytesText::from_escaped(DOCTYPE)))?;
+
+    let measures = measures(songs);
+    let mut score = writer
+        .tag("score-partwise")
+        .attr(("version", "4.0"))
+        .content()?;
+    if let Some(title) = title {
+        score = score.value_wrap("work", Elem("work-title", title))?;
+    }
+    score
+        .value_wrap("part-list", ScorePart)?
+        .value(Part(&measures))?
+        .finish()
+}
+
+fn measures(songs: &[Song]) -> Vec<Measure> {
+    let mut measures: Vec<Measure> = vec![];
+
+    for (i, song) in songs.iter().enumerate() {
+        let start = measures.len();
+
+        for block in song.blocks.iter() {
+            let verse = match block {
+                Block::Verse(verse) => verse,
+                _ => continue,
+            };
+
+            let notation = verse.notation.unwrap_or(song.notation);
+            let mut label = verse_label(&verse.label);
+            let lines = verse
+                .paragraphs
+                .iter()
+                .flat_map(|para| para.split(Inline::is_break));
+
+            for line in lines {
+                let groups = chord_groups(line, notation);
+                if groups.is_empty() {
+                    continue;
+                }
+
+                let mut measure = Measure::new(measures.len() + 1);
+                measure.print = Some("new-system");
+                measure.label = label.take();
+                measure.groups = groups;
+                measures.push(measure);
+            }
+        }
+
+        // The first measure of a song carries the title and starts a new page
+        if measures.len() == start {
+            measures.push(Measure::new(measures.len() + 1));
+        }
+        let first = &mut measures[start];
+        first.title = Some(&*song.title);
+        first.print = if i > 0 { Some("new-page") } else { None };
+    }
+
+    if measures.is_empty() {
+        // MusicXML requires at least one measure in a part
+        measures.push(Measure::new(1));
+    }
+
+    measures
+}
+
+fn verse_label(label: &VerseLabel) -> Option<String> {
+    match label {
+        VerseLabel::Verse(num) => Some(format!("{}.", num)),
+        VerseLabel::Chorus(None) => Some("Ch.".to_string()),
+        VerseLabel::Chorus(Some(num)) => Some(format!("Ch. {}", num)),
+        VerseLabel::Custom(label) => Some(label.to_string()),
+        VerseLabel::None {} => None,
+    }
+}
+
+/// Split a line into groups of a chord and the lyrics under it,
+/// lyrics before the first chord form a group without a chord.
+fn chord_groups(line: &[Inline], notation: Notation) -> Vec<ChordGroup> {
+    let mut groups = vec![ChordGroup::default()];
+
+    for inline in line {
+        if let Inline::Chord(chord) = inline {
+            let mut lyric = String::new();
+            collect_text(&chord.inlines, &mut lyric);
+            groups.push(ChordGroup {
+                harmony: Some(Harmony::parse(&chord.chord, notation)),
+                lyric,
+            });
+        } else {
+            let group = groups.last_mut().unwrap();
+            collect_text(slice::from_ref(inline), &mut group.lyric);
+        }
+    }
+
+    groups.retain(|group| group.harmony.is_some() || !group.lyric.trim().is_empty());
+    groups
+}
+
+fn collect_text(inlines: &[Inline], target: &mut String) {
+    for inline in inlines {
+        match inline {
+            Inline::Text { text } => target.push_str(text),
+            Inline::Emph(i) | Inline::Strong(i) => collect_text(&i.inlines, target),
+            Inline::Link(link) => target.push_str(&link.text),
+            _ => {}
+        }
+    }
+}
+
+/// A `<name>value</name>` element, for nesting in other elements.
+struct Elem<T>(&'static str, T);
+
+impl<T> XmlWrite for Elem<T>
+where
+    T: XmlWrite,
+{
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        w.tag(self.0).content()?.value(&self.1)?.finish()
+    }
+}
+
+/// An empty `<name/>` element.
+struct Empty(&'static str);
+
+impl XmlWrite for Empty {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        w.tag(self.0).finish()
+    }
+}
+
+struct ScorePart;
+
+impl XmlWrite for ScorePart {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        w.tag("score-part")
+            .attr(("id", "P1"))
+            .content()?
+            .value(Elem("part-name", "Chords"))?
+            .finish()
+    }
+}
+
+struct Part<'a>(&'a [Measure<'a>]);
+
+impl XmlWrite for Part<'_> {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        w.tag("part")
+            .attr(("id", "P1"))
+            .content()?
+            .many(self.0)?
+            .finish()
+    }
+}
+
+/// A measure holding one line of lyrics.
+struct Measure<'a> {
+    number: usize,
+    /// Layout break before the measure, `"new-page"` or `"new-system"`.
+    print: Option<&'static str>,
+    title: Option<&'a str>,
+    label: Option<String>,
+    groups: Vec<ChordGroup<'a>>,
+}
+
+impl<'a> Measure<'a> {
+    fn new(number: usize) -> Self {
+        Self {
+            number,
+            print: None,
+            title: None,
+            label: None,
+            groups: vec![],
+        }
+    }
+}
+
+impl XmlWrite for Measure<'_> {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        let mut measure = w.tag("measure").attr(("number", self.number)).content()?;
+        if let Some(print) = self.print {
+            measure = measure.value(Print(print))?;
+        }
+        if self.number == 1 {
+            measure = measure.value_wrap("attributes", Elem("divisions", 1))?;
+        }
+        if let Some(title) = self.title {
+            measure = measure.value(Direction("words", title))?;
+        }
+        if let Some(label) = self.label.as_deref() {
+            measure = measure.value(Direction("rehearsal", label))?;
+        }
+
+        self.groups
+            .iter()
+            .try_fold(measure, |measure, group| {
+                let measure = match group.harmony.as_ref() {
+                    Some(harmony) => measure.value(harmony)?,
+                    None => measure,
+                };
+                measure.value(Note(group.lyric.trim()))
+            })?
+            .finish()
+    }
+}
+
+struct Print(&'static str);
+
+impl XmlWrite for Print {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        w.tag("print").attr((self.0, "yes")).finish()
+    }
+}
+
+/// A text direction above the staff, `kind` is `"words"` or `"rehearsal"`.
+struct Direction<'a>(&'static str, &'a str);
+
+impl XmlWrite for Direction<'_> {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        w.tag("direction")
+            .attr(("placement", "above"))
+            .content()?
+            .value_wrap("direction-type", Elem(self.0, self.1))?
+            .finish()
+    }
+}
+
+/// An invisible placeholder note with the lyrics, if any.
+struct Note<'a>(&'a str);
+
+impl XmlWrite for Note<'_> {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        let mut note = w
+            .tag("note")
+            .attr(("print-object", "no"))
+            .content()?
+            .value(Empty("rest"))?
+            .value(Elem("duration", 1))?;
+        if !self.0.is_empty() {
+            note = note.value(Lyric(self.0))?;
+        }
+        note.finish()
+    }
+}
+
+struct Lyric<'a>(&'a str);
+
+impl XmlWrite for Lyric<'_> {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        w.tag("lyric")
+            .attr(("number", 1))
+            .content()?
+            .value(Elem("syllabic", "single"))?
+            .value(Elem("text", self.0))?
+            .finish()
+    }
+}
+
+#[derive(Default)]
+struct ChordGroup<'a> {
+    harmony: Option<Harmony<'a>>,
+    lyric: String,
+}
+
+/// A chord symbol. Chords are only understood in the English and German notation,
+/// otherwise, or if the chord can't be parsed, the chord is exported as text.
+struct Harmony<'a> {
+    chord: &'a str,
+    root: Option<Chromatic>,
+    suffix: &'a str,
+    bass: Option<Chromatic>,
+}
+
+impl<'a> Harmony<'a> {
+    fn parse(chord: &'a str, notation: Notation) -> Self {
+        let mut res = Self {
+            chord,
+            root: None,
+            suffix: "",
+            bass: None,
+        };
+
+        if !matches!(notation, Notation::English | Notation::German) {
+            return res;
+        }
+        let (root, root_size) = match Chromatic::parse_span(chord, notation) {
+            Some(root) => root,
+            None => return res,
+        };
+
+        res.root = Some(root);
+        res.suffix = &chord[root_size..];
+
+        // Slash chords, such as C/G
+        if let Some((suffix, bass)) = res.suffix.rsplit_once('/') {
+            if let Some((bass, _)) = Chromatic::parse_span(bass, notation)
+                .filter(|&(_, bass_size)| bass_size == bass.len())
+            {
+                res.suffix = suffix;
+                res.bass = Some(bass);
+            }
+        }
+
+        res
+    }
+
+    /// The MusicXML kind value of the chord, the suffix is used as display text either way.
+    fn kind(&self) -> &'static str {
+        if self.root.is_none() {
+            return "other";
+        }
+
+        match self.suffix {
+            "" => "major",
+            "m" | "mi" | "min" => "minor",
+            "7" => "dominant",
+            "maj7" | "M7" => "major-seventh",
+            "m7" | "mi7" | "min7" => "minor-seventh",
+            "6" => "major-sixth",
+            "m6" | "mi6" => "minor-sixth",
+            "9" => "dominant-ninth",
+            "dim" => "diminished",
+            "aug" | "+" => "augmented",
+            "sus2" => "suspended-second",
+            "sus4" | "sus" => "suspended-fourth",
+            _ => "other",
+        }
+    }
+}
+
+impl XmlWrite for Harmony<'_> {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        let mut harmony = w.tag("harmony").content()?;
+        harmony = match self.root {
+            Some(root) => harmony.value(Pitch("root", root))?,
+            None => harmony.value(Elem("function", self.chord))?,
+        };
+        harmony = harmony.value(Kind(self.kind(), self.suffix))?;
+        if let Some(bass) = self.bass {
+            harmony = harmony.value(Pitch("bass", bass))?;
+        }
+        harmony.finish()
+    }
+}
+
+/// A `<root>` or `<bass>` pitch of a chord.
+struct Pitch(&'static str, Chromatic);
+
+impl XmlWrite for Pitch {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        const STEPS: [(&str, i8); 12] = [
+            ("C", 0),
+            ("C", 1),
+            ("D", 0),
+            ("E", -1),
+            ("E", 0),
+            ("F", 0),
+            ("F", 1),
+            ("G", 0),
+            ("A", -1),
+            ("A", 0),
+            ("B", -1),
+            ("B", 0),
+        ];
+
+        let (step, alter) = STEPS[self.1.num() as usize];
+        let mut pitch = w
+            .tag(self.0)
+            .content()?
+            .value_wrap(&format!("{}-step", self.0), step)?;
+        if alter != 0 {
+            pitch = pitch.value_wrap(&format!("{}-alter", self.0), alter)?;
+        }
+        pitch.finish()
+    }
+}
+
+/// Chord kind value and its display text.
+struct Kind<'a>(&'static str, &'a str);
+
+impl XmlWrite for Kind<'_> {
+    fn write<W>(&self, mut w: &mut Writer<W>) -> XmlResult<()>
+    where
+        W: io::Write,
+    {
+        w.tag("kind")
+            .attr(("text", self.1))
+            .content()?
+            .text(self.0)?
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests;
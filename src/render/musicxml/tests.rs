@@ -0,0 +1,78 @@
+use super::*;
+use crate::parser::{parse, ParserConfig};
+
+fn render(title: Option<&str>, src: &str) -> String {
+    let songs = parse(src, ParserConfig::default()).unwrap();
+    let mut writer = Writer::new(vec![]);
+    write_score(&mut writer, title, &songs).unwrap();
+    String::from_utf8(writer.into_inner()).unwrap()
+}
+
+#[test]
+fn musicxml_song() {
+    let src = r#"
+# Song
+
+1. `G7`Oh Danny `C/G`Boy,
+the pipes `N.C.`
+
+> `Bbm`Chorus.
+"#;
+
+    let xml = render(Some("Songbook"), src);
+
+    assert!(xml.starts_with(
+        r#"<?xml version="1.0" encoding="UTF-8" standalone="no"?><!DOCTYPE score-partwise PUBLIC"#
+    ));
+    assert!(xml.contains(r#"<score-partwise version="4.0"><work><work-title>Songbook</work-title></work><part-list><score-part id="P1"><part-name>Chords</part-name></score-part></part-list><part id="P1">"#));
+
+    // First line with the song title and the verse label
+    assert!(xml.contains(concat!(
+        r#"<measure number="1"><attributes><divisions>1</divisions></attributes>"#,
+        r#"<direction placement="above"><direction-type><words>Song</words></direction-type></direction>"#,
+        r#"<direction placement="above"><direction-type><rehearsal>1.</rehearsal></direction-type></direction>"#,
+        r#"<harmony><root><root-step>G</root-step></root><kind text="7">dominant</kind></harmony>"#,
+        r#"<note print-object="no"><rest/><duration>1</duration><lyric number="1"><syllabic>single</syllabic><text>Oh Danny</text></lyric></note>"#,
+        r#"<harmony><root><root-step>C</root-step></root><kind text="">major</kind><bass><bass-step>G</bass-step></bass></harmony>"#,
+        r#"<note print-object="no"><rest/><duration>1</duration><lyric number="1"><syllabic>single</syllabic><text>Boy,</text></lyric></note>"#,
+        r#"</measure>"#,
+    )));
+
+    // Lyrics before the first chord, chord without lyrics, chord that's not understood
+    assert!(xml.contains(concat!(
+        r#"<measure number="2"><print new-system="yes"/>"#,
+        r#"<note print-object="no"><rest/><duration>1</duration><lyric number="1"><syllabic>single</syllabic><text>the pipes</text></lyric></note>"#,
+        r#"<harmony><function>N.C.</function><kind text="">other</kind></harmony>"#,
+        r#"<note print-object="no"><rest/><duration>1</duration></note>"#,
+        r#"</measure>"#,
+    )));
+
+    assert!(xml.contains(concat!(
+        r#"<measure number="3"><print new-system="yes"/>"#,
+        r#"<direction placement="above"><direction-type><rehearsal>Ch.</rehearsal></direction-type></direction>"#,
+        r#"<harmony><root><root-step>B</root-step><root-alter>-1</root-alter></root><kind text="m">minor</kind></harmony>"#,
+    )));
+    assert!(xml.ends_with("</measure></part></score-partwise>"));
+}
+
+#[test]
+fn musicxml_songs_pages() {
+    let src = r#"
+# Song one
+
+1. `C`La.
+
+# Song two
+
+# Song three
+
+1. `C`La.
+"#;
+
+    let xml = render(None, src);
+
+    assert!(!xml.contains("<work>"));
+    assert!(xml.contains(r#"<measure number="2"><print new-page="yes"/><direction placement="above"><direction-type><words>Song two</words></direction-type></direction></measure>"#));
+    assert!(xml.contains(r#"<measure number="3"><print new-page="yes"/><direction placement="above"><direction-type><words>Song three</words>"#));
+    assert_eq!(xml.matches("<measure ").count(), 3);
+}
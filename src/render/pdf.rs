@@ -1,20 +1,94 @@
-use handlebars::handlebars_helper;
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use handlebars::{self as hb, handlebars_helper, Handlebars, HelperDef, JsonValue, RenderError};
 use semver::Version;
 
-use super::template::{DpiHelper, HbRender};
+use super::template::{image_dpi_warning, DpiHelper, HbRender};
 use super::tex_tools::TexTools;
 use super::{Render, RenderContext};
-use crate::app::App;
+use crate::app::{keeplevel, App};
+use crate::book::Song;
 use crate::prelude::*;
 use crate::project::{Output, Project};
 use crate::render::tex_tools::TexRenderJob;
-use crate::util::ImgCache;
+use crate::util::{ImgCache, TempPath};
 
 default_template!(DEFAULT_TEMPLATE, "pdf.hbs");
 
-fn latex_escape(input: &str, pre_spaces: bool) -> String {
+/// Returns the LaTeX accent command for a Unicode combining diacritical mark.
+fn combining_accent(c: char) -> Option<&'static str> {
+    Some(match c {
+        '\u{0300}' => "`",
+        '\u{0301}' => "'",
+        '\u{0302}' => "^",
+        '\u{0303}' => "~",
+        '\u{0304}' => "=",
+        '\u{0306}' => "u",
+        '\u{0307}' => ".",
+        '\u{0308}' => "\"",
+        '\u{030A}' => "r",
+        '\u{030B}' => "H",
+        '\u{030C}' => "v",
+        '\u{0323}' => "d",
+        '\u{0327}' => "c",
+        '\u{0328}' => "k",
+        _ => return None,
+    })
+}
+
+fn is_combining(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}')
+}
+
+/// Zero-width and directional formatting characters, these are dropped from the output.
+fn is_invisible(c: char) -> bool {
+    matches!(c,
+        '\u{200B}'..='\u{200F}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2060}'
+        | '\u{2066}'..='\u{2069}'
+        | '\u{FEFF}')
+}
+
+/// Combining characters without a LaTeX accent command met while escaping,
+/// these are left as they are and reported once after rendering.
+type Unsupported = Arc<Mutex<BTreeSet<char>>>;
+
+fn latex_escape(input: &str, pre_spaces: bool, unsupported: &mut BTreeSet<char>) -> String {
     let mut res = String::with_capacity(input.len());
+    // Start of the last base character's output in res, if combining marks may apply to it.
+    let mut base_start = None;
+
     for c in input.chars() {
+        if is_combining(c) {
+            match (combining_accent(c), base_start) {
+                (Some(accent), Some(start)) => {
+                    let base = res.split_off(start);
+                    res.push_str(&format!("\\{}{{{}}}", accent, base));
+                }
+                _ => {
+                    unsupported.insert(c);
+                    res.push(c);
+                    base_start = None;
+                }
+            }
+            continue;
+        }
+
+        if is_invisible(c) {
+            base_start = None;
+            continue;
+        }
+
+        base_start = Some(res.len());
         match c {
             ' ' if pre_spaces => res.push('~'),
             '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
@@ -26,6 +100,10 @@ fn latex_escape(input: &str, pre_spaces: bool) -> String {
             '~' => res.push_str("{\\textasciitilde}"),
             '^' => res.push_str("{\\textasciicircum}"),
             '\\' => res.push_str("{\\textbackslash}"),
+            '\u{00AD}' => {
+                res.push_str("\\-");
+                base_start = None;
+            }
             c => res.push(c),
         }
     }
@@ -33,60 +111,230 @@ fn latex_escape(input: &str, pre_spaces: bool) -> String {
     res
 }
 
-fn hb_latex_escape(input: &str) -> String {
-    latex_escape(input, false)
+/// Escapes the input with non-breaking spaces.
+///
+/// Usage: `{{{ pre text }}}`
+struct PreHelper {
+    unsupported: Unsupported,
 }
 
-handlebars_helper!(hb_pre: |input: str| {
-    latex_escape(input, true)
+impl HelperDef for PreHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let input = h
+            .param(0)
+            .and_then(|x| x.value().as_str())
+            .ok_or_else(|| RenderError::new("pre: Input string not supplied"))?;
+
+        let mut unsupported = self.unsupported.lock().unwrap();
+        let res = latex_escape(input, true, &mut unsupported);
+        Ok(hb::ScopedJson::Derived(JsonValue::String(res)))
+    }
+}
+
+/// Escape a URL for the first argument of `\href`. Regular TeX escaping doesn't apply there,
+/// `#` and `%` are escaped with a backslash, other TeX special characters are percent-encoded.
+fn latex_url_escape(url: &str) -> String {
+    let mut res = String::with_capacity(url.len());
+    for c in url.chars() {
+        match c {
+            '#' | '%' => {
+                res.push('\\');
+                res.push(c);
+            }
+            '\\' | '{' | '}' | '~' | '^' => res.push_str(&format!("%{:02X}", c as u32)),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+handlebars_helper!(hb_tex_url: |url: str| {
+    latex_url_escape(url)
 });
 
 pub struct RPdf {
     hb: HbRender,
     toc_sort_key: Option<String>,
     tex_runs: u32,
+    keep_intermediate: bool,
+    dpi: f32,
+    image_dpi: (Option<f32>, Option<f32>),
+    unsupported: Unsupported,
 }
 
 impl RPdf {
     pub fn new(project: &Project, output: &Output, img_cache: &ImgCache) -> Result<Self> {
         let mut hb = HbRender::new(project, output, &DEFAULT_TEMPLATE, img_cache)?;
+        let unsupported = Unsupported::default();
 
         // Setup TeX escaping and TeX-specific helpers
-        hb.hb.register_escape_fn(hb_latex_escape);
-        hb.hb.register_helper("pre", Box::new(hb_pre));
-        hb.hb
-            .register_helper("px2mm", DpiHelper::new(output, "px2mm"));
+        hb.setup(|hb| {
+            let escape_unsupported = unsupported.clone();
+            hb.register_escape_fn(move |input| {
+                latex_escape(input, false, &mut escape_unsupported.lock().unwrap())
+            });
+            let pre = PreHelper {
+                unsupported: unsupported.clone(),
+            };
+            hb.register_helper("pre", Box::new(pre));
+            hb.register_helper("tex_url", Box::new(hb_tex_url));
+            hb.register_helper("px2mm", DpiHelper::new(output, "px2mm"));
+        });
 
         Ok(Self {
             hb,
             toc_sort_key: output.toc_sort.then(|| output.toc_sort_key.clone()),
             tex_runs: output.tex_runs,
+            keep_intermediate: output.keep_intermediate,
+            dpi: output.dpi(),
+            image_dpi: (output.image_dpi_min, output.image_dpi_max),
+            unsupported,
         })
     }
+
+    /// Warns about combining characters that were left unescaped in the TeX output, once each.
+    fn report_unsupported(&self, app: &App) {
+        let unsupported = std::mem::take(&mut *self.unsupported.lock().unwrap());
+        for c in unsupported {
+            app.warning(format!(
+                "Unsupported combining character U+{:04X}, it's left as is in the TeX output",
+                c as u32
+            ));
+        }
+    }
+
+    /// Warns if there are images and the resolution they're printed at is outside of the configured thresholds.
+    fn lint_images(&self, app: &App, context: &RenderContext) {
+        let (min, max) = self.image_dpi;
+        if context.songs.iter().flat_map(Song::images).next().is_none() {
+            return;
+        }
+
+        if let Some(warning) = image_dpi_warning(self.dpi, min, max) {
+            app.warning(format!("Output {:?}: {}", context.output.file, warning));
+        }
+    }
 }
 
 impl Render for RPdf {
     fn render(&self, app: &App, output: &Path, context: RenderContext) -> Result<()> {
         // Render TeX first
         let tex_file = output.with_extension("tex");
-        self.hb.render(&tex_file, context)?;
+        self.lint_images(app, &context);
+        let res = self.hb.render(&tex_file, context);
+        self.report_unsupported(app);
+        res?;
         if self.tex_runs == 0 || !app.post_process() {
             // TODO: test this
             return Ok(());
         }
 
         // Run TeX
+        let mut keep = app.keep_interm();
+        if self.keep_intermediate {
+            keep = keep.max(keeplevel::TEX_ONLY);
+        }
         let job = TexRenderJob::new(
             tex_file,
             output,
-            app.keep_interm(),
+            keep,
             self.toc_sort_key.as_deref(),
             self.tex_runs - 1,
         )?;
         TexTools::get().render_pdf(app, job)
     }
 
+    fn render_to_writer(&self, app: &App, w: &mut dyn Write, context: RenderContext) -> Result<()> {
+        self.lint_images(app, &context);
+        let res = self.hb.render_to_writer(w, context);
+        self.report_unsupported(app);
+        res
+    }
+
+    fn render_bytes(&self, app: &App, context: RenderContext) -> Result<Vec<u8>> {
+        let output = &context.output.file;
+        let mut dir = TempPath::make_temp_dir(output, true)?;
+        let pdf_file = dir.join(output.file_name().unwrap());
+
+        let res = self.render(app, &pdf_file, context).and_then(|_| {
+            if !pdf_file.exists() {
+                bail!("No PDF was produced, TeX is disabled");
+            }
+            fs::read(&pdf_file).with_context(|| format!("Could not read PDF file {:?}", pdf_file))
+        });
+        if res.is_err() {
+            // Keep intermediate files around for inspection
+            dir.set_remove(false);
+        }
+        res
+    }
+
     fn version(&self) -> Option<Version> {
         self.hb.version()
     }
+
+    fn song_templates(&self) -> Vec<(&Path, Option<Version>)> {
+        self.hb.song_templates()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn escape(input: &str, pre_spaces: bool) -> String {
+        latex_escape(input, pre_spaces, &mut BTreeSet::new())
+    }
+
+    #[test]
+    fn latex_escape_unicode() {
+        // Precomposed characters are left to the TeX engine
+        assert_eq!(escape("é ñ ü", false), "é ñ ü");
+
+        // Combining diacritics are converted to accent commands
+        assert_eq!(escape("e\u{0301}", false), "\\'{e}");
+        assert_eq!(escape("n\u{0303}a", false), "\\~{n}a");
+        assert_eq!(escape("u\u{0308}ber", false), "\\\"{u}ber");
+        assert_eq!(escape("c\u{0327}a", false), "\\c{c}a");
+        assert_eq!(escape("#\u{0301}", false), "\\'{\\#}");
+        assert_eq!(escape("a\u{0301}\u{0308}", false), "\\\"{\\'{a}}");
+
+        // Unsupported combining characters are passed through
+        assert_eq!(escape("a\u{0345}", false), "a\u{0345}");
+        assert_eq!(escape("\u{0301}a", false), "\u{0301}a");
+
+        // Soft hyphens, zero-width and directional characters
+        assert_eq!(escape("na\u{00AD}na", false), "na\\-na");
+        assert_eq!(escape("a\u{200B}b\u{200D}c\u{200F}", false), "abc");
+        assert_eq!(escape("a b", true), "a~b");
+    }
+
+    #[test]
+    fn latex_escape_unsupported() {
+        let mut unsupported = BTreeSet::new();
+        latex_escape("a\u{0345} e\u{0301} b\u{0345}", false, &mut unsupported);
+        latex_escape("\u{0301}a", true, &mut unsupported);
+        assert_eq!(
+            unsupported.into_iter().collect::<Vec<_>>(),
+            ['\u{0301}', '\u{0345}']
+        );
+    }
+
+    #[test]
+    fn latex_url_escape_special() {
+        assert_eq!(
+            latex_url_escape("https://example.com/a_b?q=1&r=50%#top"),
+            "https://example.com/a_b?q=1&r=50\\%\\#top"
+        );
+        assert_eq!(
+            latex_url_escape("http://example.com/~user/{x}\\^"),
+            "http://example.com/%7Euser/%7Bx%7D%5C%5E"
+        );
+    }
 }
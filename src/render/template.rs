@@ -1,22 +1,26 @@
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::fs;
-use std::io;
+use std::io::{self, Write};
 use std::str::FromStr;
 use std::sync::{Arc, Mutex};
 
-use handlebars::{self as hb, handlebars_helper, Handlebars, HelperDef, JsonValue, RenderError};
+use handlebars::{
+    self as hb, handlebars_helper, Handlebars, HelperDef, JsonValue, RenderError, Renderable,
+};
 use image::image_dimensions;
 use once_cell::sync::Lazy;
 use regex::{Error as ReError, Regex};
 use semver::Version;
+use serde::Serialize;
 use serde_json::Number;
 
 use super::RenderContext;
 use crate::prelude::*;
 use crate::project::Format;
 use crate::project::{Output, Project};
-use crate::util::ImgCache;
+use crate::util::{build_timestamp, format_timestamp, ImgCache};
 
 type RegexCache = HashMap<String, Result<Regex, ReError>>;
 
@@ -29,6 +33,22 @@ pub struct DefaultTemaplate {
     pub content: &'static str,
 }
 
+impl DefaultTemaplate {
+    /// The AST version the template declares with `version_check`.
+    pub fn version(&self) -> Option<Version> {
+        declared_version(self.content)
+    }
+}
+
+/// Finds the AST version declared by the `version_check` helper in template source without rendering it.
+pub fn declared_version(source: &str) -> Option<Version> {
+    static RE: Lazy<Regex> =
+        Lazy::new(|| Regex::new(r#"\{\{~?\s*version_check\s+"([^"]*)""#).unwrap());
+
+    RE.captures(source)
+        .and_then(|caps| Version::parse(&caps[1]).ok())
+}
+
 macro_rules! default_template {
     ($name:ident, $filename:expr) => {
         pub static $name: crate::render::template::DefaultTemaplate =
@@ -86,19 +106,525 @@ handlebars_helper!(hb_default: |value: Json, def: Json| {
     }
 });
 
+/// Object entries sorted by key, empty for non-objects.
+fn sorted_entries(value: &JsonValue) -> Vec<(&String, &JsonValue)> {
+    let mut entries: Vec<_> = value
+        .as_object()
+        .map(|obj| obj.iter().collect())
+        .unwrap_or_default();
+    entries.sort_by(|(k1, _), (k2, _)| k1.cmp(k2));
+    entries
+}
+
+handlebars_helper!(hb_keys: |value: Json| {
+    sorted_entries(value)
+        .into_iter()
+        .map(|(key, _)| JsonValue::String(key.clone()))
+        .collect::<Vec<_>>()
+});
+
+handlebars_helper!(hb_values: |value: Json| {
+    sorted_entries(value)
+        .into_iter()
+        .map(|(_, value)| value.clone())
+        .collect::<Vec<_>>()
+});
+
+/// Pairs up items of two arrays, up to the length of the `longest` one (padded with nulls)
+/// or of the shorter one. Empty if either value is not an array.
+fn zip_arrays(a: &JsonValue, b: &JsonValue, longest: bool) -> Vec<JsonValue> {
+    let (a, b) = match (a.as_array(), b.as_array()) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return vec![],
+    };
+
+    let len = if longest {
+        a.len().max(b.len())
+    } else {
+        a.len().min(b.len())
+    };
+    (0..len)
+        .map(|i| {
+            let a = a.get(i).cloned().unwrap_or(JsonValue::Null);
+            let b = b.get(i).cloned().unwrap_or(JsonValue::Null);
+            JsonValue::Array(vec![a, b])
+        })
+        .collect()
+}
+
+handlebars_helper!(hb_zip: |a: Json, b: Json| {
+    zip_arrays(a, b, false)
+});
+
+handlebars_helper!(hb_zip_longest: |a: Json, b: Json| {
+    zip_arrays(a, b, true)
+});
+
+/// Item of `array` at a 0-based `index`, negative indices count from the end (`-1` is the last item).
+/// Null if `array` is not an array or the index is out of bounds.
+fn nth(array: &JsonValue, index: i64) -> JsonValue {
+    let array = match array.as_array() {
+        Some(array) => array,
+        None => return JsonValue::Null,
+    };
+
+    let index = if index < 0 {
+        index + array.len() as i64
+    } else {
+        index
+    };
+    usize::try_from(index)
+        .ok()
+        .and_then(|i| array.get(i))
+        .cloned()
+        .unwrap_or(JsonValue::Null)
+}
+
+handlebars_helper!(hb_nth: |array: Json, index: i64| {
+    nth(array, index)
+});
+
+handlebars_helper!(hb_nth1: |array: Json, index: i64| {
+    match index {
+        0 => JsonValue::Null,
+        i if i > 0 => nth(array, i - 1),
+        i => nth(array, i),
+    }
+});
+
+/// Value at the dot-separated `path` in `item`, such as `metadata.language` or `subtitles.0`.
+fn lookup_path<'a>(item: &'a JsonValue, path: &str) -> Option<&'a JsonValue> {
+    path.split('.').try_fold(item, |value, key| match value {
+        JsonValue::Array(array) => key.parse().ok().and_then(|i: usize| array.get(i)),
+        _ => value.get(key),
+    })
+}
+
+/// Groups items of `array` by the value found at the dot-separated `path` in each item,
+/// such as `metadata.language`. Items where the value is absent or null are grouped under `""`.
+/// Returns an object with sorted keys, empty if `array` is not an array.
+fn group_by(array: &JsonValue, path: &str) -> JsonValue {
+    let mut groups: BTreeMap<String, Vec<JsonValue>> = BTreeMap::new();
+    for item in array.as_array().into_iter().flatten() {
+        let key = match lookup_path(item, path) {
+            None | Some(JsonValue::Null) => String::new(),
+            Some(JsonValue::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        };
+        groups.entry(key).or_default().push(item.clone());
+    }
+
+    JsonValue::Object(
+        groups
+            .into_iter()
+            .map(|(key, items)| (key, JsonValue::Array(items)))
+            .collect(),
+    )
+}
+
+handlebars_helper!(hb_group_by: |array: Json, path: str| {
+    group_by(array, path)
+});
+
+/// Groups items of `array` by the first tag found at the dot-separated `path` in each item,
+/// such as `metadata.tags`. Tags are given as an array or as a comma-separated string,
+/// like with the `tags` metadata field. Items without tags are grouped under `""`.
+/// Returns an array of `{ key, songs }` objects ordered by the first appearance of each key,
+/// empty if `array` is not an array.
+fn group_by_tag(array: &JsonValue, path: &str) -> JsonValue {
+    let mut groups: Vec<(String, Vec<JsonValue>)> = vec![];
+    for item in array.as_array().into_iter().flatten() {
+        let key = match lookup_path(item, path) {
+            Some(JsonValue::Array(tags)) => match tags.first() {
+                None | Some(JsonValue::Null) => String::new(),
+                Some(JsonValue::String(tag)) => tag.clone(),
+                Some(other) => other.to_string(),
+            },
+            Some(JsonValue::String(tags)) => tags
+                .split(',')
+                .map(str::trim)
+                .find(|tag| !tag.is_empty())
+                .unwrap_or("")
+                .to_string(),
+            None | Some(JsonValue::Null) => String::new(),
+            Some(other) => other.to_string(),
+        };
+
+        match groups.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, items)) => items.push(item.clone()),
+            None => groups.push((key, vec![item.clone()])),
+        }
+    }
+
+    groups
+        .into_iter()
+        .map(|(key, songs)| serde_json::json!({ "key": key, "songs": songs }))
+        .collect()
+}
+
+handlebars_helper!(hb_group_by_tag: |array: Json, path: str| {
+    group_by_tag(array, path)
+});
+
+/// Whether a paragraph (ie. an array of inlines), a verse, or an inline
+/// contains an `i-text` with some non-whitespace text, including text nested in chords and formatting.
+fn contains_text(value: &JsonValue) -> bool {
+    match value {
+        JsonValue::Array(items) => items.iter().any(contains_text),
+        JsonValue::Object(obj) if obj.get("type") == Some(&JsonValue::from("i-text")) => obj
+            .get("text")
+            .and_then(JsonValue::as_str)
+            .map_or(false, |text| !text.trim().is_empty()),
+        JsonValue::Object(obj) => ["inlines", "paragraphs"]
+            .iter()
+            .filter_map(|key| obj.get(*key))
+            .any(contains_text),
+        _ => false,
+    }
+}
+
+handlebars_helper!(hb_contains_text: |value: Json| {
+    contains_text(value)
+});
+
+/// Returns `true` if `array` contains all of `values`, compared using JSON equality.
+/// A null or any other non-array value doesn't contain anything.
+fn includes(array: &JsonValue, values: &[&JsonValue]) -> bool {
+    match array.as_array() {
+        Some(array) => values.iter().all(|value| array.contains(value)),
+        None => false,
+    }
+}
+
+handlebars_helper!(hb_includes: |array: Json, value: Json| {
+    includes(array, &[value])
+});
+
+handlebars_helper!(hb_includes_all: |array: Json, *args| {
+    includes(array, &args)
+});
+
+handlebars_helper!(hb_str_contains: |value: str, needle: str| {
+    value.contains(needle)
+});
+
+handlebars_helper!(hb_starts_with: |value: str, prefix: str| {
+    value.starts_with(prefix)
+});
+
+handlebars_helper!(hb_ends_with: |value: str, suffix: str| {
+    value.ends_with(suffix)
+});
+
+/// Joins items of `array` with `sep`. Strings are joined as they are, nulls are skipped,
+/// other values are converted to JSON. Empty if `array` is not an array.
+fn join(array: &JsonValue, sep: &str) -> String {
+    let items = array.as_array().map(Vec::as_slice).unwrap_or_default();
+    items
+        .iter()
+        .filter_map(|item| match item {
+            JsonValue::String(s) => Some(Cow::Borrowed(s.as_str())),
+            JsonValue::Null => None,
+            other => Some(Cow::Owned(other.to_string())),
+        })
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+handlebars_helper!(hb_join: |array: Json, sep: str| {
+    join(array, sep)
+});
+
+/// Splits `value` by `sep` into an array of strings. An empty string yields an empty array
+/// and a trailing separator doesn't produce an empty item at the end.
+fn split(value: &str, sep: &str) -> Vec<JsonValue> {
+    if value.is_empty() {
+        return vec![];
+    }
+    if sep.is_empty() {
+        return vec![value.into()];
+    }
+
+    let value = value.strip_suffix(sep).unwrap_or(value);
+    value.split(sep).map(JsonValue::from).collect()
+}
+
+handlebars_helper!(hb_split: |value: str, sep: str| {
+    split(value, sep)
+});
+
+/// Uppercases the first character of `value`, the rest is left unchanged.
+fn capitalize(value: &str) -> String {
+    let mut chars = value.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Uppercases the first character of each whitespace-separated word in `value`,
+/// the rest, including the whitespace, is left unchanged.
+fn title_case(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    let mut word_start = true;
+    for c in value.chars() {
+        if word_start {
+            res.extend(c.to_uppercase());
+        } else {
+            res.push(c);
+        }
+        word_start = c.is_whitespace();
+    }
+    res
+}
+
+/// Applies `f` to a string value, null yields an empty string
+/// and other values are converted to JSON first.
+fn map_str(value: &JsonValue, f: impl Fn(&str) -> String) -> String {
+    match value {
+        JsonValue::String(s) => f(s),
+        JsonValue::Null => String::new(),
+        other => f(&other.to_string()),
+    }
+}
+
+handlebars_helper!(hb_capitalize: |value: Json| {
+    map_str(value, capitalize)
+});
+
+handlebars_helper!(hb_title_case: |value: Json| {
+    map_str(value, title_case)
+});
+
+/// Counts something in a string value, null counts as empty
+/// and other values are converted to JSON first.
+fn count_str(value: &JsonValue, f: impl Fn(&str) -> usize) -> usize {
+    match value {
+        JsonValue::String(s) => f(s),
+        JsonValue::Null => 0,
+        other => f(&other.to_string()),
+    }
+}
+
+handlebars_helper!(hb_words: |value: Json| {
+    count_str(value, |s| s.split_whitespace().count())
+});
+
+handlebars_helper!(hb_chars: |value: Json| {
+    count_str(value, |s| s.chars().count())
+});
+
+/// Pads `value` to `len` characters with `pad` repeated as needed, at the start or at the end.
+/// The value is returned unchanged if it's long enough already or if `pad` is empty.
+fn pad(value: &str, len: usize, pad: &str, start: bool) -> String {
+    let missing = len.saturating_sub(value.chars().count());
+    if missing == 0 || pad.is_empty() {
+        return value.to_string();
+    }
+
+    let padding: String = pad.chars().cycle().take(missing).collect();
+    if start {
+        padding + value
+    } else {
+        value.to_string() + &padding
+    }
+}
+
+/// The optional pad argument of `pad_start` and `pad_end`, a space by default.
+fn pad_arg(args: &[&JsonValue]) -> String {
+    args.first()
+        .map_or_else(|| " ".to_string(), |arg| map_str(arg, str::to_string))
+}
+
+handlebars_helper!(hb_pad_start: |value: Json, len: u64, *args| {
+    let value = map_str(value, str::to_string);
+    pad(&value, len as usize, &pad_arg(&args), true)
+});
+
+handlebars_helper!(hb_pad_end: |value: Json, len: u64, *args| {
+    let value = map_str(value, str::to_string);
+    pad(&value, len as usize, &pad_arg(&args), false)
+});
+
+const ROMAN_NUMERALS: &[(u32, &str)] = &[
+    (1000, "M"),
+    (900, "CM"),
+    (500, "D"),
+    (400, "CD"),
+    (100, "C"),
+    (90, "XC"),
+    (50, "L"),
+    (40, "XL"),
+    (10, "X"),
+    (9, "IX"),
+    (5, "V"),
+    (4, "IV"),
+    (1, "I"),
+];
+
+/// Writes `num` in Roman numerals, `None` if it's outside of 1 to 3999.
+fn roman(mut num: u32) -> Option<String> {
+    if !(1..=3999).contains(&num) {
+        return None;
+    }
+
+    let mut res = String::new();
+    for &(value, numeral) in ROMAN_NUMERALS {
+        while num >= value {
+            res.push_str(numeral);
+            num -= value;
+        }
+    }
+    Some(res)
+}
+
+/// Parses a Roman numeral, case-insensitive. Only numerals in the standard form are accepted,
+/// ie. `IV` but not `IIII`.
+fn arabic(numeral: &str) -> Option<u32> {
+    let numeral = numeral.trim().to_ascii_uppercase();
+    let mut rest = numeral.as_str();
+    let mut res = 0;
+    for &(value, symbol) in ROMAN_NUMERALS {
+        while let Some(after) = rest.strip_prefix(symbol) {
+            res += value;
+            rest = after;
+        }
+    }
+
+    // Check the form by converting back, this rejects eg. `IIII` or `VX`
+    Some(res).filter(|&res| rest.is_empty() && roman(res).as_deref() == Some(numeral.as_str()))
+}
+
+/// Writes an integer in Roman numerals, from 1 to 3999.
+///
+/// Usage: `{{ roman 4 }}`
+struct RomanHelper;
+
+impl HelperDef for RomanHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let value = h
+            .param(0)
+            .map(|x| x.value())
+            .ok_or_else(|| hb_err!("roman: Number not supplied"))?;
+
+        value
+            .as_u64()
+            .and_then(|num| u32::try_from(num).ok())
+            .and_then(roman)
+            .map(|res| hb::ScopedJson::Derived(res.into()))
+            .ok_or_else(|| hb_err!("roman: Expected an integer from 1 to 3999, got: {}", value))
+    }
+}
+
+/// Parses a Roman numeral into an integer, the counterpart of `roman`.
+///
+/// Usage: `{{ arabic "XIV" }}`
+struct ArabicHelper;
+
+impl HelperDef for ArabicHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let value = h
+            .param(0)
+            .map(|x| x.value())
+            .ok_or_else(|| hb_err!("arabic: Roman numeral not supplied"))?;
+
+        value
+            .as_str()
+            .and_then(arabic)
+            .map(|res| hb::ScopedJson::Derived(res.into()))
+            .ok_or_else(|| hb_err!("arabic: Expected a Roman numeral, got: {}", value))
+    }
+}
+
+handlebars_helper!(hb_to_json: |value: Json| {
+    value.to_string()
+});
+
+/// Parses a JSON string into a value, the counterpart of `to_json`.
+///
+/// Usage: `{{ from_json string }}`
+struct FromJsonHelper;
+
+impl HelperDef for FromJsonHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let value = h
+            .param(0)
+            .map(|x| x.value())
+            .ok_or_else(|| hb_err!("from_json: String not supplied"))?;
+        let json = value
+            .as_str()
+            .ok_or_else(|| hb_err!("from_json: Expected a string, got: {}", value))?;
+
+        serde_json::from_str(json)
+            .map(hb::ScopedJson::Derived)
+            .map_err(|e| hb_err!(e, "from_json: Could not parse JSON: {:?}", json))
+    }
+}
+
+/// Escapes `<`, `>`, `&`, `"` and `'` as HTML entities.
+/// Strings are escaped as they are, other values are converted to JSON first, `null` yields an empty string.
+fn html_escape(value: &JsonValue) -> String {
+    let text = match value {
+        JsonValue::String(s) => Cow::Borrowed(s.as_str()),
+        JsonValue::Null => Cow::Borrowed(""),
+        other => Cow::Owned(other.to_string()),
+    };
+
+    let mut res = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '<' => res.push_str("&lt;"),
+            '>' => res.push_str("&gt;"),
+            '&' => res.push_str("&amp;"),
+            '"' => res.push_str("&quot;"),
+            '\'' => res.push_str("&#x27;"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+handlebars_helper!(hb_html_escape: |value: Json| {
+    html_escape(value)
+});
+
 struct Cat<'a>(Vec<&'a JsonValue>);
 
+impl<'a> Cat<'a> {
+    fn fmt_scalar(arg: &JsonValue, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match arg {
+            JsonValue::Null => write!(f, "[null]"),
+            JsonValue::Bool(b) => write!(f, "{}", b),
+            JsonValue::Number(n) => write!(f, "{}", n),
+            JsonValue::String(s) => write!(f, "{}", s),
+            JsonValue::Array(..) => write!(f, "[array]"),
+            JsonValue::Object(..) => write!(f, "[object]"),
+        }
+    }
+}
+
 impl<'a> fmt::Display for Cat<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         for arg in self.0.iter() {
-            match arg {
-                JsonValue::Null => write!(f, "[null]")?,
-                JsonValue::Bool(b) => write!(f, "{}", b)?,
-                JsonValue::Number(n) => write!(f, "{}", n)?,
-                JsonValue::String(s) => write!(f, "{}", s)?,
-                JsonValue::Array(..) => write!(f, "[array]")?,
-                JsonValue::Object(..) => write!(f, "[object]")?,
-            }
+            Self::fmt_scalar(arg, f)?;
         }
         Ok(())
     }
@@ -108,6 +634,65 @@ handlebars_helper!(hb_cat: |*args| {
     format!("{}", Cat(args))
 });
 
+/// Like `Cat`, except arrays are joined with `sep`, recursively, and objects are written as JSON.
+struct CatJson<'a> {
+    args: Vec<&'a JsonValue>,
+    sep: &'a str,
+}
+
+impl<'a> CatJson<'a> {
+    fn fmt_arg(&self, arg: &JsonValue, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match arg {
+            JsonValue::Array(items) => {
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "{}", self.sep)?;
+                    }
+                    self.fmt_arg(item, f)?;
+                }
+                Ok(())
+            }
+            JsonValue::Object(..) => write!(f, "{}", arg),
+            scalar => Cat::fmt_scalar(scalar, f),
+        }
+    }
+}
+
+impl<'a> fmt::Display for CatJson<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for arg in self.args.iter() {
+            self.fmt_arg(arg, f)?;
+        }
+        Ok(())
+    }
+}
+
+/// Concatenates arguments like `cat`, but joins arrays with the `sep` hash argument
+/// and writes objects as JSON.
+///
+/// Usage: `{{ cat_json "Tags: " tags sep=", " }}`
+struct CatJsonHelper;
+
+impl HelperDef for CatJsonHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let sep = match h.hash_get("sep").map(|x| x.value()) {
+            Some(JsonValue::String(sep)) => sep.as_str(),
+            Some(other) => return Err(hb_err!("cat_json: Expected a string sep, got: {}", other)),
+            None => "",
+        };
+        let args = h.params().iter().map(|x| x.value()).collect();
+
+        let res = CatJson { args, sep }.to_string();
+        Ok(hb::ScopedJson::Derived(res.into()))
+    }
+}
+
 handlebars_helper!(hb_matches: |value: str, regex: str| {
     let mut cache = REGEX_CACHE.lock().unwrap();
 
@@ -204,6 +789,29 @@ impl DpiHelper {
             name,
         })
     }
+
+    /// Converts a `size` in pixels to millimetres at `dpi`, as done by the `px2mm` helper.
+    pub fn px2mm(dpi: f32, size: f64) -> f64 {
+        (size / dpi as f64) * Self::INCH_MM
+    }
+}
+
+/// Checks the resolution images are printed at against the `min` and `max` DPI thresholds, if any.
+/// Images are sized with `px2mm`, so their effective resolution is the output's `dpi`.
+/// Returns a warning message if the resolution is below `min` (images print blurry)
+/// or above `max` (images are needlessly large).
+pub fn image_dpi_warning(dpi: f32, min: Option<f32>, max: Option<f32>) -> Option<String> {
+    match (min, max) {
+        (Some(min), _) if dpi < min => Some(format!(
+            "Images are printed at {} DPI, which is below the minimum of {} DPI, they may print blurry.",
+            dpi, min
+        )),
+        (_, Some(max)) if dpi > max => Some(format!(
+            "Images are printed at {} DPI, which is above the maximum of {} DPI, they are needlessly large.",
+            dpi, max
+        )),
+        _ => None,
+    }
 }
 
 impl HelperDef for DpiHelper {
@@ -230,28 +838,30 @@ impl HelperDef for DpiHelper {
 
         let res = match self.format {
             Format::Html => JsonValue::from((self.dpi as f64 * value).round() as u32),
-            _ => JsonValue::from((value / self.dpi as f64) * Self::INCH_MM),
+            _ => JsonValue::from(Self::px2mm(self.dpi, value)),
         };
 
         Ok(hb::ScopedJson::Derived(res))
     }
 }
 
-struct VersionCheckHelper {
-    version: Arc<Mutex<Option<Version>>>,
+/// Picks a chord to display: The `alt_chord` if it's present and the output
+/// is configured with `alt_chords_primary`, otherwise the `chord`.
+///
+/// Usage: `{{ alt_or_chord chord alt_chord }}`
+struct AltChordHelper {
+    primary: bool,
 }
 
-impl VersionCheckHelper {
-    fn new() -> (Self, Arc<Mutex<Option<Version>>>) {
-        let version = Arc::new(Mutex::new(None));
-        let this = Self {
-            version: version.clone(),
-        };
-        (this, version)
+impl AltChordHelper {
+    fn new(output: &Output) -> Self {
+        Self {
+            primary: output.alt_chords_primary,
+        }
     }
 }
 
-impl HelperDef for VersionCheckHelper {
+impl HelperDef for AltChordHelper {
     fn call_inner<'reg: 'rc, 'rc>(
         &self,
         h: &hb::Helper<'reg, 'rc>,
@@ -259,33 +869,116 @@ impl HelperDef for VersionCheckHelper {
         _: &'rc hb::Context,
         _: &mut hb::RenderContext<'reg, 'rc>,
     ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
-        let version = h
+        let chord = h
             .param(0)
             .map(|x| x.value())
-            .ok_or_else(|| hb_err!("version_check: No version number supplied"))
-            .and_then(|x| match x {
-                JsonValue::String(s) => Ok(s.as_str()),
-                _ => Err(hb_err!("version_check: Input value not a string")),
-            })
-            .and_then(|s| {
-                Version::parse(s)
-                    .map_err(|e| hb_err!(e, "version_check: Could not parse version '{}'", s))
-            })?;
+            .ok_or_else(|| hb_err!("alt_or_chord: Chord not supplied"))?;
+        let alt_chord = h.param(1).map(|x| x.value()).unwrap_or(&JsonValue::Null);
 
-        *self.version.lock().unwrap() = Some(version);
-        Ok(hb::ScopedJson::Derived(JsonValue::String(String::new())))
+        let res = if self.primary && !alt_chord.is_null() {
+            alt_chord
+        } else {
+            chord
+        };
+        Ok(hb::ScopedJson::Derived(res.clone()))
     }
 }
 
-/// Simple math helper, which can do the usual arithmetic operations on integers and floats. Tries to handle most edge-cases and provide useful error message to the user.
-///
-/// Usage: `{{ math 5 "+" 3 }}`, `{{ math 23.8 "/" -1.5 }}`, `{{ math "3" "*" 8.5 }}`
+/// Returns the output-relative path of a static asset listed in the output's `assets`,
+/// or of a file inside a listed directory.
 ///
-/// Supported operations:
-///    - \+ addition
-///    - \- subtraction
-///    - \* multiplication
-///    - / **decimal** division (integers are converted to floats)
+/// Usage: `{{ asset "style.css" }}`
+struct AssetHelper {
+    assets: Vec<PathBuf>,
+}
+
+impl AssetHelper {
+    fn new(output: &Output) -> Self {
+        Self {
+            assets: output.assets.clone(),
+        }
+    }
+}
+
+impl HelperDef for AssetHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let path = h
+            .param(0)
+            .and_then(|x| x.value().as_str())
+            .ok_or_else(|| hb_err!("asset: Path not supplied"))?;
+        let path = Path::new(path);
+
+        if !self.assets.iter().any(|asset| path.starts_with(asset)) {
+            return Err(hb_err!(
+                "asset: Path {:?} is not listed in the output's assets",
+                path
+            ));
+        }
+
+        let res = path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        Ok(hb::ScopedJson::Derived(JsonValue::String(res)))
+    }
+}
+
+struct VersionCheckHelper {
+    version: Arc<Mutex<Option<Version>>>,
+}
+
+impl VersionCheckHelper {
+    fn new() -> (Self, Arc<Mutex<Option<Version>>>) {
+        let version = Arc::new(Mutex::new(None));
+        let this = Self {
+            version: version.clone(),
+        };
+        (this, version)
+    }
+}
+
+impl HelperDef for VersionCheckHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let version = h
+            .param(0)
+            .map(|x| x.value())
+            .ok_or_else(|| hb_err!("version_check: No version number supplied"))
+            .and_then(|x| match x {
+                JsonValue::String(s) => Ok(s.as_str()),
+                _ => Err(hb_err!("version_check: Input value not a string")),
+            })
+            .and_then(|s| {
+                Version::parse(s)
+                    .map_err(|e| hb_err!(e, "version_check: Could not parse version '{}'", s))
+            })?;
+
+        *self.version.lock().unwrap() = Some(version);
+        Ok(hb::ScopedJson::Derived(JsonValue::String(String::new())))
+    }
+}
+
+/// Simple math helper, which can do the usual arithmetic operations on integers and floats. Tries to handle most edge-cases and provide useful error message to the user.
+///
+/// Usage: `{{ math 5 "+" 3 }}`, `{{ math 23.8 "/" -1.5 }}`, `{{ math "3" "*" 8.5 }}`
+///
+/// Supported operations:
+///    - \+ addition
+///    - \- subtraction
+///    - \* multiplication
+///    - / **decimal** division (integers are converted to floats)
 ///    - // integer division (both numbers must be integers)
 ///    - % modulo
 ///    - & bitwise and (integers only)
@@ -381,34 +1074,707 @@ impl HelperDef for MathHelper {
     }
 }
 
+/// A strict version of the built-in `lookup`: Returns the value under `key` in an object
+/// (or at index `key` in an array), `null` or the optional `fallback` if the key is absent
+/// or the object is `null`, and an error if the object is neither an object, an array, nor `null`.
+///
+/// Usage: `{{ lookup obj key [fallback] }}`
+struct LookupHelper;
+
+impl HelperDef for LookupHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let value = h
+            .param(0)
+            .map(|x| x.value())
+            .ok_or_else(|| hb_err!("lookup: Object not supplied"))?;
+        let key = h
+            .param(1)
+            .map(|x| x.value())
+            .ok_or_else(|| hb_err!("lookup: Key not supplied"))?;
+        let fallback = h.param(2).map(|x| x.value()).unwrap_or(&JsonValue::Null);
+
+        let found = match (value, key) {
+            (JsonValue::Null, _) => None,
+            (JsonValue::Object(obj), JsonValue::String(key)) => obj.get(key),
+            (JsonValue::Array(array), key) => {
+                let index = key
+                    .as_u64()
+                    .or_else(|| key.as_str().and_then(|s| u64::from_str(s).ok()))
+                    .ok_or_else(|| hb_err!("lookup: Invalid array index: {}", key))?;
+                array.get(index as usize)
+            }
+            (JsonValue::Object(_), key) => {
+                return Err(hb_err!("lookup: Object keys must be strings, got: {}", key))
+            }
+            (value, _) => {
+                return Err(hb_err!(
+                    "lookup: Expected an object, an array or null, got: {}",
+                    value
+                ))
+            }
+        };
+
+        Ok(hb::ScopedJson::Derived(found.unwrap_or(fallback).clone()))
+    }
+}
+
+/// Block helper rendering its content if the two parameters are equal, otherwise the `{{else}}` branch.
+/// A shorthand for `{{#if (eq a b)}}`, compares the same way as `eq`.
+///
+/// Usage: `{{#ifeq a b}}...{{else ifeq a c}}...{{else}}...{{/ifeq}}`
+struct IfEqHelper;
+
+impl HelperDef for IfEqHelper {
+    fn call<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        hb: &'reg Handlebars<'reg>,
+        ctx: &'rc hb::Context,
+        rc: &mut hb::RenderContext<'reg, 'rc>,
+        out: &mut dyn hb::Output,
+    ) -> hb::HelperResult {
+        let wrong_param_count = || {
+            hb_err!("ifeq: Found {} parameters, but ifeq helper requires 2 parameters. Example: {{{{#ifeq notation \"german\"}}}}...{{{{/ifeq}}}}.", h.params().len())
+        };
+
+        let a = h.param(0).ok_or_else(wrong_param_count)?.value();
+        let b = h.param(1).ok_or_else(wrong_param_count)?.value();
+        let branch = if a == b { h.template() } else { h.inverse() };
+        match branch {
+            Some(template) => template.render(hb, ctx, rc, out),
+            None => Ok(()),
+        }
+    }
+}
+
+struct ClampHelper;
+
+impl ClampHelper {
+    fn int(value: &JsonValue) -> Option<i64> {
+        value
+            .as_i64()
+            .or_else(|| value.as_str().and_then(|s| i64::from_str(s).ok()))
+    }
+
+    fn float(value: &JsonValue, name: &str) -> Result<f64, RenderError> {
+        value
+            .as_f64()
+            .or_else(|| value.as_str().and_then(|s| f64::from_str(s).ok()))
+            .ok_or_else(|| hb_err!("clamp: The {} is not in valid format. Valid examples: 5, -62.53. Got this: {:?}", name, value))
+    }
+}
+
+impl HelperDef for ClampHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let wrong_param_count = || {
+            hb_err!("clamp: Found {} parameters, but clamp helper requires 3 parameters: value, minimum, maximum. Example: {{{{ clamp 12 8 10 }}}}.", h.params().len())
+        };
+
+        let value = h.param(0).ok_or_else(wrong_param_count)?.value();
+        let min = h.param(1).ok_or_else(wrong_param_count)?.value();
+        let max = h.param(2).ok_or_else(wrong_param_count)?.value();
+
+        // try integers
+        if let (Some(value), Some(min), Some(max)) =
+            (Self::int(value), Self::int(min), Self::int(max))
+        {
+            if min > max {
+                return Err(hb_err!(
+                    "clamp: Minimum {} is greater than maximum {}.",
+                    min,
+                    max
+                ));
+            }
+            return Ok(hb::ScopedJson::Derived(JsonValue::Number(Number::from(
+                value.clamp(min, max),
+            ))));
+        }
+
+        // try floats
+        let value = Self::float(value, "value")?;
+        let min = Self::float(min, "minimum")?;
+        let max = Self::float(max, "maximum")?;
+        // Nb. f64::clamp() would panic on NaN bounds
+        if min > max || min.is_nan() || max.is_nan() {
+            return Err(hb_err!(
+                "clamp: Minimum {} is greater than maximum {}.",
+                min,
+                max
+            ));
+        }
+
+        let res = Number::from_f64(value.clamp(min, max))
+            .ok_or_else(|| hb_err!("clamp: Value {} cannot be converted to JSON number.", value))?;
+        Ok(hb::ScopedJson::Derived(JsonValue::Number(res)))
+    }
+}
+
+/// Rounds a number down, up, or to the nearest integer, see `RoundHelper::floor()` etc.
+///
+/// Usage: `{{ floor 23.756 }}` renders `23`, `{{ ceil 23.1 }}` renders `24`.
+/// Integers are returned unchanged. `round` also takes an optional number of decimal places:
+/// `{{ round 3.1415 2 }}` renders `3.14`.
+struct RoundHelper {
+    name: &'static str,
+    op: fn(f64) -> f64,
+    places: bool,
+}
+
+impl RoundHelper {
+    fn floor() -> Self {
+        Self {
+            name: "floor",
+            op: f64::floor,
+            places: false,
+        }
+    }
+
+    fn ceil() -> Self {
+        Self {
+            name: "ceil",
+            op: f64::ceil,
+            places: false,
+        }
+    }
+
+    fn round() -> Self {
+        Self {
+            name: "round",
+            op: f64::round,
+            places: true,
+        }
+    }
+}
+
+impl HelperDef for RoundHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let max_params = if self.places { 2 } else { 1 };
+        if h.params().is_empty() || h.params().len() > max_params {
+            return Err(hb_err!(
+                "{}: Found {} parameters, but {} parameters are supported. Example: {{{{ {} 23.756 }}}}.",
+                self.name,
+                h.params().len(),
+                max_params,
+                self.name
+            ));
+        }
+
+        let value = h.param(0).unwrap().value();
+        let places = match h.param(1).map(|p| p.value()) {
+            None | Some(JsonValue::Null) => None,
+            Some(p) => Some(
+                p.as_u64()
+                    .or_else(|| p.as_str().and_then(|s| u64::from_str(s).ok()))
+                    .filter(|&places| places <= 15)
+                    .ok_or_else(|| hb_err!("{}: Decimal places must be an integer between 0 and 15. Got this: {:?}", self.name, p))?
+                    as i32,
+            ),
+        };
+
+        let int = value
+            .as_i64()
+            .or_else(|| value.as_str().and_then(|s| i64::from_str(s).ok()));
+        if let Some(int) = int {
+            return Ok(hb::ScopedJson::Derived(JsonValue::Number(int.into())));
+        }
+
+        let float = value
+            .as_f64()
+            .or_else(|| value.as_str().and_then(|s| f64::from_str(s).ok()))
+            .filter(|float| float.is_finite())
+            .ok_or_else(|| hb_err!("{}: The value is not in valid format. Valid examples: 5, -62.53. Got this: {:?}", self.name, value))?;
+
+        let res = match places {
+            Some(places) if places > 0 => {
+                let scale = 10f64.powi(places);
+                let rounded = (self.op)(float * scale) / scale;
+                Number::from_f64(rounded).map(JsonValue::Number)
+            }
+            _ => {
+                let rounded = (self.op)(float);
+                if rounded < i64::MIN as f64 || rounded > i64::MAX as f64 {
+                    None
+                } else {
+                    Some(JsonValue::Number((rounded as i64).into()))
+                }
+            }
+        };
+
+        res.map(hb::ScopedJson::Derived)
+            .ok_or_else(|| hb_err!("{}: The value {} is out of range.", self.name, float))
+    }
+}
+
+/// Maximum or minimum of two or more numbers.
+///
+/// Usage: `{{ max a b c }}`, `{{ min a b }}`. The result is an integer if all the arguments are integers,
+/// a decimal number otherwise, as with `math`. A single argument is returned unchanged.
+struct MinMaxHelper {
+    name: &'static str,
+    max: bool,
+}
+
+impl MinMaxHelper {
+    fn max() -> Self {
+        Self {
+            name: "max",
+            max: true,
+        }
+    }
+
+    fn min() -> Self {
+        Self {
+            name: "min",
+            max: false,
+        }
+    }
+
+    fn float(&self, value: &JsonValue) -> Result<f64, RenderError> {
+        value
+            .as_f64()
+            .or_else(|| value.as_str().and_then(|s| f64::from_str(s).ok()))
+            .ok_or_else(|| hb_err!("{}: Argument is not in valid format. Valid examples: 5, -62.53. Got this: {:?}", self.name, value))
+    }
+}
+
+impl HelperDef for MinMaxHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let args: Vec<_> = h.params().iter().map(|p| p.value()).collect();
+        match &args[..] {
+            [] => {
+                return Err(hb_err!(
+                    "{}: No numbers supplied. Example: {{{{ {} 1 2.5 3 }}}}.",
+                    self.name,
+                    self.name
+                ))
+            }
+            [single] => return Ok(hb::ScopedJson::Derived((*single).clone())),
+            _ => {}
+        }
+
+        // try integers
+        let ints: Option<Vec<_>> = args.iter().map(|arg| ClampHelper::int(arg)).collect();
+        if let Some(ints) = ints {
+            let res = if self.max {
+                ints.into_iter().max()
+            } else {
+                ints.into_iter().min()
+            };
+            return Ok(hb::ScopedJson::Derived(JsonValue::Number(Number::from(
+                res.unwrap(),
+            ))));
+        }
+
+        // try floats
+        let mut res = self.float(args[0])?;
+        for arg in &args[1..] {
+            let arg = self.float(arg)?;
+            res = if self.max { res.max(arg) } else { res.min(arg) };
+        }
+        let res = Number::from_f64(res).ok_or_else(|| {
+            hb_err!(
+                "{}: Value {} cannot be converted to JSON number.",
+                self.name,
+                res
+            )
+        })?;
+        Ok(hb::ScopedJson::Derived(JsonValue::Number(res)))
+    }
+}
+
+/// Formats a number with a fixed number of decimal places and an optional decimal separator.
+///
+/// Usage: `{{ number_format 12.7000001 2 }}` renders `12.70`, `{{ number_format 3.5 1 "," }}` renders `3,5`.
+/// If the precision is omitted, integers are formatted as they are and decimal numbers
+/// with as many places as needed.
+struct NumberFormatHelper;
+
+impl HelperDef for NumberFormatHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let value = h
+            .param(0)
+            .ok_or_else(|| {
+                hb_err!(
+                    "number_format: No number supplied. Example: {{{{ number_format 12.7 2 }}}}."
+                )
+            })?
+            .value();
+        let precision = match h.param(1).map(|p| p.value()) {
+            None | Some(JsonValue::Null) => None,
+            Some(p) => Some(
+                p.as_u64()
+                    .or_else(|| p.as_str().and_then(|s| u64::from_str(s).ok()))
+                    .ok_or_else(|| hb_err!("number_format: Precision must be a non-negative integer. Got this: {:?}", p))?
+                    as usize,
+            ),
+        };
+        let separator = match h.param(2).map(|p| p.value()) {
+            None | Some(JsonValue::Null) => ".",
+            Some(JsonValue::String(s)) => s.as_str(),
+            Some(other) => {
+                return Err(hb_err!(
+                    "number_format: Decimal separator must be a string. Got this: {:?}",
+                    other
+                ))
+            }
+        };
+
+        let int = value
+            .as_i64()
+            .or_else(|| value.as_str().and_then(|s| i64::from_str(s).ok()));
+        let formatted = match (int, precision) {
+            (Some(int), None) => int.to_string(),
+            (Some(int), Some(precision)) => format!("{:.*}", precision, int as f64),
+            (None, precision) => {
+                let float = value
+                    .as_f64()
+                    .or_else(|| value.as_str().and_then(|s| f64::from_str(s).ok()))
+                    .ok_or_else(|| hb_err!("number_format: The value is not in valid format. Valid examples: 5, -62.53. Got this: {:?}", value))?;
+                match precision {
+                    Some(precision) => format!("{:.*}", precision, float),
+                    None => float.to_string(),
+                }
+            }
+        };
+
+        let formatted = if separator != "." {
+            formatted.replace('.', separator)
+        } else {
+            formatted
+        };
+        Ok(hb::ScopedJson::Derived(JsonValue::String(formatted)))
+    }
+}
+
+/// Plural rule of a language, decides which word form goes with a count.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PluralRule {
+    /// One form for 1 and another for all other counts, eg. English.
+    OneOther,
+    /// Czech and Slovak: One form for 1, another for 2 to 4, and another for all other counts.
+    OneFewOther,
+}
+
+impl PluralRule {
+    /// Rule for a language code such as `cs` or `en-GB`, English-like rule for unknown languages.
+    fn for_lang(lang: &str) -> Self {
+        let lang = lang.split(|c| c == '-' || c == '_').next().unwrap();
+        match lang.to_lowercase().as_str() {
+            "cs" | "sk" => Self::OneFewOther,
+            _ => Self::OneOther,
+        }
+    }
+
+    fn num_forms(self) -> usize {
+        match self {
+            Self::OneOther => 2,
+            Self::OneFewOther => 3,
+        }
+    }
+
+    /// Index of the form to use for `count`.
+    fn form(self, count: f64) -> usize {
+        let count = count.abs();
+        let int = if count.fract() == 0.0 {
+            Some(count as u64)
+        } else {
+            None
+        };
+        match (self, int) {
+            (_, Some(1)) => 0,
+            (Self::OneOther, _) => 1,
+            (Self::OneFewOther, Some(2..=4)) => 1,
+            (Self::OneFewOther, _) => 2,
+        }
+    }
+}
+
+/// Picks the word form that goes with a count, following the plural rule of the book's `lang`.
+///
+/// Usage: `{{ pluralize 3 "verse" "verses" }}` renders `verses`,
+/// with `lang = "cs"` `{{ pluralize 3 "verš" "verše" "veršů" }}` renders `verše`.
+struct PluralizeHelper {
+    rule: PluralRule,
+}
+
+impl PluralizeHelper {
+    fn new(project: &Project, output: &Output) -> Self {
+        let book = output.override_book_section(project.book_section());
+        let rule = book
+            .get("lang")
+            .and_then(|lang| lang.as_str())
+            .map(PluralRule::for_lang)
+            .unwrap_or(PluralRule::OneOther);
+        Self { rule }
+    }
+}
+
+impl HelperDef for PluralizeHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let num_forms = self.rule.num_forms();
+        if h.params().len() != num_forms + 1 {
+            return Err(hb_err!(
+                "pluralize: Found {} parameters, but a count and {} word forms are needed for this book's language. Example: {{{{ pluralize 3 \"verse\" \"verses\" }}}}.",
+                h.params().len(),
+                num_forms
+            ));
+        }
+
+        let count = h.param(0).unwrap().value();
+        let count = count
+            .as_f64()
+            .or_else(|| count.as_str().and_then(|s| f64::from_str(s).ok()))
+            .ok_or_else(|| {
+                hb_err!(
+                    "pluralize: The count is not a number. Got this: {:?}",
+                    count
+                )
+            })?;
+
+        let form = h.param(self.rule.form(count) + 1).unwrap().value();
+        match form {
+            JsonValue::String(_) => Ok(hb::ScopedJson::Derived(form.clone())),
+            other => Err(hb_err!(
+                "pluralize: Word forms must be strings. Got this: {:?}",
+                other
+            )),
+        }
+    }
+}
+
+/// Formats the build time (in UTC) with a strftime-like pattern, `%Y-%m-%d` by default.
+///
+/// Usage: `{{ now }}` renders eg. `2024-02-29`, `{{ now "%e %B %Y, %H:%M" }}` renders `29 February 2024, 23:59`.
+/// The time is taken once per render and may be overridden with `SOURCE_DATE_EPOCH`
+/// for reproducible builds.
+struct NowHelper {
+    timestamp: i64,
+}
+
+impl NowHelper {
+    const DEFAULT_FORMAT: &'static str = "%Y-%m-%d";
+
+    fn new() -> Result<Self> {
+        build_timestamp().map(|timestamp| Self { timestamp })
+    }
+}
+
+impl HelperDef for NowHelper {
+    fn call_inner<'reg: 'rc, 'rc>(
+        &self,
+        h: &hb::Helper<'reg, 'rc>,
+        _: &'reg Handlebars<'reg>,
+        _: &'rc hb::Context,
+        _: &mut hb::RenderContext<'reg, 'rc>,
+    ) -> Result<hb::ScopedJson<'reg, 'rc>, RenderError> {
+        let format = match h.param(0).map(|p| p.value()) {
+            None | Some(JsonValue::Null) => Self::DEFAULT_FORMAT,
+            Some(JsonValue::String(s)) => s.as_str(),
+            Some(other) => {
+                return Err(hb_err!(
+                    "now: Format must be a string, eg. \"%Y-%m-%d\". Got this: {:?}",
+                    other
+                ))
+            }
+        };
+
+        let formatted =
+            format_timestamp(self.timestamp, format).map_err(|err| hb_err!("now: {}", err))?;
+        Ok(hb::ScopedJson::Derived(JsonValue::String(formatted)))
+    }
+}
+
+/// Two-line excerpt of `source` pointing at `line` and `column` (both 1-based) with a caret.
+fn source_excerpt(source: &str, line: usize, column: usize) -> Option<String> {
+    let src_line = source.lines().nth(line.checked_sub(1)?)?;
+    // Keep tabs so that the caret lines up
+    let indent: String = src_line
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let gutter = " ".repeat(line.to_string().len());
+    Some(format!("{} | {}\n{} | {}^", line, src_line, gutter, indent))
+}
+
+/// Adds the template file, line and column to a render error,
+/// along with a source excerpt if the template is a file on disk.
+fn render_error(err: RenderError) -> Error {
+    let (name, line, column) = match (err.template_name.as_deref(), err.line_no, err.column_no) {
+        (Some(name), Some(line), Some(column)) => (name, line, column),
+        _ => return Error::from(err),
+    };
+
+    let mut msg = format!(
+        "Error in template {:?} at line {}, column {}",
+        name, line, column
+    );
+    let path = Path::new(name);
+    if path.is_absolute() {
+        if let Some(excerpt) = fs::read_to_string(path)
+            .ok()
+            .and_then(|source| source_excerpt(&source, line, column))
+        {
+            msg.push_str(":\n");
+            msg.push_str(&excerpt);
+        }
+    }
+
+    Error::from(err).context(msg)
+}
+
+/// Path of the page of the song with `id` in the `dir` of a split output.
+pub(crate) fn split_page(dir: &Path, id: &str) -> PathBuf {
+    dir.join(format!("{}.html", id))
+}
+
+/// Names and paths of the `_name.hbs` partial files in `dir`, sorted by name.
+/// A missing directory has no partials.
+pub(crate) fn partial_paths(dir: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Could not read template directory {:?}", dir))
+        }
+    };
+
+    let mut paths = vec![];
+    for entry in entries {
+        let path = entry
+            .with_context(|| format!("Could not read template directory {:?}", dir))?
+            .path();
+        if !path.is_file() || path.extension().and_then(|e| e.to_str()) != Some("hbs") {
+            continue;
+        }
+        let name = path.file_stem().and_then(|s| s.to_str());
+        if let Some(name) = name.and_then(|n| n.strip_prefix('_')) {
+            if !name.is_empty() {
+                paths.push((name.to_string(), path));
+            }
+        }
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
 #[derive(Debug)]
 pub(crate) struct HbRender {
     pub(crate) hb: Handlebars<'static>,
     pub(crate) tpl_name: String,
     pub(crate) version: Arc<Mutex<Option<Version>>>,
+    format: Format,
+    /// Per-song template overrides, keyed by the filename given in song metadata.
+    song_templates: BTreeMap<Box<str>, HbRender>,
 }
 
 impl HbRender {
-    /// Version of the template to assume if it specifies none.
-    const ASSUMED_FIRST_VERSION: Version = Version::new(1, 0, 0);
-
-    pub(crate) fn new(
+    fn registry(
         project: &Project,
         output: &Output,
-        default: &DefaultTemaplate,
         img_cache: &ImgCache,
-    ) -> Result<Self> {
+    ) -> Result<(Handlebars<'static>, Arc<Mutex<Option<Version>>>)> {
         let (version_helper, version) = VersionCheckHelper::new();
         let mut hb = Handlebars::new()
             .with_helper("eq", hb_eq)
+            .with_helper("ifeq", IfEqHelper)
             .with_helper("contains", hb_contains)
+            .with_helper("includes", hb_includes)
+            .with_helper("includes_all", hb_includes_all)
             .with_helper("cat", hb_cat)
+            .with_helper("cat_json", CatJsonHelper)
             .with_helper("default", hb_default)
+            .with_helper("keys", hb_keys)
+            .with_helper("values", hb_values)
+            .with_helper("zip", hb_zip)
+            .with_helper("zip_longest", hb_zip_longest)
+            .with_helper("nth", hb_nth)
+            .with_helper("nth1", hb_nth1)
+            .with_helper("group_by", hb_group_by)
+            .with_helper("group_by_tag", hb_group_by_tag)
+            .with_helper("contains_text", hb_contains_text)
             .with_helper("matches", hb_matches)
+            .with_helper("str_contains", hb_str_contains)
+            .with_helper("starts_with", hb_starts_with)
+            .with_helper("ends_with", hb_ends_with)
+            .with_helper("join", hb_join)
+            .with_helper("split", hb_split)
+            .with_helper("capitalize", hb_capitalize)
+            .with_helper("title_case", hb_title_case)
+            .with_helper("words", hb_words)
+            .with_helper("chars", hb_chars)
+            .with_helper("pad_start", hb_pad_start)
+            .with_helper("pad_end", hb_pad_end)
+            .with_helper("roman", RomanHelper)
+            .with_helper("arabic", ArabicHelper)
+            .with_helper("html_escape", hb_html_escape)
+            .with_helper("to_json", hb_to_json)
+            .with_helper("from_json", FromJsonHelper)
+            .with_helper("lookup", LookupHelper)
             .with_helper("math", MathHelper)
+            .with_helper("clamp", ClampHelper)
+            .with_helper("max", MinMaxHelper::max())
+            .with_helper("min", MinMaxHelper::min())
+            .with_helper("floor", RoundHelper::floor())
+            .with_helper("ceil", RoundHelper::ceil())
+            .with_helper("round", RoundHelper::round())
+            .with_helper("number_format", NumberFormatHelper)
+            .with_helper("pluralize", PluralizeHelper::new(project, output))
+            .with_helper("now", NowHelper::new()?)
             .with_helper("img_w", ImgHelper::width(project, img_cache))
             .with_helper("img_h", ImgHelper::height(project, img_cache))
+            .with_helper("alt_or_chord", AltChordHelper::new(output))
+            .with_helper("asset", AssetHelper::new(output))
             .with_helper("version_check", version_helper);
+        hb.set_strict_mode(output.template_strict);
+
+        Ok((hb, version))
+    }
+
+    pub(crate) fn new(
+        project: &Project,
+        output: &Output,
+        default: &DefaultTemaplate,
+        img_cache: &ImgCache,
+    ) -> Result<Self> {
+        let (mut hb, version) = Self::registry(project, output, img_cache)?;
 
         let tpl_name = output
             .template
@@ -418,6 +1784,8 @@ impl HbRender {
 
         if let Some(template) = output.template.as_ref() {
             if template.exists() {
+                let parent = template.parent().unwrap(); // The temaplate should've been resolved as absolute in Project
+                Self::register_partials(&mut hb, parent)?;
                 hb.register_template_file(&tpl_name, template)
                     .with_context(|| format!("Error in template file {:?}", template))?;
             } else {
@@ -441,30 +1809,198 @@ impl HbRender {
         // of the template, we will get the version in self.version.
         let _ = hb.render_to_write(&tpl_name, &(), io::sink());
 
+        let format = output.format();
+        let mut song_templates = BTreeMap::new();
+        for name in project.songs().iter().filter_map(|s| s.template(format)) {
+            if !song_templates.contains_key(name) {
+                let render = Self::new_song(project, output, img_cache, name)?;
+                song_templates.insert(name.into(), render);
+            }
+        }
+
+        Ok(Self {
+            hb,
+            tpl_name,
+            version,
+            format,
+            song_templates,
+        })
+    }
+
+    /// Load a song template override. Unlike the output template, the file must exist.
+    fn new_song(
+        project: &Project,
+        output: &Output,
+        img_cache: &ImgCache,
+        name: &str,
+    ) -> Result<Self> {
+        let (mut hb, version) = Self::registry(project, output, img_cache)?;
+
+        let template = project.settings.dir_templates().join(name);
+        let tpl_name = template.to_string_lossy().to_string();
+        if let Some(parent) = template.parent() {
+            Self::register_partials(&mut hb, parent)?;
+        }
+        hb.register_template_file(&tpl_name, &template)
+            .with_context(|| format!("Error in song template file {:?}", template))?;
+        let _ = hb.render_to_write(&tpl_name, &(), io::sink());
+
         Ok(Self {
             hb,
             tpl_name,
             version,
+            format: output.format(),
+            song_templates: BTreeMap::new(),
         })
     }
 
+    /// Register `_name.hbs` files in `dir` as partials available as `{{> name }}`.
+    fn register_partials(hb: &mut Handlebars<'static>, dir: &Path) -> Result<()> {
+        for (name, path) in partial_paths(dir)? {
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Could not read partial template file {:?}", path))?;
+            hb.register_partial(&name, content)
+                .with_context(|| format!("Error in partial template file {:?}", path))?;
+        }
+
+        Ok(())
+    }
+
+    /// Apply format-specific setup, such as the escape function and extra helpers,
+    /// to the main template as well as all the song templates.
+    pub(crate) fn setup(&mut self, f: impl Fn(&mut Handlebars<'static>)) {
+        f(&mut self.hb);
+        for render in self.song_templates.values_mut() {
+            f(&mut render.hb);
+        }
+    }
+
     pub(crate) fn render(&self, output: &Path, context: RenderContext) -> Result<()> {
-        let rendered = self.hb.render(&self.tpl_name, &context)?;
+        let rendered = self.render_context(&context)?;
+        fs::write(output, rendered.as_bytes())
+            .with_context(|| format!("Error writing output file: {:?}", output))?;
+
+        Ok(())
+    }
+
+    /// Render the template into `w` instead of the output file,
+    /// eg. to capture the output in memory.
+    pub fn render_to_writer(&self, mut w: impl Write, context: RenderContext) -> Result<()> {
+        let rendered = self.render_context(&context)?;
+        w.write_all(rendered.as_bytes())?;
+        w.flush()?;
+        Ok(())
+    }
 
+    fn render_context(&self, context: &RenderContext) -> Result<String> {
+        if self.song_templates.is_empty() {
+            self.render_data(context)
+        } else {
+            let data = self.render_songs(context)?;
+            self.render_data(&data)
+        }
+    }
+
+    /// Render the template once per song into `dir` and once more into `output` as an index page,
+    /// see the `split` output setting.
+    ///
+    /// Song pages get the song as `song` and its neighbours as `prev` and `next`,
+    /// the index page gets the usual data. Both also get `split`, `split_dir`, and `index`.
+    pub(crate) fn render_split(
+        &self,
+        output: &Path,
+        dir: &Path,
+        context: RenderContext,
+    ) -> Result<()> {
+        let mut data = self.render_songs(&context)?;
+        data["split"] = true.into();
+        data["split_dir"] = dir.file_name().unwrap().to_string_lossy().into();
+        data["index"] = output.file_name().unwrap().to_string_lossy().into();
+
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Error creating output directory: {:?}", dir))?;
+
+        let songs = data["songs"].as_array().map_or(&[][..], |songs| &songs[..]);
+        for (i, song) in songs.iter().enumerate() {
+            let mut page = JsonValue::Object(Default::default());
+            for key in [
+                "book",
+                "notation",
+                "output",
+                "program",
+                "split",
+                "split_dir",
+                "index",
+            ] {
+                page[key] = data[key].clone();
+            }
+            page["song"] = song.clone();
+            page["prev"] = i
+                .checked_sub(1)
+                .map_or(JsonValue::Null, |i| songs[i].clone());
+            page["next"] = songs.get(i + 1).cloned().unwrap_or(JsonValue::Null);
+
+            let path = split_page(dir, song["id"].as_str().unwrap_or("song"));
+            let rendered = self.render_data(&page).with_context(|| {
+                format!(
+                    "Error rendering song '{}'",
+                    song["title"].as_str().unwrap_or("")
+                )
+            })?;
+            fs::write(&path, rendered.as_bytes())
+                .with_context(|| format!("Error writing output file: {:?}", path))?;
+        }
+
+        let rendered = self.render_data(&data)?;
         fs::write(output, rendered.as_bytes())
             .with_context(|| format!("Error writing output file: {:?}", output))?;
 
         Ok(())
     }
 
+    /// Render the template with `data`, errors point to the template source.
+    fn render_data(&self, data: &impl Serialize) -> Result<String> {
+        self.hb.render(&self.tpl_name, data).map_err(render_error)
+    }
+
+    /// Render songs which have a template override and store the result
+    /// in the `rendered` field of each such song.
+    ///
+    /// The song template gets the same data as the main one, plus the song in question as `song`.
+    fn render_songs(&self, context: &RenderContext) -> Result<JsonValue> {
+        let mut data = serde_json::to_value(context)?;
+
+        for (i, song) in context.songs.iter().enumerate() {
+            let render = match song.template(self.format) {
+                Some(name) => &self.song_templates[name],
+                None => continue,
+            };
+
+            let mut song_data = data.clone();
+            song_data["song"] = data["songs"][i].clone();
+            let rendered = render.render_data(&song_data).with_context(|| {
+                format!(
+                    "Error rendering song '{}' with template {:?}",
+                    song.title, render.tpl_name
+                )
+            })?;
+            data["songs"][i]["rendered"] = rendered.into();
+        }
+
+        Ok(data)
+    }
+
+    /// Version declared by the template with `version_check`, if any.
     pub(crate) fn version(&self) -> Option<Version> {
-        Some(
-            self.version
-                .lock()
-                .unwrap()
-                .clone()
-                .unwrap_or(Self::ASSUMED_FIRST_VERSION),
-        )
+        self.version.lock().unwrap().clone()
+    }
+
+    /// Paths and declared versions of song template overrides.
+    pub(crate) fn song_templates(&self) -> Vec<(&Path, Option<Version>)> {
+        self.song_templates
+            .values()
+            .map(|render| (Path::new(&render.tpl_name), render.version()))
+            .collect()
     }
 }
 
@@ -36,3 +36,1055 @@ fn hb_helper_math() {
     assert_eq!(math(r#"90.0 "/" 3.0"#), "30.0");
     assert_eq!(math(r#"11.5 "%" 2.0"#), "1.5");
 }
+
+#[test]
+fn hb_helper_clamp() {
+    let hb = Handlebars::new().with_helper("clamp", ClampHelper);
+    let clamp = |args: &str| hb.render_template(&format!("{{{{ clamp {} }}}}", args), &0);
+
+    // Integers
+    assert_eq!(clamp("12 8 10").unwrap(), "10");
+    assert_eq!(clamp("-3 0 10").unwrap(), "0");
+    assert_eq!(clamp(r#""5" 0 "10""#).unwrap(), "5");
+
+    // Floats
+    assert_eq!(clamp("12.5 8 10").unwrap(), "10.0");
+    assert_eq!(clamp("0.5 1.5 2.5").unwrap(), "1.5");
+    assert_eq!(clamp("2 0.5 1.5").unwrap(), "1.5");
+
+    // Already in range
+    assert_eq!(clamp("9 8 10").unwrap(), "9");
+    assert_eq!(clamp("9.25 8.5 10").unwrap(), "9.25");
+    assert_eq!(clamp("8 8 8").unwrap(), "8");
+
+    // Errors
+    assert!(clamp("5 10 1").is_err());
+    assert!(clamp("5 10.5 1").is_err());
+    assert!(clamp(r#""foo" 1 2"#).is_err());
+    assert!(clamp("5 1").is_err());
+}
+
+#[test]
+fn hb_helper_max_min() {
+    let hb = Handlebars::new()
+        .with_helper("max", MinMaxHelper::max())
+        .with_helper("min", MinMaxHelper::min());
+    let render = |expr: &str| hb.render_template(&format!("{{{{ {} }}}}", expr), &0);
+
+    // Integers
+    assert_eq!(render("max 3 8 -2").unwrap(), "8");
+    assert_eq!(render("min 3 8 -2").unwrap(), "-2");
+    assert_eq!(render(r#"max "12" 7"#).unwrap(), "12");
+
+    // Mixed integers and floats
+    assert_eq!(render("max 3 2.5").unwrap(), "3.0");
+    assert_eq!(render("min 3 2.5 4").unwrap(), "2.5");
+    assert_eq!(render(r#"max 1 "7.25" 7"#).unwrap(), "7.25");
+
+    // Single argument
+    assert_eq!(render("max 5").unwrap(), "5");
+    assert_eq!(render("min 2.5").unwrap(), "2.5");
+
+    // Nested
+    assert_eq!(render("max (min 10 20) 4").unwrap(), "10");
+
+    // Errors
+    assert!(render("max").is_err());
+    assert!(render(r#"min 1 "foo""#).is_err());
+}
+
+#[test]
+fn hb_helper_lookup() {
+    let hb = Handlebars::new().with_helper("lookup", LookupHelper);
+    let data = serde_json::json!({
+        "obj": { "key": "value", "null_key": null },
+        "array": ["a", "b"],
+        "null": null,
+        "text": "text",
+    });
+    let render = |tpl: &str| hb.render_template(tpl, &data);
+
+    assert_eq!(render(r#"{{ lookup obj "key" }}"#).unwrap(), "value");
+    assert_eq!(render(r#"{{ lookup obj "missing" }}"#).unwrap(), "");
+    assert_eq!(
+        render(r#"{{ lookup obj "missing" "default" }}"#).unwrap(),
+        "default"
+    );
+    assert_eq!(
+        render(r#"{{ lookup obj "null_key" "default" }}"#).unwrap(),
+        ""
+    );
+    assert_eq!(render(r#"{{ lookup null "key" }}"#).unwrap(), "");
+    assert_eq!(
+        render(r#"{{ lookup null "key" "default" }}"#).unwrap(),
+        "default"
+    );
+    assert_eq!(render(r#"{{ lookup array 1 }}"#).unwrap(), "b");
+    assert_eq!(render(r#"{{ lookup array 2 "none" }}"#).unwrap(), "none");
+
+    assert!(render(r#"{{ lookup text "key" }}"#).is_err());
+    assert!(render(r#"{{ lookup obj 1 }}"#).is_err());
+    assert!(render(r#"{{ lookup array "x" }}"#).is_err());
+    assert!(render(r#"{{ lookup obj }}"#).is_err());
+}
+
+#[test]
+fn hb_helper_capitalize_title_case() {
+    let hb = Handlebars::new()
+        .with_helper("capitalize", hb_capitalize)
+        .with_helper("title_case", hb_title_case);
+    let render = |tpl: &str, data: JsonValue| hb.render_template(tpl, &data).unwrap();
+
+    let data = serde_json::json!({
+        "title": "oh danny boy",
+        "czech": "žluťoučký kůň",
+        "spaces": " the  pipes\tare CALLING",
+        "empty": "",
+        "null": null,
+        "number": 1,
+    });
+
+    assert_eq!(
+        render("{{ capitalize title }}", data.clone()),
+        "Oh danny boy"
+    );
+    assert_eq!(
+        render("{{ title_case title }}", data.clone()),
+        "Oh Danny Boy"
+    );
+    assert_eq!(
+        render("{{ capitalize czech }}", data.clone()),
+        "Žluťoučký kůň"
+    );
+    assert_eq!(
+        render("{{ title_case czech }}", data.clone()),
+        "Žluťoučký Kůň"
+    );
+    assert_eq!(
+        render("{{ title_case spaces }}", data.clone()),
+        " The  Pipes\tAre CALLING"
+    );
+    assert_eq!(render("{{ capitalize empty }}", data.clone()), "");
+    assert_eq!(render("{{ title_case empty }}", data.clone()), "");
+    assert_eq!(render("{{ capitalize null }}", data.clone()), "");
+    assert_eq!(render("{{ title_case null }}", data.clone()), "");
+    assert_eq!(render("{{ capitalize number }}", data), "1");
+
+    // Characters which uppercase to more than one character
+    assert_eq!(capitalize("ßtraße"), "SStraße");
+    assert_eq!(title_case("ǆ ǆ"), "Ǆ Ǆ");
+}
+
+#[test]
+fn hb_helper_join_split() {
+    let hb = Handlebars::new()
+        .with_helper("join", hb_join)
+        .with_helper("split", hb_split);
+    let render = |tpl: &str, data: JsonValue| hb.render_template(tpl, &data).unwrap();
+
+    let data = serde_json::json!({
+        "authors": ["Lennon", "McCartney", null, 1],
+        "empty": [],
+        "text": "G -> C -> D -> ",
+    });
+
+    assert_eq!(
+        render(r#"{{ join authors ", " }}"#, data.clone()),
+        "Lennon, McCartney, 1"
+    );
+    assert_eq!(render(r#"{{ join empty ", " }}"#, data.clone()), "");
+    assert_eq!(render(r#"{{ join text ", " }}"#, data.clone()), "");
+
+    assert_eq!(split("a, b, c", ", "), ["a", "b", "c"]);
+    assert_eq!(split("G -> C -> D -> ", " -> "), ["G", "C", "D"]);
+    assert_eq!(split("a,,b,", ","), ["a", "", "b"]);
+    assert_eq!(split("abc", ""), ["abc"]);
+    assert!(split("", ",").is_empty());
+    assert_eq!(
+        render(r#"{{ join (split text " -> ") "|" }}"#, data.clone()),
+        "G|C|D"
+    );
+    assert_eq!(
+        render(
+            r#"{{#each (split text " -> ")}}[{{ this }}]{{/each}}"#,
+            data
+        ),
+        "[G][C][D]"
+    );
+}
+
+#[test]
+fn hb_helper_to_from_json() {
+    let hb = Handlebars::new()
+        .with_helper("to_json", hb_to_json)
+        .with_helper("from_json", FromJsonHelper)
+        .with_helper("lookup", LookupHelper);
+    let render = |tpl: &str, data: &JsonValue| hb.render_template(tpl, data);
+
+    let data = serde_json::json!({
+        "obj": { "key": "value", "nested": [1, null] },
+        "array": [1, "two", null, { "three": 3.5 }],
+        "null": null,
+        "number": -42,
+        "meta": r#"{"tags": ["folk", "irish"]}"#,
+        "invalid": "{not json",
+    });
+
+    assert_eq!(
+        render("{{{ to_json obj }}}", &data).unwrap(),
+        r#"{"key":"value","nested":[1,null]}"#
+    );
+    assert_eq!(render("{{{ to_json null }}}", &data).unwrap(), "null");
+    assert_eq!(render("{{{ to_json number }}}", &data).unwrap(), "-42");
+
+    for key in ["obj", "array", "null", "number"] {
+        let tpl = format!("{{{{{{ to_json (from_json (to_json {})) }}}}}}", key);
+        assert_eq!(
+            render(&tpl, &data).unwrap(),
+            data[key].to_string(),
+            "round-trip of {}",
+            key
+        );
+    }
+
+    assert_eq!(
+        render(
+            r#"{{#each (lookup (from_json meta) "tags")}}[{{ this }}]{{/each}}"#,
+            &data
+        )
+        .unwrap(),
+        "[folk][irish]"
+    );
+
+    assert!(render("{{ from_json invalid }}", &data).is_err());
+    assert!(render("{{ from_json number }}", &data).is_err());
+    assert!(render("{{ from_json }}", &data).is_err());
+}
+
+#[test]
+fn hb_helper_html_escape() {
+    let hb = Handlebars::new().with_helper("html_escape", hb_html_escape);
+    let render = |tpl: &str, data: JsonValue| hb.render_template(tpl, &data).unwrap();
+
+    let data = serde_json::json!({ "title": r#"<Tom & "Jerry's">"# });
+    assert_eq!(
+        render("{{{ html_escape title }}}", data.clone()),
+        "&lt;Tom &amp; &quot;Jerry&#x27;s&quot;&gt;"
+    );
+    // Doesn't interfere with the automatic escaping
+    assert_eq!(
+        render("{{ html_escape title }}", data),
+        "&amp;lt;Tom &amp;amp; &amp;quot;Jerry&amp;#x27;s&amp;quot;&amp;gt;"
+    );
+
+    assert_eq!(render("{{{ html_escape 42 }}}", JsonValue::Null), "42");
+    assert_eq!(render("{{{ html_escape missing }}}", JsonValue::Null), "");
+    assert_eq!(
+        render(
+            "{{{ html_escape meta }}}",
+            serde_json::json!({ "meta": { "key": "C" } })
+        ),
+        "{&quot;key&quot;:&quot;C&quot;}"
+    );
+}
+
+#[test]
+fn hb_helper_floor_ceil_round() {
+    let hb = Handlebars::new()
+        .with_helper("floor", RoundHelper::floor())
+        .with_helper("ceil", RoundHelper::ceil())
+        .with_helper("round", RoundHelper::round());
+    let render = |tpl: &str| hb.render_template(tpl, &0);
+
+    // Positive
+    assert_eq!(render("{{ floor 23.756 }}").unwrap(), "23");
+    assert_eq!(render("{{ ceil 23.156 }}").unwrap(), "24");
+    assert_eq!(render("{{ round 23.756 }}").unwrap(), "24");
+    assert_eq!(render("{{ round 23.456 }}").unwrap(), "23");
+
+    // Negative
+    assert_eq!(render("{{ floor -2.5 }}").unwrap(), "-3");
+    assert_eq!(render("{{ ceil -2.5 }}").unwrap(), "-2");
+    assert_eq!(render("{{ round -2.5 }}").unwrap(), "-3");
+    assert_eq!(render("{{ round -2.4 }}").unwrap(), "-2");
+
+    // Already integers
+    assert_eq!(render("{{ floor 7 }}").unwrap(), "7");
+    assert_eq!(render("{{ ceil -7 }}").unwrap(), "-7");
+    assert_eq!(render("{{ round 7 2 }}").unwrap(), "7");
+    assert_eq!(render("{{ floor 7.0 }}").unwrap(), "7");
+    assert_eq!(render(r#"{{ ceil "7.5" }}"#).unwrap(), "8");
+
+    // Decimal places
+    assert_eq!(render("{{ round 3.1415 2 }}").unwrap(), "3.14");
+    assert_eq!(render("{{ round -3.14159 3 }}").unwrap(), "-3.142");
+    assert_eq!(render("{{ round 3.5 0 }}").unwrap(), "4");
+    assert_eq!(render("{{ round 3.5 null }}").unwrap(), "4");
+    assert_eq!(render("{{ round (round 2.5) 2 }}").unwrap(), "3");
+
+    // Errors
+    assert!(render("{{ floor }}").is_err());
+    assert!(render("{{ floor 1.5 2 }}").is_err());
+    assert!(render(r#"{{ round "foo" }}"#).is_err());
+    assert!(render("{{ round 1.5 -1 }}").is_err());
+    assert!(render("{{ round 1.5 2 3 }}").is_err());
+    assert!(render("{{ ceil 1e300 }}").is_err());
+}
+
+#[test]
+fn hb_helper_number_format() {
+    let hb = Handlebars::new().with_helper("number_format", NumberFormatHelper);
+    let fmt = |args: &str| hb.render_template(&format!("{{{{ number_format {} }}}}", args), &0);
+
+    // Rounding
+    assert_eq!(fmt("12.7000001 2").unwrap(), "12.70");
+    assert_eq!(fmt("3.14159 3").unwrap(), "3.142");
+    assert_eq!(fmt("9.999 2").unwrap(), "10.00");
+    assert_eq!(fmt("-1.26 1").unwrap(), "-1.3");
+    assert_eq!(fmt("12.7 0").unwrap(), "13");
+
+    // Integers
+    assert_eq!(fmt("12").unwrap(), "12");
+    assert_eq!(fmt("12 0").unwrap(), "12");
+    assert_eq!(fmt("12 2").unwrap(), "12.00");
+    assert_eq!(fmt(r#""7" 1"#).unwrap(), "7.0");
+
+    // No precision
+    assert_eq!(fmt("12.75").unwrap(), "12.75");
+
+    // Separator
+    assert_eq!(fmt(r#"12.7000001 2 ",""#).unwrap(), "12,70");
+    assert!(fmt(r#"12 ",""#).is_err());
+    assert_eq!(fmt(r#"12 null ",""#).unwrap(), "12");
+    assert_eq!(fmt(r#"0.5 null ",""#).unwrap(), "0,5");
+
+    // Errors
+    assert!(fmt(r#""foo" 2"#).is_err());
+    assert!(fmt("1.5 -1").is_err());
+    assert!(fmt("1.5 2 3").is_err());
+    assert!(fmt("").is_err());
+}
+
+#[test]
+fn hb_helper_now() {
+    let hb = Handlebars::new().with_helper(
+        "now",
+        NowHelper {
+            timestamp: 1709251199,
+        },
+    );
+    let fmt = |args: &str| hb.render_template(&format!("{{{{ now {} }}}}", args), &0);
+
+    assert_eq!(fmt("").unwrap(), "2024-02-29");
+    assert_eq!(fmt("null").unwrap(), "2024-02-29");
+    assert_eq!(
+        fmt(r#""%e %B %Y, %H:%M""#).unwrap(),
+        "29 February 2024, 23:59"
+    );
+    assert!(fmt(r#""%Q""#).is_err());
+    assert!(fmt("5").is_err());
+
+    // Default format with the current time
+    let hb = Handlebars::new().with_helper("now", NowHelper::new().unwrap());
+    let date = hb.render_template("{{ now }}", &0).unwrap();
+    let parts: Vec<u32> = date.split('-').map(|p| p.parse().unwrap()).collect();
+    assert!(matches!(&parts[..], [y, 1..=12, 1..=31] if *y >= 2024));
+}
+
+#[test]
+fn hb_helper_alt_or_chord() {
+    let render = |primary: bool, data: JsonValue| {
+        Handlebars::new()
+            .with_helper("alt_or_chord", AltChordHelper { primary })
+            .render_template("{{ alt_or_chord chord alt_chord }}", &data)
+            .unwrap()
+    };
+
+    let no_alt = serde_json::json!({ "chord": "Bm", "alt_chord": null });
+    let alt = serde_json::json!({ "chord": "Bm", "alt_chord": "Hm" });
+
+    assert_eq!(render(false, no_alt.clone()), "Bm");
+    assert_eq!(render(true, no_alt), "Bm");
+    assert_eq!(render(false, alt.clone()), "Bm");
+    assert_eq!(render(true, alt), "Hm");
+}
+
+#[test]
+fn hb_helper_asset() {
+    let hb = Handlebars::new().with_helper(
+        "asset",
+        AssetHelper {
+            assets: vec!["style.css".into(), "fonts".into()],
+        },
+    );
+    let render = |tpl: &str| hb.render_template(tpl, &JsonValue::Null);
+
+    assert_eq!(render(r#"{{ asset "style.css" }}"#).unwrap(), "style.css");
+    assert_eq!(
+        render(r#"{{ asset "fonts/sans/regular.woff" }}"#).unwrap(),
+        "fonts/sans/regular.woff"
+    );
+    assert!(render(r#"{{ asset "script.js" }}"#).is_err());
+    assert!(render(r#"{{ asset "style" }}"#).is_err());
+    assert!(render(r#"{{ asset }}"#).is_err());
+}
+
+#[test]
+fn hb_helper_contains_text() {
+    let hb = Handlebars::new().with_helper("contains_text", hb_contains_text);
+    let render = |data: JsonValue| {
+        hb.render_template(
+            "{{ contains_text para }}",
+            &serde_json::json!({ "para": data }),
+        )
+        .unwrap()
+    };
+
+    let text = |text: &str| serde_json::json!({ "type": "i-text", "text": text });
+    let brk = serde_json::json!({ "type": "i-break" });
+    let chord = |inlines: Vec<JsonValue>| serde_json::json!({ "type": "i-chord", "chord": "Am", "inlines": inlines });
+
+    assert_eq!(render(serde_json::json!([text("Lyrics")])), "true");
+    assert_eq!(
+        render(serde_json::json!([
+            chord(vec![]),
+            brk.clone(),
+            text("Lyrics")
+        ])),
+        "true"
+    );
+    assert_eq!(
+        render(serde_json::json!([chord(vec![text("Lyrics")])])),
+        "true"
+    );
+    assert_eq!(
+        render(serde_json::json!([{ "type": "i-strong", "inlines": [text("Bold")] }])),
+        "true"
+    );
+    assert_eq!(
+        render(
+            serde_json::json!({ "type": "b-verse", "paragraphs": [[brk.clone()], [text("x")]] })
+        ),
+        "true"
+    );
+
+    assert_eq!(render(serde_json::json!([])), "false");
+    assert_eq!(render(serde_json::json!([text("  \n ")])), "false");
+    assert_eq!(
+        render(serde_json::json!([
+            chord(vec![]),
+            brk.clone(),
+            chord(vec![text(" ")]),
+            brk
+        ])),
+        "false"
+    );
+    assert_eq!(render(JsonValue::Null), "false");
+}
+
+#[test]
+fn hb_helper_includes() {
+    let hb = Handlebars::new()
+        .with_helper("includes", hb_includes)
+        .with_helper("includes_all", hb_includes_all);
+    let render = |tpl: &str| {
+        let data = serde_json::json!({
+            "numbers": [1, 2, 3],
+            "tags": ["folk", "irish"],
+            "flags": [true, null],
+            "object": { "folk": 1 },
+            "missing": null,
+        });
+        hb.render_template(tpl, &data).unwrap()
+    };
+
+    assert_eq!(render("{{ includes numbers 2 }}"), "true");
+    assert_eq!(render("{{ includes numbers 4 }}"), "false");
+    assert_eq!(render(r#"{{ includes numbers "2" }}"#), "false");
+    assert_eq!(render(r#"{{ includes tags "irish" }}"#), "true");
+    assert_eq!(render(r#"{{ includes tags "Irish" }}"#), "false");
+    assert_eq!(render("{{ includes flags true }}"), "true");
+    assert_eq!(render("{{ includes flags false }}"), "false");
+    assert_eq!(render("{{ includes flags null }}"), "true");
+    assert_eq!(
+        render(r#"{{#if (includes tags "folk")}}yes{{else}}no{{/if}}"#),
+        "yes"
+    );
+
+    // Anything else than an array contains nothing
+    assert_eq!(render("{{ includes missing 1 }}"), "false");
+    assert_eq!(render(r#"{{ includes object "folk" }}"#), "false");
+    assert_eq!(render(r#"{{ includes "folk" "folk" }}"#), "false");
+
+    assert_eq!(render("{{ includes_all numbers 3 1 }}"), "true");
+    assert_eq!(render("{{ includes_all numbers 1 2 4 }}"), "false");
+    assert_eq!(render(r#"{{ includes_all tags "folk" "irish" }}"#), "true");
+    assert_eq!(render(r#"{{ includes_all tags "folk" 1 }}"#), "false");
+    assert_eq!(render("{{ includes_all missing 1 2 }}"), "false");
+}
+
+#[test]
+fn hb_helper_str_predicates() {
+    let hb = Handlebars::new()
+        .with_helper("str_contains", hb_str_contains)
+        .with_helper("starts_with", hb_starts_with)
+        .with_helper("ends_with", hb_ends_with);
+    let render = |tpl: &str| {
+        let data = serde_json::json!({ "subtitle": "Píseň o koních – lidová" });
+        hb.render_template(tpl, &data).unwrap()
+    };
+
+    assert_eq!(render(r#"{{ str_contains subtitle "koních" }}"#), "true");
+    assert_eq!(render(r#"{{ str_contains subtitle "Koních" }}"#), "false");
+    assert_eq!(render(r#"{{ str_contains subtitle "" }}"#), "true");
+    assert_eq!(render(r#"{{ starts_with subtitle "Píseň" }}"#), "true");
+    assert_eq!(render(r#"{{ starts_with subtitle "lidová" }}"#), "false");
+    assert_eq!(render(r#"{{ ends_with subtitle "– lidová" }}"#), "true");
+    assert_eq!(render(r#"{{ ends_with subtitle "Píseň" }}"#), "false");
+    assert_eq!(
+        render(r#"{{#if (starts_with subtitle "Pí")}}yes{{else}}no{{/if}}"#),
+        "yes"
+    );
+
+    // Non-string arguments are an error
+    let tpl = r#"{{ starts_with 42 "4" }}"#;
+    assert!(hb.render_template(tpl, &JsonValue::Null).is_err());
+}
+
+#[test]
+fn hb_helper_keys_values() {
+    let hb = Handlebars::new()
+        .with_helper("keys", hb_keys)
+        .with_helper("values", hb_values);
+    let render = |tpl: &str, data: JsonValue| hb.render_template(tpl, &data).unwrap();
+
+    let obj = serde_json::json!({ "obj": { "b": 2, "c": 3, "a": 1 } });
+    assert_eq!(
+        render("{{#each (keys obj)}}{{this}},{{/each}}", obj.clone()),
+        "a,b,c,"
+    );
+    assert_eq!(
+        render("{{#each (values obj)}}{{this}},{{/each}}", obj.clone()),
+        "1,2,3,"
+    );
+
+    // Values are in the same order as keys
+    assert_eq!(
+        render(
+            "{{#each (keys obj)}}{{this}}={{lookup (values ../obj) @index}},{{/each}}",
+            obj
+        ),
+        "a=1,b=2,c=3,"
+    );
+
+    // Non-objects yield empty arrays
+    for data in [
+        serde_json::json!({ "obj": null }),
+        serde_json::json!({ "obj": 42 }),
+        serde_json::json!({ "obj": ["a", "b"] }),
+    ] {
+        assert_eq!(
+            render("{{#each (keys obj)}}x{{else}}empty{{/each}}", data.clone()),
+            "empty"
+        );
+        assert_eq!(
+            render("{{#each (values obj)}}x{{else}}empty{{/each}}", data),
+            "empty"
+        );
+    }
+}
+
+#[test]
+fn hb_helper_group_by() {
+    let hb = Handlebars::new().with_helper("group_by", hb_group_by);
+    let render = |tpl: &str, data: &JsonValue| hb.render_template(tpl, data).unwrap();
+    let tpl = r#"{{#each (group_by songs "metadata.language")}}{{@key}}:{{#each this}}{{title}},{{/each}};{{/each}}"#;
+
+    let data = serde_json::json!({ "songs": [
+        { "title": "Danny Boy", "metadata": { "language": "en" } },
+        { "title": "Škoda lásky", "metadata": { "language": "cs" } },
+        { "title": "Intro", "metadata": {} },
+        { "title": "Whiskey in the Jar", "metadata": { "language": "en" } },
+        { "title": "Outro" },
+        { "title": "Numbers", "metadata": { "language": 1 } },
+    ]});
+    assert_eq!(
+        render(tpl, &data),
+        ":Intro,Outro,;1:Numbers,;cs:Škoda lásky,;en:Danny Boy,Whiskey in the Jar,;"
+    );
+
+    // Nested paths, including array indices
+    let data = serde_json::json!({ "songs": [
+        { "title": "A", "subtitles": ["x"], "metadata": { "info": { "tag": "folk" } } },
+        { "title": "B", "subtitles": [], "metadata": { "info": { "tag": "carol" } } },
+    ]});
+    assert_eq!(
+        render(
+            r#"{{#each (group_by songs "metadata.info.tag")}}{{@key}}={{this.[0].title}},{{/each}}"#,
+            &data
+        ),
+        "carol=B,folk=A,"
+    );
+    assert_eq!(
+        render(
+            r#"{{#each (group_by songs "subtitles.0")}}{{@key}}={{this.[0].title}},{{/each}}"#,
+            &data
+        ),
+        "=B,x=A,"
+    );
+
+    // Not an array
+    let data = serde_json::json!({ "songs": null });
+    assert_eq!(render(tpl, &data), "");
+}
+
+#[test]
+fn hb_helper_group_by_tag() {
+    let hb = Handlebars::new().with_helper("group_by_tag", hb_group_by_tag);
+    let render = |tpl: &str, data: &JsonValue| hb.render_template(tpl, data).unwrap();
+    let tpl = r#"{{#each (group_by_tag songs "metadata.tags")}}{{key}}:{{#each songs}}{{title}},{{/each}};{{/each}}"#;
+
+    let data = serde_json::json!({ "songs": [
+        { "title": "Danny Boy", "metadata": { "tags": ["irish", "folk"] } },
+        { "title": "Intro", "metadata": {} },
+        { "title": "Silent Night", "metadata": { "tags": "xmas, carol" } },
+        { "title": "Whiskey in the Jar", "metadata": { "tags": ["irish"] } },
+        { "title": "Outro", "metadata": { "tags": [] } },
+        { "title": "Jingle Bells", "metadata": { "tags": " xmas" } },
+        { "title": "Scarborough Fair", "metadata": { "tags": ["folk", "irish"] } },
+    ]});
+    assert_eq!(
+        render(tpl, &data),
+        "irish:Danny Boy,Whiskey in the Jar,;:Intro,Outro,;xmas:Silent Night,Jingle Bells,;folk:Scarborough Fair,;"
+    );
+
+    // Not an array
+    let data = serde_json::json!({ "songs": null });
+    assert_eq!(render(tpl, &data), "");
+}
+
+#[test]
+fn hb_helper_zip() {
+    let hb = Handlebars::new()
+        .with_helper("zip", hb_zip)
+        .with_helper("zip_longest", hb_zip_longest);
+    let render = |tpl: &str, data: &JsonValue| hb.render_template(tpl, data).unwrap();
+    let zip = "{{#each (zip a b)}}{{this.[0]}}-{{this.[1]}},{{else}}empty{{/each}}";
+    let zip_longest = "{{#each (zip_longest a b)}}{{this.[0]}}-{{this.[1]}},{{else}}empty{{/each}}";
+
+    // Equal length
+    let data = serde_json::json!({ "a": ["C", "G"], "b": ["x32010", "320003"] });
+    assert_eq!(render(zip, &data), "C-x32010,G-320003,");
+    assert_eq!(render(zip_longest, &data), "C-x32010,G-320003,");
+
+    // Unequal length
+    let data = serde_json::json!({ "a": ["C", "G", "Am"], "b": [1] });
+    assert_eq!(render(zip, &data), "C-1,");
+    assert_eq!(render(zip_longest, &data), "C-1,G-,Am-,");
+    assert_eq!(
+        render(
+            "{{#each (zip_longest a b)}}{{#if this.[1]}}set{{else}}null{{/if}},{{/each}}",
+            &data
+        ),
+        "set,null,null,"
+    );
+
+    // Empty
+    let data = serde_json::json!({ "a": [], "b": [1, 2] });
+    assert_eq!(render(zip, &data), "empty");
+    assert_eq!(render(zip_longest, &data), "-1,-2,");
+
+    // Null
+    for data in [
+        serde_json::json!({ "a": null, "b": [1, 2] }),
+        serde_json::json!({ "a": [1, 2], "b": null }),
+        serde_json::json!({ "a": [1, 2] }),
+    ] {
+        assert_eq!(render(zip, &data), "empty");
+        assert_eq!(render(zip_longest, &data), "empty");
+    }
+}
+
+#[test]
+fn hb_helper_nth() {
+    let hb = Handlebars::new()
+        .with_helper("nth", hb_nth)
+        .with_helper("nth1", hb_nth1);
+    let data = serde_json::json!({ "a": ["C", "G", "Am"], "b": null, "c": "C" });
+    let render = |tpl: &str| hb.render_template(tpl, &data).unwrap();
+    let is_null = |expr: &str| {
+        render(&format!(
+            "{{{{#if ({})}}}}set{{{{else}}}}null{{{{/if}}}}",
+            expr
+        ))
+    };
+
+    // In bounds
+    assert_eq!(render("{{ nth a 0 }}"), "C");
+    assert_eq!(render("{{ nth a 2 }}"), "Am");
+    assert_eq!(render("{{ nth1 a 1 }}"), "C");
+    assert_eq!(render("{{ nth1 a 3 }}"), "Am");
+
+    // Negative
+    assert_eq!(render("{{ nth a -1 }}"), "Am");
+    assert_eq!(render("{{ nth a -3 }}"), "C");
+    assert_eq!(render("{{ nth1 a -1 }}"), "Am");
+
+    // Out of bounds
+    assert_eq!(is_null("nth a 3"), "null");
+    assert_eq!(is_null("nth a -4"), "null");
+    assert_eq!(is_null("nth1 a 0"), "null");
+    assert_eq!(is_null("nth1 a 4"), "null");
+
+    // Null or not an array
+    for expr in ["nth b 0", "nth c 0", "nth missing 0", "nth1 b 1"] {
+        assert_eq!(is_null(expr), "null");
+    }
+}
+
+#[test]
+fn image_dpi_lint() {
+    // No thresholds
+    assert_eq!(image_dpi_warning(10.0, None, None), None);
+    assert_eq!(image_dpi_warning(10000.0, None, None), None);
+
+    // Boundaries are inclusive
+    assert_eq!(image_dpi_warning(150.0, Some(150.0), Some(600.0)), None);
+    assert_eq!(image_dpi_warning(600.0, Some(150.0), Some(600.0)), None);
+    assert_eq!(image_dpi_warning(144.0, Some(144.0), None), None);
+
+    // Below and above
+    let blurry = image_dpi_warning(149.0, Some(150.0), Some(600.0)).unwrap();
+    assert!(
+        blurry.contains("below the minimum of 150 DPI"),
+        "{}",
+        blurry
+    );
+    let bloated = image_dpi_warning(601.0, Some(150.0), Some(600.0)).unwrap();
+    assert!(
+        bloated.contains("above the maximum of 600 DPI"),
+        "{}",
+        bloated
+    );
+
+    // Only one threshold set
+    assert!(image_dpi_warning(100.0, Some(150.0), None).is_some());
+    assert_eq!(image_dpi_warning(1000.0, Some(150.0), None), None);
+    assert!(image_dpi_warning(1000.0, None, Some(600.0)).is_some());
+    assert_eq!(image_dpi_warning(100.0, None, Some(600.0)), None);
+}
+
+#[test]
+fn template_source_excerpt() {
+    let source = "first\n  {{ foo }}\n\t{{ bar }}\n";
+    assert_eq!(
+        source_excerpt(source, 2, 3).unwrap(),
+        "2 |   {{ foo }}\n  |   ^"
+    );
+    assert_eq!(
+        source_excerpt(source, 3, 2).unwrap(),
+        "3 | \t{{ bar }}\n  | \t^"
+    );
+    assert!(source_excerpt(source, 0, 1).is_none());
+    assert!(source_excerpt(source, 4, 1).is_none());
+}
+
+#[test]
+fn template_render_error_location() {
+    let mut hb = Handlebars::new().with_helper("number_format", NumberFormatHelper);
+    hb.register_template_string("tpl", "Line 1\n  {{ number_format }}")
+        .unwrap();
+    let err = hb.render("tpl", &0).map_err(render_error).unwrap_err();
+    let msg = err.to_string();
+    assert!(msg.contains("\"tpl\" at line 2, column 3"), "{}", msg);
+    let chain = format!("{:?}", err);
+    assert!(
+        chain.contains("number_format: No number supplied"),
+        "{}",
+        chain
+    );
+}
+
+#[test]
+fn template_declared_version() {
+    assert_eq!(
+        declared_version("{{~ version_check \"1.2.0\" ~}}\nfoo"),
+        Some(Version::new(1, 2, 0))
+    );
+    assert_eq!(declared_version("{{#if foo}}bar{{/if}}"), None);
+
+    let current = crate::book::version::current();
+    for template in crate::render::DEFAULT_TEMPLATES {
+        assert_eq!(template.version().as_ref(), Some(current));
+    }
+}
+
+#[test]
+fn hb_helper_pluralize() {
+    assert_eq!(PluralRule::for_lang("cs"), PluralRule::OneFewOther);
+    assert_eq!(PluralRule::for_lang("sk-SK"), PluralRule::OneFewOther);
+    assert_eq!(PluralRule::for_lang("en_GB"), PluralRule::OneOther);
+    assert_eq!(PluralRule::for_lang("xx"), PluralRule::OneOther);
+
+    let hb = Handlebars::new().with_helper(
+        "pluralize",
+        PluralizeHelper {
+            rule: PluralRule::OneOther,
+        },
+    );
+    let render = |count: &str| {
+        hb.render_template(
+            &format!("{{{{ pluralize {} \"verse\" \"verses\" }}}}", count),
+            &0,
+        )
+    };
+    assert_eq!(render("1").unwrap(), "verse");
+    assert_eq!(render("0").unwrap(), "verses");
+    assert_eq!(render("2").unwrap(), "verses");
+    assert_eq!(render("-1").unwrap(), "verse");
+    assert_eq!(render(r#""1""#).unwrap(), "verse");
+    assert_eq!(render("1.5").unwrap(), "verses");
+    assert!(render(r#""foo""#).is_err());
+    assert!(hb
+        .render_template("{{ pluralize 1 \"verse\" }}", &0)
+        .is_err());
+
+    let hb = Handlebars::new().with_helper(
+        "pluralize",
+        PluralizeHelper {
+            rule: PluralRule::OneFewOther,
+        },
+    );
+    let render = |count: &str| {
+        hb.render_template(
+            &format!("{{{{ pluralize {} \"verš\" \"verše\" \"veršů\" }}}}", count),
+            &0,
+        )
+    };
+    assert_eq!(render("1").unwrap(), "verš");
+    assert_eq!(render("2").unwrap(), "verše");
+    assert_eq!(render("3").unwrap(), "verše");
+    assert_eq!(render("4").unwrap(), "verše");
+    assert_eq!(render("5").unwrap(), "veršů");
+    assert_eq!(render("0").unwrap(), "veršů");
+    assert_eq!(render("12").unwrap(), "veršů");
+    assert_eq!(render("22").unwrap(), "veršů");
+    assert_eq!(render("2.5").unwrap(), "veršů");
+    assert!(hb
+        .render_template("{{ pluralize 1 \"verse\" \"verses\" }}", &0)
+        .is_err());
+}
+
+#[test]
+fn hb_helper_words_chars() {
+    let hb = Handlebars::new()
+        .with_helper("words", hb_words)
+        .with_helper("chars", hb_chars)
+        .with_helper("eq", hb_eq);
+    let render = |tpl: &str, data: JsonValue| hb.render_template(tpl, &data).unwrap();
+
+    let data = serde_json::json!({
+        "title": "Oh Danny  Boy",
+        "czech": "žluťoučký kůň",
+        "spaces": " \tthe pipes\n",
+        "empty": "",
+        "null": null,
+        "number": 123,
+    });
+
+    assert_eq!(render("{{ words title }}", data.clone()), "3");
+    assert_eq!(render("{{ chars title }}", data.clone()), "13");
+    assert_eq!(render("{{ words czech }}", data.clone()), "2");
+    assert_eq!(render("{{ chars czech }}", data.clone()), "13");
+    assert_eq!(render(r#"{{ chars "héllo" }}"#, data.clone()), "5");
+    assert_eq!(render("{{ words spaces }}", data.clone()), "2");
+    assert_eq!(render("{{ words empty }}", data.clone()), "0");
+    assert_eq!(render("{{ chars empty }}", data.clone()), "0");
+    assert_eq!(render("{{ words null }}", data.clone()), "0");
+    assert_eq!(render("{{ chars null }}", data.clone()), "0");
+    assert_eq!(render("{{ chars number }}", data.clone()), "3");
+
+    // The result is an integer
+    assert_eq!(render("{{ eq (words title) 3 }}", data), "true");
+}
+
+#[test]
+fn hb_helper_pad() {
+    let hb = Handlebars::new()
+        .with_helper("pad_start", hb_pad_start)
+        .with_helper("pad_end", hb_pad_end);
+    let render = |tpl: &str, data: JsonValue| hb.render_template(tpl, &data).unwrap();
+
+    let data = serde_json::json!({
+        "num": 7,
+        "czech": "kůň",
+        "null": null,
+    });
+
+    assert_eq!(render(r#"{{ pad_start "5" 3 "0" }}"#, data.clone()), "005");
+    assert_eq!(render(r#"{{ pad_end "5" 3 "0" }}"#, data.clone()), "500");
+    assert_eq!(
+        render(r#"[{{ pad_start "ab" 4 }}]"#, data.clone()),
+        "[  ab]"
+    );
+    assert_eq!(render(r#"[{{ pad_end "ab" 4 }}]"#, data.clone()), "[ab  ]");
+    assert_eq!(render(r#"{{ pad_start num 3 "0" }}"#, data.clone()), "007");
+    assert_eq!(
+        render(r#"{{ pad_start "x" 6 "ab" }}"#, data.clone()),
+        "ababax"
+    );
+
+    // Lengths are counted in characters
+    assert_eq!(
+        render(r#"{{ pad_end czech 5 "·" }}"#, data.clone()),
+        "kůň··"
+    );
+    assert_eq!(
+        render(r#"{{ pad_start czech 4 "ž" }}"#, data.clone()),
+        "žkůň"
+    );
+
+    // Long enough already
+    assert_eq!(
+        render(r#"{{ pad_start "hello" 3 "0" }}"#, data.clone()),
+        "hello"
+    );
+    assert_eq!(render(r#"{{ pad_end czech 3 "0" }}"#, data.clone()), "kůň");
+    assert_eq!(render(r#"{{ pad_start "abc" 5 "" }}"#, data.clone()), "abc");
+    assert_eq!(render(r#"{{ pad_start null 2 "-" }}"#, data), "--");
+}
+
+#[test]
+fn hb_helper_roman_arabic() {
+    let hb = Handlebars::new()
+        .with_helper("roman", RomanHelper)
+        .with_helper("arabic", ArabicHelper);
+    let render = |tpl: &str| hb.render_template(tpl, &()).map_err(|e| e.to_string());
+
+    for (num, numeral) in [
+        (1, "I"),
+        (3, "III"),
+        (4, "IV"),
+        (9, "IX"),
+        (14, "XIV"),
+        (40, "XL"),
+        (90, "XC"),
+        (400, "CD"),
+        (1994, "MCMXCIV"),
+        (2023, "MMXXIII"),
+        (3999, "MMMCMXCIX"),
+    ] {
+        assert_eq!(
+            render(&format!("{{{{ roman {} }}}}", num)).unwrap(),
+            numeral
+        );
+        assert_eq!(
+            render(&format!("{{{{ arabic \"{}\" }}}}", numeral)).unwrap(),
+            num.to_string()
+        );
+    }
+
+    // Case-insensitive
+    assert_eq!(render(r#"{{ arabic "xiv" }}"#).unwrap(), "14");
+    assert_eq!(render(r#"{{ arabic "MmXxIiI" }}"#).unwrap(), "2023");
+
+    for tpl in [
+        "{{ roman 0 }}",
+        "{{ roman 4000 }}",
+        "{{ roman -1 }}",
+        "{{ roman 2.5 }}",
+        r#"{{ roman "IV" }}"#,
+        "{{ roman }}",
+        r#"{{ arabic "" }}"#,
+        r#"{{ arabic "IIII" }}"#,
+        r#"{{ arabic "VX" }}"#,
+        r#"{{ arabic "MMMM" }}"#,
+        r#"{{ arabic "XIVx" }}"#,
+        r#"{{ arabic "ABC" }}"#,
+        "{{ arabic 14 }}",
+    ] {
+        assert!(render(tpl).is_err(), "{}", tpl);
+    }
+}
+
+#[test]
+fn hb_helper_cat_json() {
+    let hb = Handlebars::new()
+        .with_helper("cat", hb_cat)
+        .with_helper("cat_json", CatJsonHelper);
+    let render = |tpl: &str| {
+        let data = serde_json::json!({
+            "tags": ["folk", "irish", "ballad"],
+            "nested": [["a", "b"], "c", 1, null],
+            "object": { "key": "G" },
+            "empty": [],
+        });
+        hb.render_template(tpl, &data)
+    };
+
+    // cat keeps the placeholders
+    assert_eq!(
+        render(r#"{{ cat "Tags: " tags object 1 null }}"#).unwrap(),
+        "Tags: [array][object]1[null]"
+    );
+
+    assert_eq!(
+        render(r#"{{ cat_json "Tags: " tags sep=", " }}"#).unwrap(),
+        "Tags: folk, irish, ballad"
+    );
+    assert_eq!(render(r#"{{ cat_json tags }}"#).unwrap(), "folkirishballad");
+    assert_eq!(
+        render(r#"{{ cat_json nested sep="-" }}"#).unwrap(),
+        "a-b-c-1-[null]"
+    );
+    assert_eq!(
+        render(r#"{{{ cat_json "key: " object }}}"#).unwrap(),
+        r#"key: {"key":"G"}"#
+    );
+    assert_eq!(render(r#"{{ cat_json "[" empty "]" }}"#).unwrap(), "[]");
+    assert_eq!(render(r#"{{ cat_json 1 true "x" }}"#).unwrap(), "1truex");
+    assert!(render(r#"{{ cat_json tags sep=1 }}"#).is_err());
+}
+
+#[test]
+fn hb_helper_ifeq() {
+    let hb = Handlebars::new().with_helper("ifeq", IfEqHelper);
+    let render = |tpl: &str| {
+        let data = serde_json::json!({
+            "notation": "german",
+            "format": "html",
+            "tags": ["folk", "irish"],
+            "number": 1,
+        });
+        hb.render_template(tpl, &data).unwrap()
+    };
+
+    // Truthy and falsy
+    assert_eq!(render(r#"{{#ifeq notation "german"}}yes{{/ifeq}}"#), "yes");
+    assert_eq!(render(r#"{{#ifeq notation "english"}}yes{{/ifeq}}"#), "");
+    assert_eq!(
+        render(r#"{{#ifeq notation "english"}}yes{{else}}no{{/ifeq}}"#),
+        "no"
+    );
+
+    // Deep JSON equality, types are not coerced
+    assert_eq!(
+        render(r#"{{#ifeq tags (lookup this "tags")}}yes{{else}}no{{/ifeq}}"#),
+        "yes"
+    );
+    assert_eq!(
+        render(r#"{{#ifeq number "1"}}yes{{else}}no{{/ifeq}}"#),
+        "no"
+    );
+
+    // Else chaining
+    let tpl = r#"{{#ifeq format "pdf"}}tex{{else ifeq format "html"}}web{{else}}other{{/ifeq}}"#;
+    assert_eq!(render(tpl), "web");
+    let tpl = r#"{{#ifeq format "pdf"}}tex{{else ifeq format "json"}}data{{else}}other{{/ifeq}}"#;
+    assert_eq!(render(tpl), "other");
+
+    // Missing parameter
+    assert!(hb
+        .render_template(r#"{{#ifeq format}}yes{{/ifeq}}"#, &0)
+        .is_err());
+}
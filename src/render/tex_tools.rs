@@ -43,6 +43,16 @@ impl TexDistro {
     }
 }
 
+/// Tectonic settings, given in the `[tectonic]` section of `bard.toml`.
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct TectonicSettings {
+    /// Directory for downloaded support files, Tectonic's per-user cache if not set.
+    pub cache_dir: Option<PathBuf>,
+    /// Whether Tectonic may only use cached files and never download.
+    #[serde(default)]
+    pub offline: bool,
+}
+
 #[derive(Clone, Debug)]
 pub struct TexConfig {
     distro: TexDistro,
@@ -103,7 +113,7 @@ impl TexConfig {
         Ok(())
     }
 
-    fn render_args(&self, job: &TexRenderJob) -> Vec<OsString> {
+    fn render_args(&self, job: &TexRenderJob, tectonic: &TectonicSettings) -> Vec<OsString> {
         let mut args = match self.distro {
             TexDistro::Xelatex => vec![
                 "-interaction=nonstopmode".to_os_string(),
@@ -135,10 +145,34 @@ impl TexConfig {
             TexDistro::None => unreachable!(),
         };
 
+        match self.distro {
+            TexDistro::Tectonic if tectonic.offline => args.push("--only-cached".to_os_string()),
+            TexDistro::TectonicEmbedded => {
+                if let Some(cache_dir) = tectonic.cache_dir.as_ref() {
+                    args.extend(["--cache-dir".to_os_string(), cache_dir.into()]);
+                }
+                if tectonic.offline {
+                    args.push("--offline".to_os_string());
+                }
+            }
+            _ => {}
+        }
+
         args.extend(["--".to_os_string(), job.tex_file.to_os_string()]);
         args
     }
 
+    /// Environment variables for the TeX program. The external Tectonic binary
+    /// only takes the cache directory from the environment.
+    fn render_envs(&self, tectonic: &TectonicSettings) -> Vec<(&'static str, OsString)> {
+        match (self.distro, tectonic.cache_dir.as_ref()) {
+            (TexDistro::Tectonic, Some(cache_dir)) => {
+                vec![("TECTONIC_CACHE_DIR", cache_dir.into())]
+            }
+            _ => vec![],
+        }
+    }
+
     /// Returns what should be the stderr status prefix when logging lines in scrolled mode,
     /// see `App::subprocess_output()`.
     fn program_status(&self) -> Cow<str> {
@@ -285,6 +319,7 @@ fn run_program(
     app: &App,
     program: impl AsRef<OsStr>,
     args: &[impl AsRef<OsStr>],
+    envs: &[(&str, OsString)],
     cwd: &Path,
     status: &str,
 ) -> Result<()> {
@@ -299,6 +334,7 @@ fn run_program(
 
     let mut child = Command::new(program)
         .args(args)
+        .envs(envs.iter().map(|(k, v)| (*k, v)))
         .current_dir(cwd)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
@@ -391,10 +427,15 @@ impl<'a> TexRenderJob<'a> {
 
 pub struct TexTools {
     config: TexConfig,
+    tectonic: TectonicSettings,
 }
 
 impl TexTools {
-    pub fn initialize(app: &App, from_settings: Option<&TexConfig>) -> Result<()> {
+    pub fn initialize(
+        app: &App,
+        from_settings: Option<&TexConfig>,
+        tectonic: &TectonicSettings,
+    ) -> Result<()> {
         app.status("Locating", "TeX tools...");
 
         // 1. Priority: BARD_TEX env var
@@ -402,7 +443,7 @@ impl TexTools {
             config.probe(app).with_context(|| {
                 format!(
                     "Error using TeX distribution '{}' configured from the BARD_TEX environment variable.", config)})?;
-            return Self::set(config);
+            return Self::set(config, tectonic);
         }
 
         // 2. Config from bard.toml
@@ -413,20 +454,20 @@ impl TexTools {
                     config
                 )
             })?;
-            return Self::set(config);
+            return Self::set(config, tectonic);
         }
 
         // 3. No explicit config
         if cfg!(feature = "tectonic") {
             // We have embedded tectonic...
             let config = TexConfig::with_embedded_tectonic(app);
-            return Self::set(config);
+            return Self::set(config, tectonic);
         } else {
             // try to probe automatically...
             for kind in [TexDistro::Xelatex, TexDistro::Tectonic] {
                 let mut config = TexConfig::with_distro(kind);
                 if config.probe(app).is_ok() {
-                    return Self::set(config);
+                    return Self::set(config, tectonic);
                 }
             }
         }
@@ -448,8 +489,11 @@ impl TexTools {
         Guard(TEX_TOOLS.lock())
     }
 
-    fn set(config: TexConfig) -> Result<()> {
-        let this = Self { config };
+    fn set(config: TexConfig, tectonic: &TectonicSettings) -> Result<()> {
+        let this = Self {
+            config,
+            tectonic: tectonic.clone(),
+        };
         *TEX_TOOLS.lock() = Some(this);
         Ok(())
     }
@@ -463,18 +507,27 @@ impl TexTools {
 
         app.status("Running", "TeX...");
 
-        let args = self.config.render_args(&job);
+        let res = self.run_tex(app, &job);
+        if res.is_err() {
+            // Keep the TeX file around for inspection
+            job.tex_file.set_remove(false);
+        }
+        res.with_context(|| format!("TeX file kept at {:?}", &*job.tex_file))
+    }
+
+    fn run_tex(&self, app: &App, job: &TexRenderJob) -> Result<()> {
+        let args = self.config.render_args(job, &self.tectonic);
+        let envs = self.config.render_envs(&self.tectonic);
         let program = self.config.program.as_ref().unwrap();
         let status = self.config.program_status();
 
-        run_program(app, program, &args, job.cwd(), &status)?;
+        run_program(app, program, &args, &envs, job.cwd(), &status)?;
         for _ in 0..job.reruns {
             job.sort_toc()?;
-            run_program(app, program, &args, job.cwd(), &status)?;
+            run_program(app, program, &args, &envs, job.cwd(), &status)?;
         }
 
-        job.move_pdf()?;
-        Ok(())
+        job.move_pdf()
     }
 }
 
@@ -1,4 +1,6 @@
 //! XML Renderer.
+//!
+//! The output is described by the `bard.xsd` schema, which needs to be updated along with the AST version.
 
 use std::fs::File;
 use std::io;
@@ -11,7 +13,11 @@ use crate::prelude::*;
 use crate::ProgramMeta;
 
 use crate::project::Format;
+use crate::project::JsonStyle;
+use crate::project::LabelNumbering;
 use crate::project::Output;
+use crate::project::Script;
+use crate::project::Split;
 use crate::util::xml_support::*;
 use crate::xml_write;
 
@@ -21,6 +27,7 @@ xml_write!(struct ProgramMeta {
     description,
     homepage,
     authors,
+    build_timestamp,
 } -> |w| {
     w.tag("program")
         .content()?
@@ -29,6 +36,7 @@ xml_write!(struct ProgramMeta {
         .field(description)?
         .field(homepage)?
         .field(authors)?
+        .field_opt(build_timestamp)?
 });
 
 impl XmlWrite for Format {
@@ -40,49 +48,136 @@ impl XmlWrite for Format {
     }
 }
 
+impl XmlWrite for Split {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        match self {
+            Split::Song => writer.write_text("song"),
+        }
+    }
+}
+
+impl XmlWrite for LabelNumbering {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        match self {
+            LabelNumbering::Always => writer.write_text("always"),
+            LabelNumbering::Never => writer.write_text("never"),
+            LabelNumbering::Auto => writer.write_text("auto"),
+        }
+    }
+}
+
+impl XmlWrite for JsonStyle {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        match self {
+            JsonStyle::Pretty => writer.write_text("pretty"),
+            JsonStyle::Compact => writer.write_text("compact"),
+            JsonStyle::Ndjson => writer.write_text("ndjson"),
+        }
+    }
+}
+
+impl XmlWrite for Script {
+    fn write<W>(&self, mut writer: &mut Writer<W>) -> quick_xml::Result<()>
+    where
+        W: io::Write,
+    {
+        writer.write_text(&self.name)
+    }
+}
+
 xml_write!(struct Output {
     file,
     template,
+    name,
+    enabled,
+    only_on,
+    disabled,
+    song,
     format,
     toc_sort,
     toc_sort_key,
     sans_font,
     font_size,
     dpi,
+    image_dpi_min,
+    image_dpi_max,
     tex_runs,
+    keep_intermediate,
     script,
+    expand_chorus_refs,
+    transpose,
+    alt_chords_primary,
+    label_numbering,
+    setlist,
+    setlist_strict,
+    split,
+    template_strict,
+    json_style,
+    assets,
     book_overrides,
 } -> |w| {
     let _ = file;
     let _ = template;
+    let _ = only_on;
+    let _ = disabled;
+    let _ = song;
+    let _ = setlist;
+    let _ = assets;
     let _ = book_overrides;
     w.tag("output")
         .content()?
+        .field_opt(name)?
+        .field(enabled)?
         .field_opt(format)?
         .field(sans_font)?
         .field(font_size)?
         .field(toc_sort)?
         .field(toc_sort_key)?
         .field_opt(dpi)?
+        .field_opt(image_dpi_min)?
+        .field_opt(image_dpi_max)?
         .field(tex_runs)?
+        .field(keep_intermediate)?
         .field_opt(script)?
+        .field(expand_chorus_refs)?
+        .field(transpose)?
+        .field(alt_chords_primary)?
+        .field_opt(label_numbering)?
+        .field(setlist_strict)?
+        .field_opt(split)?
+        .field(template_strict)?
+        .field(json_style)?
 });
 
 xml_write!(struct RenderContext<'a> {
     book,
     songs,
     songs_sorted,
+    toc,
     notation,
     output,
     program,
+    schema_version,
 } -> |w| {
     w.tag("songbook")
         .attr(notation)
+        .attr(("version", schema_version.unwrap()))
         .content()?
         .comment("The [book] section in bard.toml")?
         .field(book)?
         .comment("References to <song> elements in alphabetically-sorted order")?
         .value_wrap("songs-sorted", songs_sorted)?
+        .comment("Table of contents entries in the order of <song> elements")?
+        .value_wrap("toc", &toc.unwrap()[..])?
         .comment("Fields in the [[output]] section in bard.toml")?
         .value_wrap("output", output)?
         .comment("Software metadata")?
@@ -1,4 +1,4 @@
-use std::iter;
+use std::{fs, iter};
 
 use tectonic::config::PersistentConfig;
 use tectonic::driver;
@@ -34,6 +34,12 @@ pub struct Tectonic {
     /// Output directory path
     #[arg(short)]
     out_dir: Option<PathBuf>,
+    /// Cache directory for the bundle and formats, Tectonic's per-user cache by default
+    #[arg(long)]
+    cache_dir: Option<PathBuf>,
+    /// Only use cached files, never download
+    #[arg(long)]
+    offline: bool,
 
     /// Input TeX file
     input: PathBuf,
@@ -56,14 +62,34 @@ impl Tectonic {
         let config = PersistentConfig::open(false)
             .anyhow()
             .context("Failed to open default bundle")?;
-        let bundle = config
-            .default_bundle(false, &mut *status)
-            .anyhow()
-            .context("Failed to load the default resource bundle")?;
-        let format_cache_path = config
-            .format_cache_path()
-            .anyhow()
-            .context("Failed to set up the format cache")?;
+        let bundle = match self.cache_dir.as_ref() {
+            Some(cache_dir) => {
+                fs::create_dir_all(cache_dir).with_context(|| {
+                    format!("Could not create Tectonic cache directory {:?}", cache_dir)
+                })?;
+                config.make_cached_url_provider(
+                    config.default_bundle_loc(),
+                    self.offline,
+                    Some(cache_dir),
+                    &mut *status,
+                )
+            }
+            None => config.default_bundle(self.offline, &mut *status),
+        }
+        .anyhow()
+        .with_context(|| self.offline_hint("Failed to load the default resource bundle"))?;
+        let format_cache_path = match self.cache_dir.as_ref() {
+            Some(cache_dir) => {
+                let path = cache_dir.join("formats");
+                fs::create_dir_all(&path)
+                    .with_context(|| format!("Could not create directory {:?}", path))?;
+                path
+            }
+            None => config
+                .format_cache_path()
+                .anyhow()
+                .context("Failed to set up the format cache")?,
+        };
 
         let file_name = self
             .input
@@ -106,6 +132,24 @@ impl Tectonic {
             }
         }
 
-        res.anyhow().context("The LaTeX engine failed")
+        res.anyhow()
+            .with_context(|| self.offline_hint("The LaTeX engine failed"))
+    }
+
+    /// Explains failures to find files in offline mode, where Tectonic
+    /// reports the files it would've downloaded as missing.
+    fn offline_hint(&self, msg: &str) -> String {
+        if !self.offline {
+            return msg.to_string();
+        }
+
+        let cache = match self.cache_dir.as_ref() {
+            Some(dir) => format!("{:?}", dir),
+            None => "the default Tectonic cache".to_string(),
+        };
+        format!(
+            "{}\nTectonic is in offline mode, the files reported missing above are not in {}. Build once with network access to download them.",
+            msg, cache
+        )
     }
 }
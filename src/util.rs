@@ -5,17 +5,22 @@ use std::sync::Arc;
 use std::{collections::HashMap, ffi::OsString};
 use std::{fmt, fs};
 
+use lexical_sort::iter::iterate_lexical;
 use lexical_sort::{lexical_cmp, PathSort};
 use parking_lot::RwLock;
 
 use crate::prelude::*;
 
+mod datetime;
+mod diff;
 mod path;
 mod process;
 pub mod xml_support;
 
+pub use datetime::{build_timestamp, format_timestamp, SOURCE_DATE_EPOCH};
+pub use diff::unified_diff;
 pub use path::{PathBufExt, PathExt, TempPath};
-pub use process::{ExitStatusExt, ProcessLines};
+pub use process::{kill_tree, new_process_group, ExitStatusExt, ProcessLines};
 
 #[cfg(unix)]
 pub const LINE_END: &str = "\n";
@@ -115,17 +120,41 @@ where
     slice.path_sort(lexical_cmp);
 }
 
+/// Make a slug out of `text` for use in identifiers:
+/// Text is lowercased and transliterated to ASCII, runs of other characters are replaced by a dash.
+pub fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+    let mut dash = false;
+    for c in iterate_lexical(text) {
+        if c.is_ascii_alphanumeric() {
+            if dash && !slug.is_empty() {
+                slug.push('-');
+            }
+            slug.push(c.to_ascii_lowercase());
+            dash = false;
+        } else {
+            dash = true;
+        }
+    }
+    slug
+}
+
 // fs utils
 
-fn read_dir_all_inner(res: &mut Vec<PathBuf>, path: &Path) -> Result<()> {
+fn read_dir_all_inner(
+    files: &mut Vec<PathBuf>,
+    dirs: &mut Vec<PathBuf>,
+    path: &Path,
+) -> Result<()> {
     for entry in fs::read_dir(path)? {
         let entry = entry?;
         let path = entry.path();
         if entry.file_type()?.is_dir() {
             // Recurse
-            read_dir_all_inner(res, &path)?;
+            read_dir_all_inner(files, dirs, &path)?;
+            dirs.push(path);
         } else {
-            res.push(path);
+            files.push(path);
         }
     }
 
@@ -133,9 +162,15 @@ fn read_dir_all_inner(res: &mut Vec<PathBuf>, path: &Path) -> Result<()> {
 }
 
 pub fn read_dir_all<P: AsRef<Path>>(path: P) -> Result<Vec<PathBuf>> {
-    let mut res = vec![];
-    read_dir_all_inner(&mut res, path.as_ref())?;
-    Ok(res)
+    read_dir_all_with_dirs(path).map(|(files, _)| files)
+}
+
+/// Like `read_dir_all()`, but also returns all the subdirectories.
+pub fn read_dir_all_with_dirs<P: AsRef<Path>>(path: P) -> Result<(Vec<PathBuf>, Vec<PathBuf>)> {
+    let mut files = vec![];
+    let mut dirs = vec![];
+    read_dir_all_inner(&mut files, &mut dirs, path.as_ref())?;
+    Ok((files, dirs))
 }
 
 /// A very simple cache.
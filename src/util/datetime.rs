@@ -0,0 +1,150 @@
+//! Minimal UTC date formatting for the `now` template helper.
+
+use std::env;
+use std::fmt::Write as _;
+use std::time::SystemTime;
+
+use crate::prelude::*;
+
+/// Environment variable overriding the build time as a Unix timestamp,
+/// see <https://reproducible-builds.org/specs/source-date-epoch/>.
+pub const SOURCE_DATE_EPOCH: &str = "SOURCE_DATE_EPOCH";
+
+/// Build time as a Unix timestamp, either from `SOURCE_DATE_EPOCH` or the current time.
+pub fn build_timestamp() -> Result<i64> {
+    match env::var(SOURCE_DATE_EPOCH) {
+        Ok(value) => value
+            .trim()
+            .parse()
+            .with_context(|| format!("Invalid {} value: {:?}", SOURCE_DATE_EPOCH, value)),
+        Err(_) => {
+            let now = SystemTime::now()
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            Ok(now)
+        }
+    }
+}
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+    "Sunday",
+];
+
+/// Convert days since 1970-01-01 to a (year, month, day) civil date.
+/// Based on Howard Hinnant's `civil_from_days` algorithm.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + (month <= 2) as i64;
+    (year, month, day)
+}
+
+fn is_leap(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn day_of_year(year: i64, month: u32, day: u32) -> u32 {
+    const CUMULATIVE: [u32; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    let leap = (month > 2 && is_leap(year)) as u32;
+    CUMULATIVE[month as usize - 1] + day + leap
+}
+
+/// Format a Unix `timestamp` in UTC according to a strftime-like `format`.
+///
+/// Supported conversions are `%Y`, `%y`, `%m`, `%d`, `%e`, `%j`, `%H`, `%M`, `%S`,
+/// `%B`, `%b`, `%A`, `%a`, `%F` (`%Y-%m-%d`), `%T` (`%H:%M:%S`), `%s` and `%%`.
+pub fn format_timestamp(timestamp: i64, format: &str) -> Result<String, String> {
+    let days = timestamp.div_euclid(86400);
+    let secs = timestamp.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, min, sec) = (secs / 3600, secs / 60 % 60, secs % 60);
+    // 1970-01-01 was a Thursday
+    let weekday = (days + 3).rem_euclid(7) as usize;
+
+    let mut res = String::with_capacity(format.len() * 2);
+    let mut chars = format.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            res.push(c);
+            continue;
+        }
+
+        let _ = match chars.next() {
+            Some('Y') => write!(res, "{}", year),
+            Some('y') => write!(res, "{:02}", year.rem_euclid(100)),
+            Some('m') => write!(res, "{:02}", month),
+            Some('d') => write!(res, "{:02}", day),
+            Some('e') => write!(res, "{:2}", day),
+            Some('j') => write!(res, "{:03}", day_of_year(year, month, day)),
+            Some('H') => write!(res, "{:02}", hour),
+            Some('M') => write!(res, "{:02}", min),
+            Some('S') => write!(res, "{:02}", sec),
+            Some('B') => write!(res, "{}", MONTHS[month as usize - 1]),
+            Some('b') => write!(res, "{}", &MONTHS[month as usize - 1][..3]),
+            Some('A') => write!(res, "{}", WEEKDAYS[weekday]),
+            Some('a') => write!(res, "{}", &WEEKDAYS[weekday][..3]),
+            Some('F') => write!(res, "{}-{:02}-{:02}", year, month, day),
+            Some('T') => write!(res, "{:02}:{:02}:{:02}", hour, min, sec),
+            Some('s') => write!(res, "{}", timestamp),
+            Some('%') => write!(res, "%"),
+            Some(c) => return Err(format!("Unsupported date format conversion: %{}", c)),
+            None => return Err("Date format ends with a lone %".to_string()),
+        };
+    }
+
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn format_timestamp_conversions() {
+        assert_eq!(format_timestamp(0, "%F %T").unwrap(), "1970-01-01 00:00:00");
+        assert_eq!(
+            format_timestamp(1709251199, "%a %A %b %B %e %j %y %s").unwrap(),
+            "Thu Thursday Feb February 29 060 24 1709251199"
+        );
+        assert_eq!(
+            format_timestamp(951782400, "%d. %m. %Y %H:%M:%S 100%%").unwrap(),
+            "29. 02. 2000 00:00:00 100%"
+        );
+        assert_eq!(
+            format_timestamp(-86400, "%Y-%m-%d %A").unwrap(),
+            "1969-12-31 Wednesday"
+        );
+        assert_eq!(format_timestamp(1735689599, "%j").unwrap(), "366");
+
+        format_timestamp(0, "%Q").unwrap_err();
+        format_timestamp(0, "100%").unwrap_err();
+    }
+}
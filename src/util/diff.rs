@@ -0,0 +1,140 @@
+//! Line-based unified diff, used to compare user templates with the built-in ones.
+
+use std::fmt::Write as _;
+
+/// Number of unchanged lines shown around each change.
+const CONTEXT: usize = 3;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/// Compute the line edit script from `old` to `new` via the longest common subsequence.
+fn edits<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(Op, &'a str)> {
+    let (n, m) = (old.len(), new.len());
+    let mut lcs = vec![0u32; (n + 1) * (m + 1)];
+    let idx = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[idx(i, j)] = if old[i] == new[j] {
+                lcs[idx(i + 1, j + 1)] + 1
+            } else {
+                lcs[idx(i + 1, j)].max(lcs[idx(i, j + 1)])
+            };
+        }
+    }
+
+    let mut res = Vec::with_capacity(n.max(m));
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            res.push((Op::Equal, old[i]));
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[idx(i + 1, j)] >= lcs[idx(i, j + 1)]) {
+            res.push((Op::Delete, old[i]));
+            i += 1;
+        } else {
+            res.push((Op::Insert, new[j]));
+            j += 1;
+        }
+    }
+    res
+}
+
+fn hunk_range(before: usize, len: usize) -> String {
+    match len {
+        0 => format!("{},0", before),
+        1 => format!("{}", before + 1),
+        _ => format!("{},{}", before + 1, len),
+    }
+}
+
+/// Render a unified diff of `old` and `new`, labeled with `old_name` and `new_name`.
+/// Returns an empty string if the texts have the same lines.
+pub fn unified_diff(old: &str, new: &str, old_name: &str, new_name: &str) -> String {
+    let old: Vec<_> = old.lines().collect();
+    let new: Vec<_> = new.lines().collect();
+    let edits = edits(&old, &new);
+
+    let changes: Vec<_> = edits
+        .iter()
+        .enumerate()
+        .filter(|(_, (op, _))| *op != Op::Equal)
+        .map(|(i, _)| i)
+        .collect();
+    if changes.is_empty() {
+        return String::new();
+    }
+
+    // Group changes that are close enough for their context to overlap
+    let mut hunks: Vec<(usize, usize)> = vec![];
+    for &i in &changes {
+        match hunks.last_mut() {
+            Some((_, end)) if i - *end <= 2 * CONTEXT + 1 => *end = i,
+            _ => hunks.push((i, i)),
+        }
+    }
+
+    let mut res = format!("--- {}\n+++ {}\n", old_name, new_name);
+    for (first, last) in hunks {
+        let start = first.saturating_sub(CONTEXT);
+        let end = (last + CONTEXT + 1).min(edits.len());
+
+        let old_before = edits[..start]
+            .iter()
+            .filter(|(op, _)| *op != Op::Insert)
+            .count();
+        let new_before = edits[..start]
+            .iter()
+            .filter(|(op, _)| *op != Op::Delete)
+            .count();
+        let hunk = &edits[start..end];
+        let old_len = hunk.iter().filter(|(op, _)| *op != Op::Insert).count();
+        let new_len = hunk.iter().filter(|(op, _)| *op != Op::Delete).count();
+
+        let _ = writeln!(
+            res,
+            "@@ -{} +{} @@",
+            hunk_range(old_before, old_len),
+            hunk_range(new_before, new_len)
+        );
+        for (op, line) in hunk {
+            let prefix = match op {
+                Op::Equal => ' ',
+                Op::Delete => '-',
+                Op::Insert => '+',
+            };
+            let _ = writeln!(res, "{}{}", prefix, line);
+        }
+    }
+
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unified_diff_hunks() {
+        assert_eq!(unified_diff("a\nb\n", "a\nb\n", "old", "new"), "");
+
+        let old = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n";
+        let new = "1\n2\nthree\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n";
+        assert_eq!(
+            unified_diff(old, new, "old", "new"),
+            "--- old\n+++ new\n\
+            @@ -1,6 +1,6 @@\n 1\n 2\n-3\n+three\n 4\n 5\n 6\n\
+            @@ -13,3 +13,4 @@\n 13\n 14\n 15\n+16\n"
+        );
+
+        assert_eq!(
+            unified_diff("", "x\n", "old", "new"),
+            "--- old\n+++ new\n@@ -0,0 +1 @@\n+x\n"
+        );
+    }
+}
@@ -1,10 +1,10 @@
 use std::{
     io, mem,
-    process::{ChildStderr, ChildStdout, ExitStatus},
+    process::{Child, ChildStderr, ChildStdout, Command, ExitStatus},
 };
 
 #[cfg(unix)]
-use std::os::unix::process::ExitStatusExt as _;
+use std::os::unix::process::{CommandExt as _, ExitStatusExt as _};
 
 use crate::{app::InterruptFlag, prelude::*};
 
@@ -33,6 +33,44 @@ impl ExitStatusExt for ExitStatus {
     }
 }
 
+/// Make the process spawned by `command` the leader of a new process group (on Unix)
+/// so that it can be killed along with its children with `kill_tree()`.
+pub fn new_process_group(command: &mut Command) {
+    #[cfg(unix)]
+    command.process_group(0);
+    #[cfg(not(unix))]
+    let _ = command;
+}
+
+/// Kill the `child` process and all of its children.
+/// On Unix, the child needs to have been spawned with `new_process_group()`.
+pub fn kill_tree(child: &mut Child) -> Result<()> {
+    #[cfg(unix)]
+    {
+        use nix::sys::signal::{killpg, Signal};
+        use nix::unistd::Pid;
+
+        killpg(Pid::from_raw(child.id() as _), Signal::SIGKILL)
+            .context("Could not kill process group")?;
+    }
+    #[cfg(not(unix))]
+    {
+        let status = Command::new("taskkill")
+            .args(["/T", "/F", "/PID"])
+            .arg(child.id().to_string())
+            .status()
+            .context("Could not run taskkill")?;
+        if !status.success() {
+            // Fall back to killing just the child
+            child.kill()?;
+        }
+    }
+
+    // Reap the child
+    child.wait()?;
+    Ok(())
+}
+
 mod process_generic;
 mod process_nix;
 
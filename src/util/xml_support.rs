@@ -50,16 +50,13 @@ where
 
 impl<'a, T> XmlWrite for Cow<'a, T>
 where
-    T: XmlWrite + Clone + ?Sized,
+    T: XmlWrite + ToOwned + ?Sized,
 {
     fn write<W>(&self, writer: &mut Writer<W>) -> XmlResult<()>
     where
         W: io::Write,
     {
-        match self {
-            Cow::Borrowed(b) => b.write(writer),
-            Cow::Owned(o) => o.write(writer),
-        }
+        T::write(self, writer)
     }
 }
 
@@ -1,12 +1,17 @@
-use std::fs::File;
+use std::env;
+use std::fs::{self, File};
 use std::io::{self, BufRead, BufReader, BufWriter, Write};
 use std::str::FromStr;
 
 use regex::Regex;
 
 use crate::app::App;
+use crate::book::version;
 use crate::prelude::*;
-use crate::util::sort_lexical_by;
+use crate::project::Project;
+use crate::render::template::declared_version;
+use crate::render::{self, json};
+use crate::util::{sort_lexical_by, unified_diff};
 
 #[derive(clap::Parser)]
 pub enum UtilCmd {
@@ -17,6 +22,19 @@ pub enum UtilCmd {
         /// The file whose lines to sort, in-place
         file: String,
     },
+    /// Prints the JSON Schema of the JSON output
+    JsonSchema {
+        /// Write the schema to this file instead of the standard output
+        file: Option<String>,
+    },
+    /// Shows how an output's template differs from the current built-in one
+    TemplateDiff {
+        /// Name of the output whose template to compare
+        output: String,
+        /// Replace the template with the built-in one, keeping a backup of the original
+        #[arg(long)]
+        update: bool,
+    },
 }
 
 impl UtilCmd {
@@ -30,8 +48,118 @@ impl UtilCmd {
                 }
                 Ok(())
             }
+            JsonSchema { file } => json_schema(file),
+            TemplateDiff { output, update } => {
+                let cwd = env::current_dir().context("Could not read current directory")?;
+                let diff = template_diff(app, cwd, &output, update)?;
+                print!("{}", diff);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn json_schema(file: Option<String>) -> Result<()> {
+    match file {
+        Some(path) => fs::write(&path, json::SCHEMA)
+            .with_context(|| format!("Could not write file {:?}", path)),
+        None => {
+            print!("{}", json::SCHEMA);
+            Ok(())
+        }
+    }
+}
+
+/// Compares the template of the output named `output_name` with the current built-in template,
+/// lists data format changes since the version the template declares, and returns a unified diff.
+/// With `update`, the template is backed up and replaced with the built-in one.
+pub fn template_diff(
+    app: &App,
+    cwd: impl AsRef<Path>,
+    output_name: &str,
+    update: bool,
+) -> Result<String> {
+    let project = Project::new(app, cwd.as_ref())?;
+    let output = project
+        .settings
+        .output
+        .iter()
+        .find(|output| output.is_selected_by(output_name))
+        .ok_or_else(|| anyhow!("No output named '{}' in the project", output_name))?;
+    let default = render::default_template(output.format())
+        .ok_or_else(|| anyhow!("Output {} doesn't use a template", output.label()))?;
+    let path = output.template_path().ok_or_else(|| {
+        anyhow!(
+            "Output {} uses the built-in template, there is nothing to compare",
+            output.label()
+        )
+    })?;
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => bail!(
+            "Template file {:?} doesn't exist yet, it is created with the built-in template on the next build",
+            path
+        ),
+        Err(err) => {
+            return Err(err).with_context(|| format!("Could not read template file {:?}", path))
+        }
+    };
+
+    let tpl_version = declared_version(&content).unwrap_or(version::ASSUMED_FIRST_VERSION);
+    let current = version::current();
+    app.status(
+        "Comparing",
+        format!(
+            "template {:?} (version {}) with the built-in {} (version {})",
+            path, tpl_version, default.filename, current
+        ),
+    );
+    let mut changes = version::changes_since(&tpl_version).peekable();
+    if changes.peek().is_some() {
+        app.indent(format!(
+            "Data format changes since version {}:",
+            tpl_version
+        ));
+        changes.for_each(|ver| app.indent(ver));
+    }
+
+    let old_name = path.to_string_lossy();
+    let new_name = format!("{} (built-in)", default.filename);
+    let diff = unified_diff(&content, default.content, &old_name, &new_name);
+    if diff.is_empty() {
+        app.indent("The template is the same as the built-in one.");
+        return Ok(diff);
+    }
+
+    if update {
+        let backup = backup_path(path);
+        fs::copy(path, &backup)
+            .with_context(|| format!("Could not back up template file {:?}", path))?;
+        fs::write(path, default.content)
+            .with_context(|| format!("Could not write template file {:?}", path))?;
+        app.status(
+            "Updated",
+            format!("template {:?}, the original is kept at {:?}", path, backup),
+        );
+    }
+
+    Ok(diff)
+}
+
+/// A path for a backup of `path` which doesn't exist yet: `<path>.bak`, `<path>.bak2`, ...
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(".bak");
+    let mut res = PathBuf::from(&backup);
+    for i in 2.. {
+        if !res.exists() {
+            break;
         }
+        let mut numbered = backup.clone();
+        numbered.push(i.to_string());
+        res = numbered.into();
     }
+    res
 }
 
 #[derive(Debug)]
@@ -0,0 +1,27 @@
+mod util_ng;
+pub use util_ng::*;
+
+use bard::util::SOURCE_DATE_EPOCH;
+
+#[test]
+fn project_build_date_override() {
+    // This is the only test in this binary, so the env var doesn't affect other tests
+    std::env::set_var(SOURCE_DATE_EPOCH, "1709251199");
+
+    let build = TestProject::new("build-date")
+        .output("songbook.html")
+        .template(
+            "songbook.html",
+            "songbook.hbs",
+            "{{ now }}|{{ now \"%d. %m. %Y %T\" }}|{{ program.build_timestamp }}",
+        )
+        .output("songbook.json")
+        .build()
+        .unwrap();
+
+    let html = build.read_output(".html");
+    assert_eq!(html, "2024-02-29|29. 02. 2024 23:59:59|1709251199");
+
+    let json: serde_json::Value = serde_json::from_str(&build.read_output(".json")).unwrap();
+    assert_eq!(json["program"]["build_timestamp"], 1709251199);
+}
@@ -0,0 +1,72 @@
+mod util_ng;
+pub use util_ng::*;
+
+fn prepare_project(name: &str) -> TestProject {
+    TestProject::new(name)
+        .song("outside.md", "# Outside\n\n1. Outside lyrics.\n")
+        .song("folk-1.md", "# Folk One\n\n1. Folk lyrics.\n")
+        .song("folk-2.md", "# Folk Two\n\n1. Folk lyrics.\n")
+        .song("xmas.md", "# Jingle\n\n1. Christmas lyrics.\n")
+        .settings(|toml| {
+            toml.set("songs", vec!["outside.md"]);
+            toml.set(
+                "chapter",
+                vec![
+                    toml! {
+                        name = "Folk"
+                        songs = "folk-*.md"
+                    },
+                    toml! {
+                        name = "Christmas"
+                        songs = ["xmas.md"]
+                    },
+                ],
+            );
+        })
+}
+
+#[test]
+fn project_chapters_json() {
+    let build = prepare_project("chapters-json")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&build.read_output(".json")).unwrap();
+    let songs = json["songs"].as_array().unwrap();
+    let chapters: Vec<_> = songs.iter().map(|s| s["chapter"].as_str()).collect();
+    assert_eq!(
+        chapters,
+        [None, Some("Folk"), Some("Folk"), Some("Christmas")]
+    );
+
+    let chapters = json["book"]["chapters"].as_array().unwrap();
+    assert_eq!(chapters.len(), 3);
+    assert!(chapters[0].get("name").is_none());
+    assert_eq!(chapters[1]["name"], "Folk");
+    assert_eq!(chapters[1]["songs"][0]["title"], "Folk One");
+    assert_eq!(chapters[1]["songs"][1]["idx"], 2);
+    assert_eq!(chapters[2]["name"], "Christmas");
+    assert_eq!(chapters[2]["songs"][0]["idx"], 3);
+}
+
+#[test]
+fn project_chapters_html() {
+    let build = prepare_project("chapters-html")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let html = build.read_output(".html");
+    let folk = html.find(r#"<h1 class="chapter pad">Folk</h1>"#).unwrap();
+    let xmas = html
+        .find(r#"<h1 class="chapter pad">Christmas</h1>"#)
+        .unwrap();
+    let outside = html.find("<h2>Outside</h2>").unwrap();
+    let folk_two = html.find("<h2>Folk Two</h2>").unwrap();
+    let jingle = html.find("<h2>Jingle</h2>").unwrap();
+    assert!(outside < folk && folk < folk_two && folk_two < xmas && xmas < jingle);
+    assert!(html.contains(r#"<section id="jingle" class="song pad">"#));
+}
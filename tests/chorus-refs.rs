@@ -0,0 +1,83 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[rustfmt::skip]
+fn prepare_project_with(name: &str, song: &str, expand: bool) -> TestProject {
+    TestProject::new(name)
+        .song("song.md", song)
+        .output_toml(toml! {
+            file = "songbook.json"
+            expand_chorus_refs = expand
+        })
+}
+
+fn prepare_project(name: &str, song: &str) -> TestProject {
+    prepare_project_with(name, song, true)
+}
+
+#[test]
+fn project_expand_chorus_refs() {
+    let build = prepare_project(
+        "expand-chorus-refs",
+        indoc! {"
+        # Song
+
+        > Chorus lyrics.
+
+        1. Verse lyrics. !>
+        More verse lyrics.
+        "},
+    )
+    .build()
+    .unwrap();
+
+    let json = build.read_output(".json");
+    assert!(!json.contains("i-chorus-ref"));
+    assert_eq!(json.matches("Chorus lyrics.").count(), 2);
+    assert!(json.contains("More verse lyrics."));
+}
+
+#[test]
+fn project_collapsed_chorus_refs() {
+    let build = prepare_project_with(
+        "collapsed-chorus-refs",
+        indoc! {"
+        # Song
+
+        > Chorus lyrics.
+
+        1. Verse lyrics. !>
+        "},
+        false,
+    )
+    .build()
+    .unwrap();
+
+    let json = build.read_output(".json");
+    assert!(json.contains("i-chorus-ref"));
+    assert_eq!(json.matches("Chorus lyrics.").count(), 1);
+}
+
+#[test]
+fn project_expand_chorus_refs_missing() {
+    let build = prepare_project(
+        "expand-chorus-refs-missing",
+        indoc! {"
+        # Song
+
+        > Chorus 1.
+
+        >> Chorus 2.
+
+        1. Verse lyrics. !>>>
+        "},
+    )
+    .build()
+    .unwrap();
+
+    build.assert_warning("Reference to chorus 3, but there's no such chorus in the song.");
+    let json = build.read_output(".json");
+    assert!(json.contains("i-chorus-ref"));
+    assert_eq!(json.matches("Chorus 1.").count(), 1);
+    assert!(json.contains("Verse lyrics."));
+}
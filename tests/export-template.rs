@@ -0,0 +1,49 @@
+mod util;
+use std::fs;
+
+use bard::render::{hovorka, html, pdf};
+pub use util::*;
+
+#[test]
+fn export_template() {
+    let work_dir = work_dir("export-template", true).unwrap();
+    let app = Builder::app(false);
+
+    for (format, default) in [
+        ("pdf", &pdf::DEFAULT_TEMPLATE),
+        ("html", &html::DEFAULT_TEMPLATE),
+        ("hovorka", &hovorka::DEFAULT_TEMPLATE),
+    ] {
+        let dest = work_dir.join("templates").join(default.filename);
+        bard::bard_export_template_at(&app, format, &dest).unwrap();
+        let content = fs::read_to_string(&dest).unwrap();
+        assert_eq!(content, default.content);
+    }
+
+    assert_eq!(
+        fs::read_to_string(work_dir.join("templates/pdf.hbs")).unwrap(),
+        include_str!("../src/render/templates/pdf.hbs")
+    );
+}
+
+#[test]
+fn export_template_doesnt_overwrite() {
+    let work_dir = work_dir("export-template-overwrite", true).unwrap();
+    let dest = work_dir.join("custom.hbs");
+    fs::create_dir_all(&work_dir).unwrap();
+    fs::write(&dest, "custom\n").unwrap();
+
+    let app = Builder::app(false);
+    bard::bard_export_template_at(&app, "html", &dest).unwrap_err();
+    assert_eq!(fs::read_to_string(&dest).unwrap(), "custom\n");
+}
+
+#[test]
+fn export_template_unknown_format() {
+    let work_dir = work_dir("export-template-unknown", true).unwrap();
+    let dest = work_dir.join("json.hbs");
+
+    let app = Builder::app(false);
+    bard::bard_export_template_at(&app, "json", &dest).unwrap_err();
+    assert!(!dest.exists());
+}
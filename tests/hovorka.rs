@@ -0,0 +1,71 @@
+use quick_xml::events::Event;
+use quick_xml::Reader;
+
+mod util_ng;
+pub use util_ng::*;
+
+/// Parses `xml`, panicking if it isn't well-formed, returns all text and attribute values.
+fn xml_values(xml: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    let mut values = vec![];
+    loop {
+        match reader.read_event().unwrap() {
+            Event::Start(e) | Event::Empty(e) => {
+                for attr in e.attributes() {
+                    values.push(attr.unwrap().unescape_value().unwrap().into_owned());
+                }
+            }
+            Event::Text(text) => values.push(text.unescape().unwrap().into_owned()),
+            Event::Eof => break,
+            _ => {}
+        }
+    }
+    values
+}
+
+const SONG: &str =
+    "# Rock & Roll \\<live>\n\n!author: Tom & Jerry\n\n1. `C`Rock & roll \\<all> night ]]> long.\n";
+
+#[test]
+fn project_hovorka_xml_escape() {
+    let build = TestProject::new("hovorka-xml-escape")
+        .song("song.md", SONG)
+        .output_toml(toml! {
+            file = "songbook.xml"
+            format = "hovorka"
+        })
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let xml = build.read_output(".xml");
+    let values = xml_values(&xml);
+    assert!(values.iter().any(|v| v == "Rock & Roll <live>"), "{}", xml);
+    assert!(values.iter().any(|v| v == "Tom & Jerry"), "{}", xml);
+    assert!(
+        xml.contains("[C]Rock &amp; roll &lt;all&gt; night ]]&gt; long."),
+        "{}",
+        xml
+    );
+}
+
+#[test]
+fn project_xml_escape() {
+    let build = TestProject::new("xml-escape")
+        .song("song.md", SONG)
+        .output("songbook.xml")
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let xml = build.read_output(".xml");
+    let values = xml_values(&xml);
+    assert!(values.iter().any(|v| v == "Rock & Roll <live>"), "{}", xml);
+    assert!(
+        values
+            .concat()
+            .contains("Rock & roll <all> night ]]> long."),
+        "{}",
+        xml
+    );
+}
@@ -1,4 +1,7 @@
+use std::fs;
+
 use bard::{
+    book::{Block, Inline},
     parser::{html::RESERVED_TAGS, DiagKind},
     render::{html, pdf},
 };
@@ -55,6 +58,61 @@ fn project_html() {
     tex.find(r"{\color{red}Yippie}").unwrap();
 }
 
+#[test]
+fn project_html_alt_chords() {
+    let build = TestProject::new("html-alt-chords")
+        .output("songbook.html")
+        .song(
+            "song.md",
+            "# Song\n\n!!czech\n\n1. `Bm`Oh `Bb`Danny\n\n> `F#`Chorus.\n",
+        )
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let song = &build.unwrap().songs()[0];
+    let verse = song
+        .blocks
+        .iter()
+        .find_map(|block| match block {
+            Block::Verse(verse) => Some(verse),
+            _ => None,
+        })
+        .unwrap();
+    match &verse.paragraphs[0][0] {
+        Inline::Chord(chord) => {
+            assert_eq!(&*chord.chord, "Bm");
+            assert_eq!(chord.alt_chord.as_deref(), Some("Hm"));
+        }
+        other => panic!("Unexpected inline: {:?}", other),
+    }
+
+    let html = build.read_output(".html");
+    let chord_row = |chord: &str| {
+        format!(
+            r#"<tr class="chord chord-backticks-1"><td>{}</td></tr>"#,
+            chord
+        )
+    };
+    let alt_row = |chord: &str| {
+        format!(
+            r#"<tr class="chord chord-backticks-1 chord-alt ws-pre"><td>{}</td></tr>"#,
+            chord
+        )
+    };
+    for (chord, alt_chord) in [("Bm", "Hm"), ("Bb", "B"), ("F#", "F#")] {
+        let row = chord_row(chord);
+        let pos = html.find(&row).unwrap() + row.len();
+        assert!(
+            html[pos..].trim_start().starts_with(&alt_row(alt_chord)),
+            "No alt chord {} after {} in:\n{}",
+            alt_chord,
+            chord,
+            html
+        );
+    }
+}
+
 #[test]
 fn project_html_reserved_tags() {
     let song = RESERVED_TAGS
@@ -73,5 +131,126 @@ fn project_html_reserved_tags() {
 
     build.unwrap_err();
     build.assert_parser_diag(DiagKind::HtmlReservedTag { tag: "html".into() });
-    build.assert_parser_diag(DiagKind::HtmlReservedTag { tag: "tex".into() });
+}
+
+#[test]
+fn project_raw_tex() {
+    let build = TestProject::new("raw-tex")
+        .output("songbook.pdf")
+        .output("songbook.html")
+        .song(
+            "song.md",
+            "# Song\n\n1. Tick <tex code=\"\\ding{51}\"/> 50% & more.\n",
+        )
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let tex = build.read_output(".tex");
+    assert!(tex.contains(r"Tick~\ding{51}~50\%~\&~more."), "{}", tex);
+
+    let html = build.read_output(".html");
+    assert!(html.contains("Tick  50% &amp; more."), "{}", html);
+    assert!(!html.contains("ding"));
+}
+
+#[test]
+fn project_raw_tex_missing_code() {
+    let build = TestProject::new("raw-tex-missing-code")
+        .output("songbook.pdf")
+        .song("song.md", "# Song\n\n1. Tick <tex>.\n")
+        .build()
+        .unwrap();
+
+    build.unwrap_err();
+    build.assert_parser_diag(DiagKind::RawTexCode);
+}
+
+#[test]
+fn project_html_split() {
+    let build = TestProject::new("html-split")
+        .song("a.md", "# Danny Boy\n\n1. Lyrics.\n")
+        .song("b.md", "# Whiskey in the Jar\n\n1. Lyrics.\n")
+        .output_toml(toml! {
+            file = "songbook.html"
+            split = "song"
+        })
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let index = build.read_output(".html");
+    assert!(index.contains(r#"<a href="songbook/danny-boy.html">Danny Boy</a>"#));
+    assert!(!index.contains("<section"));
+
+    let dir = build.dir_output().join("songbook");
+    let page = fs::read_to_string(dir.join("danny-boy.html")).unwrap();
+    assert!(page.contains(r#"<base href="../">"#));
+    assert!(page.contains(r#"<section id="danny-boy" class="song pad">"#));
+    assert!(page.contains(r#"<a href="songbook/whiskey-in-the-jar.html">Whiskey in the Jar</a>"#));
+    assert!(page.contains(r#"<a href="songbook.html">Contents</a>"#));
+
+    let page = fs::read_to_string(dir.join("whiskey-in-the-jar.html")).unwrap();
+    assert!(page.contains(r#"<a href="songbook/danny-boy.html">Danny Boy</a>"#));
+}
+
+#[test]
+fn project_split_not_html() {
+    let build = TestProject::new("split-not-html")
+        .output_toml(toml! {
+            file = "songbook.json"
+            split = "song"
+        })
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("only supported for HTML"), "{}", err);
+}
+
+#[test]
+fn project_html_assets() {
+    let build = TestProject::new("html-assets")
+        .template_file("style.css", "body {}\n")
+        .template_file("fonts/sans.woff", "font")
+        .template(
+            "songbook.html",
+            "songbook.hbs",
+            r#"{{ version_check "1.4.0" }}<link href="{{ asset "style.css" }}"><link href="{{ asset "fonts/sans.woff" }}">"#,
+        )
+        .output_toml(toml! {
+            file = "songbook.html"
+            assets = ["style.css", "fonts/"]
+        })
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let html = build.read_output(".html");
+    assert!(html.contains(r#"<link href="style.css"><link href="fonts/sans.woff">"#));
+
+    let dir = build.dir_output();
+    assert_eq!(
+        fs::read_to_string(dir.join("style.css")).unwrap(),
+        "body {}\n"
+    );
+    assert_eq!(
+        fs::read_to_string(dir.join("fonts/sans.woff")).unwrap(),
+        "font"
+    );
+}
+
+#[test]
+fn project_html_asset_missing() {
+    let build = TestProject::new("html-asset-missing")
+        .output_toml(toml! {
+            file = "songbook.html"
+            assets = ["style.css"]
+        })
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("Asset file not found"), "{}", err);
+    assert!(err.contains("style.css"), "{}", err);
 }
@@ -0,0 +1,183 @@
+use bard::parser::DiagKind;
+use serde_json::{json, Value};
+
+mod util_ng;
+pub use util_ng::*;
+
+const OPENLYRICS: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<song xmlns="http://openlyrics.info/namespace/2009/song" version="0.8">
+  <properties>
+    <titles>
+      <title>Amazing Grace</title>
+      <title>New Britain</title>
+    </titles>
+    <authors>
+      <author>John Newton</author>
+      <author type="music">Traditional</author>
+    </authors>
+    <copyright>Public domain</copyright>
+    <key>G</key>
+    <verseOrder>v1 c v2</verseOrder>
+    <themes><theme>Grace</theme></themes>
+  </properties>
+  <lyrics>
+    <verse name="v1">
+      <lines>
+        <chord name="G"/>Amazing grace how <chord name="C"/>sweet<br/>
+        the sound
+      </lines>
+    </verse>
+    <verse name="c">
+      <lines><chord root="D" structure="min7"/>Chorus</lines>
+    </verse>
+    <verse name="v2">
+      <lines>Second &amp; last</lines>
+    </verse>
+  </lyrics>
+</song>
+"#;
+
+const OPENSONG: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<song>
+  <title>Lord of the Dance</title>
+  <author>Sydney Carter</author>
+  <aka>Simple Gifts</aka>
+  <hymn_number></hymn_number>
+  <presentation>V1 C</presentation>
+  <lyrics>[V1]
+.Am      G
+ I danced in the morning
+;A comment
+ when the world was begun
+
+[C]
+.C   F
+.G
+ Dance, then</lyrics>
+</song>
+"#;
+
+fn text(text: &str) -> Value {
+    json!({ "type": "i-text", "text": text })
+}
+
+fn chord(chord: &str, inlines: Value) -> Value {
+    json!({
+        "type": "i-chord",
+        "chord": chord,
+        "alt_chord": null,
+        "backticks": 1,
+        "baseline": false,
+        "inlines": inlines,
+        "chords_only": false,
+    })
+}
+
+fn build_songs(name: &str, path: &str, content: &str) -> (TestBuild, Value) {
+    let build = TestProject::new(name)
+        .song(path, content)
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    build.unwrap();
+    let json: Value = serde_json::from_str(&build.read_output(".json")).unwrap();
+    let song = json["songs"][0].clone();
+    (build, song)
+}
+
+#[test]
+fn import_xml_openlyrics() {
+    let (build, song) = build_songs("import-xml-openlyrics", "grace.xml", OPENLYRICS);
+
+    assert_eq!(song["title"], "Amazing Grace");
+    assert_eq!(song["subtitles"], json!(["New Britain"]));
+    assert_eq!(song["metadata"]["author"], "John Newton, Traditional");
+    assert_eq!(song["metadata"]["copyright"], "Public domain");
+    assert_eq!(song["key"], "G");
+
+    let blocks = song["blocks"].as_array().unwrap();
+    assert_eq!(blocks.len(), 3);
+    assert_eq!(blocks[0]["label"], json!({ "verse": 1 }));
+    assert_eq!(
+        blocks[0]["paragraphs"],
+        json!([[
+            chord("G", json!([text("Amazing grace how ")])),
+            chord("C", json!([text("sweet")])),
+            { "type": "i-break" },
+            text("the sound"),
+        ]])
+    );
+    assert_eq!(
+        blocks[1]["paragraphs"],
+        json!([[chord("Dm7", json!([text("Chorus")]))]])
+    );
+    assert_eq!(blocks[2]["label"], json!({ "verse": 2 }));
+    assert_eq!(blocks[2]["paragraphs"], json!([[text("Second & last")]]));
+
+    build.assert_parser_diag(DiagKind::UnsupportedElement {
+        element: "themes".into(),
+    });
+}
+
+#[test]
+fn import_xml_opensong() {
+    let (build, song) = build_songs("import-xml-opensong", "dance.opensong", OPENSONG);
+
+    assert_eq!(song["title"], "Lord of the Dance");
+    assert_eq!(song["subtitles"], json!(["Simple Gifts"]));
+    assert_eq!(song["metadata"]["author"], "Sydney Carter");
+    assert_eq!(song["key"], "Am");
+
+    let blocks = song["blocks"].as_array().unwrap();
+    assert_eq!(blocks.len(), 2);
+    assert_eq!(blocks[0]["label"], json!({ "verse": 1 }));
+    assert_eq!(
+        blocks[0]["paragraphs"],
+        json!([[
+            chord("Am", json!([text("I danced")])),
+            chord("G", json!([text(" in the morning")])),
+            { "type": "i-break" },
+            text("when the world was begun"),
+        ]])
+    );
+
+    let mut chords_only = chord("C", json!([text(" ")]));
+    chords_only["chords_only"] = json!(true);
+    let mut chords_only_last = chord("F", json!([]));
+    chords_only_last["chords_only"] = json!(true);
+    assert_eq!(
+        blocks[1]["paragraphs"],
+        json!([[
+            chords_only,
+            chords_only_last,
+            { "type": "i-break" },
+            chord("G", json!([text("Dance, then")])),
+        ]])
+    );
+
+    // Empty elements aren't reported
+    assert!(build
+        .app()
+        .parser_diags()
+        .lock()
+        .iter()
+        .all(|diag| !matches!(diag.kind, DiagKind::UnsupportedElement { .. })));
+}
+
+#[test]
+fn import_xml_invalid() {
+    let build = TestProject::new("import-xml-invalid")
+        .song("broken.xml", "<song>\n  <title>Broken</song>\n")
+        .build()
+        .unwrap();
+    build.unwrap_err();
+    let diag = build
+        .app()
+        .parser_diags()
+        .lock()
+        .iter()
+        .find(|diag| matches!(diag.kind, DiagKind::Xml { .. }))
+        .cloned()
+        .unwrap();
+    assert_eq!(diag.line, 2);
+}
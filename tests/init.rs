@@ -1,7 +1,8 @@
 mod util;
 use std::fs;
 
-use bard::default_project::DEFAULT_PROJECT;
+use bard::default_project::{InitOptions, DEFAULT_PROJECT};
+use bard::project::Format;
 pub use util::*;
 
 #[test]
@@ -14,7 +15,7 @@ fn init_doesnt_overwrite_1() {
     let test_content = "test\n";
     let work_dir = work_dir("init-overwrite-1", true).unwrap();
     let songs_dir = work_dir.join("songs");
-    let test_file = songs_dir.join("yippie.md");
+    let test_file = songs_dir.join("example.md");
     let project_file = work_dir.join("bard.toml");
     fs::create_dir_all(&songs_dir).unwrap();
     fs::write(&test_file, test_content).unwrap();
@@ -49,3 +50,94 @@ fn init_doesnt_overwrite_2() {
     assert!(out_dir.exists());
     assert!(!project_file.exists());
 }
+
+#[test]
+fn init_project_file() {
+    let work_dir = work_dir("init-project-file", true).unwrap();
+    let app = Builder::app(false);
+    bard::bard_init_at(&app, &work_dir).unwrap();
+
+    let project_file = fs::read_to_string(work_dir.join("bard.toml")).unwrap();
+    assert_eq!(
+        project_file,
+        r#"version = 2
+
+songs = [
+    "example.md",
+]
+
+notation = "english"
+
+[[output]]
+file = "songbook.pdf"
+
+[[output]]
+file = "songbook.html"
+
+[book]
+title = "My Songbook"
+subtitle = "(You can edit that title!)"
+chorus_label = "Ch"
+title_note = "(And this note too...)"
+"#
+    );
+    assert!(work_dir.join("songs").join("example.md").exists());
+    assert!(work_dir.join("output").join("fonts").exists());
+}
+
+#[test]
+fn init_with_options() {
+    let work_dir = work_dir("init-with-options", true).unwrap();
+    let app = Builder::app(false);
+    let opts = InitOptions {
+        title: Some("Campfire Songs".to_string()),
+        author: Some("The Scouts".to_string()),
+        formats: vec![Format::Html, Format::Hovorka, Format::Html],
+        dir_output: Some("build".to_string()),
+    };
+    bard::bard_init_with_at(&app, &work_dir, &opts).unwrap();
+
+    let project_file = fs::read_to_string(work_dir.join("bard.toml")).unwrap();
+    assert_eq!(
+        project_file,
+        r#"version = 2
+
+songs = [
+    "example.md",
+]
+
+notation = "english"
+dir_output = "build"
+
+[[output]]
+file = "songbook.html"
+
+[[output]]
+file = "hovorka.xml"
+format = "hovorka"
+
+[book]
+title = "Campfire Songs"
+author = "The Scouts"
+chorus_label = "Ch"
+"#
+    );
+    assert!(work_dir.join("build").join("fonts").exists());
+    assert!(!work_dir.join("output").exists());
+
+    bard::bard_make_at(&app, &work_dir).unwrap();
+    assert!(work_dir.join("build").join("songbook.html").exists());
+    assert!(work_dir.join("build").join("hovorka.xml").exists());
+}
+
+#[test]
+fn init_dir_output_outside() {
+    let work_dir = work_dir("init-dir-output-outside", true).unwrap();
+    let app = Builder::app(false);
+    let opts = InitOptions {
+        dir_output: Some("../output".to_string()),
+        ..Default::default()
+    };
+    bard::bard_init_with_at(&app, &work_dir, &opts).unwrap_err();
+    assert!(!work_dir.join("bard.toml").exists());
+}
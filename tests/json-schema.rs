@@ -0,0 +1,339 @@
+use std::fs;
+use std::path::Path;
+
+use base64::{engine::general_purpose::STANDARD as BASE_64, Engine as _};
+use serde_json::Value;
+
+use bard::book::{Block, Inline};
+use bard::music::Notation;
+use bard::project::{Format, JsonStyle, LabelNumbering, OnFailure, Split};
+use bard::render::json::SCHEMA;
+
+mod util_ng;
+pub use util_ng::*;
+
+const SMOL_PNG: &str = "iVBORw0KGgoAAAANSUhEUgAAAQAAAAEAAQMAAABmvDolAAAAA1BMVEW10NBjBBbqAAAAH0lEQVRoge3BAQ0AAADCoPdPbQ43oAAAAAAAAAAAvg0hAAABmmDh1QAAAABJRU5ErkJggg==";
+
+fn type_matches(ty: &str, value: &Value) -> bool {
+    match ty {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "number" => value.is_number(),
+        "integer" => value.is_i64() || value.is_u64(),
+        "null" => value.is_null(),
+        _ => panic!("Unsupported type in schema: {}", ty),
+    }
+}
+
+/// Validates `value` against `schema`, supporting the subset of JSON Schema used by `bard.schema.json`.
+fn validate(root: &Value, schema: &Value, value: &Value, path: &str) -> Result<(), String> {
+    if let Some(reference) = schema.get("$ref").and_then(Value::as_str) {
+        let name = reference.strip_prefix("#/$defs/").unwrap();
+        return validate(root, &root["$defs"][name], value, path);
+    }
+
+    if let Some(expected) = schema.get("const") {
+        if value != expected {
+            return Err(format!("{}: Expected {}, got {}", path, expected, value));
+        }
+    }
+    if let Some(variants) = schema.get("enum").and_then(Value::as_array) {
+        if !variants.contains(value) {
+            return Err(format!("{}: {} is not one of {:?}", path, value, variants));
+        }
+    }
+    if let Some(types) = schema.get("type") {
+        let types: Vec<_> = match types {
+            Value::Array(types) => types.iter().filter_map(Value::as_str).collect(),
+            types => types.as_str().into_iter().collect(),
+        };
+        if !types.iter().any(|ty| type_matches(ty, value)) {
+            return Err(format!("{}: {} is not of type {:?}", path, value, types));
+        }
+    }
+    if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+        if value.as_f64().map_or(false, |n| n < min) {
+            return Err(format!("{}: {} is less than {}", path, value, min));
+        }
+    }
+    if let Some(variants) = schema.get("oneOf").and_then(Value::as_array) {
+        let errors: Vec<_> = variants
+            .iter()
+            .filter_map(|variant| validate(root, variant, value, path).err())
+            .collect();
+        if errors.len() + 1 != variants.len() {
+            return Err(format!(
+                "{}: {} doesn't match exactly one variant: {:#?}",
+                path, value, errors
+            ));
+        }
+    }
+
+    if let Value::Object(object) = value {
+        let properties = schema.get("properties").and_then(Value::as_object);
+        for key in schema
+            .get("required")
+            .and_then(Value::as_array)
+            .into_iter()
+            .flatten()
+        {
+            let key = key.as_str().unwrap();
+            if !object.contains_key(key) {
+                return Err(format!("{}: Missing property '{}'", path, key));
+            }
+        }
+        for (key, item) in object {
+            let path = format!("{}.{}", path, key);
+            match (
+                properties.and_then(|p| p.get(key)),
+                schema.get("additionalProperties"),
+            ) {
+                (Some(schema), _) => validate(root, schema, item, &path)?,
+                (None, Some(Value::Bool(false))) => {
+                    return Err(format!("{}: Property not in schema", path))
+                }
+                (None, Some(schema @ Value::Object(_))) => validate(root, schema, item, &path)?,
+                _ => {}
+            }
+        }
+    }
+    if let (Value::Array(items), Some(schema)) = (value, schema.get("items")) {
+        for (i, item) in items.iter().enumerate() {
+            validate(root, schema, item, &format!("{}[{}]", path, i))?;
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_schema(value: &Value) -> Result<(), String> {
+    let schema: Value = serde_json::from_str(SCHEMA).unwrap();
+    validate(&schema, &schema, value, "$")
+}
+
+#[rustfmt::skip]
+fn prepare_project(name: &str) -> TestProject {
+    TestProject::new(name)
+        .song("summer.md", indoc! {r#"
+            # Summer Time
+            ## Traditional
+            !author: Trad
+            !tags: folk, summer
+            !tempo: 96
+
+            ### Intro
+            `G` `C` `G`
+
+            1. x2
+            O the `G`summer^[Sometimes sung as _winter_.] `C`time `G`has come !>
+            |: And the **trees** are `D`blooming :|x3
+            See [the notes](https://example.com) and !>Bridge. <tex code="\relax"/>
+            ![smol](smol.png)
+
+            > And we'll `C`all `Em`go together
+
+            ### Bridge
+            \
+            `F`Dance to the whistle
+
+            ![smol](smol.png "center")
+            Caption
+
+            - Item 1
+            - Item 2
+
+            ---
+
+            ```tab
+            e|-----0---3---|
+            ```
+
+            ```lyrics
+            Plain text
+            ```
+        "#})
+        .song("winter.md", indoc! {r#"
+            # Winter
+
+            1. `Am`Cold and `C`dark
+
+            > First chorus
+
+            >> Second chorus
+
+            2. Back to !>> and !>
+        "#})
+        .song("chanson.md", indoc! {r#"
+            # Chanson
+
+            !=solfege
+
+            1. `Do`Douce `Sol`nuit
+
+            <div class="note"></div>
+        "#})
+        .binary_asset("smol.png", SMOL_PNG)
+        .settings(|toml| {
+            toml.set("songs", vec!["summer.md", "chanson.md"]);
+            toml.set("chapter", vec![toml! {
+                name = "Seasons"
+                songs = ["winter.md"]
+            }]);
+        })
+        .output("songbook.json")
+}
+
+#[test]
+fn json_schema_version() {
+    let schema: Value = serde_json::from_str(SCHEMA).unwrap();
+    assert_eq!(
+        schema["properties"]["schema_version"]["const"],
+        bard::book::version::current().to_string(),
+        "The JSON schema needs to be updated along with the AST version",
+    );
+}
+
+/// Lists the `type` of each variant of an AST enum.
+/// The match is exhaustive, so a new variant doesn't compile until it's listed here.
+macro_rules! ast_types {
+    ($ty:ident { $($pat:pat => $name:literal,)+ }) => {{
+        let _ = |value: &$ty| match value { $($pat => $name,)+ };
+        vec![$($name),+]
+    }};
+}
+
+/// Serializes each variant of a fieldless enum, the match is exhaustive as in `ast_types!`.
+macro_rules! enum_values {
+    ($ty:ident: $($variant:ident),+) => {{
+        let _ = |value: $ty| match value { $($ty::$variant)|+ => {} };
+        vec![$(serde_json::to_value($ty::$variant).unwrap()),+]
+    }};
+}
+
+fn schema_types<'s>(schema: &'s Value, def: &str) -> Vec<&'s str> {
+    schema["$defs"][def]["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .filter_map(|variant| variant["properties"]["type"]["const"].as_str())
+        .collect()
+}
+
+fn collect_types(value: &Value, types: &mut Vec<String>) {
+    match value {
+        Value::Object(object) => {
+            if let Some(ty) = object.get("type").and_then(Value::as_str) {
+                types.push(ty.to_owned());
+            }
+            object.values().for_each(|v| collect_types(v, types));
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_types(v, types)),
+        _ => {}
+    }
+}
+
+#[test]
+#[rustfmt::skip]
+fn json_schema_variants() {
+    let schema: Value = serde_json::from_str(SCHEMA).unwrap();
+
+    let block_types = ast_types!(Block {
+        Block::Verse(_) => "b-verse",
+        Block::BulletList(_) => "b-bullet-list",
+        Block::HorizontalLine => "b-horizontal-line",
+        Block::Pre { .. } => "b-pre",
+        Block::Tab { .. } => "b-tab",
+        Block::Image { .. } => "b-image",
+        Block::HtmlBlock(_) => "b-html-block",
+    });
+    let mut inline_types = ast_types!(Inline {
+        Inline::Text { .. } => "i-text",
+        Inline::Chord(_) => "i-chord",
+        Inline::Break => "i-break",
+        Inline::Emph(_) => "i-emph",
+        Inline::Strong(_) => "i-strong",
+        Inline::Link(_) => "i-link",
+        Inline::Image(_) => "i-image",
+        Inline::ChorusRef(_) => "i-chorus-ref",
+        Inline::VerseRef(_) => "i-verse-ref",
+        Inline::RepeatStart => "i-repeat-start",
+        Inline::RepeatEnd(_) => "i-repeat-end",
+        Inline::HtmlTag(_) => "i-tag",
+        Inline::Raw { .. } => "i-raw",
+        Inline::Footnote(_) => "i-footnote",
+        Inline::Transpose(_) => "i-transpose",
+    });
+    // Transpositions are only used internally by the parser
+    inline_types.retain(|&ty| ty != "i-transpose");
+    assert_eq!(schema_types(&schema, "block"), block_types);
+    assert_eq!(schema_types(&schema, "inline"), inline_types);
+
+    // The sample project has each of the blocks and inlines, so that all of their fields are validated
+    let build = prepare_project("json-schema-variants").build().unwrap();
+    build.unwrap();
+    let json: Value = serde_json::from_str(&build.read_output(".json")).unwrap();
+    let mut types = vec![];
+    collect_types(&json, &mut types);
+    for ty in block_types.iter().chain(&inline_types) {
+        assert!(types.iter().any(|t| t == ty), "{} missing in the sample project", ty);
+    }
+
+    let defs = &schema["$defs"];
+    let output = &defs["output"]["properties"];
+    assert_eq!(defs["notation"]["enum"], Value::from(enum_values!(Notation: English, German, Nashville, Roman, Solfege)));
+    assert_eq!(output["format"]["enum"], Value::from(enum_values!(Format: Pdf, Html, Hovorka, Json, Xml, Markdown, MusicXml, Lilypond, Abc)));
+    assert_eq!(output["script"]["properties"]["on_failure"]["enum"], Value::from(enum_values!(OnFailure: Error, Warn, Ignore)));
+    assert_eq!(output["label_numbering"]["enum"], Value::from(enum_values!(LabelNumbering: Always, Never, Auto)));
+    assert_eq!(output["split"]["enum"], Value::from(enum_values!(Split: Song)));
+    assert_eq!(output["json_style"]["enum"], Value::from(enum_values!(JsonStyle: Pretty, Compact, Ndjson)));
+}
+
+#[test]
+fn project_json_schema() {
+    let build = prepare_project("json-schema").build().unwrap();
+    build.unwrap();
+
+    let mut json: Value = serde_json::from_str(&build.read_output(".json")).unwrap();
+    validate_schema(&json).unwrap();
+    assert_eq!(json["songs"][1]["blocks"][0]["notation"], "solfege");
+    assert_eq!(
+        json["schema_version"],
+        bard::book::version::current().to_string()
+    );
+
+    // Fields missing from the schema are caught
+    json["songs"][0]["blocks"][0]["foo"] = Value::from(1);
+    let err = validate_schema(&json).unwrap_err();
+    assert!(err.contains("$.songs[0].blocks"), "{}", err);
+}
+
+#[test]
+fn project_json_schema_samples() {
+    // The default songs of a new project
+    let build = TestProject::new("json-schema-default")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    build.unwrap();
+    let json: Value = serde_json::from_str(&build.read_output(".json")).unwrap();
+    validate_schema(&json).unwrap();
+
+    // The songs of the example project
+    let example_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("example");
+    let mut project = TestProject::new("json-schema-example").output("songbook.json");
+    for entry in fs::read_dir(example_dir.join("songs")).unwrap() {
+        let path = entry.unwrap().path();
+        let content = fs::read_to_string(&path).unwrap();
+        project = project.song(path.file_name().unwrap(), content);
+    }
+    for image in ["hills.jpg", "chest.jpg", "thyme.png"] {
+        let content = fs::read(example_dir.join("output").join(image)).unwrap();
+        project = project.binary_asset(image, BASE_64.encode(content));
+    }
+    let build = project.build().unwrap();
+    assert_eq!(build.unwrap().songs().len(), 4);
+    let json: Value = serde_json::from_str(&build.read_output(".json")).unwrap();
+    validate_schema(&json).unwrap();
+}
@@ -0,0 +1,81 @@
+use serde_json::Value;
+
+mod util_ng;
+pub use util_ng::*;
+
+fn parse_json(json: &str) -> Value {
+    let mut value: Value = serde_json::from_str(json).unwrap();
+    // Outputs may have been rendered at different times
+    value["program"]
+        .as_object_mut()
+        .unwrap()
+        .remove("build_timestamp");
+    value
+}
+
+#[test]
+fn project_json_style() {
+    let build = TestProject::new("json-style")
+        .song(
+            "song.md",
+            indoc! {"
+            # Song
+
+            1. First `C`verse.
+
+            > Chorus `G`lyrics.
+            "},
+        )
+        .song(
+            "song-2.md",
+            indoc! {"
+            # Another Song
+
+            1. Lyrics.
+            "},
+        )
+        .output_toml(toml! {
+            file = "pretty.json"
+        })
+        .output_toml(toml! {
+            file = "compact.json"
+            json_style = "compact"
+        })
+        .output_toml(toml! {
+            file = "songbook.ndjson"
+            format = "json"
+            json_style = "ndjson"
+        })
+        .build();
+    let project = build.unwrap();
+
+    let pretty = build.read_output("pretty.json");
+    let compact = build.read_output("compact.json");
+    let ndjson = build.read_output(".ndjson");
+    assert!(pretty.lines().count() > 1);
+    assert_eq!(compact.lines().count(), 1);
+    assert_eq!(ndjson.lines().count(), 3);
+
+    // Songs are the same as in memory
+    let pretty = parse_json(&pretty);
+    let songs = serde_json::to_value(project.songs()).unwrap();
+    assert_eq!(pretty["songs"], songs);
+
+    // Compact is the same as pretty apart from the output settings
+    let mut compact = parse_json(&compact);
+    assert_eq!(compact["output"]["json_style"], "compact");
+    compact["output"] = pretty["output"].clone();
+    assert_eq!(compact, pretty);
+
+    // NDJSON is a header followed by songs
+    let mut lines = ndjson.lines();
+    let mut header = parse_json(lines.next().unwrap());
+    assert!(header.get("songs").is_none());
+    assert_eq!(header["output"]["json_style"], "ndjson");
+    let ndjson_songs: Vec<Value> = lines.map(|l| serde_json::from_str(l).unwrap()).collect();
+    assert_eq!(Value::Array(ndjson_songs), songs);
+    header["output"] = pretty["output"].clone();
+    let mut pretty_header = pretty.clone();
+    pretty_header.as_object_mut().unwrap().remove("songs");
+    assert_eq!(header, pretty_header);
+}
@@ -0,0 +1,116 @@
+use serde_json::{json, Value};
+
+mod util_ng;
+pub use util_ng::*;
+
+/// Labels of the verses of each song, and numbers of the chorus references.
+fn labels(build: &TestBuild, output: &str) -> Vec<(Vec<Value>, Vec<Value>)> {
+    let json: Value = serde_json::from_str(&build.read_output(output)).unwrap();
+    json["songs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|song| {
+            let verses: Vec<_> = song["blocks"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .filter(|block| block["type"] == "b-verse")
+                .collect();
+            let labels = verses.iter().map(|verse| verse["label"].clone()).collect();
+            let refs = verses
+                .iter()
+                .flat_map(|verse| verse["paragraphs"].as_array().unwrap())
+                .flat_map(|para| para.as_array().unwrap())
+                .filter(|inline| inline["type"] == "i-chorus-ref")
+                .map(|inline| inline["num"].clone())
+                .collect();
+            (labels, refs)
+        })
+        .collect()
+}
+
+#[test]
+fn project_label_numbering() {
+    let build = TestProject::new("label-numbering")
+        .song(
+            "song.md",
+            indoc! {"
+            # One Verse
+
+            1. Verse lyrics. !>
+
+            > Chorus lyrics.
+
+            # Two Verses
+
+            1. Verse lyrics. !>>
+
+            > Chorus lyrics.
+
+            2. Verse lyrics.
+
+            >> Second chorus lyrics.
+            "},
+        )
+        .output("default.json")
+        .output_toml(toml! {
+            file = "always.json"
+            label_numbering = "always"
+        })
+        .output_toml(toml! {
+            file = "never.json"
+            label_numbering = "never"
+        })
+        .output_toml(toml! {
+            file = "auto.json"
+            label_numbering = "auto"
+        })
+        .build()
+        .unwrap();
+
+    let none = json!({ "none": {} });
+    let verse = |num: u32| json!({ "verse": num });
+    let chorus = |num: Option<u32>| json!({ "chorus": num });
+
+    assert_eq!(
+        labels(&build, "default.json"),
+        [
+            (vec![verse(1), chorus(None)], vec![json!(null)]),
+            (
+                vec![verse(1), chorus(Some(1)), verse(2), chorus(Some(2))],
+                vec![json!(2)]
+            ),
+        ]
+    );
+    assert_eq!(
+        labels(&build, "always.json"),
+        [
+            (vec![verse(1), chorus(Some(1))], vec![json!(1)]),
+            (
+                vec![verse(1), chorus(Some(1)), verse(2), chorus(Some(2))],
+                vec![json!(2)]
+            ),
+        ]
+    );
+    assert_eq!(
+        labels(&build, "never.json"),
+        [
+            (vec![none.clone(), chorus(None)], vec![json!(null)]),
+            (
+                vec![none.clone(), chorus(None), none.clone(), chorus(None)],
+                vec![json!(null)]
+            ),
+        ]
+    );
+    assert_eq!(
+        labels(&build, "auto.json"),
+        [
+            (vec![none, chorus(None)], vec![json!(null)]),
+            (
+                vec![verse(1), chorus(Some(1)), verse(2), chorus(Some(2))],
+                vec![json!(2)]
+            ),
+        ]
+    );
+}
@@ -0,0 +1,180 @@
+use bard::lint::LintKind;
+use bard::parser::DiagKind;
+
+mod util_ng;
+pub use util_ng::*;
+
+fn lint_project(
+    name: &str,
+    songs: &[(&str, &str)],
+    lint: toml::Table,
+) -> Vec<(String, usize, LintKind)> {
+    let mut project = TestProject::new(name);
+    for (path, content) in songs {
+        project = project.song(*path, *content);
+    }
+    let build = project
+        .settings(move |toml| toml.set("lint", lint))
+        .build()
+        .unwrap();
+
+    build
+        .unwrap()
+        .lint(build.app())
+        .unwrap()
+        .into_iter()
+        .map(|diag| match diag.kind {
+            DiagKind::Lint(kind) => (diag.file.to_string_lossy().into(), diag.line, kind),
+            other => panic!("Unexpected diagnostic: {:?}", other),
+        })
+        .collect()
+}
+
+const SONG_LONG_VERSE: &str = "# Song\n\n1. One\nTwo\nThree\n\n2. One\nTwo\n";
+
+#[test]
+fn lint_max_verse_lines() {
+    let warnings = lint_project(
+        "lint-max-verse-lines",
+        &[("song.md", SONG_LONG_VERSE)],
+        toml! { max_verse_lines = 2 },
+    );
+    assert_eq!(
+        warnings,
+        vec![(
+            "songs/song.md".into(),
+            3,
+            LintKind::VerseLines { lines: 3, max: 2 }
+        )]
+    );
+
+    let warnings = lint_project(
+        "lint-max-verse-lines-pass",
+        &[("song.md", SONG_LONG_VERSE)],
+        toml! { max_verse_lines = 3 },
+    );
+    assert_eq!(warnings, vec![]);
+}
+
+const SONGS_SUBTITLE: &str = "# One\n\n## Sub\n\n1. `G`Lyrics.\n\n# Two\n\n1. Lyrics.\n";
+
+#[test]
+fn lint_require_subtitle() {
+    let warnings = lint_project(
+        "lint-require-subtitle",
+        &[("songs.md", SONGS_SUBTITLE)],
+        toml! { require_subtitle = true },
+    );
+    assert_eq!(
+        warnings,
+        vec![(
+            "songs/songs.md".into(),
+            7,
+            LintKind::NoSubtitle {
+                title: "Two".into()
+            }
+        )]
+    );
+
+    let warnings = lint_project(
+        "lint-require-subtitle-pass",
+        &[("songs.md", SONGS_SUBTITLE)],
+        toml! { require_subtitle = false },
+    );
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn lint_require_chords() {
+    let warnings = lint_project(
+        "lint-require-chords",
+        &[("songs.md", SONGS_SUBTITLE)],
+        toml! { require_chords = true },
+    );
+    assert_eq!(
+        warnings,
+        vec![(
+            "songs/songs.md".into(),
+            7,
+            LintKind::NoChords {
+                title: "Two".into()
+            }
+        )]
+    );
+
+    let warnings = lint_project(
+        "lint-require-chords-pass",
+        &[("song.md", "# One\n\n> Lyrics `G`with a chord.\n")],
+        toml! { require_chords = true },
+    );
+    assert_eq!(warnings, vec![]);
+}
+
+const SONGS_DUPLICATE: &[(&str, &str)] = &[
+    ("a.md", "# Song\n\n1. A.\n"),
+    ("b.md", "# Other\n\n1. B.\n\n# song\n\n1. C.\n"),
+];
+
+#[test]
+fn lint_duplicate_titles() {
+    let warnings = lint_project(
+        "lint-duplicate-titles",
+        SONGS_DUPLICATE,
+        toml! { warn_duplicate_titles = true },
+    );
+    assert_eq!(
+        warnings,
+        vec![(
+            "songs/b.md".into(),
+            5,
+            LintKind::DuplicateTitle {
+                title: "song".into(),
+                first_file: "songs/a.md".into(),
+                first_line: 1,
+            }
+        )]
+    );
+
+    // Duplicates within one file are reported as well
+    let warnings = lint_project(
+        "lint-duplicate-titles-file",
+        &[("song.md", "# Song\n\n1. A.\n\n# Song\n\n1. B.\n")],
+        toml! { warn_duplicate_titles = true },
+    );
+    assert_eq!(
+        warnings,
+        vec![(
+            "songs/song.md".into(),
+            5,
+            LintKind::DuplicateTitle {
+                title: "Song".into(),
+                first_file: "songs/song.md".into(),
+                first_line: 1,
+            }
+        )]
+    );
+
+    let warnings = lint_project(
+        "lint-duplicate-titles-pass",
+        SONGS_DUPLICATE,
+        toml! { warn_duplicate_titles = false },
+    );
+    assert_eq!(warnings, vec![]);
+}
+
+#[test]
+fn lint_fail_on_warning() {
+    let build = TestProject::new("lint-fail-on-warning")
+        .song("song.md", SONG_LONG_VERSE)
+        .settings(|toml| toml.set("lint", toml! { max_verse_lines = 2 }))
+        .build()
+        .unwrap();
+    let project_dir = build.dir_songs().parent().unwrap();
+
+    // Warnings don't fail the lint by default
+    let warnings = bard::bard_lint_at(build.app(), project_dir, false).unwrap();
+    assert_eq!(warnings.len(), 1);
+    build.assert_warning("songs/song.md:3: Verse has 3 lines, the maximum is 2.");
+
+    bard::bard_lint_at(build.app(), project_dir, true).unwrap_err();
+}
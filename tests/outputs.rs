@@ -0,0 +1,207 @@
+use std::fs;
+
+mod util_ng;
+pub use util_ng::*;
+
+fn prepare_project(name: &str, selection: &[&str]) -> TestProject {
+    TestProject::new(name)
+        .song("song.md", "# Song\n\n1. Lyrics.\n")
+        .output_toml(toml! {
+            file = "songbook.html"
+            name = "web"
+        })
+        .output("songbook.json")
+        .output("songbook.md")
+        .select_outputs(selection)
+}
+
+#[test]
+fn outputs_all() {
+    let build = prepare_project("outputs-all", &[]).build().unwrap();
+    build.unwrap();
+
+    build.read_output(".html");
+    build.read_output(".json");
+    build.read_output(".md");
+}
+
+#[test]
+fn outputs_selected() {
+    let build = prepare_project("outputs-selected", &["web", "songbook.md"])
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    build.read_output(".html");
+    build.read_output(".md");
+    build.try_read_output(".json").unwrap_err();
+}
+
+#[test]
+fn outputs_selected_by_stem() {
+    // Outputs without an explicit name are named after the file stem
+    let build = prepare_project("outputs-selected-by-stem", &["songbook"])
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    build.try_read_output(".html").unwrap_err();
+    build.read_output(".json");
+    build.read_output(".md");
+}
+
+#[test]
+fn outputs_unknown() {
+    let build = prepare_project("outputs-unknown", &["web", "pdf"])
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(
+        err.contains("Unknown output 'pdf', available outputs: songbook, web"),
+        "{}",
+        err
+    );
+    build.try_read_output(".html").unwrap_err();
+}
+
+#[test]
+fn outputs_name_in_errors() {
+    let build = TestProject::new("outputs-name-in-errors")
+        .output_toml(toml! {
+            file = "songbook.html"
+            name = "web"
+            setlist = "missing.txt"
+        })
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(
+        err.contains("Could not render output 'web' (songbook.html)"),
+        "{}",
+        err
+    );
+}
+
+fn prepare_disabled(name: &str, selection: &[&str]) -> TestProject {
+    let this_os = std::env::consts::OS;
+    let other_os = if this_os == "windows" {
+        "linux"
+    } else {
+        "windows"
+    };
+
+    TestProject::new(name)
+        .song("song.md", "# Song\n\n1. Lyrics.\n")
+        .output_toml(toml! {
+            file = "songbook.html"
+            enabled = false
+        })
+        .output_toml(toml! {
+            file = "songbook.json"
+            only_on = [other_os]
+        })
+        .output_toml(toml! {
+            file = "songbook.md"
+            only_on = [this_os]
+        })
+        .select_outputs(selection)
+}
+
+#[test]
+fn outputs_disabled_skipped() {
+    let build = prepare_disabled("outputs-disabled-skipped", &[])
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    build.try_read_output(".html").unwrap_err();
+    build.try_read_output(".json").unwrap_err();
+    build.read_output(".md");
+}
+
+#[test]
+fn outputs_disabled_selected() {
+    let build = prepare_disabled("outputs-disabled-selected", &["songbook.html"])
+        .build()
+        .unwrap();
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(
+        err.contains("Output songbook.html can't be built, it is disabled"),
+        "{}",
+        err
+    );
+
+    let build = prepare_disabled("outputs-disabled-selected-os", &["songbook.json"])
+        .build()
+        .unwrap();
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("it is only built on"), "{}", err);
+}
+
+#[cfg(not(feature = "tectonic"))]
+#[test]
+fn outputs_disabled_pdf_skips_tex() {
+    // Embedded Tectonic isn't available in this build, which is only an error
+    // if a PDF output actually gets built.
+    let prepare = |name: &str, enabled: bool| {
+        TestProject::new(name)
+            .output_toml(toml! {
+                file = "songbook.pdf"
+                enabled = enabled
+            })
+            .output("songbook.html")
+            .settings(|toml| toml.set("tex", "tectonic-embedded"))
+    };
+
+    let build = prepare("outputs-disabled-pdf", false).build().unwrap();
+    build.unwrap();
+    build.read_output(".html");
+    build.try_read_output(".tex").unwrap_err();
+
+    let build = prepare("outputs-enabled-pdf", true).build().unwrap();
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(
+        err.contains("This bard binary was not built with embedded Tectonic."),
+        "{}",
+        err
+    );
+}
+
+#[test]
+fn outputs_per_song() {
+    let build = TestProject::new("outputs-per-song")
+        .song(
+            "songs.md",
+            "# Danny Boy\n\n1. Lyrics.\n\n# Wild Mountain Thyme\n\n1. Lyrics.\n\n# Yippie Yeah\n\n1. Lyrics.\n",
+        )
+        .output("songs/{{slug}}.json")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let dir_songs = build.dir_output().join("songs");
+    let mut files: Vec<_> = fs::read_dir(&dir_songs)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    files.sort();
+    assert_eq!(
+        files,
+        [
+            "danny-boy.json",
+            "wild-mountain-thyme.json",
+            "yippie-yeah.json"
+        ]
+    );
+
+    let danny_boy = fs::read_to_string(dir_songs.join("danny-boy.json")).unwrap();
+    assert!(danny_boy.contains("Danny Boy"));
+    assert!(!danny_boy.contains("Yippie Yeah"));
+
+    let songbook = build.read_output("songbook.json");
+    assert!(songbook.contains("Danny Boy"));
+    assert!(songbook.contains("Yippie Yeah"));
+}
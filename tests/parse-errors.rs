@@ -0,0 +1,103 @@
+use bard::parser::DiagKind;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn project_parse_errors_all_files() {
+    // Errors from all the files are reported, not just the first one
+    let build = TestProject::new("parse-errors-all-files")
+        .song("one.md", "# One\n\n1. Tick <tex>.\n")
+        .song("two.md", "# Two\n\n1. Bad \u{7}char.\n")
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("one.md"), "actual error: {}", err);
+    assert!(err.contains("two.md"), "actual error: {}", err);
+    build.assert_parser_diag(DiagKind::RawTexCode);
+    build.assert_parser_diag(DiagKind::ControlChar { char: 7 });
+}
+
+const SONG_WITH_WARNING: &str = "# Song\n\n1. Lyrics.\n\n2.\n";
+
+#[test]
+fn project_parse_warnings() {
+    let build = TestProject::new("parse-warnings")
+        .song("song.md", SONG_WITH_WARNING)
+        .build()
+        .unwrap();
+
+    build.unwrap();
+    build.assert_parser_diag(DiagKind::EmptyVerse { chorus: false });
+    build.assert_warning("song.md:5: Empty verse");
+}
+
+#[test]
+fn project_parse_warnings_strict() {
+    let build = TestProject::new("parse-warnings-strict")
+        .song("song.md", SONG_WITH_WARNING)
+        .settings(|settings| settings.set("strict", true))
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("song.md"), "actual error: {}", err);
+    build.assert_parser_diag(DiagKind::EmptyVerse { chorus: false });
+}
+
+#[test]
+fn project_parse_warnings_denied() {
+    let build = TestProject::new("parse-warnings-denied")
+        .song("song.md", SONG_WITH_WARNING)
+        .deny_warnings(true)
+        .build()
+        .unwrap();
+
+    build.unwrap_err();
+    build.assert_parser_diag(DiagKind::EmptyVerse { chorus: false });
+}
+
+const SONGS_DUPLICATE_TITLES: &[(&str, &str)] = &[
+    ("a.md", "# Danny Boy\n\n1. Verse.\n"),
+    ("b.md", "# Other Song\n\n1. Verse.\n\n# danny boy\n\n1. Verse.\n"),
+];
+
+fn duplicate_titles_project(name: &str, strict: bool) -> TestBuild {
+    let mut project = TestProject::new(name);
+    for (path, content) in SONGS_DUPLICATE_TITLES {
+        project = project.song(*path, *content);
+    }
+    project
+        .settings(move |settings| settings.set("titles_strict", strict))
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn project_duplicate_titles() {
+    let build = duplicate_titles_project("duplicate-titles", false);
+
+    assert_eq!(build.unwrap().songs().len(), 3);
+    build.assert_parser_diag(DiagKind::DuplicateTitle {
+        title: "danny boy".into(),
+        first_file: "songs/a.md".into(),
+        first_line: 1,
+        strict: false,
+    });
+    build.assert_warning("songs/b.md:5: Duplicate song title");
+}
+
+#[test]
+fn project_duplicate_titles_strict() {
+    let build = duplicate_titles_project("duplicate-titles-strict", true);
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("b.md"), "actual error: {}", err);
+    build.assert_parser_diag(DiagKind::DuplicateTitle {
+        title: "danny boy".into(),
+        first_file: "songs/a.md".into(),
+        first_line: 1,
+        strict: true,
+    });
+}
@@ -0,0 +1,58 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn project_partials() {
+    let build = TestProject::new("partials")
+        .song(
+            "song.md",
+            indoc! {"
+            # Song
+
+            1. Lyrics.
+            "},
+        )
+        .output("songbook.html")
+        .template(
+            "songbook.html",
+            "songbook.hbs",
+            indoc! {"
+            {{> header }}
+            {{#each songs}}<p>{{ title }}</p>{{/each}}
+            {{> footer title=book.title }}
+            "},
+        )
+        .template_file("_header.hbs", "<header>{{ book.title }}</header>")
+        .template_file("_footer.hbs", "<footer>End of {{ title }}</footer>")
+        .template_file("header.hbs", "not a partial")
+        .build()
+        .unwrap();
+
+    let html = build.read_output(".html");
+    assert!(html.contains("<header>My Songbook</header>"));
+    assert!(html.contains("<p>Song</p>"));
+    assert!(html.contains("<footer>End of My Songbook</footer>"));
+    assert!(!html.contains("not a partial"));
+}
+
+#[test]
+fn project_partials_song_template() {
+    let build = TestProject::new("partials-song-template")
+        .song(
+            "song.md",
+            indoc! {"
+            # Song
+            !template: song.hbs
+
+            1. Lyrics.
+            "},
+        )
+        .output("songbook.html")
+        .template_file("song.hbs", "<div>{{> title }}</div>")
+        .template_file("_title.hbs", "Title: {{ song.title }}")
+        .build()
+        .unwrap();
+
+    let html = build.read_output(".html");
+    assert!(html.contains("<div>Title: Song</div>"));
+}
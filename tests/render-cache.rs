@@ -0,0 +1,105 @@
+use std::fs;
+
+use bard::prelude::*;
+use bard::project::Project;
+
+mod util_ng;
+pub use util_ng::*;
+
+const SENTINEL: &str = "not rendered by bard";
+
+fn rebuild(build: &TestBuild, force: bool) -> Project {
+    let app = build.app().clone().with_force_rebuild(force);
+    bard::bard_make_at(&app, &build.unwrap().project_dir).unwrap()
+}
+
+fn overwrite_outputs(build: &TestBuild) {
+    for suffix in [".html", ".json"] {
+        fs::write(build.output_path(suffix).unwrap(), SENTINEL).unwrap();
+    }
+}
+
+fn prepare_build(name: &str) -> TestBuild {
+    TestProject::new(name)
+        .song("song.md", "# Song\n\n1. Lyrics.\n")
+        .output("songbook.html")
+        .output("songbook.json")
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn render_cache_noop() {
+    let build = prepare_build("render-cache-noop");
+    build.unwrap();
+    assert!(build.dir_output().join(".bard-cache").exists());
+
+    // Outputs are overwritten so that we can tell whether they were rendered again
+    overwrite_outputs(&build);
+    rebuild(&build, false);
+    assert_eq!(build.read_output(".html"), SENTINEL);
+    assert_eq!(build.read_output(".json"), SENTINEL);
+
+    // Forced rebuild renders everything
+    rebuild(&build, true);
+    assert!(build.read_output(".json").contains("Lyrics."));
+    assert!(build.read_output(".html").contains("Lyrics."));
+}
+
+#[test]
+fn render_cache_change() {
+    let build = prepare_build("render-cache-change");
+    build.unwrap();
+
+    overwrite_outputs(&build);
+    let song_path = build.dir_songs().join("song.md");
+    fs::write(song_path, "# Song\n\n1. Changed lyrics.\n").unwrap();
+    rebuild(&build, false);
+    assert!(build.read_output(".json").contains("Changed lyrics."));
+    assert!(build.read_output(".html").contains("Changed lyrics."));
+}
+
+#[test]
+fn render_cache_missing_output() {
+    let build = prepare_build("render-cache-missing-output");
+    build.unwrap();
+
+    fs::remove_file(build.output_path(".json").unwrap()).unwrap();
+    rebuild(&build, false);
+    assert!(build.read_output(".json").contains("Lyrics."));
+}
+
+#[test]
+fn render_cache_partial_change() {
+    let build = TestProject::new("render-cache-partial-change")
+        .song("song.md", "# Song\n\n1. Lyrics.\n")
+        .output("songbook.html")
+        .template("songbook.html", "songbook.hbs", "{{> header }}")
+        .template_file("_header.hbs", "<header>Old</header>")
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let partial = build.unwrap().settings.dir_templates().join("_header.hbs");
+    fs::write(partial, "<header>New</header>").unwrap();
+    rebuild(&build, false);
+    assert!(build.read_output(".html").contains("<header>New</header>"));
+}
+
+#[test]
+fn render_cache_missing_split_page() {
+    let build = TestProject::new("render-cache-missing-split-page")
+        .song("song.md", "# Song\n\n1. Lyrics.\n")
+        .output_toml(toml! {
+            file = "songbook.html"
+            split = "song"
+        })
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let page = build.dir_output().join("songbook").join("song.html");
+    fs::remove_file(&page).unwrap();
+    rebuild(&build, false);
+    assert!(fs::read_to_string(&page).unwrap().contains("Lyrics."));
+}
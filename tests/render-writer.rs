@@ -0,0 +1,35 @@
+use bard::render::Renderer;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn render_to_writer() {
+    let build = TestProject::new("render-writer")
+        .song("song.md", "# Song\n\n1. `Am`Yippie yea `C`oh!\n")
+        .output("songbook.html")
+        .output("songbook.pdf")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+    let outputs = &project.settings.output;
+
+    let render = |i: usize| {
+        let renderer = Renderer::new(project, &outputs[i], build.app().img_cache()).unwrap();
+        let mut buffer = vec![];
+        renderer
+            .render_to_writer(build.app(), &mut buffer)
+            .map(|_| buffer)
+    };
+
+    let html = String::from_utf8(render(0).unwrap()).unwrap();
+    assert_eq!(html, build.read_output(".html"));
+    html.find("Yippie yea").unwrap();
+
+    let tex = String::from_utf8(render(1).unwrap()).unwrap();
+    tex.find(r"\begin{document}").unwrap();
+    tex.find("Yippie yea").unwrap();
+
+    render(2).unwrap_err();
+}
@@ -86,3 +86,138 @@ fn project_script_fail() {
         .unwrap()
         .unwrap_err();
 }
+
+fn failing_script(name: &str, on_failure: &str) -> TestProject {
+    TestProject::new(name)
+        .postprocess(true)
+        .output_toml(toml! {
+            file = "songbook.html"
+            script = { name = "script", on_failure = on_failure }
+        })
+        .script(
+            ".html",
+            "script",
+            indoc! {r#"
+            #!/bin/sh
+            exit 1
+            "#},
+            indoc! {r#"
+            @ECHO OFF
+            exit 1
+            "#},
+        )
+}
+
+#[test]
+fn project_script_env() {
+    let build = TestProject::new("script-env")
+        .postprocess(true)
+        .output_toml(toml! {
+            file = "songbook.html"
+            script = { name = "script", env = { FOO = "foo", PATH_COPY = "x${PATH}y" } }
+        })
+        .script(
+            ".html",
+            "script",
+            indoc! {r#"
+            #!/bin/sh
+            printf '%s\n%s\n' "$FOO" "$PATH_COPY" > env.txt
+            "#},
+            indoc! {r#"
+            @ECHO OFF
+            (
+            echo %FOO%
+            echo %PATH_COPY%
+            ) > env.txt
+            "#},
+        )
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let out = build.read_output("env.txt");
+    let mut lines = out.lines().map(str::trim_end);
+    assert_eq!(lines.next(), Some("foo"));
+    let path = std::env::var("PATH").unwrap();
+    assert_eq!(lines.next(), Some(format!("x{}y", path).as_str()));
+}
+
+#[test]
+fn project_script_timeout() {
+    let build = TestProject::new("script-timeout")
+        .postprocess(true)
+        .output_toml(toml! {
+            file = "songbook.html"
+            script = { name = "script", timeout_secs = 1 }
+        })
+        .script(
+            ".html",
+            "script",
+            indoc! {r#"
+            #!/bin/sh
+            sleep 60
+            "#},
+            indoc! {r#"
+            @ECHO OFF
+            ping -n 60 127.0.0.1 > nul
+            "#},
+        )
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("songbook.html"), "{}", err);
+    assert!(err.contains("timed out after 1 seconds"), "{}", err);
+}
+
+#[test]
+fn project_script_on_failure_error() {
+    failing_script("script-on-failure-error", "error")
+        .build()
+        .unwrap()
+        .unwrap_err();
+}
+
+#[test]
+fn project_script_on_failure_warn() {
+    let build = failing_script("script-on-failure-warn", "warn")
+        .build()
+        .unwrap();
+    build.unwrap();
+    build.assert_warning("Script for output songbook.html failed");
+}
+
+#[test]
+fn project_script_on_failure_ignore() {
+    let build = failing_script("script-on-failure-ignore", "ignore")
+        .build()
+        .unwrap();
+    build.unwrap();
+    assert!(build.app().warnings().lock().is_empty());
+}
+
+#[test]
+fn project_script_platform_specific() {
+    // The first script set for an output is kept, so the output uses "script",
+    // while "script.<os>" merely creates the platform-specific script files.
+    let build = TestProject::new("script-platform-specific")
+        .postprocess(true)
+        .output("songbook.html")
+        .script(
+            ".html",
+            "script",
+            "#!/bin/sh\necho generic > which.txt\n",
+            "@ECHO OFF\r\necho generic> which.txt\r\n",
+        )
+        .script(
+            ".html",
+            format!("script.{}", std::env::consts::OS),
+            "#!/bin/sh\necho specific > which.txt\n",
+            "@ECHO OFF\r\necho specific> which.txt\r\n",
+        )
+        .build()
+        .unwrap();
+
+    build.unwrap();
+    assert_eq!(build.read_output("which.txt").trim(), "specific");
+}
@@ -0,0 +1,140 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[rustfmt::skip]
+fn prepare_project(name: &str, setlist: &str, strict: bool) -> TestProject {
+    TestProject::new(name)
+        .song("a.md", "# Danny Boy\n\n1. Lyrics.\n")
+        .song("b.md", "# Wild Mountain Thyme\n\n1. Lyrics.\n")
+        .song("c.md", "# Whiskey in the Jar\n\n1. Lyrics.\n")
+        .song("setlist.txt", setlist)
+        .output("songbook.json")
+        .output_toml(toml! {
+            file = "setlist.json"
+            setlist = "songs/setlist.txt"
+            setlist_strict = strict
+        })
+}
+
+fn titles(json: &str) -> Vec<String> {
+    let json: serde_json::Value = serde_json::from_str(json).unwrap();
+    json["songs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|song| song["title"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn setlist_order() {
+    let build = prepare_project(
+        "setlist-order",
+        "Whiskey in the Jar\ndanny-boy\nDanny Boy\n",
+        false,
+    )
+    .build()
+    .unwrap();
+
+    assert_eq!(
+        titles(&build.read_output("setlist.json")),
+        ["Whiskey in the Jar", "Danny Boy", "Wild Mountain Thyme"]
+    );
+
+    // Other outputs are not affected
+    assert_eq!(
+        titles(&build.read_output("songbook.json")),
+        ["Danny Boy", "Wild Mountain Thyme", "Whiskey in the Jar"]
+    );
+}
+
+#[test]
+fn setlist_strict() {
+    let build = prepare_project("setlist-strict", "Whiskey in the Jar\nDanny Boy\n", true)
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("Wild Mountain Thyme"), "actual error: {}", err);
+}
+
+#[test]
+fn setlist_missing_song() {
+    let build = prepare_project("setlist-missing-song", "Danny Boy\nNo Such Song\n", false)
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("No Such Song"), "actual error: {}", err);
+}
+
+#[test]
+fn setlist_toc() {
+    #[rustfmt::skip]
+    let build = TestProject::new("setlist-toc")
+        .song("a.md", "# Danny Boy\n!tags: irish, ballad\n\n1. Oh `D`Danny *boy*, the `G`pipes,\nthe pipes are calling\n")
+        .song("b.md", "# Wild Mountain Thyme\n!tags: [\"scottish\"]\n\n1. `G`Oh the summer time is coming\n")
+        .song("c.md", "# Whiskey in the Jar\n\n> Musha ring dum a doo dum a da\n")
+        .song("setlist.txt", "Whiskey in the Jar\nDanny Boy\n")
+        .output("songbook.json")
+        .output_toml(toml! {
+            file = "setlist.json"
+            setlist = "songs/setlist.txt"
+        })
+        .build()
+        .unwrap();
+
+    let toc = |file: &str| {
+        let json: serde_json::Value = serde_json::from_str(&build.read_output(file)).unwrap();
+        json["toc"].clone()
+    };
+
+    assert_eq!(
+        toc("songbook.json"),
+        serde_json::json!([
+            {
+                "title": "Danny Boy",
+                "slug": "danny-boy",
+                "tags": ["irish", "ballad"],
+                "first_line": "Oh Danny boy, the pipes,",
+                "idx": 0,
+            },
+            {
+                "title": "Wild Mountain Thyme",
+                "slug": "wild-mountain-thyme",
+                "tags": ["scottish"],
+                "first_line": "Oh the summer time is coming",
+                "idx": 1,
+            },
+            {
+                "title": "Whiskey in the Jar",
+                "slug": "whiskey-in-the-jar",
+                "tags": [],
+                "first_line": "Musha ring dum a doo dum a da",
+                "idx": 2,
+            },
+        ])
+    );
+
+    // The ToC follows the setlist order
+    let toc = toc("setlist.json");
+    let entries: Vec<_> = toc
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| {
+            (
+                entry["title"].as_str().unwrap(),
+                entry["idx"].as_u64().unwrap(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        entries,
+        [
+            ("Whiskey in the Jar", 0),
+            ("Danny Boy", 1),
+            ("Wild Mountain Thyme", 2),
+        ]
+    );
+}
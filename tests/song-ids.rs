@@ -0,0 +1,48 @@
+mod util_ng;
+pub use util_ng::*;
+
+fn prepare_project(name: &str) -> TestProject {
+    TestProject::new(name)
+        .song(
+            "1.md",
+            "# Intro\n\n1. Lyrics.\n\n# Danny Boy\n\n1. Lyrics.\n",
+        )
+        .song(
+            "2.md",
+            "# Intro!\n\n1. Lyrics.\n\n# Intro\n!id: outro\n\n1. Lyrics.\n",
+        )
+        .song("3.md", "# Intro\n\n1. Lyrics.\n")
+}
+
+#[test]
+fn song_ids_json() {
+    let build = prepare_project("song-ids-json")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let json: serde_json::Value = serde_json::from_str(&build.read_output(".json")).unwrap();
+    let ids: Vec<_> = json["songs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|s| s["id"].as_str().unwrap())
+        .collect();
+    assert_eq!(ids, ["intro", "danny-boy", "intro-2", "outro", "intro-3"]);
+
+    assert_eq!(json["songs_sorted"][0]["id"], "danny-boy");
+}
+
+#[test]
+fn song_ids_html() {
+    let build = prepare_project("song-ids-html")
+        .output("songbook.html")
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let html = build.read_output(".html");
+    assert!(html.contains(r##"<li><a href="#danny-boy">Danny Boy</a></li>"##));
+    assert!(html.contains(r#"<section id="intro-3" class="song pad">"#));
+}
@@ -0,0 +1,82 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn project_song_template() {
+    let build = TestProject::new("song-template")
+        .song(
+            "normal.md",
+            indoc! {"
+            # Normal Song
+
+            1. Normal lyrics.
+            "},
+        )
+        .song(
+            "bonus.md",
+            indoc! {"
+            # Bonus Song
+            !template: bonus.hbs
+
+            1. Bonus lyrics.
+            "},
+        )
+        .output("songbook.html")
+        .template_file(
+            "bonus.hbs",
+            indoc! {"
+            {{~ version_check \"1.4.0\" ~}}
+            <div class=\"bonus\">Bonus layout: {{ song.title }} &amp; <i>{{ book.title }}</i></div>
+            "},
+        )
+        .build()
+        .unwrap();
+
+    let html = build.read_output(".html");
+    assert_eq!(html.matches("<div class=\"bonus\">").count(), 1);
+    assert!(html.contains("Bonus layout: Bonus Song"));
+    assert!(!html.contains("Bonus layout: Normal Song"));
+    assert!(html.contains("<h2>Normal Song</h2>"));
+    assert!(!html.contains("<h2>Bonus Song</h2>"));
+    assert!(!html.contains("Bonus lyrics."));
+}
+
+#[test]
+fn project_song_template_per_format() {
+    let build = TestProject::new("song-template-per-format")
+        .song(
+            "bonus.md",
+            indoc! {"
+            # Bonus Song
+            !template: { pdf = \"bonus.hbs\" }
+
+            1. Bonus lyrics.
+            "},
+        )
+        .output("songbook.html")
+        .template_file("bonus.hbs", "Bonus layout")
+        .build()
+        .unwrap();
+
+    let html = build.read_output(".html");
+    assert!(!html.contains("Bonus layout"));
+    assert!(html.contains("Bonus lyrics."));
+}
+
+#[test]
+fn project_song_template_missing() {
+    let build = TestProject::new("song-template-missing")
+        .song(
+            "bonus.md",
+            indoc! {"
+            # Bonus Song
+            !template: nonexistent.hbs
+            "},
+        )
+        .output("songbook.html")
+        .build()
+        .unwrap();
+
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("nonexistent.hbs"));
+}
@@ -0,0 +1,64 @@
+use std::fs;
+
+use bard::render::html;
+use bard::util_cmd;
+
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn project_template_diff() {
+    let old_template = html::DEFAULT_TEMPLATE.content.replacen(
+        "version_check \"1.4.0\"",
+        "version_check \"1.3.0\"",
+        1,
+    );
+    let build = TestProject::new("template-diff")
+        .output("songbook.html")
+        .template("songbook.html", "songbook.hbs", &old_template)
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+    let template = project.settings.output[0].template.clone().unwrap();
+
+    let diff =
+        util_cmd::template_diff(build.app(), &project.project_dir, "songbook", false).unwrap();
+    assert!(
+        diff.contains("-{{~ version_check \"1.3.0\" ~}}\n"),
+        "{}",
+        diff
+    );
+    assert!(
+        diff.contains("+{{~ version_check \"1.4.0\" ~}}\n"),
+        "{}",
+        diff
+    );
+    assert_eq!(fs::read_to_string(&template).unwrap(), old_template);
+
+    util_cmd::template_diff(build.app(), &project.project_dir, "songbook", true).unwrap();
+    assert_eq!(
+        fs::read_to_string(&template).unwrap(),
+        html::DEFAULT_TEMPLATE.content
+    );
+    assert_eq!(
+        fs::read_to_string(template.with_extension("hbs.bak")).unwrap(),
+        old_template
+    );
+
+    let diff =
+        util_cmd::template_diff(build.app(), &project.project_dir, "songbook", false).unwrap();
+    assert_eq!(diff, "");
+}
+
+#[test]
+fn project_template_diff_errors() {
+    let build = TestProject::new("template-diff-errors")
+        .output("songbook.html")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+    let project = build.unwrap();
+
+    util_cmd::template_diff(build.app(), &project.project_dir, "nonexistent", false).unwrap_err();
+    util_cmd::template_diff(build.app(), &project.project_dir, "songbook.json", false).unwrap_err();
+}
@@ -0,0 +1,79 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn project_template_strict_defaults() {
+    // Optional book fields are left out, the default templates must not rely on them.
+    TestProject::new("template-strict-defaults")
+        .output_toml(toml! {
+            file = "songbook.html"
+            template_strict = true
+        })
+        .output_toml(toml! {
+            file = "songbook.pdf"
+            template_strict = true
+        })
+        .output_toml(toml! {
+            file = "songbook.hovorka.xml"
+            format = "hovorka"
+            template_strict = true
+        })
+        .settings(|toml| {
+            let book = toml["book"].as_table_mut().unwrap();
+            book.remove("subtitle");
+            book.remove("title_note");
+        })
+        .build()
+        .unwrap()
+        .unwrap();
+}
+
+#[test]
+fn project_template_strict_typo() {
+    let template = indoc! {r#"
+        {{#each songs}}
+        <h1>{{ titel }}</h1>
+        {{/each}}
+        "#};
+
+    let build = TestProject::new("template-strict-lenient")
+        .output("songbook.html")
+        .template("songbook.html", "songbook.html.hbs", template)
+        .build()
+        .unwrap();
+    build.unwrap();
+    assert!(build.read_output(".html").contains("<h1></h1>"));
+
+    let build = TestProject::new("template-strict-typo")
+        .output_toml(toml! {
+            file = "songbook.html"
+            template_strict = true
+        })
+        .template("songbook.html", "songbook.html.hbs", template)
+        .build()
+        .unwrap();
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("titel"), "{}", err);
+    assert!(err.contains("songbook.html.hbs"), "{}", err);
+}
+
+#[test]
+fn project_template_error_location() {
+    let template = indoc! {r#"
+        {{#each songs}}
+        <h1>{{ title }}</h1>
+        <img width="{{ img_w }}">
+        {{/each}}
+        "#};
+
+    let build = TestProject::new("template-error-location")
+        .output("songbook.html")
+        .template("songbook.html", "songbook.html.hbs", template)
+        .build()
+        .unwrap();
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("songbook.html.hbs"), "{}", err);
+    assert!(err.contains("at line 3, column"), "{}", err);
+    assert!(err.contains("3 | <img width=\"{{ img_w }}\">"), "{}", err);
+    assert!(err.contains("img_w"), "{}", err);
+}
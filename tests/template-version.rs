@@ -0,0 +1,101 @@
+mod util_ng;
+pub use util_ng::*;
+
+#[test]
+fn template_version_missing() {
+    let build = TestProject::new("template-version-missing")
+        .song("song.md", "# Song\n\n1. Lyrics.\n")
+        .output("songbook.html")
+        .template("songbook.html", "html.hbs", "{{ book.title }}")
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    build.assert_warning("doesn't declare its version with the version_check helper");
+}
+
+#[test]
+fn template_version_declared() {
+    let build = TestProject::new("template-version-declared")
+        .song("song.md", "# Song\n\n1. Lyrics.\n")
+        .output("songbook.html")
+        .template(
+            "songbook.html",
+            "html.hbs",
+            "{{~ version_check \"1.4.0\" ~}}\n{{ book.title }}",
+        )
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    assert!(build.app().warnings().lock().is_empty());
+}
+
+fn build_with_version(name: &str, version: &str) -> TestBuild {
+    TestProject::new(name)
+        .song("song.md", "# Song\n\n1. Lyrics.\n")
+        .output("songbook.html")
+        .template(
+            "songbook.html",
+            "html.hbs",
+            format!(
+                "{{{{~ version_check \"{}\" ~}}}}\n{{{{ book.title }}}}",
+                version
+            ),
+        )
+        .build()
+        .unwrap()
+}
+
+#[test]
+fn template_version_newer_major() {
+    let build = build_with_version("template-version-newer-major", "2.0.0");
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("please upgrade bard"), "{}", err);
+}
+
+#[test]
+fn template_version_newer_minor() {
+    let build = build_with_version("template-version-newer-minor", "1.99.0");
+    build.unwrap();
+    build.assert_warning("which is newer than what this bard uses");
+}
+
+#[test]
+fn template_version_older_major() {
+    let build = build_with_version("template-version-older-major", "0.9.0");
+    build.unwrap();
+    build.assert_warning("bard util template-diff");
+}
+
+#[test]
+fn template_version_older_minor() {
+    let build = build_with_version("template-version-older-minor", "1.0.0");
+    build.unwrap();
+    assert!(build.app().warnings().lock().is_empty());
+}
+
+#[test]
+fn template_version_missing_reported_once() {
+    let build = TestProject::new("template-version-missing-once")
+        .song("song.md", "# Song\n\n1. Lyrics.\n")
+        .output_toml(toml! {
+            file = "songbook.html"
+            template = "html.hbs"
+        })
+        .output_toml(toml! {
+            file = "songbook2.html"
+            template = "html.hbs"
+        })
+        .template_file("html.hbs", "{{ book.title }}")
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let warnings = build.app().warnings().lock();
+    let count = warnings
+        .iter()
+        .filter(|w| w.contains("doesn't declare its version"))
+        .count();
+    assert_eq!(count, 1);
+}
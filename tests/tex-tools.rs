@@ -117,3 +117,45 @@ fn tex_tools_tectonic_embedded_explicit() {
     let pdf = builder.out_dir().join("songbook.pdf");
     assert_file_is_pdf(&pdf);
 }
+
+#[test]
+fn tex_tools_tectonic_offline() {
+    let builder = ExeBuilder::init("tex-tools-tectonic-offline")
+        .unwrap()
+        .with_tectonic_bin()
+        .with_env("BARD_TEX", "tectonic");
+    modify_settings(&builder.work_dir, |mut settings| {
+        let mut tectonic = toml::Table::new();
+        tectonic.insert("offline".to_string(), true.into());
+        settings.insert("tectonic".to_string(), tectonic.into());
+        Ok(settings)
+    })
+    .unwrap();
+    let builder = builder.run(&["make", "-kv"]).unwrap();
+
+    assert_file_contains(builder.out_dir().join("songbook.pdf"), "--only-cached");
+}
+
+#[cfg(feature = "tectonic")]
+#[test]
+fn tex_tools_tectonic_embedded_cache_dir() {
+    let builder = ExeBuilder::init("tex-tools-tectonic-embedded-cache-dir")
+        .unwrap()
+        .custom_path(true);
+    modify_settings(&builder.work_dir, |mut settings| {
+        let mut tectonic = toml::Table::new();
+        tectonic.insert("cache_dir".to_string(), "tectonic-cache".into());
+        settings.insert("tectonic".to_string(), tectonic.into());
+        Ok(settings)
+    })
+    .unwrap();
+    let builder = builder.run(&["make", "-kv"]).unwrap();
+
+    let pdf = builder.out_dir().join("songbook.pdf");
+    assert_file_is_pdf(&pdf);
+    let cache_dir = builder.work_dir.join("tectonic-cache");
+    assert!(std::fs::read_dir(cache_dir.join("formats"))
+        .unwrap()
+        .next()
+        .is_some());
+}
@@ -0,0 +1,83 @@
+mod util_ng;
+pub use util_ng::*;
+
+fn chords(json: &str) -> Vec<String> {
+    let json: serde_json::Value = serde_json::from_str(json).unwrap();
+    let mut chords = vec![];
+    let verse = &json["songs"][0]["blocks"][0];
+    for paragraph in verse["paragraphs"].as_array().unwrap() {
+        for inline in paragraph.as_array().unwrap() {
+            if inline["type"] == "i-chord" {
+                chords.push(inline["chord"].as_str().unwrap().to_string());
+            }
+        }
+    }
+    chords
+}
+
+#[test]
+fn project_transpose_per_output() {
+    let build = TestProject::new("transpose-per-output")
+        .song(
+            "song.md",
+            indoc! {"
+            # Song
+
+            !+2
+
+            1. `C`Lyrics `Am`more `G7`lyrics.
+            "},
+        )
+        .output("transposed.json")
+        .output_toml(toml! {
+            file = "original.json"
+            transpose = false
+        })
+        .build()
+        .unwrap();
+
+    let project = build.unwrap();
+    assert!(project.book_original.is_some());
+    assert_eq!(
+        chords(&build.read_output("transposed.json")),
+        ["D", "Bm", "A7"]
+    );
+    assert_eq!(
+        chords(&build.read_output("original.json")),
+        ["C", "Am", "G7"]
+    );
+}
+
+#[test]
+fn project_transpose_default() {
+    let build = TestProject::new("transpose-default")
+        .song("song.md", "# Song\n\n!+2\n\n1. `C`Lyrics.\n")
+        .output("songbook.json")
+        .build()
+        .unwrap();
+
+    // The book is only parsed once unless needed
+    assert!(build.unwrap().book_original.is_none());
+    assert_eq!(chords(&build.read_output(".json")), ["D"]);
+}
+
+#[test]
+fn project_transpose_key() {
+    let build = TestProject::new("transpose-key")
+        .song("song.md", "# Song\n!key: G\n\n!+2\n\n1. `G`Lyrics.\n")
+        .output("transposed.json")
+        .output_toml(toml! {
+            file = "original.json"
+            transpose = false
+        })
+        .build()
+        .unwrap();
+    build.unwrap();
+
+    let key = |output: &str| {
+        let json: serde_json::Value = serde_json::from_str(&build.read_output(output)).unwrap();
+        json["songs"][0]["key"].clone()
+    };
+    assert_eq!(key("transposed.json"), "A");
+    assert_eq!(key("original.json"), "G");
+}
@@ -34,6 +34,8 @@ pub fn interrupt() {
 pub struct TestProject {
     path: PathBuf,
     postprocess: bool,
+    deny_warnings: bool,
+    selected_outputs: Vec<String>,
     outputs: Vec<Toml>,
     modify_settings: Option<Box<dyn FnOnce(&mut toml::Table)>>,
     songs: Vec<(PathBuf, String)>,
@@ -51,6 +53,8 @@ impl TestProject {
         Self {
             path,
             postprocess: false,
+            deny_warnings: false,
+            selected_outputs: vec![],
             outputs: vec![],
             modify_settings: None,
             songs: vec![],
@@ -65,6 +69,18 @@ impl TestProject {
         self
     }
 
+    /// Fail on parser warnings, like `bard make --deny-warnings`.
+    pub fn deny_warnings(mut self, deny_warnings: bool) -> Self {
+        self.deny_warnings = deny_warnings;
+        self
+    }
+
+    /// Only build outputs with these names, like `bard make <OUTPUT>...`.
+    pub fn select_outputs(mut self, outputs: &[&str]) -> Self {
+        self.selected_outputs = outputs.iter().map(|o| o.to_string()).collect();
+        self
+    }
+
     pub fn output(self, file: impl Into<String>) -> Self {
         let file = file.into();
         self.output_toml(toml! { file = file })
@@ -97,7 +113,21 @@ impl TestProject {
         content: impl Into<String>,
     ) -> Self {
         self.templates.push(Template {
-            output: output.into(),
+            output: Some(output.into()),
+            filename: filename.into(),
+            content: content.into(),
+        });
+        self
+    }
+
+    /// Add a template file which isn't used by any output, eg. a song template.
+    pub fn template_file(
+        mut self,
+        filename: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
+        self.templates.push(Template {
+            output: None,
             filename: filename.into(),
             content: content.into(),
         });
@@ -163,7 +193,9 @@ impl TestProject {
         let bard_exe = option_env!("CARGO_BIN_EXE_bard")
             .expect("$CARGO_BIN_EXE_bard")
             .into();
-        let app = App::with_test_mode(self.postprocess, bard_exe, InterruptFlag(&INTERRUPT));
+        let app = App::with_test_mode(self.postprocess, bard_exe, InterruptFlag(&INTERRUPT))
+            .with_selected_outputs(mem::take(&mut self.selected_outputs))
+            .with_deny_warnings(self.deny_warnings);
 
         // Init default project
         bard::bard_init_at(&app, &self.path)
@@ -185,7 +217,8 @@ impl TestProject {
                 .with_context(|| format!("Couldn't create songs directory: {:?}", songs_dir))?;
             for (path, content) in self.songs.iter() {
                 let path = songs_dir.join(path);
-                fs::write(&path, content.as_bytes())
+                fs::create_dir_all(path.parent().unwrap())
+                    .and_then(|_| fs::write(&path, content.as_bytes()))
                     .with_context(|| format!("Couldn't write song file: {:?}", path))?;
             }
             let paths = self
@@ -206,13 +239,16 @@ impl TestProject {
                 .with_context(|| format!("Couldn't create templates directory: {:?}", tpl_dir))?;
             for tpl in self.templates.iter() {
                 let path = tpl_dir.join(&tpl.filename);
-                fs::write(&path, tpl.content.as_bytes())
+                fs::create_dir_all(path.parent().unwrap())
+                    .and_then(|_| fs::write(&path, tpl.content.as_bytes()))
                     .with_context(|| format!("Couldn't write template file: {:?}", path))?;
             }
             for tpl in self.templates.iter() {
-                bard_toml
-                    .output_mut(&tpl.output)
-                    .set("template", tpl.filename.as_str());
+                if let Some(output) = tpl.output.as_ref() {
+                    bard_toml
+                        .output_mut(output)
+                        .set("template", tpl.filename.as_str());
+                }
             }
         }
 
@@ -231,9 +267,11 @@ impl TestProject {
                     .with_context(|| format!("Couldn't write script file: {:?}", path_bat))?;
             }
             for script in self.scripts.iter() {
+                // A script given as a table with further options in output_toml() is kept as is
                 bard_toml
                     .output_mut(&script.output)
-                    .set("script", script.name.as_str());
+                    .entry("script")
+                    .or_insert(script.name.as_str().into());
             }
         }
 
@@ -303,6 +341,17 @@ impl TestBuild {
             .unwrap();
     }
 
+    #[track_caller]
+    pub fn assert_warning(&self, needle: &str) {
+        let warnings = self.app.warnings().lock();
+        assert!(
+            warnings.iter().any(|w| w.contains(needle)),
+            "No warning containing {:?} in {:?}",
+            needle,
+            warnings,
+        );
+    }
+
     pub fn dir_songs(&self) -> &Path {
         self.unwrap().settings.dir_songs()
     }
@@ -381,7 +430,7 @@ impl TestBuild {
 }
 
 struct Template {
-    output: String,
+    output: Option<String>,
     filename: String,
     content: String,
 }
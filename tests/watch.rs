@@ -1,4 +1,4 @@
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::Write as _;
 
 mod util_ng;
@@ -64,3 +64,62 @@ fn watch() {
 
     watch_thread.join().unwrap();
 }
+
+#[test]
+fn watch_new_file() {
+    const TEST_STR: &str = "watch new file test";
+
+    let build = TestProject::new("watch-new-file")
+        .song("a.md", "# Song A\n\n1. Lyrics A\n")
+        .output("songbook.html")
+        .settings(|toml| {
+            toml.set("songs", "*.md");
+        })
+        .build()
+        .unwrap();
+
+    let (watch_thread, control) = build.watch();
+    control.wait_watching();
+
+    // Create a new file matching the glob:
+    let md_file = build.dir_songs().join("b.md");
+    fs::write(&md_file, format!("# Song B\n\n1. {}\n", TEST_STR)).unwrap();
+
+    // Wait for the watching to resume after the triggered render pass:
+    control.wait_watching();
+    build.interrupt();
+
+    let html = build.read_output(".html");
+    assert!(html.contains(TEST_STR));
+
+    watch_thread.join().unwrap();
+}
+
+#[test]
+fn watch_partial() {
+    const TEST_STR: &str = "watch partial test";
+
+    let build = TestProject::new("watch-partial")
+        .song("song.md", "# Song\n\n1. Lyrics\n")
+        .output("songbook.html")
+        .template("songbook.html", "songbook.hbs", "{{> header }}")
+        .template_file("_header.hbs", "<header></header>")
+        .build()
+        .unwrap();
+
+    let (watch_thread, control) = build.watch();
+    control.wait_watching();
+
+    // Modify the partial:
+    let partial = build.unwrap().settings.dir_templates().join("_header.hbs");
+    fs::write(&partial, format!("<header>{}</header>", TEST_STR)).unwrap();
+
+    // Wait for the watching to resume after the triggered render pass:
+    control.wait_watching();
+    build.interrupt();
+
+    let html = build.read_output(".html");
+    assert!(html.contains(TEST_STR));
+
+    watch_thread.join().unwrap();
+}
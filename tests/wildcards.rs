@@ -53,3 +53,72 @@ fn project_wildcards_n() {
         }
     }
 }
+
+#[test]
+fn project_wildcards_dir() {
+    let build = TestProject::new("wildcards-dir")
+        .song("folk/b.md", "# Song B\n\n1. Folk B\n")
+        .song("folk/nested/a.md", "# Song A\n\n1. Folk A\n")
+        .song("folk/notes.txt", "# Notes\n\n1. Not a song\n")
+        .song("other.md", "# Other\n\n1. Other song\n")
+        .output("songbook.html")
+        .settings(|toml| {
+            toml.set("songs", vec!["folk"]);
+        })
+        .build()
+        .unwrap();
+
+    let html = build.read_output(".html");
+    let a = html.find("Folk A").unwrap();
+    let b = html.find("Folk B").unwrap();
+    assert!(b < a);
+    assert!(!html.contains("Not a song"));
+    assert!(!html.contains("Other song"));
+}
+
+#[test]
+fn project_wildcards_dedup() {
+    let build = TestProject::new("wildcards-dedup")
+        .song("a.md", "# Song A\n\n1. Lyrics A\n")
+        .song("b.md", "# Song B\n\n1. Lyrics B\n")
+        .song("c.md", "# Song C\n\n1. Lyrics C\n")
+        .output("songbook.html")
+        .settings(|toml| {
+            toml.set("songs", vec!["b.md", "*.md", "?.md"]);
+        })
+        .build()
+        .unwrap();
+
+    let paths: Vec<_> = build
+        .unwrap()
+        .input_paths()
+        .iter()
+        .map(|p| p.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(paths, ["b.md", "a.md", "c.md"]);
+
+    let html = build.read_output(".html");
+    assert_eq!(html.matches("Lyrics B").count(), 1);
+}
+
+#[test]
+fn project_wildcards_no_match() {
+    let prepare = |name: &str, strict: bool| {
+        TestProject::new(name)
+            .song("a.md", "# Song A\n\n1. Lyrics A\n")
+            .output("songbook.html")
+            .settings(move |toml| {
+                toml.set("songs", vec!["a.md", "nomatch-*.md"]);
+                toml.set("songs_strict", strict);
+            })
+            .build()
+            .unwrap()
+    };
+
+    let build = prepare("wildcards-no-match", false);
+    assert!(build.read_output(".html").contains("Lyrics A"));
+
+    let build = prepare("wildcards-no-match-strict", true);
+    let err = format!("{:?}", build.unwrap_err());
+    assert!(err.contains("nomatch-*.md"));
+}
@@ -0,0 +1,90 @@
+use std::process::Command;
+
+use bard::util::ExitStatusExt as _;
+
+mod util_ng;
+pub use util_ng::*;
+
+const SCHEMA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/src/render/bard.xsd");
+
+#[rustfmt::skip]
+fn prepare_project(name: &str) -> TestProject {
+    TestProject::new(name)
+        .song("summer.md", indoc! {r#"
+            # Summer Time & Co.
+            ## Traditional & folk
+            !author: Trad & Co.
+            !tags: folk, summer
+
+            ### Intro
+            `G` `C` `G`
+
+            1. x2
+            O the `G`summer^[Sometimes sung as _winter_.] `C`time `G`has come !>
+            |: And the **trees** are `D`blooming :| x3
+            See [the notes](https://example.com/?a=1&b=2) and !>Bridge.
+
+            > And we'll `C`all `Em`go "together"
+
+            ### Bridge
+            \
+            `F`Dance to the whistle
+
+            - Item & 1
+            - Item "2"
+
+            ---
+
+            ```tab
+            e|-----0---3---|
+            ```
+
+            ```lyrics
+            Plain <pre> & text
+            ```
+        "#})
+        .song("winter.md", indoc! {r#"
+            # Winter
+
+            1. `Am`Cold ]]> and `C`dark
+
+            > First chorus
+
+            >> Second chorus
+
+            2. Back to !>> and !>
+        "#})
+        .song("chanson.md", indoc! {r#"
+            # Chanson
+
+            !=solfege
+
+            1. `Do`Douce `Sol`nuit
+        "#})
+        .output("songbook.xml")
+}
+
+#[test]
+fn project_xml_version() {
+    let build = prepare_project("xml-version").build().unwrap();
+    build.unwrap();
+
+    let xml = build.read_output(".xml");
+    let version = format!(r#"version="{}""#, bard::book::version::current());
+    assert!(xml.lines().next().unwrap().contains(&version), "{}", xml);
+}
+
+#[test]
+#[ignore = "requires libxml2/xmllint"]
+fn project_xml_schema() {
+    let build = prepare_project("xml-schema").build().unwrap();
+    build.unwrap();
+
+    let output = build.output_path(".xml").unwrap();
+    let status = Command::new("xmllint")
+        .args(["--noout", "--schema", SCHEMA])
+        .arg(&output)
+        .status()
+        .unwrap();
+    status.into_result().unwrap();
+}